@@ -0,0 +1,193 @@
+use color_eyre::Result;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
+
+use crate::{
+    app::{App, MessageKind, procgen::FINAL_LEVEL},
+    components::Item,
+    events::GameEvent,
+    identify,
+    scores::Score,
+};
+
+// this file contains the conducts kept during a run, and the achievements unlocked across
+// every run. achievements live in a profile file next to the save, which outlives the runs
+
+/// file holding the achievements unlocked so far
+const PROFILE_FILE: &str = "profile.json";
+
+/// the floor reaching which counts as halfway down
+const HALFWAY_LEVEL: u16 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Achievement {
+    Pacifist,
+    TrollSlayer,
+    Halfway,
+    RockBottom,
+    Questing,
+    Escaped,
+    Teetotaller,
+}
+
+/// every achievement, in the order they're listed
+pub const ACHIEVEMENTS: [Achievement; 7] = [
+    Achievement::Pacifist,
+    Achievement::TrollSlayer,
+    Achievement::Halfway,
+    Achievement::RockBottom,
+    Achievement::Questing,
+    Achievement::Escaped,
+    Achievement::Teetotaller,
+];
+
+impl Achievement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::Pacifist => "Pacifist",
+            Achievement::TrollSlayer => "Troll Slayer",
+            Achievement::Halfway => "Halfway There",
+            Achievement::RockBottom => "Rock Bottom",
+            Achievement::Questing => "Questing",
+            Achievement::Escaped => "Escape Artist",
+            Achievement::Teetotaller => "Teetotaller",
+        }
+    }
+
+    /// what it takes to unlock the achievement
+    pub fn description(&self) -> String {
+        match self {
+            Achievement::Pacifist => String::from("leave a floor without killing anything"),
+            Achievement::TrollSlayer => String::from("see a troll dead"),
+            Achievement::Halfway => format!("reach depth {}", HALFWAY_LEVEL),
+            Achievement::RockBottom => String::from("reach the bottom of the dungeon"),
+            Achievement::Questing => String::from("finish a quest"),
+            Achievement::Escaped => String::from("escape with the Amulet of Ages"),
+            Achievement::Teetotaller => String::from("escape without drinking a potion"),
+        }
+    }
+}
+
+/// everything that's kept from one run to the next
+#[derive(Default, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub unlocked: Vec<Achievement>,
+    /// the best finished runs, see scores.rs
+    #[serde(default)]
+    pub scores: Vec<Score>,
+}
+
+impl Profile {
+    /// reads the profile file, starting a fresh profile if there isn't one
+    pub fn load() -> Self {
+        File::open(PROFILE_FILE)
+            .ok()
+            .and_then(|mut file| {
+                let mut profile_string = String::new();
+                file.read_to_string(&mut profile_string).ok()?;
+                serde_json::from_str(&profile_string).ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let data_str = serde_json::to_string(&self)?;
+        let mut file = File::create(PROFILE_FILE)?;
+        file.write_all(data_str.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+}
+
+/// the voluntary restrictions the player has kept to, or broken, this run
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Conduct {
+    pub potions_drunk: u32,
+    pub scrolls_read: u32,
+    /// floors the player left without anything dying
+    pub pacifist_floors: u32,
+}
+
+impl Conduct {
+    /// notes down an item the player used up
+    pub fn record_use(&mut self, item: &Item) {
+        if identify::is_potion(item) {
+            self.potions_drunk += 1;
+        } else if identify::is_scroll(item) {
+            self.scrolls_read += 1;
+        }
+    }
+
+    /// a line for each conduct, for the morgue file
+    pub fn lines(&self) -> Vec<String> {
+        let potions = match self.potions_drunk {
+            0 => String::from("never drank a potion"),
+            n => format!("drank {} potions", n),
+        };
+        let scrolls = match self.scrolls_read {
+            0 => String::from("never read a scroll"),
+            n => format!("read {} scrolls", n),
+        };
+        let floors = format!("left {} floors without a kill", self.pacifist_floors);
+        vec![potions, scrolls, floors]
+    }
+}
+
+/// unlocks an achievement, unless it already was. the profile is only written for
+/// runs that keep records, so the demo can't unlock anything for good
+pub fn unlock(app: &mut App, achievement: Achievement) {
+    if app.profile.has(achievement) {
+        return;
+    }
+    app.profile.unlocked.push(achievement);
+    app.add_to_log(
+        format!("Achievement unlocked: {}!", achievement.name()),
+        Color::LightMagenta,
+        MessageKind::System,
+    );
+    if app.keeps_records() {
+        let _ = app.profile.save();
+    }
+}
+
+/// unlocks anything the event earns
+pub fn on_event(app: &mut App, event: &GameEvent) {
+    match event {
+        GameEvent::MonsterDied { name, .. } if name.starts_with("Troll") => {
+            unlock(app, Achievement::TrollSlayer);
+        }
+        GameEvent::LevelUp { level, .. } => {
+            if *level >= HALFWAY_LEVEL {
+                unlock(app, Achievement::Halfway);
+            }
+            if *level >= FINAL_LEVEL {
+                unlock(app, Achievement::RockBottom);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// keeps track of the floors the player leaves without a kill
+pub fn leave_floor(app: &mut App) {
+    if app.kills == app.gamemap.stats.start_kills {
+        app.conduct.pacifist_floors += 1;
+        unlock(app, Achievement::Pacifist);
+    }
+}
+
+/// unlocks the achievements for getting out alive
+pub fn escape(app: &mut App) {
+    unlock(app, Achievement::Escaped);
+    if app.conduct.potions_drunk == 0 {
+        unlock(app, Achievement::Teetotaller);
+    }
+}
@@ -0,0 +1,58 @@
+// short animations drawn over the map, so that spells don't resolve invisibly
+
+use ratatui::style::Color;
+
+use crate::components::{Position, Renderable};
+
+/// one frame of an animation, made of glyphs drawn on top of the map.
+/// each frame stays on screen for one tick of the game loop
+#[derive(Clone)]
+pub struct AnimationFrame {
+    pub cells: Vec<(Position, Renderable)>,
+}
+
+fn cell(position: Position, glyph: char, color: Color) -> (Position, Renderable) {
+    let renderable = Renderable {
+        glyph,
+        fg: color,
+        bg: Color::Reset,
+        role: None,
+    };
+    (position, renderable)
+}
+
+/// a glyph flying along a path, one tile per frame
+pub fn projectile(path: &[Position], glyph: char, color: Color) -> Vec<AnimationFrame> {
+    path.iter()
+        .map(|&position| AnimationFrame {
+            cells: vec![cell(position, glyph, color)],
+        })
+        .collect()
+}
+
+/// a burst growing outwards from the center, one ring per frame
+pub fn explosion(center: Position, radius: u16, color: Color) -> Vec<AnimationFrame> {
+    (0..=radius as i32)
+        .map(|ring| {
+            let mut cells = Vec::new();
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    // only the outer edge of the square, the inside already went off
+                    if dx.abs() != ring && dy.abs() != ring {
+                        continue;
+                    }
+                    let (x, y) = (center.x as i32 + dx, center.y as i32 + dy);
+                    if x < 0 || y < 0 {
+                        continue;
+                    }
+                    let position = Position {
+                        x: x as u16,
+                        y: y as u16,
+                    };
+                    cells.push(cell(position, '*', color));
+                }
+            }
+            AnimationFrame { cells }
+        })
+        .collect()
+}
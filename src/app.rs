@@ -1,33 +1,98 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
-    usize,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    time::Duration,
 };
 
-use ratatui::style::Style;
+use crossterm::event::KeyEvent;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use ratatui::style::{Color, Style};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    components::{Object, Position, SLOT_ORDERING},
-    engine::TargetingMode,
+    achievements::{Conduct, Profile},
+    animation::AnimationFrame,
+    components::{Item, Object, Position, SLOT_ORDERING},
+    data::EntityData,
+    engine::{Death, InputDirection, TargetingMode},
     entities::{self},
-    gamemap::GameMap,
+    events::GameEvent,
+    gamemap::{GameMap, LevelSummary},
+    identify::Identification,
+    inventory,
+    los::RayTable,
+    pathfinding::{FlowMap, PathScratch},
+    quests::Quest,
+    settings::{AnimationSpeed, Settings},
+    spells,
+    theme::Theme,
 };
 
 mod event_handler;
+mod ghost;
+#[cfg(test)]
+mod harness;
+pub mod headless;
+mod macros;
+mod morgue;
 pub mod procgen;
 pub mod render;
 mod saving;
 
+use ghost::Ghost;
+use macros::Macros;
+use saving::LoadError;
+
 pub const PLAYER: usize = 0;
-pub const VIEW_RADIUS: u16 = 8;
-pub const INVENTORY_SIZE: usize = 10;
+/// the pack can't hold more items than there are number keys to use them with
+pub const MAX_INVENTORY_SIZE: usize = 10;
+/// how often the game updates on its own, without waiting for input
+pub const DEFAULT_TICK_RATE: Duration = Duration::from_millis(50);
+/// how long the main menu sits without input before a demo game starts
+pub const DEMO_IDLE_TICKS: u64 = 300;
+/// how often the bot presses a key during the demo
+pub const DEMO_STEP_TICKS: u64 = 2;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64 bit fnv-1a hash of some bytes. it's the same on every machine and every version,
+/// so it can be used for seeds and checksums that have to stay put
+pub fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// what a log message is about, so the fullscreen log can be filtered
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageKind {
+    /// fighting, statuses, and anything else that can hurt
+    Combat,
+    /// picking up, using, identifying and trading items
+    Items,
+    /// everything else, like travelling and stairs
+    #[default]
+    System,
+}
+
+impl std::fmt::Display for MessageKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageKind::Combat => write!(f, "combat"),
+            MessageKind::Items => write!(f, "items"),
+            MessageKind::System => write!(f, "system"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct LogEntry {
     time: u64,
     message: String,
     style: Style,
+    #[serde(default)]
+    kind: MessageKind,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -49,6 +114,55 @@ impl Log {
     pub fn len(&self) -> usize {
         self.messages.len()
     }
+
+    /// returns the messages of a kind, or every message if there is no filter
+    pub fn filtered(&self, filter: Option<MessageKind>) -> Vec<&LogEntry> {
+        self.messages
+            .iter()
+            .filter(|entry| filter.is_none_or(|kind| entry.kind == kind))
+            .collect()
+    }
+
+    /// whether the message at an offset from the bottom of the filtered log contains the query
+    pub fn matches(&self, query: &str, filter: Option<MessageKind>, offset: usize) -> bool {
+        let entries = self.filtered(filter);
+        !query.is_empty()
+            && offset < entries.len()
+            && entries[entries.len() - 1 - offset]
+                .message
+                .to_lowercase()
+                .contains(&query.to_lowercase())
+    }
+
+    /// returns the offset from the bottom of the filtered log of the next message
+    /// containing the query, searching older messages from just above the given offset
+    /// (or newer ones from just below it) and wrapping around the whole log
+    pub fn find_match(
+        &self,
+        query: &str,
+        filter: Option<MessageKind>,
+        offset: usize,
+        older: bool,
+    ) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        let query = query.to_lowercase();
+        let entries = self.filtered(filter);
+        let count = entries.len();
+
+        (1..=count)
+            .map(|step| match older {
+                true => (offset + step) % count,
+                false => (offset + count - step % count) % count,
+            })
+            .find(|&idx| {
+                entries[count - 1 - idx]
+                    .message
+                    .to_lowercase()
+                    .contains(&query)
+            })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -93,7 +207,11 @@ impl ObjectMap {
         &mut self.objects
     }
 
-    #[allow(dead_code)]
+    /// iterate over every object along with its id
+    pub fn iter(&self) -> impl Iterator<Item = (&usize, &Object)> {
+        self.objects.iter()
+    }
+
     pub fn next_id(&self) -> usize {
         self.next_id
     }
@@ -111,10 +229,10 @@ impl Ord for Action {
         // BinaryHeap is a max heap
         // so we reverse the comparison for the time
         // id doesn't really matter since its just a tiebreak
-        return other
+        other
             .time
             .cmp(&self.time)
-            .then_with(|| self.id.cmp(&other.id));
+            .then_with(|| self.id.cmp(&other.id))
     }
 }
 
@@ -133,6 +251,94 @@ pub struct App {
     pub inventory: Vec<usize>,
     pub equipment: Vec<Option<usize>>,
     pub log: Log,
+    pub fov_rays: RayTable,    // precomputed rays reused by every fov update
+    pub show_turn_order: bool, // whether to render the upcoming turn order strip
+    /// whether to show the status, equipment and inventory panels beside the map
+    pub show_sidebar: bool,
+    /// whether to show the message log below the map
+    pub show_log: bool,
+    /// whether to mark the tiles the player has walked on
+    pub show_breadcrumbs: bool,
+    /// whether to shade tiles that visible monsters could attack next turn
+    pub show_danger: bool,
+    /// where the player is automatically walking to, if anywhere
+    pub travel_destination: Option<Position>,
+    /// whether to go down the stairs once the player finishes travelling
+    pub auto_descend: bool,
+    /// the time the player started resting at, if they're resting
+    pub resting_since: Option<u64>,
+    /// the wall the player last walked into, so that walking into it again can say so
+    pub bumped_wall: Option<Position>,
+    /// number of monsters that died this run
+    pub kills: u32,
+    /// what killed the player, once the run is over
+    pub death: Option<Death>,
+    /// the seed the current run was started from
+    pub seed: u64,
+    /// every random roll in the game goes through this, so runs can be reproduced
+    pub rng: StdRng,
+    /// when set, moving into a monster never attacks it
+    pub safe_move: bool,
+    /// whether the player is sneaking, moving slower to be harder to notice
+    pub sneaking: bool,
+    /// colors everything on screen is drawn with
+    pub theme: Theme,
+    /// frames waiting to be drawn over the map, one per render
+    pub animations: VecDeque<AnimationFrame>,
+    /// time between updates of the game loop
+    pub tick_rate: Duration,
+    /// number of updates since the game was started
+    pub ticks: u64,
+    /// buffers reused by every pathfinder
+    pub path_scratch: PathScratch,
+    /// distances from the player, shared by every pack monster for the turn
+    pub pack_flow: FlowMap,
+    /// what each kind of potion and scroll looks like this run, and which are known
+    pub identification: Identification,
+    /// key sequences the player has recorded, and the one being played back
+    pub macros: Macros,
+    /// where the player has been this run, written out when the run ends
+    pub replay: Ghost,
+    /// the path of the last attempt on this seed, if there was one
+    pub ghost: Option<Ghost>,
+    /// whether to draw where the last attempt was at the same time
+    pub show_ghost: bool,
+    /// options read from the settings file
+    pub settings: Settings,
+    /// whether the bot is playing a demo game behind the menu
+    pub demo: bool,
+    /// the tick the player last pressed a key on
+    pub last_input_tick: u64,
+    /// whether the game is being played without a terminal, see headless.rs
+    pub headless: bool,
+    /// monsters and items read from the data file
+    pub entity_data: EntityData,
+    /// everything that happened since the events were last taken, see events.rs
+    pub events: Vec<GameEvent>,
+    /// the optional quests handed out for this run, see quests.rs
+    pub quests: Vec<Quest>,
+    /// the date of the daily challenge this run is on, if it is one. see scores.rs
+    pub daily: Option<String>,
+    /// the conducts kept this run, see achievements.rs
+    pub conduct: Conduct,
+    /// the achievements unlocked over every run, read from the profile file
+    pub profile: Profile,
+    /// objects that just came into view for the first time, and the tick their glyph
+    /// stops flashing on
+    pub flashes: HashMap<usize, u64>,
+}
+
+/// an action held back until the player answers yes on the confirm screen
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PendingAction {
+    /// save and quit the game
+    Quit,
+    /// go down the stairs with monsters next to the player
+    Descend,
+    /// step into lava
+    Move(InputDirection),
+    /// take off the item in an equipment slot and drop it
+    DropEquipped(usize),
 }
 
 /// a singleton enum describing the current screen to display
@@ -140,47 +346,235 @@ pub struct App {
 pub enum GameScreen {
     /// the main menu
     Menu,
+    /// every achievement, and which have been unlocked
+    Achievements,
+    /// the best runs, with today's daily challenge runs kept apart
+    HighScores,
+    /// summary of the run after the player dies
+    GameOver,
+    /// summary of the run after the player escapes with the amulet
+    Victory,
+    /// typing in the seed for a new game
+    EnterSeed { text: String },
+    /// picking a class before starting a new game
+    CharacterCreation { cursor: usize },
+    /// explaining why the save couldn't be loaded, offering to load a backup instead
+    LoadFailed {
+        error: LoadError,
+        backup: Option<usize>,
+    },
+    /// listing everything wrong with the entities data file, before going on to the menu
+    DataError { problems: Vec<String> },
     /// default gameplay screen, with world map and log
     Main,
-    /// display fullscreen log with offset
-    Log { offset: usize },
+    /// display fullscreen log with offset, showing only one kind of message if filtered.
+    /// while searching, keys are typed into the query instead
+    Log {
+        offset: usize,
+        query: String,
+        searching: bool,
+        filter: Option<MessageKind>,
+    },
+    /// waiting for the player to pick which adjacent monster to attack
+    AttackDirection,
+    /// typing in a name for a new waypoint at the player's position
+    NameWaypoint { name: String },
+    /// picking a function key to bind the keys that were just recorded to
+    BindMacro { keys: Vec<KeyEvent> },
+    /// picking a waypoint on this floor to travel to
+    Waypoints { cursor: usize },
+    /// picking one of the spells the player knows to cast
+    Spells { cursor: usize },
+    /// recap of the floor the player just left, dismissed with any key
+    LevelSummary { summary: LevelSummary },
+    /// list of this run's quests, dismissed with any key
+    Quests,
+    /// every key binding, grouped by category and scrolled down by offset lines
+    Help { offset: usize },
+    /// changing the settings, with the cursor on one of them. in_game is false when
+    /// the screen was opened from the menu
+    Settings { cursor: usize, in_game: bool },
+    /// asking whether to go ahead with an action that can't be taken back
+    Confirm {
+        prompt: String,
+        pending: PendingAction,
+    },
+    /// typing the name of a command to run, with the cursor on one of the matches
+    CommandPalette { input: String, cursor: usize },
+    /// talking with a friendly npc, at one node of its dialogue tree
+    Dialogue { speaker: usize, node: usize },
+    /// reading a page of the manual, scrolled down by offset lines.
+    /// while searching, keys are typed into the query instead
+    Manual {
+        page: usize,
+        offset: usize,
+        query: String,
+        searching: bool,
+    },
+    /// picking an item in the inventory or equipment to use an item on
+    ChooseItem { text: String, inventory_idx: usize },
     /// use the examine cursor to look at tiles
     Examine { cursor: Position },
+    /// trading with a shopkeeper. cursor indexes into the stock when buying,
+    /// or the player's inventory when selling
+    Shop {
+        shopkeeper: usize,
+        cursor: usize,
+        selling: bool,
+    },
     /// mode for aiming targetable skills at enemies
     Targeting {
         cursor: Position,
         targeting: TargetingMode,
         text: String,
-        inventory_idx: usize,
+        source: TargetSource,
     },
 }
 
+/// what gets used once a target is picked on the targeting screen
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TargetSource {
+    /// the item in this inventory slot
+    Inventory(usize),
+    /// the known spell at this index of the spell menu
+    Spell(usize),
+}
+
+impl TargetSource {
+    /// returns the item whose effect is being aimed
+    pub fn item(&self, app: &App) -> Item {
+        match *self {
+            TargetSource::Inventory(idx) => inventory::get_item_in_inventory(app, idx).clone(),
+            TargetSource::Spell(idx) => spells::known_spells(app)[idx].effect(),
+        }
+    }
+}
+
 impl App {
     pub fn new() -> Self {
         let player = entities::player();
         let objects = ObjectMap::new(player);
+        let seed = rand::rng().random();
+        let (settings, settings_warnings) = Settings::load();
+        // a preset picked on the settings screen wins over the theme file
+        let (theme, theme_warnings) = match settings.theme.as_deref().and_then(Theme::preset) {
+            Some(theme) => (theme, Vec::new()),
+            None => Theme::load(),
+        };
+        // a broken data file is reported on its own screen, and the game carries on without it
+        let (entity_data, data_problems) = match EntityData::load() {
+            Ok(entity_data) => (entity_data, Vec::new()),
+            Err(problems) => (EntityData::builtin(), problems),
+        };
 
-        Self {
+        let mut app = Self {
             // NOTE: this is a dummy gamemap that should get overwritten when
             // loading or creating a new game
             gamemap: GameMap::new(0, 0, 0),
 
-            game_screen: GameScreen::Menu, // start the game on the main menu
+            // start the game on the main menu
+            game_screen: match data_problems.is_empty() {
+                true => GameScreen::Menu,
+                false => GameScreen::DataError {
+                    problems: data_problems,
+                },
+            },
             objects,
             action_queue: BinaryHeap::new(),
             time: 0,
             inventory: Vec::new(),
             equipment: vec![None; SLOT_ORDERING.len()],
             log: Log::new(),
+            fov_rays: RayTable::new(settings.view_radius),
+            show_turn_order: false,
+            show_sidebar: true,
+            show_log: true,
+            show_breadcrumbs: false,
+            show_danger: false,
+            travel_destination: None,
+            auto_descend: false,
+            resting_since: None,
+            bumped_wall: None,
+            kills: 0,
+            death: None,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            safe_move: false,
+            sneaking: false,
+            theme,
+            animations: VecDeque::new(),
+            tick_rate: DEFAULT_TICK_RATE,
+            ticks: 0,
+            path_scratch: PathScratch::default(),
+            pack_flow: FlowMap::default(),
+            identification: Identification::default(),
+            macros: Macros::default(),
+            replay: Ghost::default(),
+            ghost: None,
+            show_ghost: true,
+            settings,
+            demo: false,
+            last_input_tick: 0,
+            headless: false,
+            entity_data,
+            events: Vec::new(),
+            quests: Vec::new(),
+            daily: None,
+            conduct: Conduct::default(),
+            profile: Profile::load(),
+            flashes: HashMap::new(),
+        };
+
+        // the log is the only place these can be seen once the terminal is taken over
+        for warning in theme_warnings.into_iter().chain(settings_warnings) {
+            app.add_to_log(warning, Color::Yellow, MessageKind::System);
         }
+        app
+    }
+
+    /// returns true if the run is played by a person, so its ghost and morgue are kept.
+    /// the demo and headless games leave nothing behind
+    pub fn keeps_records(&self) -> bool {
+        !self.demo && !self.headless
+    }
+
+    /// returns every scheduled action in the order they will be performed,
+    /// without removing anything from the action queue
+    pub fn peek_actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = self.action_queue.iter().copied().collect();
+        // Action's ordering is reversed for the max heap, so sort descending
+        actions.sort_by(|a, b| b.cmp(a));
+        actions
     }
 
     /// add the new message as a tuple, with the text and the style
-    pub fn add_to_log<T: Into<String>, U: Into<Style>>(&mut self, message: T, style: U) {
+    /// queues up an animation to play after everything already queued,
+    /// sped up or slowed down by the animation speed setting
+    pub fn animate(&mut self, frames: Vec<AnimationFrame>) {
+        match self.settings.animation_speed {
+            AnimationSpeed::Off => {}
+            AnimationSpeed::Fast => self.animations.extend(frames.into_iter().step_by(2)),
+            AnimationSpeed::Normal => self.animations.extend(frames),
+            AnimationSpeed::Slow => {
+                for frame in frames {
+                    self.animations.push_back(frame.clone());
+                    self.animations.push_back(frame);
+                }
+            }
+        }
+    }
+
+    pub fn add_to_log<T: Into<String>, U: Into<Style>>(
+        &mut self,
+        message: T,
+        style: U,
+        kind: MessageKind,
+    ) {
         let entry = LogEntry {
             time: self.time,
             message: message.into(),
             style: style.into(),
+            kind,
         };
         self.log.messages.push(entry);
     }
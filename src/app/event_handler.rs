@@ -1,17 +1,35 @@
+use std::time::Instant;
+
 use color_eyre::{Result, eyre::Ok};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use ratatui::DefaultTerminal;
 use ratatui::style::Color;
 
-use crate::components::SLOT_ORDERING;
+use crate::components::{CLASS_ORDERING, Item, PlayerClass, SLOT_ORDERING, slot_index};
 use crate::engine::{
-    InputDirection, TargetingMode, UseResult, bump_action, go_down_stairs, handle_monster_turns,
-    update_fov,
+    InputDirection, SHOUT_NOISE_RADIUS, TargetingMode, UseResult, action_time,
+    adjacent_hostile_directions, announce_level_feeling, attack_action, bump_action, burn_in_lava,
+    expire_statuses, go_down_stairs, go_up_stairs, handle_monster_turns, known_item,
+    known_stairs_position, known_up_stairs_position, make_noise, movement_time, player_is_hurt,
+    regenerate, rest_step, rot_corpses, safe_move_action, search_for_traps, steps_into_lava,
+    target_problem, travel_step, update_fov, visible_monsters, visible_monsters_by_distance,
 };
-use crate::inventory;
+use crate::events::GameEvent;
+use crate::gamemap::Waypoint;
+use crate::identify::{self, Identification};
+use crate::keybindings::{self, CommandAction};
+use crate::settings::{self, SETTING_ROWS};
+use crate::{bot, dialogue, entities, fire, gas, inventory, manual, quests, scores, shop, spells};
 
+use super::ghost::Ghost;
+use super::macros::{MACRO_SLOTS, MAX_MACRO_LENGTH};
 use super::procgen::DungeonConfig;
-use super::{App, GameScreen, INVENTORY_SIZE, PLAYER, VIEW_RADIUS};
+use super::saving::LoadError;
+use super::{
+    App, DEMO_IDLE_TICKS, DEMO_STEP_TICKS, GameScreen, MessageKind, PLAYER, PendingAction,
+    TargetSource,
+};
 
 // NOTE: i want this file to contain logic for handling player controls
 
@@ -19,17 +37,23 @@ use super::{App, GameScreen, INVENTORY_SIZE, PLAYER, VIEW_RADIUS};
 /// NOTE: the Exit variant is here because it impacts the main game loop
 /// other actions that only change the state of the app but don't affect the main loop
 /// should be handled locally, and not set as a separate enum
-enum PlayerAction {
-    /// the player took a turn, and their action took u64 time
-    TookTurn(u64),
-    /// the player didn't take a turn, and we shouldn't increment the time at all
-    /// this variant exists to make code more readable
-    NoTimeTaken,
+pub(super) enum PlayerAction {
+    /// the player took an action, which took u64 time. every action has a cost:
+    /// free actions (examining, reading the log, checking the inventory) take no time
+    /// and don't let monsters act, while slow actions can take several turns
+    TookTime(u64),
     Exit,
 }
 
+/// time cost of actions that don't advance the clock
+const FREE_ACTION_TIME: u64 = 0;
 const PLAYER_MOVEMENT_TIME: u64 = 100;
+const PLAYER_ATTACK_TIME: u64 = 100;
+const PLAYER_SHOUT_TIME: u64 = 100;
+const PLAYER_SEARCH_TIME: u64 = 100;
 const PLAYER_ITEM_USE_TIME: u64 = 50;
+// NOTE: default wait time is 100, independent of player movement speed
+const PLAYER_WAIT_TIME: u64 = 100;
 
 /// match generic keybinds, used for menu navigation
 /// returns a PlayerAction if a keybind was succesfully matched, or None otherwise
@@ -38,22 +62,60 @@ fn match_menu_keys(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
         KeyModifiers::CONTROL => match key.code {
             KeyCode::Char('l') => {
                 app.toggle_fullscreen_log();
-                return Some(PlayerAction::TookTurn(0));
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
             }
             KeyCode::Esc | KeyCode::Char('q') => {
+                if app.settings.confirm_quit
+                    && app.run_in_progress()
+                    && !matches!(app.game_screen, GameScreen::Confirm { .. })
+                {
+                    app.game_screen = GameScreen::Confirm {
+                        prompt: String::from("Save and quit?"),
+                        pending: PendingAction::Quit,
+                    };
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+                }
                 return Some(PlayerAction::Exit);
             }
             _ => {}
         },
-        _ => match key.code {
-            KeyCode::Esc => {
-                app.switch_to_main_screen();
-                return Some(PlayerAction::TookTurn(0));
+        _ => {
+            if key.code == KeyCode::Esc {
+                match app.game_screen {
+                    // there is no game to return to yet, so go back to the menu
+                    GameScreen::CharacterCreation { .. }
+                    | GameScreen::EnterSeed { .. }
+                    | GameScreen::LoadFailed { .. }
+                    | GameScreen::DataError { .. }
+                    | GameScreen::Achievements
+                    | GameScreen::HighScores
+                    | GameScreen::Settings { in_game: false, .. } => {
+                        app.game_screen = GameScreen::Menu
+                    }
+                    // the run is over, so start fresh from the menu
+                    GameScreen::GameOver | GameScreen::Victory => *app = App::new(),
+                    _ => app.switch_to_main_screen(),
+                }
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
             }
-            _ => {}
-        },
+        }
     };
-    return None;
+    None
+}
+
+/// returns the direction a movement key points in, or None if it isn't a movement key
+fn key_to_direction(code: KeyCode) -> Option<InputDirection> {
+    match code {
+        KeyCode::Right | KeyCode::Char('l') => Some(InputDirection::Right),
+        KeyCode::Left | KeyCode::Char('h') => Some(InputDirection::Left),
+        KeyCode::Down | KeyCode::Char('j') => Some(InputDirection::Down),
+        KeyCode::Up | KeyCode::Char('k') => Some(InputDirection::Up),
+        KeyCode::Char('u') => Some(InputDirection::UpRight),
+        KeyCode::Char('y') => Some(InputDirection::UpLeft),
+        KeyCode::Char('n') => Some(InputDirection::DownRight),
+        KeyCode::Char('b') => Some(InputDirection::DownLeft),
+        _ => None,
+    }
 }
 
 /// match keybinds for movement
@@ -61,47 +123,39 @@ fn match_menu_keys(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
 fn match_movement_keys(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
     // movement related controls
     match app.game_screen {
-        GameScreen::Main => match key.code {
-            // movement keys during the main screen
-            KeyCode::Right | KeyCode::Char('l') => {
-                bump_action(app, PLAYER, InputDirection::Right);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Left | KeyCode::Char('h') => {
-                bump_action(app, PLAYER, InputDirection::Left);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Down | KeyCode::Char('j') => {
-                bump_action(app, PLAYER, InputDirection::Down);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Up | KeyCode::Char('k') => {
-                bump_action(app, PLAYER, InputDirection::Up);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Char('u') => {
-                bump_action(app, PLAYER, InputDirection::UpRight);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Char('y') => {
-                bump_action(app, PLAYER, InputDirection::UpLeft);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Char('n') => {
-                bump_action(app, PLAYER, InputDirection::DownRight);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Char('b') => {
-                bump_action(app, PLAYER, InputDirection::DownLeft);
-                return Some(PlayerAction::TookTurn(PLAYER_MOVEMENT_TIME));
-            }
-            KeyCode::Char('.') => {
+        GameScreen::Main => {
+            if key.code == KeyCode::Char('.') {
                 // wait action, nothing is done
-                // NOTE: default wait time is 100, independent of player movement speed
-                return Some(PlayerAction::TookTurn(100));
+                return Some(PlayerAction::TookTime(PLAYER_WAIT_TIME));
             }
-            _ => {}
-        },
+
+            // holding alt while moving never attacks, even when safe move is off
+            let careful = app.safe_move || key.modifiers.contains(KeyModifiers::ALT);
+
+            // movement keys during the main screen
+            if let Some(direction) = key_to_direction(key.code) {
+                let acted = if careful {
+                    safe_move_action(app, PLAYER, direction)
+                } else if steps_into_lava(app, direction) {
+                    app.game_screen = GameScreen::Confirm {
+                        prompt: String::from("Really step into the lava?"),
+                        pending: PendingAction::Move(direction),
+                    };
+                    false
+                } else {
+                    bump_action(app, PLAYER, direction)
+                };
+                // walking into a wall doesn't give monsters a free turn
+                if !acted {
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+                }
+                return Some(PlayerAction::TookTime(movement_time(
+                    app,
+                    PLAYER,
+                    PLAYER_MOVEMENT_TIME,
+                )));
+            }
+        }
         GameScreen::Examine { ref mut cursor } | GameScreen::Targeting { ref mut cursor, .. } => {
             match key.code {
                 // move cursor around during examine and targeting modes
@@ -140,7 +194,7 @@ fn match_movement_keys(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
         }
         _ => {}
     };
-    return None;
+    None
 }
 
 /// matches controls on the main menu
@@ -153,16 +207,66 @@ fn match_main_menu_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
 
     match key.code {
         KeyCode::Char('n') => {
-            // start new game
-            app.new_game();
+            // pick a class before starting a new game with a random seed
+            app.seed = rand::rng().random();
+            app.game_screen = GameScreen::CharacterCreation { cursor: 0 };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('s') => {
+            // type in a seed before picking a class
+            app.game_screen = GameScreen::EnterSeed {
+                text: String::new(),
+            };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('d') => {
+            // everyone gets the same dungeon and class on the same day
+            let date = scores::today();
+            app.seed = scores::daily_seed(&date);
+            app.daily = Some(date.clone());
+            scores::lock_config(app);
+            let class = scores::daily_class(app.seed);
+            app.new_game(class);
             app.switch_to_main_screen();
-            Some(PlayerAction::NoTimeTaken)
+            app.add_to_log(
+                format!(
+                    "The daily challenge for {}: everyone plays this dungeon as a {} today.",
+                    date, class
+                ),
+                Color::LightYellow,
+                MessageKind::System,
+            );
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('a') => {
+            app.game_screen = GameScreen::Achievements;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('h') => {
+            app.game_screen = GameScreen::HighScores;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('o') => {
+            app.game_screen = GameScreen::Settings {
+                cursor: 0,
+                in_game: false,
+            };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
         }
         KeyCode::Char('l') => {
             // loads an existing game from a save file
-            let _ = app.load_game();
-            app.switch_to_main_screen();
-            Some(PlayerAction::NoTimeTaken)
+            match app.load_game() {
+                Err(error) => {
+                    // a corrupted save can be recovered by going back to an older one
+                    let backup = match error {
+                        LoadError::Corrupted => app.newest_backup(),
+                        _ => None,
+                    };
+                    app.game_screen = GameScreen::LoadFailed { error, backup };
+                }
+                _ => app.switch_to_main_screen(),
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
         }
         KeyCode::Char('q') => {
             // quit the game
@@ -172,29 +276,209 @@ fn match_main_menu_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
     }
 }
 
+/// matches controls on the popup explaining why a save couldn't be loaded
+fn match_load_failed_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::LoadFailed { backup, .. } = app.game_screen else {
+        return None;
+    };
+
+    match (key.code, backup) {
+        (KeyCode::Char('y'), Some(backup)) => match app.load_backup(backup) {
+            Err(error) => {
+                app.game_screen = GameScreen::LoadFailed {
+                    error,
+                    backup: None,
+                }
+            }
+            _ => {
+                app.switch_to_main_screen();
+                app.add_to_log(
+                    format!("Your save was corrupted, so backup {} was loaded.", backup),
+                    Color::Yellow,
+                    MessageKind::System,
+                );
+            }
+        },
+        // anything else goes back to the menu
+        _ => app.game_screen = GameScreen::Menu,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the screen listing problems with the entities data file
+fn match_data_error_controls(app: &mut App, _key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::DataError { .. } = app.game_screen else {
+        return None;
+    };
+
+    // any key carries on to the menu, with only the built in entities
+    app.game_screen = GameScreen::Menu;
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the game over and victory screens
+fn match_game_over_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    if !matches!(app.game_screen, GameScreen::GameOver | GameScreen::Victory) {
+        return None;
+    }
+
+    if key.code == KeyCode::Enter {
+        // throw away the finished run, so that a new game starts from scratch
+        *app = App::new();
+    }
+
+    // swallow every other key, the dead can't move
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the level summary popup
+fn match_level_summary_controls(app: &mut App, _key: KeyEvent) -> Option<PlayerAction> {
+    if !matches!(app.game_screen, GameScreen::LevelSummary { .. }) {
+        return None;
+    }
+
+    // any key gets back to the game, without doing anything else
+    app.switch_to_main_screen();
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the achievements and high score screens
+fn match_achievements_controls(app: &mut App, _key: KeyEvent) -> Option<PlayerAction> {
+    if !matches!(
+        app.game_screen,
+        GameScreen::Achievements | GameScreen::HighScores
+    ) {
+        return None;
+    }
+
+    // any key goes back to the menu
+    app.game_screen = GameScreen::Menu;
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the quest list
+fn match_quest_list_controls(app: &mut App, _key: KeyEvent) -> Option<PlayerAction> {
+    if app.game_screen != GameScreen::Quests {
+        return None;
+    }
+
+    // any key gets back to the game
+    app.switch_to_main_screen();
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls while talking with an npc
+fn match_dialogue_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Dialogue { speaker, node } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Char(c @ '1'..='9') => {
+            let option = c as usize - '1' as usize;
+            dialogue::choose(app, speaker, node, option);
+        }
+        KeyCode::Esc => app.switch_to_main_screen(),
+        _ => {}
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// max number of digits in a seed
+const SEED_LENGTH: usize = 19;
+
+/// matches controls on the seed entry screen
+fn match_seed_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::EnterSeed { ref mut text } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Char(c @ '0'..='9') => {
+            if text.len() < SEED_LENGTH {
+                text.push(c);
+            }
+        }
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        KeyCode::Enter => {
+            // an empty seed picks a random one, like a regular new game
+            app.seed = text.parse().unwrap_or_else(|_| rand::rng().random());
+            app.game_screen = GameScreen::CharacterCreation { cursor: 0 };
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches controls on the character creation screen
+fn match_character_creation_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::CharacterCreation { ref mut cursor } = app.game_screen else {
+        return None;
+    };
+
+    let class = match key.code {
+        KeyCode::Down | KeyCode::Char('j') => {
+            *cursor = (*cursor + 1).min(CLASS_ORDERING.len() - 1);
+            None
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            *cursor = cursor.saturating_sub(1);
+            None
+        }
+        KeyCode::Enter => Some(CLASS_ORDERING[*cursor]),
+        KeyCode::Char(c @ '1'..='9') => CLASS_ORDERING.get(c as usize - '1' as usize).copied(),
+        _ => return None,
+    };
+
+    if let Some(class) = class {
+        // start new game
+        app.new_game(class);
+        app.switch_to_main_screen();
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
 fn match_inventory_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
     if app.game_screen != GameScreen::Main {
         return None;
     }
 
     // use alt-number to drop item from inventory
-    match key.modifiers {
-        KeyModifiers::ALT => {
-            match key.code {
-                // drop item from inventory
-                KeyCode::Char(c @ '1'..='9') | KeyCode::Char(c @ '0') => {
-                    let index = match c {
-                        '1'..='9' => c as usize - '1' as usize,
-                        '0' => 9,
-                        _ => unreachable!(),
-                    };
-                    inventory::drop_item(app, index);
-                    return Some(PlayerAction::NoTimeTaken);
+    if key.modifiers == KeyModifiers::ALT {
+        match key.code {
+            // drop item from inventory
+            KeyCode::Char(c @ '1'..='9') | KeyCode::Char(c @ '0') => {
+                let index = match c {
+                    '1'..='9' => c as usize - '1' as usize,
+                    '0' => 9,
+                    _ => unreachable!(),
+                };
+                inventory::drop_item(app, index);
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+            }
+            // take off and drop equipment, after asking
+            KeyCode::Char(c) if slot_index(c).is_some() => {
+                let slot = slot_index(c).unwrap();
+                match app.equipment[slot] {
+                    Some(id) => {
+                        let name = identify::item_name(app, id);
+                        app.game_screen = GameScreen::Confirm {
+                            prompt: format!("Take off and drop your {}?", name),
+                            pending: PendingAction::DropEquipped(slot),
+                        };
+                    }
+                    None => app.add_to_log(
+                        format!("No item equipped on {}.", SLOT_ORDERING[slot]),
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
                 }
-                _ => {}
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
             }
+            _ => {}
         }
-        _ => {}
     }
 
     match key.code {
@@ -211,38 +495,69 @@ fn match_inventory_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
 
                 if item.targeting_mode() == TargetingMode::None {
                     // item can be used directly
+                    let item_id = app.inventory[index];
+                    // equipping over another item takes it off first
+                    let replaced = match item {
+                        Item::Equipment => inventory::displaced_equipment(app, item_id),
+                        _ => Vec::new(),
+                    };
                     let use_result = inventory::use_item(app, index, None);
                     return match use_result {
-                        UseResult::UsedUp => Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME)),
-                        UseResult::Equipped => Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME)),
-                        UseResult::Cancelled => Some(PlayerAction::NoTimeTaken),
+                        UseResult::UsedUp => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+                        UseResult::Equipped => {
+                            let swap_time: u64 = replaced
+                                .iter()
+                                .map(|&id| inventory::equip_time(app, id))
+                                .sum();
+                            Some(PlayerAction::TookTime(
+                                inventory::equip_time(app, item_id) + swap_time,
+                            ))
+                        }
+                        UseResult::Cancelled => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+                        UseResult::Kept(time) => Some(PlayerAction::TookTime(time)),
                     };
                 } else {
                     // item needs targeting, switch to targeting mode
-                    item.on_targeting(app, index);
-                    return Some(PlayerAction::NoTimeTaken);
+                    item.on_targeting(app, TargetSource::Inventory(index));
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
                 }
             }
+
+            app.add_to_log("No item to use.", Color::default(), MessageKind::Items);
+            return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
         }
 
         // unequip item from equipment
-        KeyCode::Char(c @ 'A'..='C') => {
-            let index = c as usize - 'A' as usize;
+        KeyCode::Char(c) if slot_index(c).is_some() => {
+            let index = slot_index(c).unwrap();
             match app.equipment[index] {
                 Some(id) => {
+                    let obj = app.objects.get(&id).unwrap();
+                    let name = identify::item_name(app, id);
+                    if obj.equipment.as_ref().unwrap().cursed {
+                        app.add_to_log(
+                            format!("Cannot unequip: your {} is cursed!", name),
+                            Color::Red,
+                            MessageKind::Items,
+                        );
+                        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+                    }
+
                     // check we have enough space in inventory to unequip the item
-                    if app.inventory.len() >= INVENTORY_SIZE {
+                    if app.inventory.len() >= app.settings.inventory_size {
                         app.add_to_log(
                             "Cannot unequip: not enough space in inventory.",
                             Color::default(),
+                            MessageKind::Items,
                         );
-                        return Some(PlayerAction::NoTimeTaken);
+                        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
                     }
 
                     // unequip and move to inventory
+                    app.emit(GameEvent::Unequipped { id, name });
                     app.inventory.push(id);
                     app.equipment[index] = None;
-                    return Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME));
+                    return Some(PlayerAction::TookTime(inventory::equip_time(app, id)));
                 }
                 None => {
                     app.add_to_log(
@@ -251,8 +566,9 @@ fn match_inventory_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
                             SLOT_ORDERING[index]
                         ),
                         Color::default(),
+                        MessageKind::Items,
                     );
-                    return Some(PlayerAction::NoTimeTaken);
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
                 }
             }
         }
@@ -260,21 +576,20 @@ fn match_inventory_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
         // `g`rab the first item at player's location
         KeyCode::Char('g') => {
             let player_pos = &app.gamemap.get_position(PLAYER).unwrap();
-            let tile = app.gamemap.get_ref(player_pos.x, player_pos.y);
-            match tile.item {
+            match known_item(app, player_pos.x, player_pos.y) {
                 Some(id) => {
-                    inventory::pick_item_up(app, id.clone());
-                    return Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME));
+                    inventory::pick_item_up(app, id);
+                    return Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME));
                 }
                 None => {
-                    return Some(PlayerAction::TookTurn(0));
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
                 }
             }
         }
         _ => {}
     }
 
-    return None;
+    None
 }
 
 /// matches any remaining game controls on the main screen
@@ -287,42 +602,320 @@ fn match_misc_game_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
         // move to examine mode
         KeyCode::Char('x') => {
             app.toggle_examine_mode();
-            Some(PlayerAction::NoTimeTaken)
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // `a`ttack the adjacent enemy, asking for a direction if there's more than one
+        KeyCode::Char('a') => {
+            let directions = adjacent_hostile_directions(app);
+            match directions[..] {
+                [] => {
+                    app.add_to_log(
+                        "There is nothing next to you to attack.",
+                        Color::default(),
+                        MessageKind::Combat,
+                    );
+                    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+                }
+                [direction] => {
+                    attack_action(app, PLAYER, direction);
+                    Some(PlayerAction::TookTime(PLAYER_ATTACK_TIME))
+                }
+                _ => {
+                    app.add_to_log(
+                        "Attack in which direction?",
+                        Color::default(),
+                        MessageKind::Combat,
+                    );
+                    app.game_screen = GameScreen::AttackDirection;
+                    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+                }
+            }
+        }
+
+        // `R`est until healed, or until something shows up
+        KeyCode::Char('R') => {
+            let problem = if !player_is_hurt(app) {
+                Some("You don't need to rest.")
+            } else if !visible_monsters(app).is_empty() {
+                Some("You can't rest with monsters nearby.")
+            } else {
+                None
+            };
+            match problem {
+                Some(message) => app.add_to_log(message, Color::default(), MessageKind::System),
+                None => app.resting_since = Some(app.time),
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // `S`earch the surrounding tiles for hidden traps
+        KeyCode::Char('S') => {
+            search_for_traps(app);
+            Some(PlayerAction::TookTime(PLAYER_SEARCH_TIME))
+        }
+
+        // toggle safe move, so that moving into monsters doesn't attack them
+        KeyCode::Char('m') => {
+            app.safe_move = !app.safe_move;
+            let message = if app.safe_move {
+                "Safe move on: moving will no longer attack."
+            } else {
+                "Safe move off: moving into monsters will attack them."
+            };
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // show or hide the player's footsteps on explored tiles
+        KeyCode::Char('f') => {
+            app.show_breadcrumbs = !app.show_breadcrumbs;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // turn `P`icking up items by walking over them on or off
+        KeyCode::Char('P') => {
+            let auto_pickup = &mut app.settings.auto_pickup;
+            auto_pickup.enabled = !auto_pickup.enabled;
+            let message = if auto_pickup.enabled {
+                "Auto pickup on: walking over gold, potions and scrolls picks them up."
+            } else {
+                "Auto pickup off: press g to pick items up."
+            };
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // show or hide the `G`host of the last attempt on this seed
+        KeyCode::Char('G') => {
+            app.show_ghost = !app.show_ghost;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // change the `O`ptions
+        KeyCode::Char('O') => {
+            app.game_screen = GameScreen::Settings {
+                cursor: 0,
+                in_game: true,
+            };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // run a command by typing its name
+        KeyCode::Char(':') => {
+            app.game_screen = GameScreen::CommandPalette {
+                input: String::new(),
+                cursor: 0,
+            };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // mark a new `w`aypoint, or open the list of waypoints to travel to
+        KeyCode::Char('w') => {
+            app.game_screen = GameScreen::NameWaypoint {
+                name: String::new(),
+            };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        // `z`ap one of the spells the player has learned
+        KeyCode::Char('z') => {
+            if spells::known_spells(app).is_empty() {
+                app.add_to_log(
+                    "You don't know any spells.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+            } else {
+                app.game_screen = GameScreen::Spells { cursor: 0 };
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('W') => {
+            if app.gamemap.waypoints.is_empty() {
+                app.add_to_log(
+                    "No waypoints marked on this floor.",
+                    Color::default(),
+                    MessageKind::System,
+                );
+            } else {
+                app.game_screen = GameScreen::Waypoints { cursor: 0 };
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // show or hide the tiles visible monsters can reach and attack
+        KeyCode::Char('d') => {
+            app.show_danger = !app.show_danger;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // toggle sneaking, which makes the player slower but harder to notice
+        KeyCode::Char('s') => {
+            app.sneaking = !app.sneaking;
+            let message = if app.sneaking {
+                "You start sneaking: you move slower, but monsters are less likely to notice you."
+            } else {
+                "You stop sneaking."
+            };
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // `Y`ell, drawing every monster nearby towards the player
+        KeyCode::Char('Y') => {
+            app.add_to_log("You shout!", Color::default(), MessageKind::Combat);
+            let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+            make_noise(app, player_pos, SHOUT_NOISE_RADIUS);
+            Some(PlayerAction::TookTime(PLAYER_SHOUT_TIME))
+        }
+
+        // list every key, pressing it again opens the manual
+        KeyCode::Char('?') => {
+            app.game_screen = GameScreen::Help { offset: 0 };
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // show or hide the turn order strip
+        KeyCode::Char('t') => {
+            app.show_turn_order = !app.show_turn_order;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // hide the sidebar (`i`nventory and stats) or the `L`og, giving their space to the map
+        KeyCode::Char('i') => {
+            app.show_sidebar = !app.show_sidebar;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        KeyCode::Char('L') => {
+            app.show_log = !app.show_log;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // list this run's `Q`uests
+        KeyCode::Char('Q') => {
+            app.game_screen = GameScreen::Quests;
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
         }
 
         // go down stairs if stairs exist
+        // or travel to them first, if they have been found
         KeyCode::Char('>') => {
-            let _ = go_down_stairs(app);
-            app.switch_to_main_screen();
-            Some(PlayerAction::NoTimeTaken)
+            let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+            match known_stairs_position(app) {
+                Some(stairs_pos) if stairs_pos != player_pos => {
+                    app.add_to_log(
+                        "You head for the stairs.",
+                        Color::default(),
+                        MessageKind::System,
+                    );
+                    app.travel_destination = Some(stairs_pos);
+                    app.auto_descend = true;
+                }
+                Some(_) if !adjacent_hostile_directions(app).is_empty() => {
+                    app.game_screen = GameScreen::Confirm {
+                        prompt: String::from("There are monsters next to you. Go down anyway?"),
+                        pending: PendingAction::Descend,
+                    };
+                }
+                _ => {
+                    // the level summary takes over the screen after a successful descent
+                    if !go_down_stairs(app) {
+                        app.switch_to_main_screen();
+                    }
+                }
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+
+        // leave the dungeon if standing on the way out,
+        // or travel there first, if it has been found
+        KeyCode::Char('<') => {
+            let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+            match known_up_stairs_position(app) {
+                Some(stairs_pos) if stairs_pos != player_pos => {
+                    app.add_to_log(
+                        "You head for the way out.",
+                        Color::default(),
+                        MessageKind::System,
+                    );
+                    app.travel_destination = Some(stairs_pos);
+                }
+                _ => {
+                    // the victory screen takes over after a successful escape
+                    if !go_up_stairs(app) {
+                        app.switch_to_main_screen();
+                    }
+                }
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
         }
         _ => None,
     }
 }
 
 fn match_log_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
-    match app.game_screen {
-        GameScreen::Log { ref mut offset } => match key.code {
-            KeyCode::PageUp => {
-                *offset += 10;
-                Some(PlayerAction::NoTimeTaken)
+    let GameScreen::Log {
+        ref mut offset,
+        ref mut query,
+        ref mut searching,
+        ref mut filter,
+    } = app.game_screen
+    else {
+        return None;
+    };
+
+    // typing in a search, until enter jumps to the closest match
+    if *searching {
+        match key.code {
+            KeyCode::Char(c) => {
+                if query.len() < LOG_QUERY_LENGTH {
+                    query.push(c);
+                }
             }
-            KeyCode::PageDown => {
-                *offset = offset.saturating_sub(10);
-                Some(PlayerAction::NoTimeTaken)
+            KeyCode::Backspace => {
+                query.pop();
             }
-            KeyCode::Char('k') => {
-                *offset += 1;
-                Some(PlayerAction::NoTimeTaken)
+            KeyCode::Enter => {
+                *searching = false;
+                // the message at the bottom of the screen counts as the closest match
+                if !app.log.matches(query, *filter, *offset)
+                    && let Some(found) = app.log.find_match(query, *filter, *offset, true)
+                {
+                    *offset = found;
+                }
             }
-            KeyCode::Char('j') => {
-                *offset = offset.saturating_sub(1);
-                Some(PlayerAction::NoTimeTaken)
+            _ => return None,
+        }
+        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+    }
+
+    match key.code {
+        KeyCode::PageUp => *offset += 10,
+        KeyCode::PageDown => *offset = offset.saturating_sub(10),
+        KeyCode::Char('k') => *offset += 1,
+        KeyCode::Char('j') => *offset = offset.saturating_sub(1),
+        KeyCode::Char('/') => {
+            query.clear();
+            *searching = true;
+        }
+        KeyCode::Char(c @ ('n' | 'N')) => {
+            if let Some(found) = app.log.find_match(query, *filter, *offset, c == 'n') {
+                *offset = found;
             }
-            _ => None,
-        },
-        _ => None,
+        }
+        // cycle through showing every message, then each kind of message on its own
+        KeyCode::Char('f') => {
+            *filter = match filter {
+                None => Some(MessageKind::Combat),
+                Some(MessageKind::Combat) => Some(MessageKind::Items),
+                Some(MessageKind::Items) => Some(MessageKind::System),
+                Some(MessageKind::System) => None,
+            };
+            *offset = 0;
+        }
+        _ => return None,
     }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
 }
 
 fn match_examine_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
@@ -332,7 +925,7 @@ fn match_examine_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction>
             // exit examine mode
             KeyCode::Char('x') => {
                 app.toggle_examine_mode();
-                Some(PlayerAction::NoTimeTaken)
+                Some(PlayerAction::TookTime(FREE_ACTION_TIME))
             }
             _ => None,
         },
@@ -340,22 +933,78 @@ fn match_examine_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction>
     }
 }
 
+/// tab and shift-tab jump the examine or targeting cursor between visible monsters,
+/// starting to examine if pressed on the main screen
+fn match_cycle_target_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let forward = match key.code {
+        KeyCode::Tab => true,
+        KeyCode::BackTab => false,
+        _ => return None,
+    };
+    if app.game_screen == GameScreen::Main {
+        app.toggle_examine_mode();
+    }
+    let cursor = match app.game_screen {
+        GameScreen::Examine { cursor } | GameScreen::Targeting { cursor, .. } => cursor,
+        _ => return None,
+    };
+
+    let monsters = visible_monsters_by_distance(app);
+    if monsters.is_empty() {
+        app.add_to_log(
+            "No monsters in sight.",
+            Color::default(),
+            MessageKind::System,
+        );
+        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+    }
+
+    // step from the monster under the cursor, or start at either end of the list
+    let current = monsters
+        .iter()
+        .position(|id| app.gamemap.get_position(*id) == Some(cursor));
+    let next = match (current, forward) {
+        (Some(idx), true) => (idx + 1) % monsters.len(),
+        (Some(idx), false) => (idx + monsters.len() - 1) % monsters.len(),
+        (None, true) => 0,
+        (None, false) => monsters.len() - 1,
+    };
+    let new_cursor = app.gamemap.get_position(monsters[next]).unwrap();
+
+    match app.game_screen {
+        GameScreen::Examine { ref mut cursor } | GameScreen::Targeting { ref mut cursor, .. } => {
+            *cursor = new_cursor;
+        }
+        _ => unreachable!(),
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
 fn match_targeting_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
     match app.game_screen {
         GameScreen::Targeting {
-            ref cursor,
-            inventory_idx,
-            ..
+            ref cursor, source, ..
         } => match key.code {
             KeyCode::Enter => {
-                // use the item and exit targeting mode
-                let use_result = inventory::use_item(app, inventory_idx, Some(cursor.clone()));
+                // stay in targeting mode until a tile the item can reach is picked
+                let item = source.item(app);
+                if let Some(problem) = target_problem(app, &item, *cursor) {
+                    app.add_to_log(problem, Color::default(), MessageKind::Items);
+                    return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+                }
+
+                // use the item or cast the spell, and exit targeting mode
+                let use_result = match source {
+                    TargetSource::Inventory(idx) => inventory::use_item(app, idx, Some(*cursor)),
+                    TargetSource::Spell(idx) => spells::cast_spell(app, idx, Some(*cursor)),
+                };
                 app.game_screen = GameScreen::Main;
 
                 match use_result {
-                    UseResult::UsedUp => Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME)),
-                    UseResult::Equipped => Some(PlayerAction::TookTurn(PLAYER_ITEM_USE_TIME)),
-                    UseResult::Cancelled => Some(PlayerAction::NoTimeTaken),
+                    UseResult::UsedUp => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+                    UseResult::Equipped => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+                    UseResult::Cancelled => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+                    UseResult::Kept(time) => Some(PlayerAction::TookTime(time)),
                 }
             }
             _ => None,
@@ -364,63 +1013,904 @@ fn match_targeting_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction
     }
 }
 
+fn match_choose_item_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::ChooseItem { inventory_idx, .. } = app.game_screen else {
+        return None;
+    };
+
+    // NOTE: escape to cancel is handled by the menu keys
+    let target_id = match key.code {
+        KeyCode::Char(c @ '1'..='9') | KeyCode::Char(c @ '0') => {
+            let index = match c {
+                '1'..='9' => c as usize - '1' as usize,
+                '0' => 9,
+                _ => unreachable!(),
+            };
+            app.inventory.get(index).copied()
+        }
+        KeyCode::Char(c) if slot_index(c).is_some() => app.equipment[slot_index(c).unwrap()],
+        _ => return None,
+    };
+
+    let Some(target_id) = target_id else {
+        app.add_to_log(
+            "There is no item there.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+    };
+
+    let use_result = inventory::use_item_on(app, inventory_idx, target_id);
+    app.game_screen = GameScreen::Main;
+
+    match use_result {
+        UseResult::UsedUp => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+        UseResult::Equipped => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+        UseResult::Cancelled => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+        UseResult::Kept(time) => Some(PlayerAction::TookTime(time)),
+    }
+}
+
+fn match_attack_direction_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    if app.game_screen != GameScreen::AttackDirection {
+        return None;
+    }
+
+    // NOTE: escape to cancel is handled by the menu keys
+    let direction = key_to_direction(key.code)?;
+    app.switch_to_main_screen();
+    if attack_action(app, PLAYER, direction) {
+        Some(PlayerAction::TookTime(PLAYER_ATTACK_TIME))
+    } else {
+        app.add_to_log(
+            "There is nothing there to attack.",
+            Color::default(),
+            MessageKind::Combat,
+        );
+        Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+    }
+}
+
+/// matches controls on the help screen
+fn match_help_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Help { ref mut offset } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Down | KeyCode::Char('j') => *offset += 1,
+        KeyCode::Up | KeyCode::Char('k') => *offset = offset.saturating_sub(1),
+        KeyCode::PageDown => *offset += 10,
+        KeyCode::PageUp => *offset = offset.saturating_sub(10),
+        KeyCode::Char('?') => {
+            app.game_screen = GameScreen::Manual {
+                page: 0,
+                offset: 0,
+                query: String::new(),
+                searching: false,
+            };
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches moving between settings and changing them on the settings screen
+fn match_settings_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Settings { ref mut cursor, .. } = app.game_screen else {
+        return None;
+    };
+
+    let row = SETTING_ROWS[*cursor];
+    match key.code {
+        KeyCode::Down | KeyCode::Char('j') => *cursor = (*cursor + 1).min(SETTING_ROWS.len() - 1),
+        KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+        KeyCode::Left | KeyCode::Char('h') => settings::adjust(app, row, false),
+        KeyCode::Right | KeyCode::Char('l') | KeyCode::Enter | KeyCode::Char(' ') => {
+            settings::adjust(app, row, true)
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// matches the answer on the confirm screen, and carries out the pending action on a yes
+fn match_confirm_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Confirm { pending, .. } = app.game_screen else {
+        return None;
+    };
+
+    // anything but yes carries on playing without doing it
+    app.switch_to_main_screen();
+    if key.code != KeyCode::Char('y') {
+        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+    }
+    match pending {
+        PendingAction::Quit => Some(PlayerAction::Exit),
+        PendingAction::Descend => {
+            // the level summary takes over the screen after a successful descent
+            if !go_down_stairs(app) {
+                app.switch_to_main_screen();
+            }
+            Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+        }
+        PendingAction::Move(direction) => match bump_action(app, PLAYER, direction) {
+            true => Some(PlayerAction::TookTime(movement_time(
+                app,
+                PLAYER,
+                PLAYER_MOVEMENT_TIME,
+            ))),
+            false => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+        },
+        PendingAction::DropEquipped(slot) => match inventory::drop_equipped(app, slot) {
+            Some(id) => Some(PlayerAction::TookTime(inventory::equip_time(app, id))),
+            None => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+        },
+    }
+}
+
+/// max length of a search in the manual
+const MANUAL_QUERY_LENGTH: usize = 30;
+
+fn match_manual_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Manual {
+        ref mut page,
+        ref mut offset,
+        ref mut query,
+        ref mut searching,
+    } = app.game_screen
+    else {
+        return None;
+    };
+
+    // typing in a search, until enter jumps to the first match
+    if *searching {
+        match key.code {
+            KeyCode::Char(c) => {
+                if query.len() < MANUAL_QUERY_LENGTH {
+                    query.push(c);
+                }
+            }
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Enter => {
+                *searching = false;
+                if let Some((found_page, line)) = manual::find_match(query, *page, *offset, true) {
+                    *page = found_page;
+                    *offset = line;
+                }
+            }
+            _ => return None,
+        }
+        return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+    }
+
+    let page_count = manual::MANUAL_PAGES.len();
+    match key.code {
+        KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => {
+            *page = (*page + 1) % page_count;
+            *offset = 0;
+        }
+        KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => {
+            *page = (*page + page_count - 1) % page_count;
+            *offset = 0;
+        }
+        KeyCode::Down | KeyCode::Char('j') => *offset += 1,
+        KeyCode::Up | KeyCode::Char('k') => *offset = offset.saturating_sub(1),
+        KeyCode::PageDown => *offset += 10,
+        KeyCode::PageUp => *offset = offset.saturating_sub(10),
+        KeyCode::Char('/') => {
+            query.clear();
+            *searching = true;
+        }
+        KeyCode::Char(c @ ('n' | 'N')) => {
+            if let Some((found_page, line)) = manual::find_match(query, *page, *offset, c == 'n') {
+                *page = found_page;
+                *offset = line;
+            }
+        }
+        KeyCode::Char('?') => app.switch_to_main_screen(),
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// max length of a search in the fullscreen log
+const LOG_QUERY_LENGTH: usize = 30;
+
+/// max length of a waypoint's name
+const WAYPOINT_NAME_LENGTH: usize = 20;
+
+fn match_name_waypoint_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::NameWaypoint { ref mut name } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Char(c) => {
+            if name.len() < WAYPOINT_NAME_LENGTH {
+                name.push(c);
+            }
+        }
+        KeyCode::Backspace => {
+            name.pop();
+        }
+        KeyCode::Enter => {
+            let name = match name.trim() {
+                "" => format!("waypoint {}", app.gamemap.waypoints.len() + 1),
+                trimmed => trimmed.to_string(),
+            };
+            let position = app.gamemap.get_position(PLAYER).unwrap();
+            app.add_to_log(
+                format!("Marked waypoint {}.", name),
+                Color::default(),
+                MessageKind::System,
+            );
+            app.gamemap.waypoints.push(Waypoint { name, position });
+            app.switch_to_main_screen();
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// max length of what can be typed into the command palette
+const COMMAND_LENGTH: usize = 30;
+
+/// matches typing into the command palette, and runs the chosen command on enter
+fn match_command_palette_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::CommandPalette {
+        ref mut input,
+        ref mut cursor,
+    } = app.game_screen
+    else {
+        return None;
+    };
+
+    // NOTE: escape to close is handled by the menu keys
+    match key.code {
+        KeyCode::Char(c) => {
+            if input.len() < COMMAND_LENGTH {
+                input.push(c);
+                *cursor = 0;
+            }
+        }
+        KeyCode::Backspace => {
+            input.pop();
+            *cursor = 0;
+        }
+        KeyCode::Down => {
+            let (query, _) = keybindings::split_query(input);
+            let count = keybindings::matching_commands(query).len();
+            *cursor = (*cursor + 1).min(count.saturating_sub(1));
+        }
+        KeyCode::Up => *cursor = cursor.saturating_sub(1),
+        KeyCode::Enter => {
+            let (query, number) = keybindings::split_query(input);
+            let matches = keybindings::matching_commands(query);
+            let chosen = matches.get(*cursor).map(|command| command.action);
+            app.switch_to_main_screen();
+            let Some(action) = chosen else {
+                app.add_to_log(
+                    "There is no command by that name.",
+                    Color::default(),
+                    MessageKind::System,
+                );
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+            };
+            return Some(run_command(app, action, number));
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// runs a command from the palette on the main screen. commands press the same keys the
+/// player would, so they take the same time and go through the same checks
+fn run_command(app: &mut App, action: CommandAction, number: Option<usize>) -> PlayerAction {
+    let item_key = |app: &mut App, verb: &str| {
+        let Some(number) = number else {
+            app.add_to_log(
+                format!("{} which item? Type its number after the command.", verb),
+                Color::default(),
+                MessageKind::Items,
+            );
+            return None;
+        };
+        if number == 0 || number > app.inventory.len() {
+            app.add_to_log(
+                "There is no item there.",
+                Color::default(),
+                MessageKind::Items,
+            );
+            return None;
+        }
+        // the tenth item is on the 0 key
+        char::from_digit((number % 10) as u32, 10).map(|c| (number - 1, c))
+    };
+
+    let key = match action {
+        CommandAction::Key(code, modifiers) => KeyEvent::new(code, modifiers),
+        CommandAction::Drop => match item_key(app, "Drop") {
+            Some((_, c)) => KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT),
+            None => return PlayerAction::TookTime(FREE_ACTION_TIME),
+        },
+        CommandAction::Throw => match item_key(app, "Throw") {
+            Some((index, c)) if inventory::get_item_in_inventory(app, index).is_thrown() => {
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+            }
+            Some(_) => {
+                app.add_to_log(
+                    "You can't throw that.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                return PlayerAction::TookTime(FREE_ACTION_TIME);
+            }
+            None => return PlayerAction::TookTime(FREE_ACTION_TIME),
+        },
+        CommandAction::Save => {
+            let message = if !app.keeps_records() {
+                String::from("This game can't be saved.")
+            } else if let Err(error) = app.save_game() {
+                format!("Couldn't save: {}", error)
+            } else {
+                String::from("Game saved.")
+            };
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+    };
+    app.handle_keys(key)
+}
+
+/// starts and stops recording macros with ctrl-r, and plays them back with the function keys
+fn match_macro_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    if app.game_screen != GameScreen::Main {
+        return None;
+    }
+
+    match key.code {
+        KeyCode::Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+            match app.macros.recording.take() {
+                Some(keys) if keys.is_empty() => {
+                    app.add_to_log(
+                        "Nothing was recorded.",
+                        Color::default(),
+                        MessageKind::System,
+                    );
+                }
+                Some(keys) => app.game_screen = GameScreen::BindMacro { keys },
+                None => {
+                    app.macros.recording = Some(Vec::new());
+                    app.add_to_log(
+                        "Recording a macro. Press ctrl-r again to stop.",
+                        Color::default(),
+                        MessageKind::System,
+                    );
+                }
+            }
+        }
+        KeyCode::F(slot) if slot <= MACRO_SLOTS => {
+            if app.macros.is_recording() {
+                app.add_to_log(
+                    "You can't play a macro while recording one.",
+                    Color::default(),
+                    MessageKind::System,
+                );
+            } else if !visible_monsters(app).is_empty() {
+                app.add_to_log(
+                    "You can't play a macro with monsters nearby.",
+                    Color::default(),
+                    MessageKind::System,
+                );
+            } else if app.macros.play(slot) {
+                app.add_to_log(
+                    format!("You play the macro on F{}.", slot),
+                    Color::default(),
+                    MessageKind::System,
+                );
+            } else {
+                app.add_to_log(
+                    format!("There is no macro on F{}.", slot),
+                    Color::default(),
+                    MessageKind::System,
+                );
+            }
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+/// binds the keys that were just recorded to the function key pressed
+fn match_bind_macro_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::BindMacro { ref mut keys } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::F(slot) if slot <= MACRO_SLOTS => {
+            let keys = std::mem::take(keys);
+            app.add_to_log(
+                format!("Bound {} keys to F{}.", keys.len(), slot),
+                Color::default(),
+                MessageKind::System,
+            );
+            app.macros.bound.insert(slot, keys);
+            app.switch_to_main_screen();
+        }
+        _ => return None,
+    }
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+fn match_waypoint_list_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Waypoints { mut cursor } = app.game_screen else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Down | KeyCode::Char('j') => {
+            cursor += 1;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            cursor = cursor.saturating_sub(1);
+        }
+        KeyCode::Enter => {
+            // start travelling, the steps are taken in the main loop
+            let waypoint = &app.gamemap.waypoints[cursor];
+            let message = format!("You travel towards {}.", waypoint.name);
+            app.travel_destination = Some(waypoint.position);
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            app.switch_to_main_screen();
+            return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+        }
+        KeyCode::Char('d') => {
+            let waypoint = app.gamemap.waypoints.remove(cursor);
+            app.add_to_log(
+                format!("Removed waypoint {}.", waypoint.name),
+                Color::default(),
+                MessageKind::System,
+            );
+            if app.gamemap.waypoints.is_empty() {
+                app.switch_to_main_screen();
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+            }
+        }
+        _ => return None,
+    }
+
+    app.game_screen = GameScreen::Waypoints {
+        cursor: cursor.min(app.gamemap.waypoints.len() - 1),
+    };
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+fn match_spell_list_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Spells { mut cursor } = app.game_screen else {
+        return None;
+    };
+    let known = spells::known_spells(app);
+
+    match key.code {
+        KeyCode::Down | KeyCode::Char('j') => {
+            cursor += 1;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            cursor = cursor.saturating_sub(1);
+        }
+        KeyCode::Enter => {
+            // stay on the list, another spell might be cheap enough
+            let spell = known[cursor];
+            if !spells::has_mana_for(app, spell) {
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+            }
+
+            // aimed spells go through the same targeting screens as scrolls
+            let effect = spell.effect();
+            if effect.targeting_mode() != TargetingMode::None {
+                effect.on_targeting(app, TargetSource::Spell(cursor));
+                return Some(PlayerAction::TookTime(FREE_ACTION_TIME));
+            }
+
+            let use_result = spells::cast_spell(app, cursor, None);
+            app.switch_to_main_screen();
+            return match use_result {
+                UseResult::UsedUp => Some(PlayerAction::TookTime(PLAYER_ITEM_USE_TIME)),
+                _ => Some(PlayerAction::TookTime(FREE_ACTION_TIME)),
+            };
+        }
+        _ => return None,
+    }
+
+    app.game_screen = GameScreen::Spells {
+        cursor: cursor.min(known.len() - 1),
+    };
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
+fn match_shop_controls(app: &mut App, key: KeyEvent) -> Option<PlayerAction> {
+    let GameScreen::Shop {
+        shopkeeper,
+        mut cursor,
+        mut selling,
+    } = app.game_screen
+    else {
+        return None;
+    };
+
+    match key.code {
+        KeyCode::Down | KeyCode::Char('j') => {
+            cursor += 1;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            cursor = cursor.saturating_sub(1);
+        }
+        KeyCode::Tab => {
+            // switch between buying and selling
+            selling = !selling;
+            cursor = 0;
+        }
+        KeyCode::Enter => {
+            if selling {
+                shop::sell_item(app, shopkeeper, cursor);
+            } else {
+                shop::buy_item(app, shopkeeper, cursor);
+            }
+        }
+        _ => return None,
+    }
+
+    // keep the cursor on the list, which may have shrunk after trading
+    let listing_len = if selling {
+        app.inventory.len()
+    } else {
+        shop::get_stock(app, shopkeeper).len()
+    };
+    app.game_screen = GameScreen::Shop {
+        shopkeeper,
+        cursor: cursor.min(listing_len.saturating_sub(1)),
+        selling,
+    };
+
+    // trading doesn't take any time
+    Some(PlayerAction::TookTime(FREE_ACTION_TIME))
+}
+
 impl App {
     pub fn run(&mut self, mut terminal: DefaultTerminal) -> Result<()> {
+        let mut last_tick = Instant::now();
         loop {
             terminal.draw(|frame| self.render(frame))?;
-            if let Event::Key(key) = event::read()? {
-                let action = self.handle_keys(key);
-                match action {
-                    PlayerAction::TookTurn(time_taken) => {
-                        if time_taken == 0 {
-                            continue;
-                        }
 
-                        self.time += time_taken;
-                        handle_monster_turns(self);
-                        update_fov(self, VIEW_RADIUS);
-                    }
-                    PlayerAction::NoTimeTaken => {
-                        continue;
-                    }
-                    PlayerAction::Exit => {
-                        self.save_game()?;
-                        break Ok(());
+            // wait for input, but only until the next tick is due
+            let timeout = self.tick_rate.saturating_sub(last_tick.elapsed());
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) => {
+                        if let PlayerAction::Exit = self.handle_input(key) {
+                            if self.run_in_progress() {
+                                self.save_game()?;
+                            }
+                            break Ok(());
+                        }
                     }
+                    // the layout gets recomputed for the new size on the next draw,
+                    // clear out anything left over from the old one
+                    Event::Resize(_, _) => terminal.clear()?,
+                    _ => {}
+                }
+            }
+
+            if last_tick.elapsed() >= self.tick_rate {
+                self.update();
+                last_tick = Instant::now();
+            }
+        }
+    }
+
+    /// reacts to a single key press from the player
+    fn handle_input(&mut self, key: KeyEvent) -> PlayerAction {
+        // any key ends the demo and goes back to the menu
+        if self.demo {
+            *self = App::new();
+            self.last_input_tick = self.ticks;
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+        self.last_input_tick = self.ticks;
+
+        // pressing a key skips the rest of any animation
+        self.animations.clear();
+
+        // and stops the player from walking any further
+        if self.travel_destination.is_some() {
+            self.travel_destination = None;
+            self.auto_descend = false;
+            self.add_to_log(
+                "You stop travelling.",
+                Color::default(),
+                MessageKind::System,
+            );
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+
+        // or from resting any longer
+        if self.resting_since.is_some() {
+            self.resting_since = None;
+            self.add_to_log("You stop resting.", Color::default(), MessageKind::System);
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+
+        // or from playing the rest of a macro
+        if self.macros.is_playing() {
+            self.macros.playback.clear();
+            self.add_to_log("You stop the macro.", Color::default(), MessageKind::System);
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+
+        // everything but the key that stops recording goes into the macro
+        let stops_recording = key.code == KeyCode::Char('r')
+            && key.modifiers == KeyModifiers::CONTROL
+            && self.game_screen == GameScreen::Main;
+        if self.macros.is_recording() && !stops_recording && !self.macros.record(key) {
+            self.add_to_log(
+                format!(
+                    "Macros can't be longer than {} keys, press ctrl-r to stop recording.",
+                    MAX_MACRO_LENGTH
+                ),
+                Color::default(),
+                MessageKind::System,
+            );
+            return PlayerAction::TookTime(FREE_ACTION_TIME);
+        }
+
+        self.press_key(key)
+    }
+
+    /// performs whatever a key press does, letting time pass if it took any
+    pub(super) fn press_key(&mut self, key: KeyEvent) -> PlayerAction {
+        // picking things up and dropping them can change how fast the player moves
+        let in_game = !matches!(
+            self.game_screen,
+            GameScreen::Menu
+                | GameScreen::EnterSeed { .. }
+                | GameScreen::CharacterCreation { .. }
+                | GameScreen::LoadFailed { .. }
+                | GameScreen::DataError { .. }
+        );
+        // events stick around until the next turn starts, so whatever happened on this
+        // one can still be looked at once the key has been handled
+        self.events.clear();
+        let burden = inventory::encumbrance(self);
+        let action = self.handle_keys(key);
+        if in_game {
+            inventory::announce_encumbrance(self, burden);
+        }
+        if let PlayerAction::TookTime(time_taken) = action
+            && time_taken != FREE_ACTION_TIME
+        {
+            self.pass_time(time_taken);
+        }
+        action
+    }
+
+    /// advances everything that happens without the player pressing a key.
+    /// called once every tick, whether or not there was any input
+    pub(super) fn update(&mut self) {
+        self.ticks += 1;
+        let ticks = self.ticks;
+        self.flashes.retain(|_, until| *until > ticks);
+
+        // show off the game once the menu has been left alone for a while
+        if self.game_screen == GameScreen::Menu
+            && self.ticks - self.last_input_tick >= DEMO_IDLE_TICKS
+        {
+            self.start_demo();
+            return;
+        }
+
+        // each frame of an animation stays on screen for a whole tick
+        if self.animations.pop_front().is_some() {
+            return;
+        }
+
+        // walk a step per tick until the player arrives or something interrupts them
+        if self.travel_destination.is_some() || self.resting_since.is_some() {
+            self.events.clear();
+        }
+        if travel_step(self) {
+            self.pass_time(movement_time(self, PLAYER, PLAYER_MOVEMENT_TIME));
+            return;
+        }
+
+        // wait a turn per tick until the player is healed or disturbed
+        if rest_step(self) {
+            self.pass_time(PLAYER_WAIT_TIME);
+            return;
+        }
+
+        // finish an auto-stairs command, unless the travel got interrupted
+        if self.auto_descend && self.travel_destination.is_none() {
+            self.auto_descend = false;
+            let player_pos = self.gamemap.get_position(PLAYER).unwrap();
+            if known_stairs_position(self) == Some(player_pos) {
+                go_down_stairs(self);
+            }
+            return;
+        }
+
+        // the bot plays at a watchable pace, starting over once its run ends
+        if self.demo {
+            if matches!(self.game_screen, GameScreen::GameOver | GameScreen::Victory) {
+                if self.ticks - self.last_input_tick >= DEMO_IDLE_TICKS {
+                    self.start_demo();
+                }
+            } else if self.ticks.is_multiple_of(DEMO_STEP_TICKS) {
+                let key = bot::choose_key(self);
+                self.press_key(key);
+                // let the end of the run stay on screen for a while
+                self.last_input_tick = self.ticks;
+            }
+            return;
+        }
+
+        // press the next key of a macro, as long as it's still safe to keep going
+        if self.macros.is_playing() {
+            if !matches!(
+                self.game_screen,
+                GameScreen::GameOver | GameScreen::Victory | GameScreen::Menu
+            ) && visible_monsters(self).is_empty()
+            {
+                let key = self.macros.playback.pop_front().unwrap();
+                if let PlayerAction::Exit = self.press_key(key) {
+                    self.macros.playback.clear();
+                }
+            } else {
+                self.macros.playback.clear();
+                if matches!(self.game_screen, GameScreen::Main) {
+                    self.add_to_log(
+                        "You stop the macro, there are monsters nearby.",
+                        Color::default(),
+                        MessageKind::System,
+                    );
                 }
             }
         }
     }
 
+    /// lets the rest of the world catch up after the player took an action
+    fn pass_time(&mut self, time_taken: u64) {
+        // the player's action happened at the current time, before the world caught up
+        self.record_ghost_step();
+        let start = self.time;
+        self.time += action_time(self, PLAYER, time_taken);
+        handle_monster_turns(self);
+        expire_statuses(self);
+        regenerate(self, start, self.time);
+        burn_in_lava(self, start, self.time);
+        fire::burn(self, start, self.time);
+        gas::drift(self, start, self.time);
+        rot_corpses(self);
+        update_fov(self);
+
+        let interval = self.settings.autosave_turns * 100;
+        if interval > 0 && self.time / interval > start / interval {
+            self.autosave();
+        }
+    }
+
     /// translate the key event into the appropriate gameplay actions
     fn handle_keys(&mut self, key: KeyEvent) -> PlayerAction {
+        self.handled_keys(key)
+            .unwrap_or(PlayerAction::TookTime(FREE_ACTION_TIME))
+    }
+
+    /// the action of the first handler that answers to the key, or none if no screen does
+    pub(super) fn handled_keys(&mut self, key: KeyEvent) -> Option<PlayerAction> {
         let handlers = &[
             match_menu_keys,
+            match_level_summary_controls,
+            match_quest_list_controls,
+            match_dialogue_controls,
             match_movement_keys,
             match_main_menu_controls,
+            match_achievements_controls,
+            match_load_failed_controls,
+            match_data_error_controls,
+            match_seed_controls,
+            match_character_creation_controls,
+            match_game_over_controls,
             match_misc_game_controls,
             match_inventory_controls,
             match_log_controls,
             match_examine_controls,
+            match_cycle_target_controls,
             match_targeting_controls,
+            match_choose_item_controls,
+            match_attack_direction_controls,
+            match_name_waypoint_controls,
+            match_command_palette_controls,
+            match_macro_controls,
+            match_bind_macro_controls,
+            match_waypoint_list_controls,
+            match_spell_list_controls,
+            match_shop_controls,
+            match_manual_controls,
+            match_help_controls,
+            match_settings_controls,
+            match_confirm_controls,
         ];
 
         // iterates through handlers, and gives the first one with a non-none result
-        handlers
-            .iter()
-            .find_map(|handler| handler(self, key))
-            .unwrap_or(PlayerAction::NoTimeTaken)
+        handlers.iter().find_map(|handler| handler(self, key))
     }
 
-    pub fn new_game(&mut self) {
+    pub fn new_game(&mut self, class: PlayerClass) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+        *self.objects.get_mut(&PLAYER).unwrap() = entities::player_of_class(class);
+        for item in entities::starting_inventory(class) {
+            let id = self.objects.add(item);
+            inventory::add_to_inventory(self, id);
+        }
+
         self.generate_dungeon(DungeonConfig::default());
-        update_fov(self, VIEW_RADIUS);
+
+        // the player knows what they packed for the trip
+        self.identification = Identification::new(&mut self.rng);
+        for &id in &self.inventory {
+            if let Some(item) = &self.objects.get(&id).unwrap().item {
+                self.identification.identify(item);
+            }
+        }
+
+        // NOTE: quests are picked after the first floor is built, so that they don't
+        // change how it's generated. none of them are on the first floor anyway
+        self.quests = quests::generate(&mut self.rng);
+
+        update_fov(self);
+        announce_level_feeling(self);
+
+        // race against the last attempt on this seed, if there was one
+        self.replay = Ghost::default();
+        self.record_ghost_step();
+        // the ghost of a real attempt has nothing to do with the demo
+        if self.keeps_records() {
+            self.load_ghost();
+        }
+        if self.ghost.is_some() {
+            self.add_to_log(
+                "The ghost of your last attempt on this seed runs alongside you. Press G to hide it.",
+                Color::Gray,
+                MessageKind::System,
+            );
+        }
+    }
+
+    /// starts a game on a random seed and class for the bot to play
+    fn start_demo(&mut self) {
+        *self = App::new();
+        let class = CLASS_ORDERING[self.rng.random_range(0..CLASS_ORDERING.len())];
+        self.demo = true;
+        self.new_game(class);
+        self.switch_to_main_screen();
     }
 
     fn toggle_fullscreen_log(&mut self) {
         match self.game_screen {
-            GameScreen::Log { offset: _ } => self.game_screen = GameScreen::Main,
-            _ => self.game_screen = GameScreen::Log { offset: 0 },
+            // the end of run screens stay up until the player leaves them
+            GameScreen::GameOver | GameScreen::Victory => {}
+            GameScreen::Log { .. } => self.game_screen = GameScreen::Main,
+            _ => {
+                self.game_screen = GameScreen::Log {
+                    offset: 0,
+                    query: String::new(),
+                    searching: false,
+                    filter: None,
+                }
+            }
         }
     }
 
@@ -436,7 +1926,7 @@ impl App {
         }
     }
 
-    fn switch_to_main_screen(&mut self) {
+    pub fn switch_to_main_screen(&mut self) {
         self.game_screen = GameScreen::Main;
     }
 }
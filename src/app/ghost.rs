@@ -0,0 +1,84 @@
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
+
+use super::{App, PLAYER};
+use crate::components::Position;
+
+// NOTE: this file contains logic for recording where the player went during a run,
+// so that a later attempt on the same seed can race against it
+
+/// where the player was standing from a point in time until their next step
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct GhostStep {
+    pub time: u64,
+    pub level: u16,
+    pub position: Position,
+}
+
+/// the path the player took through a run, ordered by time
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Ghost {
+    steps: Vec<GhostStep>,
+}
+
+impl Ghost {
+    /// adds a step to the path, unless the player is still standing in the same place
+    pub fn record(&mut self, time: u64, level: u16, position: Position) {
+        let moved = self
+            .steps
+            .last()
+            .is_none_or(|step| step.level != level || step.position != position);
+        if moved {
+            self.steps.push(GhostStep {
+                time,
+                level,
+                position,
+            });
+        }
+    }
+
+    /// returns where the player was at the given time, if they were on the given floor
+    pub fn position_at(&self, time: u64, level: u16) -> Option<Position> {
+        let idx = self.steps.partition_point(|step| step.time <= time);
+        let step = self.steps.get(idx.checked_sub(1)?)?;
+        (step.level == level).then_some(step.position)
+    }
+}
+
+/// replays are indexed by seed, so only attempts on the same dungeon are raced
+fn ghost_file_name(seed: u64) -> String {
+    format!("ghost-{}.json", seed)
+}
+
+impl App {
+    /// records the player's current position into this run's replay
+    pub fn record_ghost_step(&mut self) {
+        if let Some(position) = self.gamemap.get_position(PLAYER) {
+            self.replay.record(self.time, self.gamemap.level, position);
+        }
+    }
+
+    /// writes this run's replay, so the next attempt on this seed can race it.
+    /// replaces the replay of any earlier attempt
+    pub fn write_ghost(&self) -> Result<()> {
+        let data_str = serde_json::to_string(&self.replay)?;
+        let mut file = File::create(ghost_file_name(self.seed))?;
+        file.write_all(data_str.as_bytes())?;
+        Ok(())
+    }
+
+    /// loads the replay of the last finished attempt on this seed, if there was one
+    pub fn load_ghost(&mut self) {
+        self.ghost = File::open(ghost_file_name(self.seed))
+            .ok()
+            .and_then(|mut file| {
+                let mut ghost_string = String::new();
+                file.read_to_string(&mut ghost_string).ok()?;
+                serde_json::from_str(&ghost_string).ok()
+            });
+    }
+}
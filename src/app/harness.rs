@@ -0,0 +1,1506 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rand::{SeedableRng, rngs::StdRng};
+use ratatui::{Terminal, backend::TestBackend};
+
+use super::{Action, App, GameScreen, PLAYER, PendingAction};
+use crate::{
+    achievements::Profile,
+    components::{Object, PlayerClass, RenderLayer},
+    engine::update_fov,
+    entities,
+    gamemap::{GameMap, TileType},
+    inventory,
+};
+
+// NOTE: this file contains a test only api for building a game by hand: a fixed map, objects
+// placed at exact coordinates, and keys pressed one at a time. the same seed is used every
+// time, so a test that passes once passes every time
+
+/// the seed every arena's rng starts from
+const ARENA_SEED: u64 = 0;
+
+impl App {
+    /// builds a game on an empty room with walls all the way around it, with a fighter
+    /// standing at (x, y). there are no monsters, items or stairs until a test places them
+    pub fn test_arena(width: u16, height: u16, x: u16, y: u16) -> Self {
+        let mut app = App::new();
+        app.headless = true;
+        // arenas start from a fresh profile, whatever the player has unlocked
+        app.profile = Profile::default();
+        app.rng = StdRng::seed_from_u64(ARENA_SEED);
+        *app.objects.get_mut(&PLAYER).unwrap() = entities::player_of_class(PlayerClass::Fighter);
+
+        app.gamemap = GameMap::new(width, height, 1);
+        for tile_x in 1..width - 1 {
+            for tile_y in 1..height - 1 {
+                app.gamemap.set_tile_type(tile_x, tile_y, TileType::Floor);
+            }
+        }
+        app.gamemap.place_blocker(PLAYER, x, y);
+        app.game_screen = GameScreen::Main;
+        update_fov(&mut app);
+        app
+    }
+
+    /// puts an object on the map, as a blocker or an item depending on its render layer.
+    /// monsters get their first turn a turn from now
+    pub fn place(&mut self, object: Object, x: u16, y: u16) -> usize {
+        let is_blocker = object.render_layer == RenderLayer::Blocking;
+        let is_monster = object.ai.is_some();
+        let id = self.objects.add(object);
+        match is_blocker {
+            true => self.gamemap.place_blocker(id, x, y),
+            false => self.gamemap.place_item(id, x, y),
+        }
+        if is_monster {
+            self.action_queue.push(Action {
+                time: self.time + 100,
+                id,
+            });
+        }
+        update_fov(self);
+        id
+    }
+
+    /// puts an item straight into the player's inventory
+    pub fn give(&mut self, object: Object) -> usize {
+        let id = self.objects.add(object);
+        inventory::add_to_inventory(self, id);
+        id
+    }
+
+    /// presses a key the way the player would, letting time pass if it takes any
+    pub fn press(&mut self, code: KeyCode) {
+        self.press_key(KeyEvent::from(code));
+    }
+
+    /// presses a key while holding alt
+    pub fn press_alt(&mut self, code: KeyCode) {
+        self.press_key(KeyEvent::new(code, KeyModifiers::ALT));
+    }
+
+    /// presses every character of a string as a key, in order
+    pub fn type_keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            self.press(KeyCode::Char(c));
+        }
+    }
+
+    /// draws the current screen on a 120x40 terminal, and returns its text row by row
+    pub fn screen_text(&mut self) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| self.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                text.push_str(buffer[(x, y)].symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// returns how much hp an object has left, or None once it's gone from the map
+    pub fn hp_of(&self, id: usize) -> Option<u16> {
+        self.gamemap.get_position(id)?;
+        Some(self.objects.get(&id)?.fighter.as_ref()?.hp)
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::{
+        achievements::{self, Achievement},
+        animation,
+        app::procgen::{self, DungeonConfig},
+        components::Spell,
+        components::{AIType, Alertness, Item, Position, Slot, StatusKind},
+        data::EntityData,
+        engine::{self, DamageSource, Dice},
+        events::{AttackOutcome, GameEvent},
+        gamemap::{Alarm, EffectKind, Lock},
+        identify, items,
+        keybindings::{self, Category},
+        lighting,
+        pathfinding::{Costs, Diagonals, Pathfinder},
+        quests::{self, Goal, Quest, QuestState},
+        scores,
+        settings::AnimationSpeed,
+        spells,
+    };
+    use ratatui::style::Color;
+
+    /// a 12x7 room with the player near its left wall
+    fn arena() -> App {
+        App::test_arena(12, 7, 2, 3)
+    }
+
+    /// an orc that can take a beating and hits back for nothing
+    fn punching_bag() -> Object {
+        let mut orc = entities::orc();
+        let fighter = orc.fighter.as_mut().unwrap();
+        fighter.max_hp = 1000;
+        fighter.hp = 1000;
+        fighter.defense = 0;
+        fighter.damage = Dice::flat(0);
+        fighter.power = 0;
+        orc
+    }
+
+    /// returns the outcome of every attack one fighter made on another this turn
+    fn attacks(app: &App, from: usize, to: usize) -> Vec<AttackOutcome> {
+        app.events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::DamageDealt {
+                    attacker,
+                    target,
+                    outcome,
+                    ..
+                } if *attacker == from && *target == to => Some(*outcome),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn arenas_repeat_exactly() {
+        let play = || {
+            let mut app = arena();
+            let orc = app.place(punching_bag(), 3, 3);
+            app.type_keys("llllllllll");
+            app.hp_of(orc)
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn crowds_play_the_same_on_any_number_of_threads() {
+        let play = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                let mut app = App::test_arena(24, 12, 2, 6);
+                let fighter = app
+                    .objects
+                    .get_mut(&PLAYER)
+                    .unwrap()
+                    .fighter
+                    .as_mut()
+                    .unwrap();
+                fighter.max_hp = u16::MAX;
+                fighter.hp = u16::MAX;
+                // enough monsters that their turns are planned together, and fast enough
+                // that some of them act twice in one turn
+                let jackals: Vec<usize> = (0..40)
+                    .map(|n| app.place(entities::jackal(), 12 + n % 10, 2 + n / 10 * 2))
+                    .collect();
+                app.gamemap.alarm = Some(Alarm {
+                    position: Position { x: 2, y: 6 },
+                    expires: u64::MAX,
+                });
+                app.type_keys("..........");
+                jackals
+                    .iter()
+                    .map(|&id| (app.gamemap.get_position(id), app.hp_of(id)))
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(play(1), play(4));
+    }
+
+    #[test]
+    fn melee_damage_matches_the_attack() {
+        let mut app = arena();
+        app.objects
+            .get_mut(&PLAYER)
+            .unwrap()
+            .fighter
+            .as_mut()
+            .unwrap()
+            .damage = Dice::flat(4);
+        let orc = app.place(punching_bag(), 3, 3);
+
+        let mut expected = 1000;
+        for _ in 0..20 {
+            app.press(KeyCode::Char('l'));
+            let outcomes = attacks(&app, PLAYER, orc);
+            assert_eq!(outcomes.len(), 1);
+            // the fighter's power is added onto the flat roll, and the orc has no defense
+            match outcomes[0] {
+                AttackOutcome::Hit(damage) => {
+                    assert_eq!(damage, 5);
+                    expected -= damage;
+                }
+                AttackOutcome::Critical(damage) => {
+                    assert_eq!(damage, 10);
+                    expected -= damage;
+                }
+                AttackOutcome::Miss => {}
+                AttackOutcome::NoDamage => panic!("a flat roll against no defense did nothing"),
+            }
+            assert_eq!(app.hp_of(orc), Some(expected));
+        }
+        assert!(expected < 1000, "every attack missed");
+    }
+
+    #[test]
+    fn monsters_fight_back() {
+        let mut app = arena();
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().damage = Dice::flat(3);
+        let orc = app.place(orc, 3, 3);
+
+        let before = app.hp_of(PLAYER).unwrap();
+        let mut taken = 0;
+        for _ in 0..5 {
+            app.press(KeyCode::Char('.'));
+            for outcome in attacks(&app, orc, PLAYER) {
+                if let AttackOutcome::Hit(damage) | AttackOutcome::Critical(damage) = outcome {
+                    taken += damage;
+                }
+            }
+        }
+        assert!(taken > 0, "the orc never landed a hit");
+        assert!(app.hp_of(PLAYER).unwrap() >= before - taken);
+    }
+
+    #[test]
+    fn killing_a_monster_removes_it() {
+        let mut app = arena();
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().hp = 1;
+        let orc = app.place(orc, 3, 3);
+
+        let kills = app.kills;
+        for _ in 0..20 {
+            if app.hp_of(orc).is_none() {
+                break;
+            }
+            app.press(KeyCode::Char('l'));
+        }
+        assert_eq!(app.hp_of(orc), None);
+        assert_eq!(app.kills, kills + 1);
+        assert!(app.gamemap.get_ref(3, 3).blocker.is_none());
+    }
+
+    #[test]
+    fn lightning_hits_the_target_under_the_cursor() {
+        let mut app = arena();
+        let scroll = app.give(items::scroll_lightning());
+        let orc = app.place(punching_bag(), 6, 3);
+
+        app.press(KeyCode::Char('1'));
+        assert!(matches!(app.game_screen, GameScreen::Targeting { .. }));
+        app.type_keys("llll");
+        app.press(KeyCode::Enter);
+
+        assert!(app.game_screen == GameScreen::Main);
+        let hp = app.hp_of(orc).unwrap();
+        assert!(hp < 1000);
+        assert!(!app.inventory.contains(&scroll));
+        assert!(app.events.iter().any(|event| matches!(
+            event,
+            GameEvent::ItemHit { item: Item::Lightning, target, damage, .. }
+                if *target == orc && *damage == 1000 - hp
+        )));
+
+        let fizzle = GameEvent::ItemHit {
+            item: Item::Lightning,
+            target: orc,
+            target_name: String::from("Orc"),
+            damage: 0,
+        };
+        let (line, _, _) = fizzle.log_line().unwrap();
+        assert_eq!(line, "Lightning smites the Orc but does no damage.");
+    }
+
+    #[test]
+    fn lightning_cant_hit_what_the_player_cant_see() {
+        let mut app = arena();
+        let scroll = app.give(items::scroll_lightning());
+        for y in 1..6 {
+            app.gamemap.set_tile_type(5, y, TileType::Wall);
+        }
+        let orc = app.place(punching_bag(), 7, 3);
+        assert!(!app.gamemap.is_visible(7, 3));
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("lllll");
+        app.press(KeyCode::Enter);
+
+        // the player stays in targeting mode with the scroll unread
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Targeting {
+                cursor: Position { x: 7, y: 3 },
+                ..
+            }
+        ));
+        assert_eq!(app.hp_of(orc), Some(1000));
+        assert!(app.inventory.contains(&scroll));
+        let last = app.log.messages.last().unwrap();
+        assert_eq!(last.message, "You can't see there.");
+    }
+
+    #[test]
+    fn spells_are_learned_from_books_and_cost_mana() {
+        let mut app = arena();
+        app.give(spells::spellbook(Spell::Lightning));
+        let orc = app.place(punching_bag(), 6, 3);
+
+        app.press(KeyCode::Char('1'));
+        assert!(app.inventory.is_empty());
+        assert_eq!(spells::known_spells(&app), vec![Spell::Lightning]);
+        let (mana, max_mana) = spells::player_mana(&app).unwrap();
+        assert_eq!(mana, max_mana);
+
+        app.press(KeyCode::Char('z'));
+        app.press(KeyCode::Enter);
+        assert!(matches!(app.game_screen, GameScreen::Targeting { .. }));
+        app.type_keys("llll");
+        app.press(KeyCode::Enter);
+
+        assert!(app.game_screen == GameScreen::Main);
+        assert!(app.hp_of(orc).unwrap() < 1000);
+        let (after, _) = spells::player_mana(&app).unwrap();
+        assert_eq!(after, mana - Spell::Lightning.mana_cost());
+    }
+
+    #[test]
+    fn spells_cant_be_cast_without_enough_mana() {
+        let mut app = arena();
+        app.give(spells::spellbook(Spell::Charm));
+        app.press(KeyCode::Char('1'));
+        let caster = app
+            .objects
+            .get_mut(&PLAYER)
+            .unwrap()
+            .caster
+            .as_mut()
+            .unwrap();
+        caster.mana = Spell::Charm.mana_cost() - 1;
+
+        // the menu stays open, in case another spell is cheap enough
+        app.press(KeyCode::Char('z'));
+        app.press(KeyCode::Enter);
+        assert!(app.game_screen == GameScreen::Spells { cursor: 0 });
+        assert!(
+            app.log
+                .messages
+                .last()
+                .unwrap()
+                .message
+                .starts_with("You need")
+        );
+    }
+
+    #[test]
+    fn examining_a_monster_shows_its_health_and_state() {
+        let mut app = arena();
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().hp = 400;
+        app.place(orc, 5, 3);
+
+        app.press(KeyCode::Char('x'));
+        app.type_keys("lll");
+        let text = app.screen_text();
+        assert!(text.contains("HP: 400/1000"));
+        assert!(text.contains("idle"));
+    }
+
+    #[test]
+    fn hurt_monsters_get_health_bars() {
+        let mut app = arena();
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().hp = 500;
+        app.place(orc, 5, 3);
+
+        assert!(!app.screen_text().contains("==-"));
+        app.settings.monster_health_bars = true;
+        assert!(app.screen_text().contains("==-"));
+    }
+
+    #[test]
+    fn monsters_are_announced_the_first_time_they_come_into_view() {
+        let mut app = arena();
+        for y in 1..6 {
+            app.gamemap.set_tile_type(5, y, TileType::Wall);
+        }
+        let orc = app.place(punching_bag(), 8, 3);
+        assert!(!app.objects.get(&orc).unwrap().spotted);
+
+        // knock a hole in the wall so the orc can be seen
+        app.gamemap.set_tile_type(5, 3, TileType::Floor);
+        app.press(KeyCode::Char('.'));
+        let spotted = |app: &App| {
+            app.events
+                .iter()
+                .any(|event| matches!(event, GameEvent::Spotted { id, .. } if *id == orc))
+        };
+        assert!(spotted(&app));
+        assert!(app.flashes.contains_key(&orc));
+
+        app.press(KeyCode::Char('.'));
+        assert!(!spotted(&app));
+    }
+
+    #[test]
+    fn items_are_remembered_until_seen_again() {
+        let mut app = arena();
+        app.place(items::potion_cure_wounds(), 8, 3);
+
+        // wall the potion off, it should still be drawn where it was
+        for y in 1..6 {
+            app.gamemap.set_tile_type(5, y, TileType::Wall);
+        }
+        app.press(KeyCode::Char('.'));
+        assert!(!app.gamemap.is_visible(8, 3));
+        assert_eq!(app.gamemap.get_remembered(8, 3).unwrap().glyph, '!');
+
+        // someone took it while the player wasn't looking
+        app.gamemap.remove_item(8, 3);
+        assert!(app.gamemap.get_remembered(8, 3).is_some());
+        app.gamemap.set_tile_type(5, 3, TileType::Floor);
+        app.press(KeyCode::Char('.'));
+        assert!(app.gamemap.get_remembered(8, 3).is_none());
+    }
+
+    #[test]
+    fn equipping_and_unequipping_a_weapon() {
+        let mut app = arena();
+        let dagger = app.give(entities::weapon_dagger());
+
+        let time = app.time;
+        app.press(KeyCode::Char('1'));
+        assert_eq!(app.equipment[Slot::Weapon as usize], Some(dagger));
+        assert!(app.inventory.is_empty());
+        assert!(app.time > time);
+        assert!(app.events.contains(&GameEvent::Equipped {
+            id: dagger,
+            name: app.objects.get(&dagger).unwrap().name.clone(),
+        }));
+
+        app.press(KeyCode::Char('A'));
+        assert_eq!(app.equipment[Slot::Weapon as usize], None);
+        assert_eq!(app.inventory, vec![dagger]);
+    }
+
+    /// reads a scroll of enchant equipment on a worn dagger, with the rng reseeded right
+    /// before the scroll's rolls. returns the dagger and its power beforehand
+    fn enchant_worn_dagger(app: &mut App, seed: u64) -> (usize, i16) {
+        let dagger = app.give(entities::weapon_dagger());
+        app.press(KeyCode::Char('1'));
+        let power = app
+            .objects
+            .get(&dagger)
+            .unwrap()
+            .equipment
+            .as_ref()
+            .unwrap()
+            .power();
+
+        // the scroll picks its target from the pack or the equipment slots
+        app.give(items::scroll_enchant());
+        app.press(KeyCode::Char('1'));
+        assert!(matches!(app.game_screen, GameScreen::ChooseItem { .. }));
+        app.rng = StdRng::seed_from_u64(seed);
+        app.press(KeyCode::Char('A'));
+        assert!(app.inventory.is_empty());
+        (dagger, power)
+    }
+
+    #[test]
+    fn enchanting_worn_equipment_raises_its_bonus() {
+        let mut app = arena();
+        // seed 0 doesn't backfire
+        let (dagger, power) = enchant_worn_dagger(&mut app, 0);
+
+        let equip = app
+            .objects
+            .get(&dagger)
+            .unwrap()
+            .equipment
+            .as_ref()
+            .unwrap();
+        assert!(!equip.cursed);
+        assert_eq!(equip.enchantment, 1);
+        assert_eq!(equip.power(), power + 1);
+        assert_eq!(identify::item_name(&app, dagger), "+1 dagger");
+    }
+
+    #[test]
+    fn an_enchant_scroll_can_backfire_and_curse() {
+        let mut app = arena();
+        // seed 7 rolls the scroll's small chance of cursing the item instead
+        let (dagger, power) = enchant_worn_dagger(&mut app, 7);
+
+        let equip = app
+            .objects
+            .get(&dagger)
+            .unwrap()
+            .equipment
+            .as_ref()
+            .unwrap();
+        assert!(equip.cursed);
+        assert_eq!(equip.enchantment, -1);
+        assert_eq!(equip.power(), power - 1);
+        assert_eq!(identify::item_name(&app, dagger), "-1 dagger");
+
+        // cursed equipment can't be taken off
+        app.press(KeyCode::Char('A'));
+        assert_eq!(app.equipment[Slot::Weapon as usize], Some(dagger));
+    }
+
+    #[test]
+    fn two_handed_weapons_take_the_off_hand() {
+        let mut app = arena();
+        let shield = app.give(entities::shield());
+        let greatsword = app.give(entities::weapon_greatsword());
+
+        app.press(KeyCode::Char('1'));
+        assert_eq!(app.equipment[Slot::OffHand as usize], Some(shield));
+
+        // the greatsword's inventory slot is taken by the shield it pushed off
+        app.press(KeyCode::Char('1'));
+        assert_eq!(app.equipment[Slot::Weapon as usize], Some(greatsword));
+        assert_eq!(app.equipment[Slot::OffHand as usize], None);
+        assert_eq!(app.inventory, vec![shield]);
+
+        // and putting the shield back on takes the greatsword off
+        app.press(KeyCode::Char('1'));
+        assert_eq!(app.equipment[Slot::OffHand as usize], Some(shield));
+        assert_eq!(app.equipment[Slot::Weapon as usize], None);
+        assert_eq!(app.inventory, vec![greatsword]);
+    }
+
+    #[test]
+    fn picking_up_and_dropping_items() {
+        let mut app = arena();
+        let potion = app.place(items::potion_cure_wounds(), 2, 3);
+
+        app.press(KeyCode::Char('g'));
+        assert_eq!(app.inventory, vec![potion]);
+        assert!(app.gamemap.get_ref(2, 3).item.is_none());
+        assert!(matches!(
+            app.events.as_slice(),
+            [GameEvent::ItemPickedUp { id, .. }] if *id == potion
+        ));
+
+        // dropping it somewhere else puts it under the player
+        app.press(KeyCode::Char('l'));
+        app.press_alt(KeyCode::Char('1'));
+        assert!(app.inventory.is_empty());
+        assert_eq!(app.gamemap.get_ref(3, 3).item, Some(potion));
+        assert_eq!(
+            app.gamemap.get_position(potion),
+            Some(Position { x: 3, y: 3 })
+        );
+    }
+
+    #[test]
+    fn walking_onto_a_monster_doesnt_move_the_player() {
+        let mut app = arena();
+        app.place(punching_bag(), 3, 3);
+        app.press(KeyCode::Char('l'));
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 2, y: 3 })
+        );
+    }
+
+    #[test]
+    fn corners_can_be_forbidden() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(3, 3, TileType::Wall);
+
+        app.settings.forbid_corner_cutting = true;
+        app.press(KeyCode::Char('n'));
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 2, y: 3 })
+        );
+
+        app.settings.forbid_corner_cutting = false;
+        app.press(KeyCode::Char('n'));
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 3, y: 4 })
+        );
+    }
+
+    #[test]
+    fn orthogonal_only_rules_out_every_diagonal_step() {
+        let mut app = arena();
+        app.settings.orthogonal_only = true;
+        app.place(punching_bag(), 3, 4);
+
+        let time = app.time;
+        app.press(KeyCode::Char('u'));
+        assert_eq!(app.time, time);
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 2, y: 3 })
+        );
+        assert!(engine::adjacent_hostile_directions(&app).is_empty());
+
+        let pathfinder = Pathfinder::new(
+            &app.gamemap,
+            &mut app.path_scratch,
+            Costs::Walkable,
+            (2, 3),
+            2,
+            3,
+            app.settings.diagonals(),
+        );
+        // four cardinal steps, each costing 2 plus 1 for the tile, instead of two diagonal ones
+        assert_eq!(pathfinder.distance_to((4, 5)), Some(12));
+    }
+
+    #[test]
+    fn orthogonal_only_blocks_sight_between_touching_corners() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(2, 2, TileType::Wall);
+        app.gamemap.set_tile_type(3, 3, TileType::Wall);
+        update_fov(&mut app);
+        assert!(app.gamemap.is_visible(4, 1));
+
+        // nothing on the map changed, but the view still has to be worked out again
+        app.settings.orthogonal_only = true;
+        update_fov(&mut app);
+        assert!(!app.gamemap.is_visible(4, 1));
+    }
+
+    #[test]
+    fn hurt_monsters_run_away() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().hp = 100;
+        let orc = app.place(orc, 3, 3);
+
+        app.type_keys("...");
+        let pos = app.gamemap.get_position(orc).unwrap();
+        assert!(pos.x > 3, "the orc stayed at {}, {}", pos.x, pos.y);
+    }
+
+    #[test]
+    fn cornered_monsters_fight_back() {
+        let mut app = arena();
+        let mut orc = punching_bag();
+        orc.fighter.as_mut().unwrap().hp = 100;
+        orc.fighter.as_mut().unwrap().damage = Dice::flat(3);
+        // the orc is stuck in a nook, and the player stands in its only way out
+        app.gamemap.set_tile_type(2, 1, TileType::Wall);
+        app.gamemap.set_tile_type(1, 2, TileType::Wall);
+        let orc = app.place(orc, 1, 1);
+        app.gamemap.remove_blocker(2, 3);
+        app.gamemap.place_blocker(PLAYER, 2, 2);
+        update_fov(&mut app);
+
+        let mut attacked = false;
+        for _ in 0..5 {
+            app.press(KeyCode::Char('.'));
+            attacked |= !attacks(&app, orc, PLAYER).is_empty();
+        }
+        assert_eq!(app.gamemap.get_position(orc), Some(Position { x: 1, y: 1 }));
+        assert!(attacked);
+    }
+
+    #[test]
+    fn corpses_rot_away_on_time() {
+        let mut app = arena();
+        let rots_at = app.time + 300;
+        let on_floor = app.place(entities::corpse(&entities::orc(), rots_at), 6, 3);
+        let carried = app.give(entities::corpse(&entities::rat(), rots_at));
+
+        app.type_keys("..");
+        assert!(app.objects.get(&on_floor).is_some());
+        assert_eq!(app.inventory, vec![carried]);
+
+        app.press(KeyCode::Char('.'));
+        assert!(app.objects.get(&on_floor).is_none());
+        assert!(app.gamemap.get_ref(6, 3).item.is_none());
+        assert!(app.inventory.is_empty());
+        assert!(
+            app.events
+                .iter()
+                .any(|event| matches!(event, GameEvent::ItemRotted { .. }))
+        );
+    }
+
+    #[test]
+    fn an_alert_necromancer_raises_a_corpse_it_can_see() {
+        let mut app = arena();
+        let necromancer = app.place(entities::necromancer(), 9, 3);
+        if let Some(AIType::Melee(ai_data)) = &mut app.objects.get_mut(&necromancer).unwrap().ai {
+            ai_data.alertness = Alertness::Alert;
+        }
+        let corpse = app.place(entities::corpse(&entities::orc(), u64::MAX), 7, 3);
+
+        app.press(KeyCode::Char('.'));
+        assert!(app.objects.get(&corpse).is_none());
+        let risen = app.gamemap.get_ref(7, 3).blocker.unwrap();
+        assert_eq!(app.objects.get(&risen).unwrap().name, "Zombie");
+    }
+
+    #[test]
+    fn monsters_only_notice_the_player_within_their_perception() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        let far = app.place(entities::orc(), 10, 3);
+        let near = app.place(entities::orc(), 3, 3);
+        app.press(KeyCode::Char('.'));
+
+        let alertness = |id: usize| match &app.objects.get(&id).unwrap().ai {
+            Some(AIType::Melee(ai_data)) => ai_data.alertness,
+            _ => unreachable!(),
+        };
+        assert!(alertness(near) == Alertness::Alert);
+        assert!(alertness(far) != Alertness::Alert);
+    }
+
+    #[test]
+    fn sneaking_is_slower_and_harder_to_notice() {
+        let mut app = arena();
+        let orc = match entities::orc().ai {
+            Some(AIType::Melee(ai_data)) => ai_data,
+            _ => unreachable!(),
+        };
+        let chance = engine::notice_chance(&app, &orc, 3);
+
+        app.press(KeyCode::Char('s'));
+        assert!(app.sneaking);
+        assert_eq!(engine::notice_chance(&app, &orc, 3), chance / 2);
+
+        app.press(KeyCode::Char('l'));
+        assert_eq!(app.time, 150);
+    }
+
+    #[test]
+    fn only_lit_tiles_can_be_seen_in_the_dark() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        app.gamemap.lighting = true;
+        update_fov(&mut app);
+        assert!(app.gamemap.is_visible(3, 3));
+        assert!(!app.gamemap.is_visible(8, 3));
+
+        app.place(lighting::glowing_mushroom(), 9, 3);
+        assert!(app.gamemap.is_visible(8, 3));
+        assert!(!app.gamemap.is_visible(14, 3));
+
+        app.give(lighting::lantern());
+        update_fov(&mut app);
+        assert!(app.gamemap.is_visible(5, 3));
+    }
+
+    #[test]
+    fn monsters_cant_see_the_player_in_the_dark() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        app.gamemap.lighting = true;
+        let orc = app.place(entities::orc(), 4, 3);
+        let rat = app.place(entities::rat(), 4, 4);
+        let orc_pos = app.gamemap.get_position(orc).unwrap();
+        let rat_pos = app.gamemap.get_position(rat).unwrap();
+        assert!(!lighting::monster_sees_player(&app, orc, orc_pos));
+        assert!(lighting::monster_sees_player(&app, rat, rat_pos));
+
+        app.give(lighting::lantern());
+        assert!(lighting::monster_sees_player(&app, orc, orc_pos));
+    }
+
+    #[test]
+    fn wading_through_water_is_slow() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(3, 3, TileType::Water);
+        app.press(KeyCode::Char('l'));
+        assert_eq!(app.time, 200);
+        app.press(KeyCode::Char('l'));
+        assert_eq!(app.time, 300);
+    }
+
+    #[test]
+    fn lava_burns_whatever_stands_in_it() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(3, 3, TileType::Lava);
+        let orc = app.place(punching_bag(), 8, 3);
+        app.gamemap.set_tile_type(8, 3, TileType::Lava);
+
+        // careful movement stops at the edge
+        app.press_alt(KeyCode::Char('l'));
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 2, y: 3 })
+        );
+
+        // walking in asks first
+        let before = app.hp_of(PLAYER).unwrap();
+        app.press(KeyCode::Char('l'));
+        assert!(matches!(app.game_screen, GameScreen::Confirm { .. }));
+        app.press(KeyCode::Char('y'));
+        assert!(app.hp_of(PLAYER).unwrap() < before);
+        assert!(app.hp_of(orc).unwrap() < 1000);
+        assert!(app.events.iter().any(|event| matches!(
+            event,
+            GameEvent::HazardDamage { target, source: DamageSource::Lava, .. } if *target == orc
+        )));
+    }
+
+    #[test]
+    fn a_wand_of_digging_bores_a_tunnel() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        for y in 1..6 {
+            app.gamemap.set_tile_type(5, y, TileType::Wall);
+            app.gamemap.set_tile_type(6, y, TileType::Wall);
+        }
+        let wand = app.give(items::wand_of_digging());
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("lll");
+        app.press(KeyCode::Enter);
+
+        assert!(app.gamemap.get_ref(5, 3).tile_type == TileType::Floor);
+        assert!(app.gamemap.get_ref(6, 3).tile_type == TileType::Floor);
+        assert!(app.gamemap.get_ref(5, 2).tile_type == TileType::Wall);
+        assert!(app.gamemap.is_visible(8, 3));
+        assert_eq!(app.objects.get(&wand).unwrap().charges, Some(3));
+    }
+
+    #[test]
+    fn a_pickaxe_digs_one_wall_and_keeps_going() {
+        let mut app = App::test_arena(12, 7, 1, 3);
+        app.gamemap.set_tile_type(2, 3, TileType::Wall);
+        let pickaxe = app.give(items::pickaxe());
+
+        app.press(KeyCode::Char('1'));
+        app.press(KeyCode::Char('l'));
+        app.press(KeyCode::Enter);
+        assert!(app.gamemap.get_ref(2, 3).tile_type == TileType::Floor);
+        assert_eq!(app.time, 500);
+        assert!(app.inventory.contains(&pickaxe));
+
+        // the edge of the map is solid rock
+        app.press(KeyCode::Char('1'));
+        app.press(KeyCode::Char('h'));
+        app.press(KeyCode::Enter);
+        assert!(app.gamemap.get_ref(0, 3).tile_type == TileType::Wall);
+        assert_eq!(app.time, 500);
+    }
+
+    #[test]
+    fn rock_worms_tunnel_through_walls() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        for y in 1..6 {
+            app.gamemap.set_tile_type(5, y, TileType::Wall);
+        }
+        let worm = app.place(entities::rock_worm(), 8, 3);
+        if let Some(AIType::Melee(ai_data)) = &mut app.objects.get_mut(&worm).unwrap().ai {
+            ai_data.alertness = Alertness::Alert;
+            ai_data.target = Some(PLAYER);
+            ai_data.last_seen_pos = Some(Position { x: 2, y: 3 });
+            ai_data.last_seen_time = Some(0);
+        }
+
+        for _ in 0..20 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!((1..6).any(|y| app.gamemap.get_ref(5, y).tile_type == TileType::Floor));
+    }
+
+    #[test]
+    fn a_fireball_sets_the_blast_alight() {
+        let mut app = arena();
+        let orc = app.place(punching_bag(), 7, 3);
+        app.give(items::scroll_fireball());
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("lllll");
+        app.press(KeyCode::Enter);
+
+        assert!(app.hp_of(orc).unwrap() < 1000);
+        assert!(app.gamemap.is_burning(6, 2));
+        let burning = |app: &App| {
+            let fighter = app.objects.get(&orc).unwrap().fighter.as_ref().unwrap();
+            fighter
+                .statuses
+                .iter()
+                .any(|status| status.kind == StatusKind::Burning)
+        };
+        assert!(burning(&app));
+        app.press(KeyCode::Char('.'));
+        assert!(app.events.iter().any(|event| matches!(
+            event,
+            GameEvent::HazardDamage { target, source: DamageSource::Fire, .. } if *target == orc
+        )));
+
+        // with nothing to feed on, the flames soon die down
+        for _ in 0..4 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!(app.gamemap.effects.is_empty());
+        assert!(!burning(&app));
+    }
+
+    #[test]
+    fn fire_spreads_over_grass_and_burns_it_away() {
+        let mut app = arena();
+        for x in 4..11 {
+            for y in 1..6 {
+                app.gamemap.set_tile_type(x, y, TileType::Grass);
+            }
+        }
+        app.give(items::scroll_fireball());
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("lll");
+        app.press(KeyCode::Enter);
+        for _ in 0..5 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!(app.gamemap.is_burning(8, 3) || app.gamemap.is_burning(9, 3));
+
+        for _ in 0..50 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!(app.gamemap.effects.is_empty());
+        assert!(app.gamemap.get_ref(5, 3).tile_type == TileType::Floor);
+    }
+
+    #[test]
+    fn wading_into_water_puts_out_the_flames() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(3, 3, TileType::Water);
+        engine::apply_status(&mut app, PLAYER, StatusKind::Burning, 1000);
+
+        app.press(KeyCode::Char('.'));
+        let before = app.hp_of(PLAYER).unwrap();
+        app.press(KeyCode::Char('l'));
+        app.press(KeyCode::Char('.'));
+        assert!(app.hp_of(PLAYER).unwrap() >= before);
+        let fighter = app.objects.get(&PLAYER).unwrap().fighter.as_ref().unwrap();
+        assert!(fighter.statuses.is_empty());
+    }
+
+    #[test]
+    fn poison_gas_drifts_out_and_poisons_whatever_is_inside() {
+        let mut app = arena();
+        let orc = app.place(punching_bag(), 7, 3);
+        app.give(items::flask_of_poison_gas());
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("lllll");
+        app.press(KeyCode::Enter);
+        app.press(KeyCode::Char('.'));
+
+        assert!(app.gamemap.effect_at(7, 2, EffectKind::Poison).is_some());
+        assert!(app.hp_of(orc).unwrap() < 1000);
+        assert!(app.events.iter().any(|event| matches!(
+            event,
+            GameEvent::HazardDamage { target, source: DamageSource::Poison, .. } if *target == orc
+        )));
+
+        // the cloud thins out and blows away
+        for _ in 0..20 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!(app.gamemap.effects.is_empty());
+    }
+
+    #[test]
+    fn smoke_blocks_the_view() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        app.give(items::smoke_bomb());
+        assert!(app.gamemap.is_visible(9, 3));
+
+        app.press(KeyCode::Char('1'));
+        app.type_keys("llll");
+        app.press(KeyCode::Enter);
+        app.press(KeyCode::Char('.'));
+        assert!(!app.gamemap.is_visible(9, 3));
+
+        for _ in 0..20 {
+            app.press(KeyCode::Char('.'));
+        }
+        assert!(app.gamemap.is_visible(9, 3));
+    }
+
+    #[test]
+    fn a_shattering_flask_wakes_monsters_nearby() {
+        let mut app = App::test_arena(20, 7, 2, 3);
+        // the wall keeps the orc from seeing the player, so only the noise can wake it
+        for y in 1..6 {
+            app.gamemap.set_tile_type(12, y, TileType::Wall);
+        }
+        app.gamemap.set_tile_type(12, 5, TileType::Floor);
+        let orc = app.place(entities::orc(), 14, 3);
+        let alertness = |app: &App| match &app.objects.get(&orc).unwrap().ai {
+            Some(AIType::Melee(ai_data)) => ai_data.alertness,
+            _ => unreachable!(),
+        };
+        if let Some(AIType::Melee(ai_data)) = &mut app.objects.get_mut(&orc).unwrap().ai {
+            ai_data.alertness = Alertness::Sleeping;
+        }
+
+        app.give(items::smoke_bomb());
+        app.press(KeyCode::Char('1'));
+        app.type_keys("llllll");
+        app.press(KeyCode::Enter);
+        assert!(alertness(&app) != Alertness::Sleeping);
+    }
+
+    #[test]
+    fn the_spawner_adds_wanderers_out_of_sight_until_the_floor_is_full() {
+        let mut app = App::test_arena(40, 7, 2, 3);
+        for y in 1..6 {
+            app.gamemap.set_tile_type(10, y, TileType::Wall);
+        }
+        update_fov(&mut app);
+        let monsters = |app: &App| -> Vec<usize> {
+            app.objects
+                .iter()
+                .filter(|(id, obj)| **id != PLAYER && obj.ai.is_some())
+                .map(|(id, _)| *id)
+                .collect()
+        };
+
+        app.handle_spawner_turn();
+        let spawned = monsters(&app);
+        assert_eq!(spawned.len(), 1);
+        let pos = app.gamemap.get_position(spawned[0]).unwrap();
+        assert!(pos.x > 10 && !app.gamemap.is_visible(pos.x, pos.y));
+        assert!(
+            app.action_queue
+                .iter()
+                .any(|action| action.id == spawned[0])
+        );
+        assert_eq!(app.gamemap.wanderers_spawned, 1);
+
+        app.gamemap.wanderers_spawned = usize::MAX;
+        app.handle_spawner_turn();
+        assert_eq!(monsters(&app).len(), 1);
+    }
+
+    #[test]
+    fn a_vault_key_opens_its_door() {
+        let mut app = arena();
+        app.gamemap.set_tile_type(5, 3, TileType::LockedDoor);
+        let key = app.objects.add(items::vault_key());
+        app.gamemap.locks.push(Lock {
+            position: Position { x: 5, y: 3 },
+            key,
+        });
+
+        app.type_keys("lll");
+        assert!(app.gamemap.get_ref(5, 3).tile_type == TileType::LockedDoor);
+        assert_eq!(
+            app.gamemap.get_position(PLAYER),
+            Some(Position { x: 4, y: 3 })
+        );
+
+        app.inventory.push(key);
+        app.press(KeyCode::Char('l'));
+        assert!(app.gamemap.get_ref(5, 3).tile_type == TileType::Floor);
+        assert!(app.gamemap.locks.is_empty());
+        assert!(app.inventory.is_empty());
+    }
+
+    #[test]
+    fn the_built_in_spawn_tables_are_valid() {
+        let problems = procgen::spawn_table_problems(&EntityData::builtin());
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn data_monsters_pick_their_ai_by_name() {
+        let watcher = |ai: &str| {
+            format!(
+                r#"{{ "entities": [{{ "name": "Watcher", "glyph": "w", "color": "Enemy",
+                    "ai": "{}", "monster": {{ "hp": 3 }} }}] }}"#,
+                ai
+            )
+        };
+        let data = EntityData::parse(&watcher("Sentry")).ok().unwrap();
+        let monster = data.monsters().next().unwrap().spawn();
+        assert!(matches!(monster.ai, Some(AIType::Sentry)));
+
+        let problems = EntityData::parse(&watcher("Flying")).err().unwrap();
+        assert_eq!(
+            problems,
+            vec!["Watcher has an unknown ai Flying, instead of one of Melee, Sentry."]
+        );
+    }
+
+    #[test]
+    fn every_vault_key_can_be_reached() {
+        let mut vaults = 0;
+        for seed in 0..200 {
+            let mut app = arena();
+            app.rng = StdRng::seed_from_u64(seed);
+            app.generate_dungeon(DungeonConfig::default().set_level(5));
+            let Some(lock) = app.gamemap.locks.first().copied() else {
+                continue;
+            };
+            vaults += 1;
+
+            // the key lies on the floor, or is carried by a monster out there
+            let key_pos = app
+                .objects
+                .iter()
+                .find(|(_, obj)| obj.carried.contains(&lock.key))
+                .map(|(&id, _)| id)
+                .or(Some(lock.key))
+                .and_then(|id| app.gamemap.get_position(id))
+                .unwrap();
+            let player = app.gamemap.get_position(PLAYER).unwrap();
+            let pathfinder = Pathfinder::new(
+                &app.gamemap,
+                &mut app.path_scratch,
+                Costs::Walkable,
+                (player.x, player.y),
+                2,
+                3,
+                Diagonals::Free,
+            );
+            assert!(
+                pathfinder.distance_to((key_pos.x, key_pos.y)).is_some(),
+                "seed {}",
+                seed
+            );
+            if vaults >= 5 {
+                break;
+            }
+        }
+        assert!(vaults >= 5);
+    }
+
+    /// an active quest on the arena's floor, after the given target
+    fn quest_for(goal: Goal, target: usize) -> Quest {
+        Quest {
+            goal,
+            level: 1,
+            state: QuestState::Active,
+            target: Some(target),
+            target_name: None,
+        }
+    }
+
+    #[test]
+    fn killing_the_captain_completes_the_quest() {
+        let mut app = arena();
+        let mut captain = punching_bag();
+        captain.fighter.as_mut().unwrap().hp = 1;
+        let captain = app.place(captain, 3, 3);
+        app.quests.push(quest_for(Goal::Slay, captain));
+
+        for _ in 0..20 {
+            if app.hp_of(captain).is_none() {
+                break;
+            }
+            app.press(KeyCode::Char('l'));
+        }
+        assert!(app.quests[0].state == QuestState::Completed);
+        assert!(app.objects.get(&PLAYER).unwrap().gold.unwrap_or(0) > 0);
+        assert_eq!(app.inventory.len(), 1);
+    }
+
+    #[test]
+    fn picking_up_the_idol_completes_the_quest() {
+        let mut app = arena();
+        let idol = app.place(items::golden_idol(), 2, 3);
+        app.quests.push(quest_for(Goal::Retrieve, idol));
+        let other = app.place(items::golden_idol(), 3, 3);
+
+        app.type_keys("lg");
+        assert!(app.inventory.contains(&other));
+        assert!(app.quests[0].state == QuestState::Active);
+
+        app.type_keys("hg");
+        assert!(app.inventory.contains(&idol));
+        assert!(app.quests[0].state == QuestState::Completed);
+    }
+
+    #[test]
+    fn a_quest_puts_its_goal_on_its_floor_and_fails_once_left_behind() {
+        for goal in [Goal::Slay, Goal::Retrieve] {
+            let mut app = arena();
+            app.quests.push(Quest {
+                goal,
+                level: 3,
+                state: QuestState::Waiting,
+                target: None,
+                target_name: None,
+            });
+            app.generate_dungeon(DungeonConfig::default().set_level(3));
+
+            let quest = &app.quests[0];
+            assert!(quest.state == QuestState::Active);
+            assert!(app.gamemap.get_position(quest.target.unwrap()).is_some());
+
+            quests::leave_floor(&mut app);
+            assert!(app.quests[0].state == QuestState::Failed);
+        }
+    }
+
+    #[test]
+    fn talking_to_a_shopkeeper_leads_to_trading() {
+        let mut app = arena();
+        let shopkeeper = app.place(entities::shopkeeper(Vec::new()), 3, 3);
+
+        app.press(KeyCode::Char('l'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Dialogue { speaker, node: 0 } if speaker == shopkeeper
+        ));
+        app.press(KeyCode::Char('2'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Dialogue { node: 1, .. }
+        ));
+        app.press(KeyCode::Char('1'));
+        assert!(matches!(app.game_screen, GameScreen::Shop { .. }));
+    }
+
+    #[test]
+    fn a_lost_adventurer_hands_out_their_quest_once() {
+        let mut app = App::test_arena(30, 7, 2, 3);
+        let adventurer = app.place(entities::lost_adventurer(Goal::Retrieve), 3, 3);
+
+        app.type_keys("l11");
+        assert!(app.game_screen == GameScreen::Main);
+        assert_eq!(app.quests.len(), 1);
+        let quest = &app.quests[0];
+        assert!(quest.state == QuestState::Active && quest.level == app.gamemap.level);
+        let idol = app.gamemap.get_position(quest.target.unwrap()).unwrap();
+        let player = app.gamemap.get_position(PLAYER).unwrap();
+        assert!(idol.distance_to(player) >= 10);
+
+        // later conversations pick up after the quest was handed out
+        app.press(KeyCode::Char('l'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Dialogue { speaker, node: 2 } if speaker == adventurer
+        ));
+        app.press(KeyCode::Esc);
+        assert_eq!(app.quests.len(), 1);
+    }
+
+    #[test]
+    fn leaving_a_floor_without_a_kill_is_pacifist() {
+        let mut app = arena();
+        app.place(entities::stairs(), 2, 3);
+        app.press(KeyCode::Char('>'));
+        assert_eq!(app.gamemap.level, 2);
+        assert_eq!(app.conduct.pacifist_floors, 1);
+        assert!(app.profile.has(Achievement::Pacifist));
+        assert!(!app.profile.has(Achievement::TrollSlayer));
+    }
+
+    #[test]
+    fn drinking_a_potion_breaks_the_teetotal_conduct() {
+        let mut app = arena();
+        let mut troll = entities::troll();
+        troll.fighter.as_mut().unwrap().hp = 1;
+        let troll = app.place(troll, 3, 3);
+        for _ in 0..20 {
+            if app.hp_of(troll).is_none() {
+                break;
+            }
+            app.press(KeyCode::Char('l'));
+        }
+        assert!(app.profile.has(Achievement::TrollSlayer));
+
+        // cure wounds won't be drunk at full health
+        app.objects
+            .get_mut(&PLAYER)
+            .unwrap()
+            .fighter
+            .as_mut()
+            .unwrap()
+            .hp = 1;
+        app.give(items::potion_cure_wounds());
+        app.press(KeyCode::Char('1'));
+        assert_eq!(app.conduct.potions_drunk, 1);
+
+        achievements::escape(&mut app);
+        assert!(app.profile.has(Achievement::Escaped));
+        assert!(!app.profile.has(Achievement::Teetotaller));
+    }
+
+    #[test]
+    fn daily_runs_share_a_dungeon_and_score_apart() {
+        let start_daily = || {
+            let mut app = App::new();
+            app.headless = true;
+            app.profile = Profile::default();
+            app.press(KeyCode::Char('d'));
+            app
+        };
+        let mut app = start_daily();
+        let today = scores::today();
+        assert_eq!(app.daily.as_deref(), Some(today.as_str()));
+        assert_eq!(app.seed, scores::daily_seed(&today));
+        assert!(app.game_screen == GameScreen::Main);
+        let class = app.objects.get(&PLAYER).unwrap().class;
+        assert_eq!(class, Some(scores::daily_class(app.seed)));
+        let player = app.gamemap.get_position(PLAYER);
+        assert_eq!(start_daily().gamemap.get_position(PLAYER), player);
+
+        scores::record(&mut app);
+        assert_eq!(app.profile.high_scores(Some(&today)).len(), 1);
+        assert!(app.profile.high_scores(None).is_empty());
+    }
+
+    #[test]
+    fn every_key_on_the_help_screen_is_handled() {
+        for binding in keybindings::KEY_BINDINGS {
+            assert!(
+                !binding.presses.is_empty() || binding.category == Category::Talking,
+                "{} has no presses to check",
+                binding.keys
+            );
+            for &(code, modifiers) in binding.presses {
+                let mut app = arena();
+                let handled = app.handled_keys(KeyEvent::new(code, modifiers));
+                assert!(handled.is_some(), "{} isn't handled", binding.keys);
+            }
+        }
+
+        // and a key that isn't in the table isn't quietly swallowed either
+        let mut app = arena();
+        assert!(
+            app.handled_keys(KeyEvent::from(KeyCode::Char('~')))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn help_lists_every_category_and_leads_to_the_manual() {
+        for category in keybindings::CATEGORIES {
+            assert!(keybindings::bindings_in(category).next().is_some());
+        }
+
+        let mut app = arena();
+        app.press(KeyCode::Char('?'));
+        assert!(app.game_screen == GameScreen::Help { offset: 0 });
+        app.press(KeyCode::Char('j'));
+        assert!(app.game_screen == GameScreen::Help { offset: 1 });
+        app.press(KeyCode::Char('?'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Manual { page: 0, .. }
+        ));
+        app.press(KeyCode::Esc);
+        assert!(app.game_screen == GameScreen::Main);
+    }
+
+    #[test]
+    fn the_command_palette_runs_commands_by_name() {
+        assert_eq!(
+            keybindings::matching_commands("auto")[0].name,
+            "toggle autopickup"
+        );
+        assert_eq!(keybindings::split_query("drop 2"), ("drop", Some(2)));
+
+        let mut app = arena();
+        let enabled = app.settings.auto_pickup.enabled;
+        app.type_keys(":auto");
+        app.press(KeyCode::Enter);
+        assert!(app.game_screen == GameScreen::Main);
+        assert_eq!(app.settings.auto_pickup.enabled, !enabled);
+
+        let potion = app.give(items::potion_cure_wounds());
+        app.type_keys(":throw 1");
+        app.press(KeyCode::Enter);
+        assert_eq!(app.inventory, vec![potion]);
+
+        app.type_keys(":drop 1");
+        app.press(KeyCode::Enter);
+        assert!(app.inventory.is_empty());
+        assert_eq!(app.gamemap.get_ref(2, 3).item, Some(potion));
+    }
+
+    #[test]
+    fn the_settings_screen_changes_the_view_and_animations() {
+        let mut app = arena();
+        let radius = app.settings.view_radius;
+        app.press(KeyCode::Char('O'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Settings { cursor: 0, .. }
+        ));
+        app.press(KeyCode::Char('h'));
+        assert_eq!(app.settings.view_radius, radius - 1);
+        assert_eq!(app.fov_rays.radius(), radius);
+        app.press(KeyCode::Esc);
+        assert!(app.game_screen == GameScreen::Main);
+        app.press(KeyCode::Char('.'));
+        assert_eq!(app.fov_rays.radius(), radius - 1);
+
+        app.settings.animation_speed = AnimationSpeed::Off;
+        app.animate(animation::projectile(
+            &[Position { x: 3, y: 3 }],
+            '*',
+            Color::Red,
+        ));
+        assert!(app.animations.is_empty());
+
+        app.press_key(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Confirm {
+                pending: PendingAction::Quit,
+                ..
+            }
+        ));
+        app.press(KeyCode::Char('n'));
+        assert!(app.game_screen == GameScreen::Main);
+    }
+
+    #[test]
+    fn dangerous_actions_wait_for_a_yes() {
+        let mut app = arena();
+        app.place(entities::stairs(), 2, 3);
+        let dagger = app.give(entities::weapon_dagger());
+        app.press(KeyCode::Char('1'));
+
+        // anything but y leaves the dagger in hand
+        app.press_alt(KeyCode::Char('A'));
+        assert!(matches!(
+            app.game_screen,
+            GameScreen::Confirm {
+                pending: PendingAction::DropEquipped(0),
+                ..
+            }
+        ));
+        app.press(KeyCode::Char('n'));
+        assert!(app.game_screen == GameScreen::Main);
+        assert_eq!(app.equipment[Slot::Weapon as usize], Some(dagger));
+
+        app.press_alt(KeyCode::Char('A'));
+        app.press(KeyCode::Char('y'));
+        assert_eq!(app.equipment[Slot::Weapon as usize], None);
+        assert!(app.gamemap.get_position(dagger).is_some());
+
+        app.place(punching_bag(), 3, 3);
+        app.press(KeyCode::Char('>'));
+        assert_eq!(app.gamemap.level, 1);
+        app.press(KeyCode::Char('y'));
+        assert_eq!(app.gamemap.level, 2);
+    }
+
+    #[test]
+    fn walking_into_a_wall_takes_no_time() {
+        // the arena's left wall is at x 0
+        let mut app = App::test_arena(12, 7, 1, 3);
+        let time = app.time;
+        app.press(KeyCode::Char('h'));
+        assert_eq!(app.time, time);
+        assert!(app.bumped_wall.is_some());
+
+        app.press(KeyCode::Char('h'));
+        assert_eq!(app.time, time);
+        let last = app.log.messages.last().unwrap();
+        assert_eq!(last.message, "There's a wall there.");
+    }
+}
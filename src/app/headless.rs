@@ -0,0 +1,144 @@
+use crossterm::event::KeyEvent;
+use rand::Rng;
+
+use super::{App, GameScreen, PLAYER};
+use crate::{bot, components::CLASS_ORDERING};
+
+// NOTE: this file contains a driver that plays the game without a terminal. nothing is drawn
+// and nothing is written to disk, so bots can play thousands of games for balance statistics
+
+/// something that plays the game by pressing keys
+pub trait Controller {
+    fn choose_key(&mut self, app: &App) -> KeyEvent;
+}
+
+/// the same bot that plays the demo on the main menu
+pub struct Bot;
+
+impl Controller for Bot {
+    fn choose_key(&mut self, app: &App) -> KeyEvent {
+        bot::choose_key(app)
+    }
+}
+
+/// how a headless game went
+pub struct RunSummary {
+    pub seed: u64,
+    pub class: String,
+    pub level: u16,
+    pub kills: u32,
+    pub turns: u64,
+    pub won: bool,
+    /// what killed the player, if they died
+    pub death: Option<String>,
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match (&self.death, self.won) {
+            (_, true) => String::from("escaped"),
+            (Some(death), _) => death.clone(),
+            (None, false) => String::from("still alive"),
+        };
+        write!(
+            f,
+            "seed {:<20} {:<8} level {:>2}  kills {:>3}  turns {:>6}  {}",
+            self.seed, self.class, self.level, self.kills, self.turns, outcome
+        )
+    }
+}
+
+impl App {
+    /// starts a new headless game on a seed, with a class picked from the seed
+    pub fn new_headless(seed: u64) -> Self {
+        let mut app = App::new();
+        app.headless = true;
+        app.seed = seed;
+        let class = CLASS_ORDERING[seed as usize % CLASS_ORDERING.len()];
+        app.new_game(class);
+        app.switch_to_main_screen();
+        app
+    }
+
+    /// plays the game with a controller until the run ends or it has pressed max_keys keys.
+    /// travel and resting carry on by themselves between key presses, like they do on screen
+    pub fn play_headless(&mut self, controller: &mut impl Controller, max_keys: usize) {
+        let mut keys = 0;
+        while keys < max_keys && !self.run_is_over() {
+            self.animations.clear();
+            if self.is_busy() {
+                self.update();
+            } else {
+                let key = controller.choose_key(self);
+                self.press_key(key);
+                keys += 1;
+            }
+        }
+    }
+
+    /// returns true while the player is travelling, resting or playing a macro, which
+    /// carries on without any keys being pressed
+    fn is_busy(&self) -> bool {
+        self.travel_destination.is_some()
+            || self.resting_since.is_some()
+            || self.auto_descend
+            || self.macros.is_playing()
+    }
+
+    /// returns true once the player has died or escaped
+    pub fn run_is_over(&self) -> bool {
+        matches!(self.game_screen, GameScreen::GameOver | GameScreen::Victory)
+    }
+
+    /// summarizes the run so far
+    pub fn run_summary(&self) -> RunSummary {
+        let class = self
+            .objects
+            .get(&PLAYER)
+            .and_then(|player| player.class)
+            .map_or(String::from("none"), |class| class.to_string());
+        RunSummary {
+            seed: self.seed,
+            class,
+            level: self.gamemap.level,
+            kills: self.kills,
+            turns: self.time / 100,
+            won: self.game_screen == GameScreen::Victory,
+            death: self.death.as_ref().map(|death| death.to_string()),
+        }
+    }
+}
+
+/// plays a number of games with the bot, starting from a seed, and prints how each one went
+/// along with the averages. used by the --simulate command line flag
+pub fn simulate(games: usize, seed: Option<u64>, max_keys: usize) {
+    let first_seed = seed.unwrap_or_else(|| rand::rng().random());
+    let summaries: Vec<RunSummary> = (0..games as u64)
+        .map(|game| {
+            let mut app = App::new_headless(first_seed.wrapping_add(game));
+            app.play_headless(&mut Bot, max_keys);
+            let summary = app.run_summary();
+            println!("{}", summary);
+            summary
+        })
+        .collect();
+
+    if summaries.is_empty() {
+        return;
+    }
+    let count = summaries.len() as f64;
+    let average = |get: fn(&RunSummary) -> f64| summaries.iter().map(get).sum::<f64>() / count;
+    println!();
+    println!("games         {}", summaries.len());
+    println!(
+        "escaped       {}",
+        summaries.iter().filter(|s| s.won).count()
+    );
+    println!(
+        "deaths        {}",
+        summaries.iter().filter(|s| s.death.is_some()).count()
+    );
+    println!("avg level     {:.2}", average(|s| s.level as f64));
+    println!("avg kills     {:.2}", average(|s| s.kills as f64));
+    println!("avg turns     {:.0}", average(|s| s.turns as f64));
+}
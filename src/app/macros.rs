@@ -0,0 +1,54 @@
+use std::collections::{HashMap, VecDeque};
+
+use crossterm::event::KeyEvent;
+
+// NOTE: this file contains the state for recording key presses and playing them back later
+
+/// longest sequence of keys that can be recorded into a single macro
+pub const MAX_MACRO_LENGTH: usize = 64;
+
+/// number of function keys that macros can be bound to, starting from F1
+pub const MACRO_SLOTS: u8 = 12;
+
+#[derive(Default)]
+pub struct Macros {
+    /// keys pressed since recording started, or None if not recording
+    pub recording: Option<Vec<KeyEvent>>,
+    /// recorded sequences, by the number of the function key they are bound to
+    pub bound: HashMap<u8, Vec<KeyEvent>>,
+    /// keys of the macro being played that haven't been pressed yet, one per tick
+    pub playback: VecDeque<KeyEvent>,
+}
+
+impl Macros {
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.playback.is_empty()
+    }
+
+    /// adds a key to the macro being recorded.
+    /// returns false if the macro is already as long as it can get
+    pub fn record(&mut self, key: KeyEvent) -> bool {
+        match &mut self.recording {
+            Some(keys) if keys.len() < MAX_MACRO_LENGTH => {
+                keys.push(key);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// queues up the keys bound to a function key. returns false if nothing is bound to it
+    pub fn play(&mut self, slot: u8) -> bool {
+        match self.bound.get(&slot) {
+            Some(keys) if !keys.is_empty() => {
+                self.playback = keys.iter().copied().collect();
+                true
+            }
+            _ => false,
+        }
+    }
+}
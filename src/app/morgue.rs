@@ -0,0 +1,97 @@
+use color_eyre::Result;
+use std::{
+    fs::File,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{App, GameScreen, PLAYER};
+use crate::{
+    components::SLOT_ORDERING,
+    engine::{attack_dice, defense},
+    shop,
+};
+
+// NOTE: this file contains logic for writing character dumps when the run ends
+
+/// number of log messages to include at the bottom of the morgue file
+const MORGUE_LOG_LENGTH: usize = 15;
+
+impl App {
+    /// writes a summary of the run to a new morgue file, returning its name
+    pub fn write_morgue(&self) -> Result<String> {
+        let player = self.objects.get(&PLAYER).unwrap();
+        let fighter = player.fighter.as_ref().unwrap();
+
+        let mut lines = vec![
+            String::from("=== character dump ==="),
+            match player.class {
+                Some(class) => format!("{} the {}", player.name, class),
+                None => player.name.clone(),
+            },
+            match self.game_screen {
+                GameScreen::Victory => format!(
+                    "escaped the dungeon with the Amulet of Ages after {} turns",
+                    self.time / 100
+                ),
+                _ => match &self.death {
+                    Some(death) => format!("{} after {} turns", death, self.time / 100),
+                    None => format!(
+                        "died on dungeon level {} after {} turns",
+                        self.gamemap.level,
+                        self.time / 100
+                    ),
+                },
+            },
+            format!("kills: {}", self.kills),
+            format!("seed: {}", self.seed),
+            format!("gold: {}", shop::player_gold(self)),
+            String::new(),
+            format!(
+                "HP {}/{}  ATK {}  DEF {}",
+                fighter.hp,
+                fighter.max_hp,
+                attack_dice(self, PLAYER),
+                defense(self, PLAYER)
+            ),
+            String::new(),
+            String::from("equipment:"),
+        ];
+        for (slot, item) in SLOT_ORDERING.iter().zip(self.equipment.iter()) {
+            let name = match item {
+                Some(id) => self.objects.get(id).unwrap().name.clone(),
+                None => String::from("-"),
+            };
+            lines.push(format!("    {:8} {}", slot, name));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("inventory:"));
+        for id in self.inventory.iter() {
+            lines.push(format!(
+                "    {}",
+                self.objects.get(id).unwrap().stack_name()
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("conduct:"));
+        for line in self.conduct.lines() {
+            lines.push(format!("    {}", line));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("last messages:"));
+        let skip = self.log.len().saturating_sub(MORGUE_LOG_LENGTH);
+        for entry in self.log.iter().skip(skip) {
+            lines.push(format!("    {}", entry.message));
+        }
+
+        // timestamp the file name so that runs don't overwrite each other
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let file_name = format!("morgue-{}.txt", timestamp);
+        let mut file = File::create(&file_name)?;
+        file.write_all(lines.join("\n").as_bytes())?;
+        Ok(file_name)
+    }
+}
@@ -1,11 +1,16 @@
+use std::collections::{BTreeMap, HashSet};
+
 use rand::Rng;
 use rand::distr::Distribution;
 use rand::distr::weighted::WeightedIndex;
+use rand::seq::SliceRandom;
 
 use crate::app::{Action, App, PLAYER};
-use crate::components::Object;
-use crate::gamemap::{GameMap, Tile, TileType};
-use crate::{entities, items, los};
+use crate::components::{AIType, Alertness, GuardPost, Loadout, Object, Pack, Position, Spell};
+use crate::data::{EntityData, EntityDef, SpawnWeight, weight_at};
+use crate::gamemap::{GameMap, LevelStats, Lock, TileType, coords_to_idx, idx_to_coords};
+use crate::quests::{self, Goal, Quest, QuestState};
+use crate::{entities, items, lighting, los, spells};
 
 struct RectangularRoom {
     x1: u16,
@@ -41,24 +46,199 @@ impl RectangularRoom {
     }
 }
 
-pub fn tunnel_between(start: (u16, u16), end: (u16, u16)) -> Vec<(u16, u16)> {
+/// a rectangular section of the map that the bsp generator keeps splitting in two
+#[derive(Clone, Copy)]
+struct Leaf {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+/// a part of a freshly generated floor that objects get spread across,
+/// such as a room, or a sector of a cave
+struct Area {
+    tiles: Vec<(u16, u16)>, // floor tiles that objects can be placed on
+    center: (u16, u16),     // floor tile for the player, stairs or a shopkeeper
+    tag: Option<AreaTag>,   // what the area was set aside for, if anything special
+}
+
+/// special purposes an area of the floor can be set aside for
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum AreaTag {
+    /// holds a shopkeeper, and no monsters
+    Shop,
+    /// holds extra loot, with guards posted at its entrances
+    Treasure,
+}
+
+impl Area {
+    fn from_room(room: &RectangularRoom) -> Self {
+        Self {
+            tiles: room.inner().collect(),
+            center: room.center(),
+            tag: None,
+        }
+    }
+
+    /// the center is the tile closest to the middle of the bounding box of the tiles
+    fn from_tiles(tiles: Vec<(u16, u16)>) -> Self {
+        let (min_x, max_x) = (
+            tiles.iter().map(|t| t.0).min(),
+            tiles.iter().map(|t| t.0).max(),
+        );
+        let (min_y, max_y) = (
+            tiles.iter().map(|t| t.1).min(),
+            tiles.iter().map(|t| t.1).max(),
+        );
+        let middle = (
+            (min_x.unwrap() + max_x.unwrap()) / 2,
+            (min_y.unwrap() + max_y.unwrap()) / 2,
+        );
+        let center = *tiles
+            .iter()
+            .min_by_key(|&&(x, y)| x.abs_diff(middle.0) + y.abs_diff(middle.1))
+            .unwrap();
+        Self {
+            tiles,
+            center,
+            tag: None,
+        }
+    }
+
+    /// returns the floor tiles just outside the area that lead into it,
+    /// such as the ends of corridors
+    fn entrances(&self, dungeon: &GameMap) -> Vec<(u16, u16)> {
+        let inside: HashSet<(u16, u16)> = self.tiles.iter().copied().collect();
+        let mut entrances = Vec::new();
+        for &(x, y) in self.tiles.iter() {
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (nx, ny) = (x as i16 + dx, y as i16 + dy);
+                if !dungeon.in_bounds(nx, ny) {
+                    continue;
+                }
+                let next = (nx as u16, ny as u16);
+                if !inside.contains(&next)
+                    && dungeon.get_ref(next.0, next.1).is_walkable()
+                    && !entrances.contains(&next)
+                {
+                    entrances.push(next);
+                }
+            }
+        }
+        entrances
+    }
+}
+
+/// counts the walls in the 8 tiles around a position. out of bounds counts as wall
+fn count_wall_neighbours(walls: &[bool], x: u16, y: u16, width: u16) -> usize {
+    let height = (walls.len() / width as usize) as i32;
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            let out_of_bounds = nx < 0 || ny < 0 || nx >= width as i32 || ny >= height;
+            if out_of_bounds || walls[coords_to_idx(nx as u16, ny as u16, width)] {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// flood fills every open region of the grid, returning the tiles of the largest one
+fn largest_open_region(walls: &[bool], width: u16, height: u16) -> Vec<(u16, u16)> {
+    let mut visited = vec![false; walls.len()];
+    let mut largest = Vec::new();
+
+    for start in 0..walls.len() {
+        if walls[start] || visited[start] {
+            continue;
+        }
+
+        let mut region = Vec::new();
+        let mut stack = vec![start];
+        visited[start] = true;
+        while let Some(idx) = stack.pop() {
+            let (x, y) = idx_to_coords(idx, width);
+            region.push((x, y));
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let next = coords_to_idx(nx as u16, ny as u16, width);
+                if !walls[next] && !visited[next] {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+
+    largest
+}
+
+/// flood fills the walkable tiles connected to start, returning which tiles were reached
+fn reachable_from(dungeon: &GameMap, start: Position) -> Vec<bool> {
+    let mut reached = vec![false; dungeon.tiles.len()];
+    let mut stack = vec![(start.x, start.y)];
+    reached[coords_to_idx(start.x, start.y, dungeon.width)] = true;
+    while let Some((x, y)) = stack.pop() {
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (nx, ny) = (x as i16 + dx, y as i16 + dy);
+            if !dungeon.in_bounds(nx, ny) {
+                continue;
+            }
+            let (nx, ny) = (nx as u16, ny as u16);
+            let idx = coords_to_idx(nx, ny, dungeon.width);
+            if !reached[idx] && dungeon.get_ref(nx, ny).is_walkable() {
+                reached[idx] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+    reached
+}
+
+/// turns the inside of an area into a chasm, leaving a ledge of floor around the edge
+/// so that anything connected to the area stays connected. the chasm tiles are removed
+/// from the area, so nothing gets placed on them
+fn carve_chasm(area: &mut Area, dungeon: &mut GameMap) {
+    let tiles: HashSet<(u16, u16)> = area.tiles.iter().copied().collect();
+    let is_inner = |&(x, y): &(u16, u16)| {
+        (-1..=1).all(|dy: i32| {
+            (-1..=1)
+                .all(|dx: i32| tiles.contains(&((x as i32 + dx) as u16, (y as i32 + dy) as u16)))
+        })
+    };
+
+    let (chasm, ledge): (Vec<_>, Vec<_>) = area.tiles.iter().partition(|tile| is_inner(tile));
+    for &(x, y) in chasm.iter() {
+        dungeon.set_tile_type(x, y, TileType::Chasm);
+    }
+    area.tiles = ledge;
+}
+
+pub fn tunnel_between(start: (u16, u16), end: (u16, u16), rng: &mut impl Rng) -> Vec<(u16, u16)> {
     // returns an L-shaped tunnel between these two points
 
     let (x1, y1) = (start.0 as i32, start.1 as i32);
     let (x2, y2) = (end.0 as i32, end.1 as i32);
 
-    let mut rng = rand::rng();
     let (corner_x, corner_y) = { if rng.random() { (x2, y1) } else { (x1, y2) } };
 
-    let seg_one: Vec<(u16, u16)> = los::bresenham((x1, y1), (corner_x, corner_y))
-        .iter()
-        .map(|&(x, y)| (x as u16, y as u16))
-        .collect();
-    let seg_two: Vec<(u16, u16)> = los::bresenham((corner_x, corner_y), (x2, y2))
-        .iter()
-        .map(|&(x, y)| (x as u16, y as u16))
-        .collect();
-    [seg_one, seg_two].concat()
+    los::LineIter::new((x1, y1), (corner_x, corner_y))
+        .chain(los::LineIter::new((corner_x, corner_y), (x2, y2)))
+        .map(|(x, y)| (x as u16, y as u16))
+        .collect()
 }
 
 pub struct DungeonConfig {
@@ -70,6 +250,18 @@ pub struct DungeonConfig {
     width: u16,
     height: u16,
     level: u16,
+    generator: Option<MapGenerator>, // picked based on the level if not set
+}
+
+/// the different algorithms that can lay out a floor
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MapGenerator {
+    /// rectangular rooms connected by corridors
+    Rooms,
+    /// open caverns grown with cellular automata
+    Caves,
+    /// rooms in a binary space partition, which never overlap and are all connected
+    Bsp,
 }
 
 impl DungeonConfig {
@@ -81,14 +273,32 @@ impl DungeonConfig {
             room_max_width: 25,
             room_min_height: 4,
             room_max_height: 7,
-            width: 80,
-            height: 24,
+            width: from_dungeon_level(MAP_WIDTH_TABLE, 1) as u16,
+            height: from_dungeon_level(MAP_HEIGHT_TABLE, 1) as u16,
             level: 1,
+            generator: None,
         }
     }
 
+    /// sets the level of the floor, along with a map size that grows with depth
     pub fn set_level(mut self, level: u16) -> Self {
         self.level = level;
+        self.width = from_dungeon_level(MAP_WIDTH_TABLE, level) as u16;
+        self.height = from_dungeon_level(MAP_HEIGHT_TABLE, level) as u16;
+        self
+    }
+
+    /// overrides the size of the map. should be called after set_level()
+    #[allow(dead_code)]
+    pub fn set_size(mut self, width: u16, height: u16) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn set_generator(mut self, generator: MapGenerator) -> Self {
+        self.generator = Some(generator);
         self
     }
 }
@@ -109,78 +319,258 @@ fn from_dungeon_level(table: &[Transition], level: u16) -> usize {
         .map_or(0, |transition| transition.value)
 }
 
-fn monster_table(level: u16) -> Vec<(fn() -> Object, usize)> {
-    let orc_weight = 80;
-    const RAT_WEIGHT_TABLE: &[Transition; 3] = &[
-        Transition {
-            level: 2,
-            value: 30,
-        },
-        Transition {
-            level: 3,
-            value: 50,
-        },
-        Transition {
-            level: 5,
-            value: 70,
-        },
-    ];
-    let rat_weight = from_dungeon_level(RAT_WEIGHT_TABLE, level);
+/// constructor for a spawnable object
+type Spawner = fn() -> Object;
 
-    const TROLL_WEIGHT_TABLE: &[Transition; 3] = &[
-        Transition {
-            level: 3,
-            value: 30,
-        },
-        Transition {
-            level: 5,
-            value: 45,
-        },
-        Transition {
-            level: 7,
-            value: 60,
-        },
+/// something a spawn table can pick. built in entities come from entities.rs and items.rs,
+/// the rest are described in the data file
+#[derive(Clone)]
+enum Spawn {
+    Builtin(Spawner),
+    Data(EntityDef),
+}
+
+impl Spawn {
+    fn spawn(&self) -> Object {
+        match self {
+            Spawn::Builtin(spawner) => spawner(),
+            Spawn::Data(def) => def.spawn(),
+        }
+    }
+}
+
+/// adds entities from the data file to a table of built in ones. a built in entity is
+/// left out if the data file has its own version of it, and otherwise spawns with its
+/// weights from the spawn tables
+fn with_data<'a>(
+    table: Vec<Spawner>,
+    weights: &BTreeMap<String, Vec<SpawnWeight>>,
+    defs: impl Iterator<Item = &'a EntityDef>,
+    data: &EntityData,
+    level: u16,
+) -> Vec<(Spawn, usize)> {
+    let builtin = table.into_iter().filter_map(|spawner| {
+        let name = spawner().name;
+        if data.overrides(&name) {
+            return None;
+        }
+        let weight = weights
+            .get(&name)
+            .map_or(0, |weights| weight_at(weights, level));
+        Some((Spawn::Builtin(spawner), weight))
+    });
+    let defined = defs.map(|def| (Spawn::Data(def.clone()), weight_at(&def.spawn, level)));
+    builtin.chain(defined).collect()
+}
+
+/// returns the monsters that can spawn on a level, built in and from the data file
+fn monster_spawns(data: &EntityData, level: u16) -> Vec<(Spawn, usize)> {
+    let weights = &data.spawn_tables.monsters;
+    with_data(monster_table(), weights, data.monsters(), data, level)
+}
+
+/// returns the items that can spawn on a level, built in and from the data file
+fn item_spawns(data: &EntityData, level: u16) -> Vec<(Spawn, usize)> {
+    let weights = &data.spawn_tables.items;
+    with_data(item_table(), weights, data.items(), data, level)
+}
+
+/// returns the traps that can spawn on a level. traps can't be defined in the data file,
+/// but their weights can be changed there
+fn trap_spawns(data: &EntityData, level: u16) -> Vec<(Spawn, usize)> {
+    let weights = &data.spawn_tables.traps;
+    with_data(trap_table(), weights, std::iter::empty(), data, level)
+}
+
+/// checks the spawn tables, returning a line for each problem. every name has to be a
+/// built in entity, every built in entity needs weights, and every level needs something
+/// to spawn
+pub fn spawn_table_problems(data: &EntityData) -> Vec<String> {
+    let mut problems = Vec::new();
+    let tables = [
+        (
+            "monsters",
+            &data.spawn_tables.monsters,
+            monster_table as fn() -> Vec<Spawner>,
+        ),
+        ("items", &data.spawn_tables.items, item_table),
+        ("traps", &data.spawn_tables.traps, trap_table),
     ];
-    let troll_weight = from_dungeon_level(TROLL_WEIGHT_TABLE, level);
+    for (kind, weights, table) in tables {
+        let names: Vec<String> = table().iter().map(|spawner| spawner().name).collect();
+        for name in names.iter().filter(|name| !weights.contains_key(*name)) {
+            problems.push(format!("spawn_tables.{kind} has no weights for {name}."));
+        }
+        for (name, weights) in weights {
+            if !names.contains(name) {
+                problems.push(format!("spawn_tables.{kind} has no built in {name}."));
+            } else if weights.is_empty() {
+                problems.push(format!("spawn_tables.{kind}.{name} has no weights."));
+            } else if !weights.is_sorted_by_key(|weight| weight.level) {
+                problems.push(format!(
+                    "spawn_tables.{kind}.{name} has levels out of order."
+                ));
+            }
+        }
+    }
+
+    // an empty table would leave the dungeon generator with nothing to pick
+    for level in 1..=FINAL_LEVEL {
+        let spawns = [
+            ("monsters", monster_spawns(data, level)),
+            ("items", item_spawns(data, level)),
+            ("traps", trap_spawns(data, level)),
+        ];
+        for (kind, spawns) in spawns {
+            if spawns.iter().all(|(_, weight)| *weight == 0) {
+                problems.push(format!("No {kind} can spawn on level {level}."));
+            }
+        }
+    }
+    problems
+}
+
+/// the gear a monster can spawn wearing. each table is rolled once,
+/// giving the monster at most one piece of gear from it
+fn loadout_tables(loadout: Loadout) -> Vec<Vec<(Option<Spawner>, usize)>> {
+    match loadout {
+        Loadout::Orc => vec![
+            vec![
+                (None, 50),
+                (Some(entities::weapon_axe), 30),
+                (Some(entities::weapon_dagger), 20),
+            ],
+            vec![(None, 80), (Some(entities::helmet), 20)],
+        ],
+        Loadout::Troll => vec![vec![(None, 70), (Some(entities::leather_armor), 30)]],
+    }
+}
 
+/// the built in monsters. how often each one spawns is in data/spawn_tables.json
+fn monster_table() -> Vec<Spawner> {
     vec![
-        (entities::orc, orc_weight),
-        (entities::rat, rat_weight),
-        (entities::troll, troll_weight),
+        entities::orc,
+        entities::rat,
+        entities::troll,
+        entities::spider,
+        entities::sentry,
+        entities::necromancer,
+        entities::rock_worm,
     ]
 }
 
-fn item_table(level: u16) -> Vec<(fn() -> Object, usize)> {
-    let potion_weight = 30;
+/// a group of monsters that spawns around a leader and hunts together
+struct PackSpawn {
+    leader: Spawner,
+    follower: Spawner,
+    min_followers: usize,
+    max_followers: usize,
+}
 
-    let lightning_weight = from_dungeon_level(
+fn pack_table(level: u16) -> Vec<(PackSpawn, usize)> {
+    let orc_weight = from_dungeon_level(
         &[Transition {
-            level: 2,
-            value: 15,
+            level: 4,
+            value: 30,
         }],
         level,
     );
+    vec![
+        (
+            PackSpawn {
+                leader: entities::jackal,
+                follower: entities::jackal,
+                min_followers: 2,
+                max_followers: 4,
+            },
+            60,
+        ),
+        (
+            PackSpawn {
+                leader: entities::orc,
+                follower: entities::orc,
+                min_followers: 1,
+                max_followers: 3,
+            },
+            orc_weight,
+        ),
+    ]
+}
 
-    let hexbolt_weight = 30;
-
-    let dagger_weight = 5;
-    let longsword_weight = from_dungeon_level(&[Transition { level: 4, value: 5 }], level);
-    let helmet_weight = from_dungeon_level(&[Transition { level: 3, value: 5 }], level);
-    let leather_weight = from_dungeon_level(&[Transition { level: 2, value: 5 }], level);
-    let plate_weight = from_dungeon_level(&[Transition { level: 5, value: 5 }], level);
+/// the built in traps. how often each one spawns is in data/spawn_tables.json
+fn trap_table() -> Vec<Spawner> {
+    vec![
+        entities::spike_trap,
+        entities::alarm_trap,
+        entities::teleport_trap,
+    ]
+}
 
+/// the built in items. how often each one spawns is in data/spawn_tables.json
+fn item_table() -> Vec<Spawner> {
     vec![
-        (items::potion_cure_wounds, potion_weight),
-        (items::scroll_lightning, lightning_weight),
-        (items::scroll_hexbolt, hexbolt_weight),
-        (entities::weapon_dagger, dagger_weight),
-        (entities::weapon_longsword, longsword_weight),
-        (entities::helmet, helmet_weight),
-        (entities::leather_armor, leather_weight),
-        (entities::plate_armor, plate_weight),
+        items::potion_cure_wounds,
+        items::scroll_lightning,
+        items::scroll_hexbolt,
+        items::scroll_fireball,
+        items::scroll_charm,
+        items::potion_haste,
+        items::scroll_time_warp,
+        items::scroll_enchant,
+        items::scroll_identify,
+        items::scroll_remove_curse,
+        items::rope,
+        lighting::lantern,
+        items::pickaxe,
+        items::wand_of_digging,
+        items::flask_of_poison_gas,
+        items::smoke_bomb,
+        entities::weapon_dagger,
+        entities::weapon_longsword,
+        entities::helmet,
+        entities::leather_armor,
+        entities::plate_armor,
+        entities::weapon_greatsword,
+        entities::shield,
+        entities::ring_protection,
+        || spells::spellbook(Spell::Hexbolt),
+        || spells::spellbook(Spell::Heal),
+        || spells::spellbook(Spell::Lightning),
+        || spells::spellbook(Spell::Haste),
+        || spells::spellbook(Spell::Charm),
     ]
 }
 
+/// deeper floors are bigger. the camera scrolls the map if it doesn't fit on screen
+const MAP_WIDTH_TABLE: &[Transition; 3] = &[
+    Transition {
+        level: 1,
+        value: 80,
+    },
+    Transition {
+        level: 4,
+        value: 100,
+    },
+    Transition {
+        level: 7,
+        value: 120,
+    },
+];
+const MAP_HEIGHT_TABLE: &[Transition; 3] = &[
+    Transition {
+        level: 1,
+        value: 24,
+    },
+    Transition {
+        level: 4,
+        value: 30,
+    },
+    Transition {
+        level: 7,
+        value: 36,
+    },
+];
+
 const MAX_MONSTERS_TABLE: &[Transition; 3] = &[
     Transition { level: 1, value: 2 },
     Transition { level: 4, value: 3 },
@@ -192,132 +582,1221 @@ const MAX_ITEMS_TABLE: &[Transition; 2] = &[
     Transition { level: 3, value: 2 },
 ];
 
+/// most hidden traps that can be placed on a whole floor
+const MAX_TRAPS_TABLE: &[Transition; 4] = &[
+    Transition { level: 1, value: 1 },
+    Transition { level: 3, value: 2 },
+    Transition { level: 5, value: 3 },
+    Transition { level: 7, value: 4 },
+];
+
+/// time between wandering monster spawns. deeper levels repopulate faster
+const WANDERER_INTERVAL_TABLE: &[Transition; 3] = &[
+    Transition {
+        level: 1,
+        value: 3000,
+    },
+    Transition {
+        level: 3,
+        value: 2000,
+    },
+    Transition {
+        level: 5,
+        value: 1500,
+    },
+];
+
+/// maximum number of wandering monsters that can spawn on a single floor
+const MAX_WANDERERS_TABLE: &[Transition; 3] = &[
+    Transition { level: 1, value: 3 },
+    Transition { level: 4, value: 5 },
+    Transition { level: 6, value: 8 },
+];
+
+/// percent chance for each area to have torches on its walls, past the first area,
+/// which is always lit. deeper floors are darker
+const LIT_AREA_TABLE: &[Transition; 3] = &[
+    Transition {
+        level: 1,
+        value: 90,
+    },
+    Transition {
+        level: 4,
+        value: 70,
+    },
+    Transition {
+        level: 7,
+        value: 50,
+    },
+];
+
+/// areas with at least this many floor tiles get a second torch
+const TWO_TORCH_AREA_SIZE: usize = 40;
+
+/// most glowing mushrooms that can grow on a floor. caves grow more of them
+const MAX_MUSHROOMS: usize = 2;
+const CAVE_MAX_MUSHROOMS: usize = 8;
+
+/// chance for each area between the first and last to get a patch of water, rubble, grass
+/// or lava
+const TERRAIN_CHANCE: f64 = 0.3;
+/// patches of terrain cover at most this fraction of their area, and this many tiles
+const TERRAIN_AREA_FRACTION: usize = 3;
+const TERRAIN_MAX_SIZE: usize = 24;
+/// lava can appear from this level onwards
+const LAVA_MIN_LEVEL: u16 = 4;
+
+/// shops can appear from this level onwards, with the given chance per floor
+const SHOP_MIN_LEVEL: u16 = 2;
+const SHOP_CHANCE: f64 = 0.3;
+const SHOP_STOCK_SIZE: usize = 5;
+
+/// chance for a monster placed during generation to start out asleep
+const SLEEP_CHANCE: f64 = 0.4;
+
+/// chance that equipment found on the floor is cursed
+const CURSED_EQUIPMENT_CHANCE: f64 = 0.15;
+
+/// caves can appear from this level onwards, with the given chance per floor
+const CAVE_MIN_LEVEL: u16 = 3;
+const CAVE_CHANCE: f64 = 0.35;
+/// chance for each tile to start out as a wall before the cave is smoothed
+const CAVE_WALL_CHANCE: f64 = 0.45;
+const CAVE_SMOOTHING_STEPS: usize = 4;
+/// caves smaller than this percentage of the map get thrown away
+const CAVE_MIN_OPEN_PERCENT: usize = 35;
+const CAVE_ATTEMPTS: usize = 10;
+/// caves are split into sectors of this size for placing objects
+const CAVE_SECTOR_WIDTH: u16 = 16;
+const CAVE_SECTOR_HEIGHT: u16 = 8;
+const CAVE_MIN_SECTOR_SIZE: usize = 10;
+
+/// bsp floors can appear from this level onwards, with the given chance per floor
+const BSP_MIN_LEVEL: u16 = 2;
+const BSP_CHANCE: f64 = 0.35;
+/// leaves are only split if both halves would be at least this big
+const BSP_MIN_LEAF_WIDTH: u16 = 14;
+const BSP_MIN_LEAF_HEIGHT: u16 = 7;
+
+/// chasms can appear from this level onwards, with the given chance per floor.
+/// ropes start spawning on the same level, see data/spawn_tables.json
+const CHASM_MIN_LEVEL: u16 = 2;
+const CHASM_CHANCE: f64 = 0.25;
+
+/// the orc warlord can appear from this level onwards, guarding the stairs
+const WARLORD_MIN_LEVEL: u16 = 5;
+const WARLORD_CHANCE: f64 = 0.25;
+
+/// packs can appear from this level onwards, with the given chance per floor
+const PACK_MIN_LEVEL: u16 = 2;
+const PACK_CHANCE: f64 = 0.35;
+
+/// treasure rooms can appear from this level onwards, with the given chance per floor
+const TREASURE_MIN_LEVEL: u16 = 2;
+const TREASURE_CHANCE: f64 = 0.3;
+/// most extra items a treasure room holds, on top of the usual loot
+const TREASURE_EXTRA_ITEMS: usize = 4;
+/// most guards posted at the entrances of a treasure room
+const MAX_GUARDS: usize = 2;
+/// how far from their post guards will chase something
+const GUARD_LEASH: u16 = 5;
+
+/// locked vaults can appear from this level onwards, with the given chance per floor
+const VAULT_MIN_LEVEL: u16 = 3;
+const VAULT_CHANCE: f64 = 0.25;
+/// how many spots in the rock are tried for a vault before giving up
+const VAULT_ATTEMPTS: usize = 100;
+/// the size of a vault's floor, not counting its walls
+const VAULT_MIN_WIDTH: u16 = 3;
+const VAULT_MAX_WIDTH: u16 = 6;
+const VAULT_MIN_HEIGHT: u16 = 2;
+const VAULT_MAX_HEIGHT: u16 = 4;
+/// how far the corridor from a vault's door may run before it meets the rest of the floor
+const VAULT_CORRIDOR_MAX: u16 = 8;
+/// how many items a vault holds, and how much deeper than the floor they're rolled for
+const VAULT_ITEMS: usize = 4;
+const VAULT_LOOT_LEVELS: u16 = 2;
+/// chance that a monster carries the key, rather than it lying hidden in another area
+const VAULT_KEY_CARRIED_CHANCE: f64 = 0.5;
+
+const ADVENTURER_MIN_LEVEL: u16 = 2;
+const ADVENTURER_CHANCE: f64 = 0.2;
+/// the goal of a quest an npc hands out is put at least this far from the player
+const OFFERED_QUEST_MIN_DISTANCE: u16 = 10;
+
+/// the deepest level of the dungeon, where the lich guards the amulet
+pub const FINAL_LEVEL: u16 = 10;
+
+/// wandering monsters never spawn closer than this to the player
+const WANDERER_MIN_PLAYER_DISTANCE: u16 = 12;
+
+/// number of random tiles to try before giving up on placing a wandering monster
+const WANDERER_PLACEMENT_ATTEMPTS: usize = 100;
+
 impl App {
     /// replaces the current gamemap for the app with a new one
     pub fn generate_dungeon(&mut self, config: DungeonConfig) {
         let mut dungeon = GameMap::new(config.width, config.height, config.level);
+
+        let generator = match config.generator {
+            Some(generator) => generator,
+            None => self.pick_generator(config.level),
+        };
+        let mut areas = match generator {
+            MapGenerator::Rooms => self.carve_rooms(&mut dungeon, &config),
+            MapGenerator::Bsp => {
+                let leaf = Leaf {
+                    x: 0,
+                    y: 0,
+                    width: config.width,
+                    height: config.height,
+                };
+                let rooms = self.carve_bsp(&mut dungeon, &config, leaf);
+                rooms.iter().map(Area::from_room).collect()
+            }
+            MapGenerator::Caves => match self.carve_caves(&mut dungeon) {
+                Some(areas) => areas,
+                None => {
+                    // couldn't grow a big enough cave, so fall back to rooms
+                    dungeon = GameMap::new(config.width, config.height, config.level);
+                    self.carve_rooms(&mut dungeon, &config)
+                }
+            },
+        };
+
+        // spawn player in the center of the first area
+        let (player_x, player_y) = areas.first().unwrap().center;
+        dungeon.place_blocker(PLAYER, player_x, player_y);
+
+        // spawn the stairs in the center of the last area.
+        // the final floor has the amulet there instead, and the way out under the player
+        let (stairs_x, stairs_y) = areas.last().unwrap().center;
+        if dungeon.level >= FINAL_LEVEL {
+            let up_stairs_id = self.objects.add(entities::up_stairs());
+            dungeon.place_item(up_stairs_id, player_x, player_y);
+            let amulet_id = self.objects.add(entities::amulet());
+            dungeon.place_item(amulet_id, stairs_x, stairs_y);
+        } else {
+            let stairs_id = self.objects.add(entities::stairs());
+            dungeon.place_item(stairs_id, stairs_x, stairs_y);
+        }
+
+        // some floors have a shop in one of the areas between the first and last
+        if dungeon.level >= SHOP_MIN_LEVEL && areas.len() > 2 && self.rng.random_bool(SHOP_CHANCE) {
+            let idx = self.rng.random_range(1..areas.len() - 1);
+            self.place_shop(&areas[idx], &mut dungeon);
+            areas[idx].tag = Some(AreaTag::Shop);
+        }
+
+        // and some have a treasure room, watched over by guards
+        if dungeon.level >= TREASURE_MIN_LEVEL
+            && areas.len() > 2
+            && self.rng.random_bool(TREASURE_CHANCE)
+        {
+            let idx = self.rng.random_range(1..areas.len() - 1);
+            if areas[idx].tag.is_none() {
+                areas[idx].tag = Some(AreaTag::Treasure);
+            }
+        }
+
+        // some floors built out of rooms have a chasm in one of the areas between the first
+        // and last. cave sectors aren't convex, so hollowing them out could cut the cave apart
+        if generator != MapGenerator::Caves
+            && dungeon.level >= CHASM_MIN_LEVEL
+            && areas.len() > 2
+            && self.rng.random_bool(CHASM_CHANCE)
+        {
+            let idx = self.rng.random_range(1..areas.len() - 1);
+            if areas[idx].tag.is_none() {
+                carve_chasm(&mut areas[idx], &mut dungeon);
+            }
+        }
+
+        // pools of water, heaps of rubble, patches of grass and lakes of lava break up some
+        // of the areas between the first and last. lava could cut a cave sector in two,
+        // so it stays in the middle of rooms
+        for idx in 1..areas.len().saturating_sub(1) {
+            if areas[idx].tag.is_none() && self.rng.random_bool(TERRAIN_CHANCE) {
+                let lava = generator != MapGenerator::Caves && dungeon.level >= LAVA_MIN_LEVEL;
+                self.paint_terrain(&mut areas[idx], &mut dungeon, lava);
+            }
+        }
+
+        // the lich waits on the amulet for the player to come to it
+        if dungeon.level >= FINAL_LEVEL {
+            let lich_id = self.objects.add(entities::lich());
+            dungeon.place_blocker(lich_id, stairs_x, stairs_y);
+            self.action_queue.push(Action {
+                time: self.time + 100,
+                id: lich_id,
+            });
+        }
+
+        // some deeper floors have a boss guarding the stairs
+        if dungeon.level >= WARLORD_MIN_LEVEL
+            && dungeon.level < FINAL_LEVEL
+            && areas.len() > 1
+            && self.rng.random_bool(WARLORD_CHANCE)
+        {
+            let area = areas.last().unwrap();
+            let (x, y) = area.tiles[self.rng.random_range(0..area.tiles.len())];
+            if dungeon.get_ref(x, y).blocker.is_none() {
+                let mut warlord = entities::orc_warlord();
+                self.equip_monster(&mut warlord);
+                let warlord_id = self.objects.add(warlord);
+                dungeon.place_blocker(warlord_id, x, y);
+                self.action_queue.push(Action {
+                    time: self.time + 100,
+                    id: warlord_id,
+                });
+            }
+        }
+
+        // hidden traps are scattered around, away from the player's starting area
+        if areas.len() > 1 {
+            self.place_traps(&areas[1..], &mut dungeon);
+        }
+
+        // a pack roams one of the areas away from the player's starting area
+        if config.level >= PACK_MIN_LEVEL && areas.len() > 1 && self.rng.random_bool(PACK_CHANCE) {
+            let area = &areas[self.rng.random_range(1..areas.len())];
+            if area.tag != Some(AreaTag::Shop) {
+                self.place_pack(area, &mut dungeon);
+            }
+        }
+
+        // a lost adventurer sometimes waits in one of the areas between the first and last,
+        // with a quest to hand out
+        if dungeon.level >= ADVENTURER_MIN_LEVEL
+            && dungeon.level < FINAL_LEVEL
+            && areas.len() > 2
+            && self.rng.random_bool(ADVENTURER_CHANCE)
+        {
+            let idx = self.rng.random_range(1..areas.len() - 1);
+            let (x, y) = areas[idx].center;
+            let tile = dungeon.get_ref(x, y);
+            if areas[idx].tag.is_none()
+                && tile.tile_type == TileType::Floor
+                && tile.blocker.is_none()
+            {
+                let goal = match self.rng.random_bool(0.5) {
+                    true => Goal::Slay,
+                    false => Goal::Retrieve,
+                };
+                let adventurer_id = self.objects.add(entities::lost_adventurer(goal));
+                dungeon.place_blocker(adventurer_id, x, y);
+            }
+        }
+
+        // generate contents in each area
+        // NOTE: this step happens last to ensure player and
+        // stairs have priority on where they get placed
+        for area in areas.iter() {
+            // loot tables for monsters and items
+            let max_monsters = from_dungeon_level(MAX_MONSTERS_TABLE, dungeon.level);
+            let max_items = from_dungeon_level(MAX_ITEMS_TABLE, dungeon.level);
+
+            let monsters = monster_spawns(&self.entity_data, dungeon.level);
+            let items = item_spawns(&self.entity_data, dungeon.level);
+
+            // add these items to the gamemap. monsters stay out of shops
+            if area.tag != Some(AreaTag::Shop) {
+                self.place_objects(area, &mut dungeon, &monsters, max_monsters, false);
+            }
+            self.place_objects(area, &mut dungeon, &items, max_items, true);
+
+            if area.tag == Some(AreaTag::Treasure) {
+                self.place_objects(area, &mut dungeon, &items, TREASURE_EXTRA_ITEMS, true);
+                self.place_guards(area, &mut dungeon, &monsters);
+            }
+        }
+
+        // some floors have a locked vault dug out of the rock, full of better loot.
+        // NOTE: this happens after the other areas are filled, so that a monster can
+        // be picked to carry the key
+        if dungeon.level >= VAULT_MIN_LEVEL
+            && dungeon.level < FINAL_LEVEL
+            && self.rng.random_bool(VAULT_CHANCE)
+        {
+            self.place_vault(&areas, &mut dungeon, (stairs_x, stairs_y));
+        }
+
+        // torches light up most areas, and mushrooms glow here and there in the dark
+        self.place_lights(&areas, &mut dungeon, generator);
+
+        // the floor's spawner acts through the action queue like any monster,
+        // and periodically repopulates the floor
+        let spawner_id = self.objects.add(entities::monster_spawner());
+        self.action_queue.push(Action {
+            time: self.time + from_dungeon_level(WANDERER_INTERVAL_TABLE, dungeon.level) as u64,
+            id: spawner_id,
+        });
+
+        dungeon.stats = LevelStats {
+            start_time: self.time,
+            start_kills: self.kills,
+            items_found: 0,
+        };
+        self.gamemap = dungeon;
+
+        // the floor a quest was handed out for holds its captain or idol, away from the
+        // area the player starts in.
+        // NOTE: this happens once the floor is in place, the same way an npc hands out a
+        // quest on the floor the player is already on
+        let level = self.gamemap.level;
+        if let Some(idx) = self
+            .quests
+            .iter()
+            .position(|quest| quest.level == level && quest.state == QuestState::Waiting)
+        {
+            let tiles = areas
+                .iter()
+                .skip(1)
+                .filter(|area| area.tag != Some(AreaTag::Shop))
+                .flat_map(|area| area.tiles.iter().copied());
+            let spots = self.quest_spots(tiles);
+            self.place_quest_goal(idx, &spots);
+        }
+    }
+
+    /// picks which generator to build a floor with. the first floor is always
+    /// rooms and corridors, deeper floors are sometimes bsp rooms or caves instead
+    fn pick_generator(&mut self, level: u16) -> MapGenerator {
+        if level >= CAVE_MIN_LEVEL && self.rng.random_bool(CAVE_CHANCE) {
+            MapGenerator::Caves
+        } else if level >= BSP_MIN_LEVEL && self.rng.random_bool(BSP_CHANCE) {
+            MapGenerator::Bsp
+        } else {
+            MapGenerator::Rooms
+        }
+    }
+
+    /// digs out randomly placed rectangular rooms, each connected to the previous one
+    /// by an L-shaped tunnel. returns the rooms in the order they were dug
+    fn carve_rooms(&mut self, dungeon: &mut GameMap, config: &DungeonConfig) -> Vec<Area> {
         let mut rooms: Vec<RectangularRoom> = Vec::new();
 
-        let mut rng = rand::rng();
         for _ in 0..config.max_rooms {
-            let room_width = rng.random_range(config.room_min_width..=config.room_max_width);
-            let room_height = rng.random_range(config.room_min_height..=config.room_max_height);
+            let room_width = self
+                .rng
+                .random_range(config.room_min_width..=config.room_max_width);
+            let room_height = self
+                .rng
+                .random_range(config.room_min_height..=config.room_max_height);
 
-            let x = rng.random_range(0..dungeon.width - room_width);
-            let y = rng.random_range(0..dungeon.height - room_height);
+            let x = self.rng.random_range(0..dungeon.width - room_width);
+            let y = self.rng.random_range(0..dungeon.height - room_height);
 
             let new_room = RectangularRoom::new(x, y, room_width, room_height);
 
             // break if the new room intersects with a previous room
-            let has_intersection = rooms
-                .iter()
-                .fold(false, |b, room| b || room.intersects(&new_room));
+            let has_intersection = rooms.iter().any(|room| room.intersects(&new_room));
             if has_intersection {
                 continue;
             }
 
             // dig out the room's inner area
             for (x, y) in new_room.inner() {
-                *dungeon.get_mut(x, y) = Tile::new(TileType::Floor);
+                dungeon.set_tile_type(x, y, TileType::Floor);
             }
 
             if !rooms.is_empty() {
                 // dig tunnel between current room and previous
-                for (x, y) in tunnel_between(rooms.last().unwrap().center(), new_room.center()) {
-                    *dungeon.get_mut(x, y) = Tile::new(TileType::Floor);
+                for (x, y) in tunnel_between(
+                    rooms.last().unwrap().center(),
+                    new_room.center(),
+                    &mut self.rng,
+                ) {
+                    dungeon.set_tile_type(x, y, TileType::Floor);
                 }
             }
 
             rooms.push(new_room);
         }
 
-        // spawn player in the center of the first room
-        let first_room = rooms.first().unwrap();
-        let (player_x, player_y) = first_room.center();
-        dungeon.place_blocker(PLAYER, player_x, player_y);
+        rooms.iter().map(Area::from_room).collect()
+    }
 
-        // spawn the stairs in the center of the last room
-        let last_room = rooms.last().unwrap();
-        let (stairs_x, stairs_y) = last_room.center();
-        let stairs_id = self.objects.add(entities::stairs());
-        dungeon.place_item(stairs_id, stairs_x, stairs_y);
+    /// recursively splits the leaf in two until the pieces get too small, then digs out
+    /// a room in each piece. the two halves of every split are joined by a tunnel, so
+    /// all rooms are connected. returns the rooms ordered from one corner to the other
+    fn carve_bsp(
+        &mut self,
+        dungeon: &mut GameMap,
+        config: &DungeonConfig,
+        leaf: Leaf,
+    ) -> Vec<RectangularRoom> {
+        let can_split_x = leaf.width >= BSP_MIN_LEAF_WIDTH * 2;
+        let can_split_y = leaf.height >= BSP_MIN_LEAF_HEIGHT * 2;
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => return vec![self.carve_leaf_room(dungeon, config, leaf)],
+            (true, false) => true,
+            (false, true) => false,
+            // tiles are about twice as tall as they are wide, so compare against double the height
+            (true, true) => leaf.width > leaf.height * 2 || self.rng.random(),
+        };
 
-        // generate contents in rooms
-        // NOTE: this step happens last to ensure player and
-        // stairs have priority on where they get placed
-        for room in &rooms {
-            // loot tables for monsters and items
-            let max_monsters = from_dungeon_level(MAX_MONSTERS_TABLE, dungeon.level);
-            let max_items = from_dungeon_level(MAX_ITEMS_TABLE, dungeon.level);
+        let (first, second) = if split_x {
+            let at = self
+                .rng
+                .random_range(BSP_MIN_LEAF_WIDTH..=leaf.width - BSP_MIN_LEAF_WIDTH);
+            (
+                Leaf { width: at, ..leaf },
+                Leaf {
+                    x: leaf.x + at,
+                    width: leaf.width - at,
+                    ..leaf
+                },
+            )
+        } else {
+            let at = self
+                .rng
+                .random_range(BSP_MIN_LEAF_HEIGHT..=leaf.height - BSP_MIN_LEAF_HEIGHT);
+            (
+                Leaf { height: at, ..leaf },
+                Leaf {
+                    y: leaf.y + at,
+                    height: leaf.height - at,
+                    ..leaf
+                },
+            )
+        };
 
-            let monsters = monster_table(dungeon.level);
-            let items = item_table(dungeon.level);
+        let mut rooms = self.carve_bsp(dungeon, config, first);
+        let second_rooms = self.carve_bsp(dungeon, config, second);
 
-            // add these items to the gamemap
-            self.place_objects(&room, &mut dungeon, &monsters, max_monsters, false);
-            self.place_objects(&room, &mut dungeon, &items, max_items, true);
+        // join the closest rooms of each half
+        for (x, y) in tunnel_between(
+            rooms.last().unwrap().center(),
+            second_rooms.first().unwrap().center(),
+            &mut self.rng,
+        ) {
+            dungeon.set_tile_type(x, y, TileType::Floor);
         }
 
-        self.gamemap = dungeon;
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// digs out a randomly sized room that fits inside the leaf, leaving a gap
+    /// to the rooms in neighbouring leaves
+    fn carve_leaf_room(
+        &mut self,
+        dungeon: &mut GameMap,
+        config: &DungeonConfig,
+        leaf: Leaf,
+    ) -> RectangularRoom {
+        let max_width = config.room_max_width.min(leaf.width - 1);
+        let max_height = config.room_max_height.min(leaf.height - 1);
+        let room_width = self
+            .rng
+            .random_range(config.room_min_width.min(max_width)..=max_width);
+        let room_height = self
+            .rng
+            .random_range(config.room_min_height.min(max_height)..=max_height);
+
+        let x = self
+            .rng
+            .random_range(leaf.x..=leaf.x + leaf.width - 1 - room_width);
+        let y = self
+            .rng
+            .random_range(leaf.y..=leaf.y + leaf.height - 1 - room_height);
+
+        let room = RectangularRoom::new(x, y, room_width, room_height);
+        for (x, y) in room.inner() {
+            dungeon.set_tile_type(x, y, TileType::Floor);
+        }
+        room
+    }
+
+    /// grows a cave with cellular automata, keeping only its largest connected part.
+    /// the cave is split into sectors, ordered from left to right, so the player
+    /// and stairs end up on opposite sides. returns None if the cave came out too small
+    fn carve_caves(&mut self, dungeon: &mut GameMap) -> Option<Vec<Area>> {
+        let (width, height) = (dungeon.width, dungeon.height);
+        let is_border = |x: u16, y: u16| x == 0 || y == 0 || x == width - 1 || y == height - 1;
+
+        for _ in 0..CAVE_ATTEMPTS {
+            // start from random noise, with solid walls around the border
+            let mut walls: Vec<bool> = (0..width as usize * height as usize)
+                .map(|idx| {
+                    let (x, y) = idx_to_coords(idx, width);
+                    is_border(x, y) || self.rng.random_bool(CAVE_WALL_CHANCE)
+                })
+                .collect();
+
+            // each step, a tile follows what most of its neighbours are, or stays the same on a tie
+            for _ in 0..CAVE_SMOOTHING_STEPS {
+                walls = (0..walls.len())
+                    .map(|idx| {
+                        let (x, y) = idx_to_coords(idx, width);
+                        match count_wall_neighbours(&walls, x, y, width) {
+                            _ if is_border(x, y) => true,
+                            5.. => true,
+                            4 => walls[idx],
+                            _ => false,
+                        }
+                    })
+                    .collect();
+            }
+
+            // keep the largest region, so every part of the cave is reachable
+            let region = largest_open_region(&walls, width, height);
+            if region.len() < walls.len() * CAVE_MIN_OPEN_PERCENT / 100 {
+                continue;
+            }
+            for &(x, y) in region.iter() {
+                dungeon.set_tile_type(x, y, TileType::Floor);
+            }
+
+            // group the floor into sectors that objects get spread across
+            let sectors_x = width.div_ceil(CAVE_SECTOR_WIDTH);
+            let sectors_y = height.div_ceil(CAVE_SECTOR_HEIGHT);
+            let mut sectors = vec![Vec::new(); (sectors_x * sectors_y) as usize];
+            for &(x, y) in region.iter() {
+                let sector = (x / CAVE_SECTOR_WIDTH) * sectors_y + y / CAVE_SECTOR_HEIGHT;
+                sectors[sector as usize].push((x, y));
+            }
+
+            let areas: Vec<Area> = sectors
+                .into_iter()
+                .filter(|tiles| tiles.len() >= CAVE_MIN_SECTOR_SIZE)
+                .map(Area::from_tiles)
+                .collect();
+            if areas.len() >= 2 {
+                return Some(areas);
+            }
+
+            // not enough space to put the player and stairs apart, try again
+            for &(x, y) in region.iter() {
+                dungeon.set_tile_type(x, y, TileType::Wall);
+            }
+        }
+
+        None
+    }
+
+    /// takes a turn for the floor's monster spawner, spawning a wandering monster
+    /// if the floor isn't full yet. returns the time until the spawner acts again.
+    /// floors aren't kept once the player leaves them, so this only ever repopulates the
+    /// current floor; respawning on revisited floors waits on floors being persistent
+    pub fn handle_spawner_turn(&mut self) -> u64 {
+        let level = self.gamemap.level;
+        let max_wanderers = from_dungeon_level(MAX_WANDERERS_TABLE, level);
+
+        if self.gamemap.wanderers_spawned < max_wanderers && self.place_wandering_monster() {
+            self.gamemap.wanderers_spawned += 1;
+        }
+
+        from_dungeon_level(WANDERER_INTERVAL_TABLE, level) as u64
+    }
+
+    /// places a random monster from the monster table on a walkable tile the player can't see,
+    /// away from the player so they don't get ambushed out of nowhere.
+    /// returns false if no suitable tile was found
+    fn place_wandering_monster(&mut self) -> bool {
+        let monsters = monster_spawns(&self.entity_data, self.gamemap.level);
+        let dist = WeightedIndex::new(monsters.iter().map(|x| x.1)).unwrap();
+        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
+
+        for _ in 0..WANDERER_PLACEMENT_ATTEMPTS {
+            let x = self.rng.random_range(0..self.gamemap.width);
+            let y = self.rng.random_range(0..self.gamemap.height);
+
+            let tile = self.gamemap.get_ref(x, y);
+            if !tile.is_walkable() || tile.blocker.is_some() || self.gamemap.is_visible(x, y) {
+                continue;
+            }
+
+            let player_dist = player_pos.x.abs_diff(x).max(player_pos.y.abs_diff(y));
+            if player_dist < WANDERER_MIN_PLAYER_DISTANCE {
+                continue;
+            }
+
+            let spawn = &monsters[dist.sample(&mut self.rng)].0;
+            let object_id = self.add_spawned(spawn, false);
+            self.gamemap.place_blocker(object_id, x, y);
+            return true;
+        }
+
+        false
+    }
+
+    /// rolls the gear for a freshly spawned monster, based on its loadout
+    fn equip_monster(&mut self, monster: &mut Object) {
+        let Some(loadout) = monster.loadout else {
+            return;
+        };
+
+        for table in loadout_tables(loadout) {
+            let dist = WeightedIndex::new(table.iter().map(|x| x.1)).unwrap();
+            if let Some(spawner) = table[dist.sample(&mut self.rng)].0 {
+                let item_id = self.objects.add(spawner());
+                monster.equipped.push(item_id);
+            }
+        }
+    }
+
+    /// equipment found lying around is unidentified, and is sometimes cursed
+    fn disguise_equipment(&mut self, object: &mut Object) {
+        let Some(equip) = &mut object.equipment else {
+            return;
+        };
+        equip.unidentified = true;
+        if self.rng.random_bool(CURSED_EQUIPMENT_CHANCE) {
+            equip.cursed = true;
+            equip.enchantment -= self.rng.random_range(1..=2);
+        }
+    }
+
+    /// places a shopkeeper in the center of the area, stocked with items for this level
+    fn place_shop(&mut self, area: &Area, dungeon: &mut GameMap) {
+        let items = item_spawns(&self.entity_data, dungeon.level);
+        let dist = WeightedIndex::new(items.iter().map(|x| x.1)).unwrap();
+
+        let stock = (0..SHOP_STOCK_SIZE)
+            .map(|_| {
+                self.objects
+                    .add(items[dist.sample(&mut self.rng)].0.spawn())
+            })
+            .collect();
+
+        let (x, y) = area.center;
+        let shopkeeper_id = self.objects.add(entities::shopkeeper(stock));
+        dungeon.place_blocker(shopkeeper_id, x, y);
+    }
+
+    /// digs a vault out of solid rock, with a single corridor leading out of it that is
+    /// closed off by a locked door. returns the vault and the position of its door, or None
+    /// if there was no room for one, or the corridor couldn't reach the player and stairs
+    fn carve_vault(
+        &mut self,
+        dungeon: &mut GameMap,
+        stairs: (u16, u16),
+    ) -> Option<(Area, Position)> {
+        let player = dungeon.get_position(PLAYER).unwrap();
+        for _ in 0..VAULT_ATTEMPTS {
+            let width = self.rng.random_range(VAULT_MIN_WIDTH..=VAULT_MAX_WIDTH) + 1;
+            let height = self.rng.random_range(VAULT_MIN_HEIGHT..=VAULT_MAX_HEIGHT) + 1;
+            if width + 2 >= dungeon.width || height + 2 >= dungeon.height {
+                return None;
+            }
+            let x = self.rng.random_range(1..dungeon.width - width - 1);
+            let y = self.rng.random_range(1..dungeon.height - height - 1);
+            let room = RectangularRoom::new(x, y, width, height);
+
+            // the vault and its walls have to fit in untouched rock
+            let solid = (room.y1..=room.y2).all(|y| {
+                (room.x1..=room.x2).all(|x| dungeon.get_ref(x, y).tile_type == TileType::Wall)
+            });
+            if !solid {
+                continue;
+            }
+
+            // the door sits in the middle of one of the walls, with a corridor running
+            // straight out from it until it meets open floor
+            let (center_x, center_y) = room.center();
+            let mut sides = [
+                ((center_x, room.y1), (0, -1)),
+                ((center_x, room.y2), (0, 1)),
+                ((room.x1, center_y), (-1, 0)),
+                ((room.x2, center_y), (1, 0)),
+            ];
+            sides.shuffle(&mut self.rng);
+            let found = sides.into_iter().find_map(|((door_x, door_y), (dx, dy))| {
+                let mut corridor = Vec::new();
+                for step in 1..=VAULT_CORRIDOR_MAX as i16 {
+                    let (cx, cy) = (door_x as i16 + dx * step, door_y as i16 + dy * step);
+                    // the edge of the map stays solid
+                    if cx <= 0
+                        || cy <= 0
+                        || cx >= dungeon.width as i16 - 1
+                        || cy >= dungeon.height as i16 - 1
+                    {
+                        return None;
+                    }
+                    let (cx, cy) = (cx as u16, cy as u16);
+                    if dungeon.get_ref(cx, cy).is_walkable() {
+                        return Some((
+                            Position {
+                                x: door_x,
+                                y: door_y,
+                            },
+                            corridor,
+                            (cx, cy),
+                        ));
+                    }
+                    if dungeon.get_ref(cx, cy).tile_type != TileType::Wall {
+                        return None;
+                    }
+                    corridor.push((cx, cy));
+                }
+                None
+            });
+            let Some((door, corridor, outside)) = found else {
+                continue;
+            };
+
+            // the corridor has to join up with the part of the floor the player starts in,
+            // and can't have cut anyone off from the stairs
+            for &(x, y) in room
+                .inner()
+                .collect::<Vec<_>>()
+                .iter()
+                .chain(corridor.iter())
+            {
+                dungeon.set_tile_type(x, y, TileType::Floor);
+            }
+            dungeon.set_tile_type(door.x, door.y, TileType::LockedDoor);
+            let reached = reachable_from(dungeon, player);
+            let reaches = |(x, y): (u16, u16)| reached[coords_to_idx(x, y, dungeon.width)];
+            if reaches(outside) && reaches(stairs) {
+                return Some((Area::from_room(&room), door));
+            }
+            for &(x, y) in room
+                .inner()
+                .collect::<Vec<_>>()
+                .iter()
+                .chain(corridor.iter())
+            {
+                dungeon.set_tile_type(x, y, TileType::Wall);
+            }
+            dungeon.set_tile_type(door.x, door.y, TileType::Wall);
+        }
+        None
+    }
+
+    /// fills a freshly dug vault with loot from deeper floors, and puts its key somewhere
+    /// the player can reach: in the hands of a monster, or hidden in another area
+    fn place_vault(&mut self, areas: &[Area], dungeon: &mut GameMap, stairs: (u16, u16)) {
+        let Some((vault, door)) = self.carve_vault(dungeon, stairs) else {
+            return;
+        };
+
+        let items = item_spawns(&self.entity_data, dungeon.level + VAULT_LOOT_LEVELS);
+        let dist = WeightedIndex::new(items.iter().map(|x| x.1)).unwrap();
+        let mut spots = vault.tiles.clone();
+        spots.shuffle(&mut self.rng);
+        for (x, y) in spots.into_iter().take(VAULT_ITEMS) {
+            let item_id = self
+                .objects
+                .add(items[dist.sample(&mut self.rng)].0.spawn());
+            dungeon.place_item(item_id, x, y);
+        }
+
+        let key_id = self.objects.add(items::vault_key());
+        dungeon.locks.push(Lock {
+            position: door,
+            key: key_id,
+        });
+
+        let player = dungeon.get_position(PLAYER).unwrap();
+        let reached = reachable_from(dungeon, player);
+        let reaches = |(x, y): (u16, u16)| reached[coords_to_idx(x, y, dungeon.width)];
+
+        // any ordinary monster the player can get to might be the one carrying the key
+        let mut carriers: Vec<usize> = areas
+            .iter()
+            .flat_map(|area| area.tiles.iter())
+            .filter(|&&tile| reaches(tile))
+            .filter_map(|&(x, y)| dungeon.get_ref(x, y).blocker)
+            .filter(|id| {
+                let obj = self.objects.get(id).unwrap();
+                matches!(obj.ai, Some(AIType::Melee(_))) && obj.boss.is_none()
+            })
+            .collect();
+        carriers.sort();
+        carriers.dedup();
+        if !carriers.is_empty() && self.rng.random_bool(VAULT_KEY_CARRIED_CHANCE) {
+            let carrier = carriers[self.rng.random_range(0..carriers.len())];
+            self.objects.get_mut(&carrier).unwrap().carried.push(key_id);
+            return;
+        }
+
+        // otherwise it's tucked away in an area other than the one the player starts in
+        let mut hiding_spots: Vec<(u16, u16)> = areas
+            .iter()
+            .skip(1)
+            .filter(|area| area.tag != Some(AreaTag::Shop))
+            .flat_map(|area| area.tiles.iter().copied())
+            .filter(|&(x, y)| reaches((x, y)) && dungeon.get_ref(x, y).item.is_none())
+            .collect();
+        if hiding_spots.is_empty() {
+            hiding_spots.push(areas[0].center);
+        }
+        let (x, y) = hiding_spots[self.rng.random_range(0..hiding_spots.len())];
+        dungeon.area_place_item(x, y, key_id, &mut self.rng);
+    }
+
+    /// returns the tiles out of a list where a quest's captain or idol could go: open
+    /// floor with nothing on it, that the player can walk to
+    fn quest_spots(&self, tiles: impl Iterator<Item = (u16, u16)>) -> Vec<(u16, u16)> {
+        let player = self.gamemap.get_position(PLAYER).unwrap();
+        let reached = reachable_from(&self.gamemap, player);
+        tiles
+            .filter(|&(x, y)| {
+                let tile = self.gamemap.get_ref(x, y);
+                reached[coords_to_idx(x, y, self.gamemap.width)]
+                    && matches!(tile.tile_type, TileType::Floor | TileType::Grass)
+                    && tile.blocker.is_none()
+                    && tile.item.is_none()
+            })
+            .collect()
+    }
+
+    /// puts the captain or idol of a quest on one of the spots, and sets the quest going.
+    /// returns false if there was nowhere to put it. see quests.rs
+    fn place_quest_goal(&mut self, idx: usize, spots: &[(u16, u16)]) -> bool {
+        if spots.is_empty() {
+            return false;
+        }
+        let (x, y) = spots[self.rng.random_range(0..spots.len())];
+
+        let level = self.gamemap.level;
+        let (target, name) = match self.quests[idx].goal {
+            Goal::Slay => {
+                let monsters = monster_spawns(&self.entity_data, level);
+                let dist = WeightedIndex::new(monsters.iter().map(|x| x.1)).unwrap();
+                let mut captain = monsters[dist.sample(&mut self.rng)].0.spawn();
+                captain.name = format!("{} Captain", captain.name);
+                if let Some(fighter) = &mut captain.fighter {
+                    fighter.max_hp *= quests::CAPTAIN_HP_MULTIPLIER;
+                    fighter.hp = fighter.max_hp;
+                    fighter.power += quests::CAPTAIN_POWER_BONUS;
+                }
+                self.equip_monster(&mut captain);
+                let name = captain.name.clone();
+                let captain_id = self.objects.add(captain);
+                self.gamemap.place_blocker(captain_id, x, y);
+                self.action_queue.push(Action {
+                    time: self.time + 100,
+                    id: captain_id,
+                });
+                (captain_id, name)
+            }
+            Goal::Retrieve => {
+                let idol = items::golden_idol();
+                let name = idol.name.clone();
+                let idol_id = self.objects.add(idol);
+                self.gamemap.place_item(idol_id, x, y);
+                (idol_id, name)
+            }
+        };
+
+        let quest = &mut self.quests[idx];
+        quest.target = Some(target);
+        quest.target_name = Some(name);
+        quest.state = QuestState::Active;
+        true
+    }
+
+    /// hands out a new quest on the floor the player is on, with its goal somewhere out
+    /// of the way. returns false if there was nowhere to put the goal
+    pub fn offer_quest(&mut self, goal: Goal) -> bool {
+        let player = self.gamemap.get_position(PLAYER).unwrap();
+        let tiles = (0..self.gamemap.tiles.len())
+            .map(|idx| idx_to_coords(idx, self.gamemap.width))
+            .filter(|&(x, y)| Position { x, y }.distance_to(player) >= OFFERED_QUEST_MIN_DISTANCE);
+        let spots = self.quest_spots(tiles);
+
+        self.quests.push(Quest {
+            goal,
+            level: self.gamemap.level,
+            state: QuestState::Waiting,
+            target: None,
+            target_name: None,
+        });
+        let placed = self.place_quest_goal(self.quests.len() - 1, &spots);
+        if !placed {
+            self.quests.pop();
+        }
+        placed
+    }
+
+    /// rolls an item from a level's spawn table, and returns its id
+    pub fn random_item(&mut self, level: u16) -> usize {
+        let items = item_spawns(&self.entity_data, level);
+        let dist = WeightedIndex::new(items.iter().map(|x| x.1)).unwrap();
+        let item = items[dist.sample(&mut self.rng)].0.spawn();
+        self.objects.add(item)
+    }
+
+    /// posts guards at the entrances of an area, or in its center if it has none
+    fn place_guards(&mut self, area: &Area, dungeon: &mut GameMap, monsters: &[(Spawn, usize)]) {
+        let dist = WeightedIndex::new(monsters.iter().map(|x| x.1)).unwrap();
+
+        let mut posts = area.entrances(dungeon);
+        posts.shuffle(&mut self.rng);
+        posts.truncate(MAX_GUARDS);
+        if posts.is_empty() {
+            posts.push(area.center);
+        }
+
+        for (x, y) in posts {
+            if dungeon.get_ref(x, y).blocker.is_some() {
+                continue;
+            }
+
+            let mut guard = monsters[dist.sample(&mut self.rng)].0.spawn();
+            self.equip_monster(&mut guard);
+            if let Some(AIType::Melee(ai_data)) = &mut guard.ai {
+                ai_data.post = Some(GuardPost {
+                    position: Position { x, y },
+                    leash: GUARD_LEASH,
+                });
+            }
+            let guard_id = self.objects.add(guard);
+            dungeon.place_blocker(guard_id, x, y);
+            self.action_queue.push(Action {
+                time: self.time + 100,
+                id: guard_id,
+            });
+        }
+    }
+
+    /// places a pack in an area, with its followers gathered as close to the leader as
+    /// there is room for. the whole pack sleeps or wakes together
+    fn place_pack(&mut self, area: &Area, dungeon: &mut GameMap) {
+        let table = pack_table(dungeon.level);
+        let dist = WeightedIndex::new(table.iter().map(|x| x.1)).unwrap();
+        let pack = &table[dist.sample(&mut self.rng)].0;
+
+        let mut free_tiles: Vec<(u16, u16)> = area
+            .tiles
+            .iter()
+            .copied()
+            .filter(|&(x, y)| dungeon.get_ref(x, y).blocker.is_none())
+            .collect();
+        if free_tiles.is_empty() {
+            return;
+        }
+        let (leader_x, leader_y) = free_tiles[self.rng.random_range(0..free_tiles.len())];
+        let leader_pos = Position {
+            x: leader_x,
+            y: leader_y,
+        };
+        free_tiles.sort_by_key(|&(x, y)| Position { x, y }.distance_to(leader_pos));
+
+        let followers = self
+            .rng
+            .random_range(pack.min_followers..=pack.max_followers);
+        let asleep = self.rng.random_bool(SLEEP_CHANCE);
+        let leader_id = self.objects.next_id();
+        for (i, &(x, y)) in free_tiles.iter().take(followers + 1).enumerate() {
+            let mut monster = match i {
+                0 => (pack.leader)(),
+                _ => (pack.follower)(),
+            };
+            self.equip_monster(&mut monster);
+            monster.pack = Some(Pack { leader: leader_id });
+            if let Some(AIType::Melee(ai_data)) = &mut monster.ai
+                && asleep
+            {
+                ai_data.alertness = Alertness::Sleeping;
+            }
+            let id = self.objects.add(monster);
+            dungeon.place_blocker(id, x, y);
+            self.action_queue.push(Action {
+                time: self.time + 100,
+                id,
+            });
+        }
+    }
+
+    /// puts torches on the walls of lit areas, and grows glowing mushrooms on the floor
+    fn place_lights(&mut self, areas: &[Area], dungeon: &mut GameMap, generator: MapGenerator) {
+        dungeon.lighting = true;
+
+        let lit_chance = from_dungeon_level(LIT_AREA_TABLE, dungeon.level) as f64 / 100.0;
+        for (idx, area) in areas.iter().enumerate() {
+            if idx > 0 && !self.rng.random_bool(lit_chance) {
+                continue;
+            }
+
+            // torches go on walls right next to the area's floor
+            let mut walls: Vec<(u16, u16)> = Vec::new();
+            for &(x, y) in area.tiles.iter() {
+                for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                    let (nx, ny) = (x as i16 + dx, y as i16 + dy);
+                    if !dungeon.in_bounds(nx, ny) {
+                        continue;
+                    }
+                    let wall = (nx as u16, ny as u16);
+                    let tile = dungeon.get_ref(wall.0, wall.1);
+                    if tile.tile_type == TileType::Wall
+                        && tile.item.is_none()
+                        && !walls.contains(&wall)
+                    {
+                        walls.push(wall);
+                    }
+                }
+            }
+
+            let torches = match area.tiles.len() >= TWO_TORCH_AREA_SIZE {
+                true => 2,
+                false => 1,
+            };
+            walls.shuffle(&mut self.rng);
+            for &(x, y) in walls.iter().take(torches) {
+                let torch_id = self.objects.add(lighting::torch());
+                dungeon.mount_on_wall(torch_id, x, y);
+            }
+        }
+
+        let max_mushrooms = match generator {
+            MapGenerator::Caves => CAVE_MAX_MUSHROOMS,
+            _ => MAX_MUSHROOMS,
+        };
+        for _ in 0..self.rng.random_range(0..=max_mushrooms) {
+            let area = &areas[self.rng.random_range(0..areas.len())];
+            let (x, y) = area.tiles[self.rng.random_range(0..area.tiles.len())];
+            if dungeon.get_ref(x, y).item.is_some() {
+                continue;
+            }
+            let mushroom_id = self.objects.add(lighting::glowing_mushroom());
+            dungeon.place_item(mushroom_id, x, y);
+        }
+    }
+
+    /// grows a patch of water, rubble, grass or lava somewhere in the area. lava only covers
+    /// tiles with floor all around them, like a chasm, and is removed from the area
+    /// so that nothing gets placed in it
+    fn paint_terrain(&mut self, area: &mut Area, dungeon: &mut GameMap, allow_lava: bool) {
+        let tiles: HashSet<(u16, u16)> = area.tiles.iter().copied().collect();
+        let is_inner = |&(x, y): &(u16, u16)| {
+            (-1..=1).all(|dy: i32| {
+                (-1..=1).all(|dx: i32| {
+                    tiles.contains(&((x as i32 + dx) as u16, (y as i32 + dy) as u16))
+                })
+            })
+        };
+
+        let mut kinds = vec![
+            (TileType::Water, 3),
+            (TileType::Rubble, 2),
+            (TileType::Grass, 3),
+        ];
+        if allow_lava {
+            kinds.push((TileType::Lava, 2));
+        }
+        let dist = WeightedIndex::new(kinds.iter().map(|kind| kind.1)).unwrap();
+        let kind = kinds[dist.sample(&mut self.rng)].0;
+
+        let candidates: HashSet<(u16, u16)> = match kind {
+            TileType::Lava => area.tiles.iter().copied().filter(|t| is_inner(t)).collect(),
+            _ => tiles.clone(),
+        };
+        let mut starts: Vec<(u16, u16)> = candidates.iter().copied().collect();
+        starts.sort();
+        if starts.is_empty() {
+            return;
+        }
+        let start = starts[self.rng.random_range(0..starts.len())];
+
+        // grow the patch outwards from the start, one random neighbour at a time
+        let size = (area.tiles.len() / TERRAIN_AREA_FRACTION).min(TERRAIN_MAX_SIZE);
+        let mut patch = vec![start];
+        for _ in 0..size * 4 {
+            if patch.len() >= size {
+                break;
+            }
+            let (x, y) = patch[self.rng.random_range(0..patch.len())];
+            let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][self.rng.random_range(0..4)];
+            let next = ((x as i32 + dx) as u16, (y as i32 + dy) as u16);
+            if candidates.contains(&next) && !patch.contains(&next) {
+                patch.push(next);
+            }
+        }
+
+        for &(x, y) in patch.iter() {
+            dungeon.set_tile_type(x, y, kind);
+        }
+        if kind == TileType::Lava {
+            area.tiles.retain(|tile| !patch.contains(tile));
+        }
+    }
+
+    /// hides a level-scaled number of traps in random areas of the floor
+    fn place_traps(&mut self, areas: &[Area], dungeon: &mut GameMap) {
+        let traps = trap_spawns(&self.entity_data, dungeon.level);
+        let dist = WeightedIndex::new(traps.iter().map(|x| x.1)).unwrap();
+
+        let max_traps = from_dungeon_level(MAX_TRAPS_TABLE, dungeon.level);
+        for _ in 0..self.rng.random_range(0..=max_traps) {
+            let area = &areas[self.rng.random_range(0..areas.len())];
+            let (x, y) = area.tiles[self.rng.random_range(0..area.tiles.len())];
+            if dungeon.get_ref(x, y).item.is_some() {
+                continue;
+            }
+
+            let trap = traps[dist.sample(&mut self.rng)].0.spawn();
+            let trap_id = self.objects.add(trap);
+            dungeon.place_item(trap_id, x, y);
+        }
     }
 
     fn place_objects(
         &mut self,
-        room: &RectangularRoom,
+        area: &Area,
         dungeon: &mut GameMap,
-        object_weights: &Vec<(fn() -> Object, usize)>,
+        object_weights: &[(Spawn, usize)],
         maximum_objects: usize,
         is_item: bool,
     ) {
-        let mut rng = rand::rng();
         let dist = WeightedIndex::new(object_weights.iter().map(|x| x.1)).unwrap();
 
-        let number_of_items = rng.random_range(0..=maximum_objects);
+        let number_of_items = self.rng.random_range(0..=maximum_objects);
         for _ in 0..number_of_items {
-            let x = rng.random_range((room.x1 + 1)..room.x2);
-            let y = rng.random_range((room.y1 + 1)..room.y2);
+            let (x, y) = area.tiles[self.rng.random_range(0..area.tiles.len())];
 
             // check if it intersects with any entities
             let tile = dungeon.get_ref(x, y);
             if is_item {
-                match tile.item {
-                    Some(_) => {
-                        continue;
-                    }
-                    None => {}
+                if tile.item.is_some() {
+                    continue;
                 }
             } else {
-                match tile.blocker {
-                    Some(_) => {
-                        continue;
-                    }
-                    None => {}
+                if tile.blocker.is_some() {
+                    continue;
                 }
             }
 
             // randomly select which object to spawn
-            let entity_callback = object_weights[dist.sample(&mut rng)].0;
-
-            let object = entity_callback();
-            let has_ai = object.ai.is_some();
-            let object_id = self.objects.add(object);
-
+            let spawn = &object_weights[dist.sample(&mut self.rng)].0;
+            let object_id = self.add_spawned(spawn, true);
             if is_item {
                 dungeon.place_item(object_id, x, y);
             } else {
                 dungeon.place_blocker(object_id, x, y);
             }
+        }
+    }
 
-            // objects with an AI component should be added into the action queue
-            if has_ai {
-                self.action_queue.push(Action {
-                    // NOTE: 100 is magic number to ensure monsters don't double act on the first turn
-                    // ideally we should add something to the effect of how long it takes the
-                    // monster to take an action
-                    time: self.time + 100,
-                    id: object_id,
-                });
-            }
+    /// creates an object from the spawn tables with its gear rolled, and puts it into the
+    /// action queue if it has an ai. monsters placed when the floor is built may start asleep
+    fn add_spawned(&mut self, spawn: &Spawn, may_sleep: bool) -> usize {
+        let mut object = spawn.spawn();
+        self.equip_monster(&mut object);
+        self.disguise_equipment(&mut object);
+        if let Some(AIType::Melee(ai_data)) = &mut object.ai
+            && may_sleep
+            && self.rng.random_bool(SLEEP_CHANCE)
+        {
+            ai_data.alertness = Alertness::Sleeping;
+        }
+        let has_ai = object.ai.is_some();
+        let object_id = self.objects.add(object);
+
+        // objects with an AI component should be added into the action queue
+        if has_ai {
+            self.action_queue.push(Action {
+                // NOTE: 100 is magic number to ensure monsters don't double act on the first turn
+                // ideally we should add something to the effect of how long it takes the
+                // monster to take an action
+                time: self.time + 100,
+                id: object_id,
+            });
         }
+        object_id
     }
 }
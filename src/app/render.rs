@@ -4,34 +4,78 @@ use ratatui::{
     Frame,
     buffer::Buffer,
     layout::{self, Constraint, Direction, Flex, Layout, Margin, Rect},
-    style::{Color, Style, Styled, Stylize},
-    text::Line,
-    widgets::{Block, Borders, Paragraph, Widget},
+    style::{Color, Modifier, Style, Styled, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Tabs, Widget, Wrap},
 };
 
-use super::{App, GameScreen, PLAYER};
+use super::{
+    App, GameScreen, LogEntry, MessageKind, PLAYER, macros::MACRO_SLOTS, saving::LoadError,
+};
 use crate::{
-    components::{Position, Renderable, SLOT_ORDERING},
-    engine::{TargetingMode, defense, power},
-    gamemap::{self, Tile, TileType, shroud_renderable},
-    los,
+    achievements::ACHIEVEMENTS,
+    animation::AnimationFrame,
+    components::{
+        AIType, Alertness, CLASS_ORDERING, MeleeAIData, Object, Position, Renderable,
+        SLOT_ORDERING, Slot, slot_key,
+    },
+    data::ENTITIES_FILE,
+    engine::{
+        TargetingMode, active_alarm, attack_dice, danger_map, defense, equip_delta, hit_chance,
+        hp_percent, is_charmed, known_item, target_problem, visible_monsters,
+    },
+    gamemap::{self, EffectKind, LevelSummary, Tile, TileType, shroud_renderable},
+    identify,
+    inventory::{self, Encumbrance},
+    keybindings::{self, CATEGORIES},
+    lighting, los, manual,
+    quests::QuestState,
+    scores,
+    settings::{self, SETTING_ROWS, SETTINGS_FILE},
+    shop, spells,
+    theme::ThemeColor,
 };
 
+/// number of lines on the help screen: a heading and a blank line around each category
+fn help_line_count() -> usize {
+    keybindings::KEY_BINDINGS.len() + 2 * CATEGORIES.len()
+}
+
+/// drawn in place of remembered floor tiles when the theme uses glyph accents
+const REMEMBERED_FLOOR_ACCENT: char = '·';
+
+/// how many cells wide the dialogue popup is, borders included
+const DIALOGUE_WIDTH: u16 = 56;
+
+/// how many cells wide the health bars over monsters are
+const MONSTER_HEALTH_BAR_WIDTH: u16 = 3;
+
+/// how wide the health bar in the examine panel gets, label included
+const EXAMINE_HEALTH_BAR_WIDTH: u16 = 32;
+
+/// maximum number of monsters shown in the turn order strip
+const TURN_ORDER_LENGTH: usize = 6;
+
+/// height of the search bar under the fullscreen log, including its border
+const LOG_SEARCH_HEIGHT: u16 = 3;
+
 #[derive(Clone)]
 pub struct CharWidget {
     position: Position,
     renderable: Renderable,
+    modifier: Modifier,
 }
 
 impl Widget for CharWidget {
     fn render(self, area: ratatui::layout::Rect, buf: &mut Buffer) {
-        let tx = area.x + self.position.x as u16;
-        let ty = area.y + self.position.y as u16;
+        let tx = area.x + self.position.x;
+        let ty = area.y + self.position.y;
         if tx < area.right() && ty < area.bottom() {
             buf[(tx, ty)]
                 .set_symbol(&self.renderable.glyph.to_string())
                 .set_fg(self.renderable.fg)
-                .set_bg(self.renderable.bg);
+                .set_bg(self.renderable.bg)
+                .set_style(self.modifier);
         }
     }
 }
@@ -107,63 +151,160 @@ fn center(area: Rect, horizontal: Constraint, vertical: Constraint) -> Rect {
     area
 }
 
+/// describes what a melee monster is up to, e.g. "asleep" or "fleeing"
+fn ai_state(object: &Object, ai_data: &MeleeAIData) -> &'static str {
+    let fleeing = ai_data
+        .flee
+        .is_some_and(|flee| hp_percent(object) <= flee.hp_percent);
+    match ai_data.alertness {
+        Alertness::Sleeping => "asleep",
+        Alertness::Idle => "idle",
+        Alertness::Alert if fleeing => "fleeing",
+        Alertness::Alert if ai_data.target.is_some() => "hunting",
+        Alertness::Alert => "searching",
+    }
+}
+
 /// converts the given time to a string
 /// used to consistently format time in different locations
 fn time_string(time: u64) -> String {
     format!("{:<5.1}", (time as f64) / 100.0)
 }
 
-/// computes offset x and y relative to a center location and the center of the area
-/// used for rendering objects to the worldmap
-fn relative_coords(area: Rect, center_pos: Position, target_pos: Position) -> Option<Position> {
-    let center = Position {
-        x: area.width / 2,
-        y: area.height / 2,
-    };
+/// translates between world coordinates and cells of the map view, keeping
+/// the focus of the camera in the middle of the view
+struct Camera {
+    area: Rect,           // screen area that the map is drawn into
+    top_left: (i32, i32), // world coordinates shown in the top left cell of the area
+}
 
-    let x = match (center.x + target_pos.x).checked_sub(center_pos.x) {
-        Some(x) => x,
-        None => {
-            return None;
+impl Camera {
+    fn new(area: Rect, focus: Position) -> Self {
+        Self {
+            area,
+            top_left: (
+                focus.x as i32 - (area.width / 2) as i32,
+                focus.y as i32 - (area.height / 2) as i32,
+            ),
         }
-    };
-    let y = match (center.y + target_pos.y).checked_sub(center_pos.y) {
-        Some(y) => y,
-        None => {
-            return None;
+    }
+
+    /// returns where a world position is drawn relative to the view's top left corner,
+    /// or None if it is out of view
+    fn to_view(&self, pos: Position) -> Option<Position> {
+        let x = pos.x as i32 - self.top_left.0;
+        let y = pos.y as i32 - self.top_left.1;
+        if x < 0 || y < 0 || x >= self.area.width as i32 || y >= self.area.height as i32 {
+            None
+        } else {
+            Some(Position {
+                x: x as u16,
+                y: y as u16,
+            })
         }
-    };
+    }
 
-    if x > area.width || y > area.height {
-        None
-    } else {
-        Some(Position { x, y })
+    /// returns the screen cell that a world position is drawn to, or None if it is out of view
+    fn to_screen(&self, pos: Position) -> Option<(u16, u16)> {
+        self.to_view(pos)
+            .map(|view| (self.area.x + view.x, self.area.y + view.y))
     }
-}
 
-/// returns the way that a tile will appear on the map,
-/// based on what items/blockers are on top of it
-pub fn tile_topmost_renderable(app: &App, tile: &Tile) -> Renderable {
-    if let Some(blocker_id) = tile.blocker {
-        let blocker = app.objects.get(&blocker_id).unwrap();
-        return blocker.renderable.clone();
+    /// returns the world position drawn at a cell of the view. may lie outside of the map
+    fn to_world(&self, view: Position) -> (i32, i32) {
+        (
+            self.top_left.0 + view.x as i32,
+            self.top_left.1 + view.y as i32,
+        )
     }
-    if let Some(item_id) = tile.item {
-        let item = app.objects.get(&item_id).unwrap();
-        return item.renderable.clone();
+}
+
+/// returns the way that the tile at x, y will appear on the map,
+/// based on what items/blockers are on top of it. fires burn over any items,
+/// smoke hides everything but the player, and poison gas tints the tile
+pub fn tile_topmost_renderable(app: &App, x: u16, y: u16) -> Renderable {
+    let tile = app.gamemap.get_ref(x, y);
+    let smoky = app.gamemap.effect_at(x, y, EffectKind::Smoke).is_some();
+    let mut renderable = if smoky && tile.blocker != Some(PLAYER) {
+        Renderable::themed('▒', ThemeColor::Smoke)
+    } else if let Some(blocker_id) = tile.blocker {
+        let mut renderable = app.objects.get(&blocker_id).unwrap().renderable.clone();
+        // allies are drawn in the player's color
+        if is_charmed(app, blocker_id) {
+            renderable.role = Some(ThemeColor::Player);
+        }
+        renderable
+    } else if app.gamemap.is_burning(x, y) {
+        Renderable::themed('^', ThemeColor::Fire)
+    } else if let Some(item_id) = tile.item.filter(|id| !app.objects.get(id).unwrap().hidden) {
+        identify::item_renderable(app, item_id)
+    } else {
+        tile.renderable()
+    };
+    renderable.fg = renderable.fg_in(&app.theme);
+    if app.gamemap.effect_at(x, y, EffectKind::Poison).is_some() {
+        renderable.bg = app.theme.gas;
     }
-    tile.renderable()
+    renderable
+}
+
+/// returns how the monster or item on a tile should be remembered once it's out of view,
+/// or None if there's nothing on it but the player
+pub fn remembered_renderable(app: &App, tile: &Tile) -> Option<Renderable> {
+    let mut renderable = match tile.blocker {
+        Some(blocker_id) if blocker_id != PLAYER => {
+            let mut renderable = app.objects.get(&blocker_id).unwrap().renderable.clone();
+            if is_charmed(app, blocker_id) {
+                renderable.role = Some(ThemeColor::Player);
+            }
+            renderable
+        }
+        _ => {
+            let item_id = tile
+                .item
+                .filter(|id| !app.objects.get(id).unwrap().hidden)?;
+            identify::item_renderable(app, item_id)
+        }
+    };
+    renderable.fg = renderable.fg_in(&app.theme);
+    Some(renderable)
 }
 
+/// smallest terminal the game can be laid out in
+pub const MIN_TERMINAL_WIDTH: u16 = 80;
+pub const MIN_TERMINAL_HEIGHT: u16 = 24;
+
 impl App {
     pub fn render(&mut self, frame: &mut Frame) {
+        // the panels can't fit in a smaller terminal, so ask for a bigger one instead
+        let area = frame.area();
+        if area.width < MIN_TERMINAL_WIDTH || area.height < MIN_TERMINAL_HEIGHT {
+            self.render_resize_prompt(frame, area);
+            return;
+        }
+
+        // screens that use the bottom panel for a prompt keep it even when the log is hidden
+        let shows_log = self.show_log
+            || !matches!(
+                self.game_screen,
+                GameScreen::Main
+                    | GameScreen::AttackDirection
+                    | GameScreen::Waypoints { .. }
+                    | GameScreen::Spells { .. }
+                    | GameScreen::LevelSummary { .. }
+                    | GameScreen::Quests
+                    | GameScreen::Dialogue { .. }
+            );
+
+        let horizontal_constraints = if self.show_sidebar {
+            [Constraint::Min(15), Constraint::Percentage(70)]
+        } else {
+            [Constraint::Length(0), Constraint::Percentage(100)]
+        };
         let horizontal_split = layout::Layout::default()
             .direction(layout::Direction::Horizontal)
-            .constraints(vec![
-                layout::Constraint::Min(15),
-                layout::Constraint::Percentage(70),
-            ])
-            .split(frame.area());
+            .constraints(horizontal_constraints)
+            .split(area);
 
         let ui_layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
@@ -174,68 +315,75 @@ impl App {
             ])
             .split(horizontal_split[0]);
 
+        let world_constraints = if shows_log {
+            [Constraint::Percentage(70), Constraint::Min(5)]
+        } else {
+            [Constraint::Percentage(100), Constraint::Length(0)]
+        };
         let world_layout = layout::Layout::default()
             .direction(layout::Direction::Vertical)
-            .constraints(vec![
-                layout::Constraint::Percentage(70),
-                layout::Constraint::Min(5),
-            ])
+            .constraints(world_constraints)
             .split(horizontal_split[1]);
 
+        // without the sidebar, the most important stats go in a line above the map
+        let (map_area, status_line_area) = if self.show_sidebar {
+            (world_layout[0], None)
+        } else {
+            let [status_line_area, map_area] =
+                Layout::vertical([Constraint::Length(1), Constraint::Percentage(100)])
+                    .areas(world_layout[0]);
+            (map_area, Some(status_line_area))
+        };
+
+        // optionally carve out a strip below the map to show the turn order
+        let (map_area, turn_order_area) = if self.show_turn_order {
+            let [map_area, turn_order_area] =
+                Layout::vertical([Constraint::Percentage(100), Constraint::Length(3)])
+                    .areas(map_area);
+            (map_area, Some(turn_order_area))
+        } else {
+            (map_area, None)
+        };
+
         // correct game screen variables before they get rendered
         // need to do this first because game_screen needs to be borrowed as mut
-        match &mut self.game_screen {
-            GameScreen::Log { offset } => {
-                // correct the offset before it gets passed to render fullscreen log
-                let display_idx = self
-                    .log
-                    .len()
-                    .saturating_sub(horizontal_split[1].height as usize - 2);
-                *offset = (*offset).min(display_idx);
-            }
-            GameScreen::Examine { cursor } | GameScreen::Targeting { cursor, .. } => {
-                // keep the cursor within bounds of the renderable area
-                let inner_area = world_layout[0].inner(Margin {
-                    horizontal: 1,
-                    vertical: 1,
-                });
-                let center = Position {
-                    x: inner_area.width / 2,
-                    y: inner_area.height / 2,
-                };
-                let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-
-                match (player_pos.x + inner_area.width).checked_sub(center.x) {
-                    Some(x) => {
-                        cursor.x = cursor.x.min(x);
-                    }
-                    None => {}
-                }
-                match player_pos.x.checked_sub(center.x) {
-                    Some(x) => {
-                        cursor.x = cursor.x.max(x);
-                    }
-                    None => {}
-                }
-                match (player_pos.y + inner_area.height).checked_sub(center.y) {
-                    Some(y) => {
-                        cursor.y = cursor.y.min(y);
-                    }
-                    None => {}
-                }
-                match player_pos.y.checked_sub(center.y) {
-                    Some(y) => {
-                        cursor.y = cursor.y.max(y);
-                    }
-                    None => {}
-                }
-            }
-            _ => {}
+        if let GameScreen::Log { offset, filter, .. } = &mut self.game_screen {
+            // correct the offset before it gets passed to render fullscreen log.
+            // the search bar takes up the bottom of the screen
+            let display_idx = self.log.filtered(*filter).len().saturating_sub(
+                horizontal_split[1]
+                    .height
+                    .saturating_sub(2 + LOG_SEARCH_HEIGHT) as usize,
+            );
+            *offset = (*offset).min(display_idx);
+        }
+        if let GameScreen::Manual { page, offset, .. } = &mut self.game_screen {
+            // keep at least the last line of the page on screen
+            *offset = (*offset).min(manual::page_lines(*page).len().saturating_sub(1));
+        }
+        if let GameScreen::Help { offset } = &mut self.game_screen {
+            // keep at least the last key binding on screen
+            *offset = (*offset).min(help_line_count().saturating_sub(1));
         }
 
         // left side status + inventory is rendered on all game screens except the main menu
         match self.game_screen {
-            GameScreen::Menu => {}
+            GameScreen::Menu
+            | GameScreen::LoadFailed { .. }
+            | GameScreen::DataError { .. }
+            | GameScreen::EnterSeed { .. }
+            | GameScreen::CharacterCreation { .. }
+            | GameScreen::GameOver
+            | GameScreen::Victory
+            | GameScreen::Achievements
+            | GameScreen::HighScores
+            | GameScreen::Settings { .. }
+            | GameScreen::Manual { .. } => {}
+            _ if !self.show_sidebar => {
+                if let Some(area) = status_line_area {
+                    self.render_status_line(frame, area);
+                }
+            }
             _ => {
                 let status_area = ui_layout[0];
                 let equipment_area = ui_layout[1];
@@ -250,31 +398,177 @@ impl App {
             GameScreen::Menu => {
                 self.render_main_menu(frame, frame.area());
             }
-            GameScreen::Main => {
-                self.render_tiles(frame, world_layout[0]);
+            GameScreen::Achievements => {
+                self.render_achievements(frame, frame.area());
+            }
+            GameScreen::Settings { cursor, .. } => {
+                self.render_settings(frame, frame.area(), cursor);
+            }
+            GameScreen::Confirm { ref prompt, .. } => {
+                self.render_tiles(frame, map_area);
+                self.render_confirm(frame, map_area, prompt);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::HighScores => {
+                self.render_high_scores(frame, frame.area());
+            }
+            GameScreen::CharacterCreation { cursor } => {
+                self.render_character_creation(frame, frame.area(), cursor);
+            }
+            GameScreen::GameOver => {
+                self.render_game_over(frame, frame.area(), false);
+            }
+            GameScreen::Victory => {
+                self.render_game_over(frame, frame.area(), true);
+            }
+            GameScreen::EnterSeed { ref text } => {
+                self.render_seed_entry(frame, frame.area(), text);
+            }
+            GameScreen::LoadFailed { ref error, backup } => {
+                self.render_load_failed(frame, frame.area(), error, backup);
+            }
+            GameScreen::DataError { ref problems } => {
+                self.render_data_error(frame, frame.area(), problems);
+            }
+            GameScreen::Manual {
+                page,
+                offset,
+                ref query,
+                searching,
+            } => {
+                self.render_manual(frame, frame.area(), page, offset, query, searching);
+            }
+            GameScreen::Main | GameScreen::AttackDirection => {
+                self.render_tiles(frame, map_area);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::NameWaypoint { ref name } => {
+                self.render_tiles(frame, map_area);
+                self.render_waypoint_prompt(frame, world_layout[1], name);
+            }
+            GameScreen::BindMacro { ref keys } => {
+                self.render_tiles(frame, map_area);
+                self.render_bind_macro_prompt(frame, world_layout[1], keys.len());
+            }
+            GameScreen::Waypoints { cursor } => {
+                self.render_waypoints(frame, map_area, cursor);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::Spells { cursor } => {
+                self.render_spells(frame, map_area, cursor);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::LevelSummary { ref summary } => {
+                self.render_tiles(frame, map_area);
+                self.render_level_summary(frame, map_area, summary);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::Help { offset } => {
+                self.render_help(frame, map_area, offset);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::CommandPalette { ref input, cursor } => {
+                self.render_tiles(frame, map_area);
+                self.render_command_palette(frame, map_area, input, cursor);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::Quests => {
+                self.render_tiles(frame, map_area);
+                self.render_quests(frame, map_area);
+                self.render_log(frame, world_layout[1]);
+            }
+            GameScreen::Dialogue { speaker, node } => {
+                self.render_tiles(frame, map_area);
+                self.render_dialogue(frame, map_area, speaker, node);
                 self.render_log(frame, world_layout[1]);
             }
-            GameScreen::Log { offset } => {
-                self.render_fullscreen_log(frame, horizontal_split[1], offset);
+            GameScreen::ChooseItem { ref text, .. } => {
+                self.render_tiles(frame, map_area);
+                self.render_choose_item_prompt(frame, world_layout[1], text);
+            }
+            GameScreen::Log {
+                offset,
+                ref query,
+                searching,
+                filter,
+            } => {
+                self.render_fullscreen_log(
+                    frame,
+                    horizontal_split[1],
+                    offset,
+                    query,
+                    searching,
+                    filter,
+                );
             }
             GameScreen::Examine { ref cursor } => {
-                self.render_tiles(frame, world_layout[0]);
+                self.render_tiles(frame, map_area);
 
-                self.render_examine_cursor(frame, world_layout[0], &cursor);
-                self.render_examine_info(frame, world_layout[1], &cursor);
+                self.render_examine_cursor(frame, map_area, cursor);
+                self.render_examine_info(frame, world_layout[1], cursor);
+            }
+            GameScreen::Shop {
+                shopkeeper,
+                cursor,
+                selling,
+            } => {
+                self.render_shop(frame, map_area, shopkeeper, cursor, selling);
+                self.render_shop_info(frame, world_layout[1], shopkeeper, cursor, selling);
             }
             GameScreen::Targeting {
                 ref cursor,
                 ref text,
                 ref targeting,
-                ..
+                source,
             } => {
-                self.render_tiles(frame, world_layout[0]);
+                self.render_tiles(frame, map_area);
+
+                let item = source.item(self);
+                let problem = target_problem(self, &item, *cursor);
+                self.render_targeting_overlay(frame, map_area, cursor, targeting);
+                if problem.is_some() {
+                    // the item can't reach the cursor, so there's nothing to highlight
+                    self.mark_line_blocked(frame, map_area, cursor);
+                }
+                self.render_targeting_info(frame, world_layout[1], cursor, text, problem);
+            }
+        }
+
+        // draw the next frame of any animation over the map
+        if let Some(animation_frame) = self.animations.front() {
+            match self.game_screen {
+                GameScreen::Main
+                | GameScreen::AttackDirection
+                | GameScreen::Examine { .. }
+                | GameScreen::Targeting { .. } => {
+                    self.render_animation_frame(frame, map_area, animation_frame);
+                }
+                _ => {}
+            }
+        }
 
-                self.render_targeting_overlay(frame, world_layout[0], &cursor, targeting);
-                self.render_targeting_info(frame, world_layout[1], &cursor, text);
+        if let Some(area) = turn_order_area {
+            match self.game_screen {
+                GameScreen::Main
+                | GameScreen::AttackDirection
+                | GameScreen::Examine { .. }
+                | GameScreen::Targeting { .. } => {
+                    self.render_turn_order(frame, area);
+                }
+                _ => {}
             }
         }
+
+        if self.demo {
+            self.render_demo_banner(frame, area);
+        }
+    }
+
+    /// renders a reminder along the top of the screen that the bot is playing
+    fn render_demo_banner(&self, frame: &mut Frame, area: Rect) {
+        let [banner_area] = Layout::vertical([Constraint::Length(1)]).areas(area);
+        let banner = Line::from(" demo - press any key ").reversed().bold();
+        frame.render_widget(Paragraph::new(banner).centered(), banner_area);
     }
 
     fn render_main_menu(&self, frame: &mut Frame, area: layout::Rect) {
@@ -294,7 +588,12 @@ impl App {
         ];
         let instruction_lines: Vec<Line> = vec![
             Line::from("(n) New Game"),
+            Line::from("(s) Seeded Game"),
+            Line::from("(d) Daily Game"),
             Line::from("(l) Load Game"),
+            Line::from("(h) High Scores"),
+            Line::from("(a) Achievements"),
+            Line::from("(o) Options"),
             Line::from("(q) Quit"),
         ];
 
@@ -307,7 +606,7 @@ impl App {
         .areas(inner);
 
         // magic number for the length of the instruction text
-        let [instruction_area] = Layout::horizontal([Constraint::Length(15)])
+        let [instruction_area] = Layout::horizontal([Constraint::Length(16)])
             .flex(Flex::Center)
             .areas(instruction_area);
 
@@ -317,15 +616,294 @@ impl App {
         frame.render_widget(instruction_paragraph, instruction_area);
     }
 
-    /// render tiles in gamemap
-    fn render_tiles(&self, frame: &mut Frame, area: layout::Rect) {
-        let title_block = Block::bordered().title("world");
-        frame.render_widget(title_block, area);
+    /// renders every achievement in the middle of the screen, with the ones not yet
+    /// unlocked dimmed
+    fn render_achievements(&self, frame: &mut Frame, area: Rect) {
+        let unlocked = ACHIEVEMENTS
+            .iter()
+            .filter(|&&achievement| self.profile.has(achievement))
+            .count();
+        let mut lines = vec![
+            Line::from(format!("{}/{} unlocked", unlocked, ACHIEVEMENTS.len())),
+            Line::from(""),
+        ];
+        for achievement in ACHIEVEMENTS {
+            let line = Line::from(format!(
+                "{:<14}  {}",
+                achievement.name(),
+                achievement.description()
+            ));
+            match self.profile.has(achievement) {
+                true => lines.push(line.fg(self.theme.gold)),
+                false => lines.push(line.fg(self.theme.muted)),
+            }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("press any key to go back").fg(self.theme.muted));
+
+        let height = lines.len() as u16 + 2;
+        let popup = center(area, Constraint::Length(58), Constraint::Length(height));
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title("achievements").borders(Borders::ALL));
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders every setting and its value in the middle of the screen,
+    /// with the cursor on the one that h and l change
+    fn render_settings(&self, frame: &mut Frame, area: Rect, cursor: usize) {
+        let mut lines = Vec::new();
+        for (index, &row) in SETTING_ROWS.iter().enumerate() {
+            let line = Line::from(format!(
+                "{:<26}{}",
+                row.name(),
+                settings::row_value(self, row)
+            ));
+            lines.push(match index == cursor {
+                true => line.reversed(),
+                false => line,
+            });
+        }
+        lines.push(Line::from(""));
+        lines.push(
+            Line::from(format!(
+                "j/k: move  h/l: change  esc: back. saved to {}",
+                SETTINGS_FILE
+            ))
+            .fg(self.theme.muted),
+        );
 
+        let height = lines.len() as u16 + 2;
+        let popup = center(area, Constraint::Length(62), Constraint::Length(height));
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("settings").borders(Borders::ALL));
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders a popup over the map asking a yes or no question
+    fn render_confirm(&self, frame: &mut Frame, area: Rect, prompt: &str) {
+        let text = format!("{} (y/n)", prompt);
+        let width = (text.len() as u16 + 4).min(area.width);
+        let popup = center(area, Constraint::Length(width), Constraint::Length(3));
+        let paragraph = Paragraph::new(Line::from(text))
+            .centered()
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders the best runs in the middle of the screen, with the runs on today's
+    /// daily challenge in a table of their own
+    fn render_high_scores(&self, frame: &mut Frame, area: Rect) {
+        let today = scores::today();
+        let tables = [
+            (String::from("best runs"), self.profile.high_scores(None)),
+            (
+                format!("daily challenge {}", today),
+                self.profile.high_scores(Some(&today)),
+            ),
+        ];
+
+        let mut lines = Vec::new();
+        for (title, table) in tables {
+            lines.push(Line::from(title).bold());
+            if table.is_empty() {
+                lines.push(Line::from("no runs yet").fg(self.theme.muted));
+            }
+            for score in table {
+                let class = score
+                    .class
+                    .map_or(String::from("-"), |class| class.to_string());
+                let ending = match score.won {
+                    true => String::from("escaped"),
+                    false => format!("died on depth {}", score.depth),
+                };
+                lines.push(Line::from(format!(
+                    "{:>6}  {:<8} {:>4} kills  {}",
+                    score.points, class, score.kills, ending
+                )));
+            }
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from("press any key to go back").fg(self.theme.muted));
+
+        let height = lines.len() as u16 + 2;
+        let popup = center(area, Constraint::Length(52), Constraint::Length(height));
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title("high scores").borders(Borders::ALL));
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// render the seed entry box in the middle of the screen
+    fn render_seed_entry(&self, frame: &mut Frame, area: Rect, text: &str) {
+        let inner = center(area, Constraint::Percentage(50), Constraint::Length(6));
+        let block = Block::default().title("enter a seed").borders(Borders::ALL);
+        frame.render_widget(block, inner);
+
+        let inner = inner.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+        let lines = vec![
+            Line::from(format!("> {}_", text)),
+            Line::from(""),
+            Line::from("(enter) continue, leave blank for a random seed"),
+        ];
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+
+    /// render the reason a save couldn't be loaded in the middle of the screen
+    fn render_load_failed(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        error: &LoadError,
+        backup: Option<usize>,
+    ) {
+        let inner = center(area, Constraint::Percentage(50), Constraint::Length(6));
+        let block = Block::default().title("load game").borders(Borders::ALL);
+        frame.render_widget(block, inner);
+
+        let inner = inner.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+        let lines = match backup {
+            Some(backup) => vec![
+                Line::from(error.to_string()),
+                Line::from(""),
+                Line::from(format!("(y) load backup {}    (n) back", backup)),
+            ],
+            None => vec![
+                Line::from(error.to_string()),
+                Line::from(""),
+                Line::from("(any key) back"),
+            ],
+        };
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+
+    /// lists what's wrong with the entities data file
+    fn render_data_error(&self, frame: &mut Frame, area: Rect, problems: &[String]) {
+        let inner = center(area, Constraint::Percentage(70), Constraint::Percentage(60));
+        let block = Block::default()
+            .title(format!("problems in {}", ENTITIES_FILE))
+            .borders(Borders::ALL);
+        frame.render_widget(block, inner);
+
+        let inner = inner.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+        let mut lines: Vec<Line> = problems
+            .iter()
+            .map(|problem| Line::from(format!("- {}", problem)).fg(self.theme.log_danger))
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from("the file was not loaded."));
+        lines.push(Line::from("(any key) continue with the built in entities"));
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, inner);
+    }
+
+    /// render the class selection list in the middle of the screen
+    fn render_character_creation(&self, frame: &mut Frame, area: Rect, cursor: usize) {
+        let inner = center(area, Constraint::Percentage(60), Constraint::Percentage(50));
+        let block = Block::default()
+            .title("choose your class")
+            .borders(Borders::ALL);
+        frame.render_widget(block, inner);
+
+        let inner = inner.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+
+        let mut lines: Vec<Line> = Vec::new();
+        for (index, class) in CLASS_ORDERING.iter().enumerate() {
+            let line = Line::from(format!("({}) {}", index + 1, class));
+            if index == cursor {
+                lines.push(line.style(Style::new().black().on_gray()));
+            } else {
+                lines.push(line);
+            }
+            lines.push(Line::from(format!("    {}", class.description())));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("(enter) start    (esc) back"));
+
+        let paragraph = Paragraph::new(lines);
+        frame.render_widget(paragraph, inner);
+    }
+
+    /// render a summary of the run in the middle of the screen after the player dies
+    /// summary of a finished run, either won by escaping with the amulet or lost to death
+    fn render_game_over(&self, frame: &mut Frame, area: Rect, victory: bool) {
+        let inner = center(area, Constraint::Percentage(50), Constraint::Percentage(50));
+        let title = if victory { "you escaped" } else { "you died" };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        frame.render_widget(block, inner);
+
+        let inner = inner.inner(Margin {
+            horizontal: 2,
+            vertical: 1,
+        });
+
+        let player = self.objects.get(&PLAYER).unwrap();
+        let headline = match victory {
+            true => {
+                Line::from("the Amulet of Ages is yours").set_style(Style::new().bold().yellow())
+            }
+            false => Line::from("rest in peace").set_style(Style::new().bold().red()),
+        };
+        let mut lines: Vec<Line> = vec![headline];
+        if let Some(death) = &self.death {
+            // capitalize the first letter, e.g. "Killed by a troll on level 5"
+            let cause = death.to_string();
+            lines.push(Line::from(format!(
+                "{}{}",
+                cause[..1].to_uppercase(),
+                &cause[1..]
+            )));
+        }
+        lines.push(Line::from(""));
+        if let Some(class) = player.class {
+            lines.push(Line::from(format!("Class: {}", class)));
+        }
+        lines.extend([
+            Line::from(format!("Turns survived: {}", self.time / 100)),
+            Line::from(format!("Dungeon level: {}", self.gamemap.level)),
+            Line::from(format!("Kills: {}", self.kills)),
+            Line::from(format!("Gold: {}", shop::player_gold(self))),
+            Line::from(format!("Seed: {}", self.seed)),
+            Line::from(""),
+            Line::from("(enter) return to the main menu"),
+        ]);
+
+        let paragraph = Paragraph::new(lines);
+        frame.render_widget(paragraph, inner);
+    }
+
+    /// render tiles in gamemap
+    /// returns the camera for drawing the map into an area. the camera follows the
+    /// cursor while examining or targeting, and the player otherwise
+    fn camera(&self, area: Rect) -> Camera {
         let inner_area = area.inner(Margin {
             horizontal: 1,
             vertical: 1,
         });
+        let focus = match self.game_screen {
+            GameScreen::Examine { cursor } | GameScreen::Targeting { cursor, .. } => cursor,
+            _ => self.gamemap.get_position(PLAYER).unwrap(),
+        };
+        Camera::new(inner_area, focus)
+    }
+
+    fn render_tiles(&self, frame: &mut Frame, area: layout::Rect) {
+        let title_block = Block::bordered().title("world");
+        frame.render_widget(title_block, area);
+
+        let camera = self.camera(area);
+        let inner_area = camera.area;
 
         // cover inner area in dark tiles
         for x in 0..area.width {
@@ -336,70 +914,228 @@ impl App {
                         glyph: '.',
                         fg: Color::Black,
                         bg: Color::Reset,
+                        role: None,
                     },
+                    modifier: Modifier::empty(),
                 };
                 frame.render_widget(ch, inner_area);
             }
         }
 
-        // render the tiles in the gamemap
-        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-        for x in 0..self.gamemap.width {
-            for y in 0..self.gamemap.height {
-                let target_pos = match relative_coords(inner_area, player_pos, Position { x, y }) {
-                    Some(pos) => pos,
-                    None => {
-                        continue;
-                    }
+        // render the tiles of the gamemap that are in view of the camera
+        let danger = if self.show_danger {
+            danger_map(self)
+        } else {
+            Vec::new()
+        };
+        for view_x in 0..inner_area.width {
+            for view_y in 0..inner_area.height {
+                let target_pos = Position {
+                    x: view_x,
+                    y: view_y,
                 };
+                let (x, y) = camera.to_world(target_pos);
+                if !self.gamemap.in_bounds(x as i16, y as i16) {
+                    continue;
+                }
+                let (x, y) = (x as u16, y as u16);
 
                 let tile = self.gamemap.get_ref(x, y);
+                let mut modifier = Modifier::empty();
                 let ch = CharWidget {
                     position: target_pos,
                     renderable: {
                         if self.gamemap.is_visible(x, y) {
-                            tile_topmost_renderable(self, tile)
+                            let mut renderable = tile_topmost_renderable(self, x, y);
+                            if danger
+                                .get(gamemap::coords_to_idx(x, y, self.gamemap.width))
+                                .is_some_and(|&d| d)
+                            {
+                                renderable.bg = self.theme.danger;
+                            }
+                            // floors and walls in the light of a torch are brighter
+                            // than the ones the player only makes out up close
+                            if self.gamemap.lighting
+                                && self.gamemap.is_lit(x, y)
+                                && matches!(
+                                    renderable.role,
+                                    Some(ThemeColor::FloorLit | ThemeColor::WallLit)
+                                )
+                            {
+                                modifier = Modifier::BOLD;
+                            }
+                            // hostiles stand out even when their color doesn't
+                            if self.theme.accents && renderable.role.is_some_and(|r| r.is_hostile())
+                            {
+                                modifier = Modifier::BOLD | Modifier::UNDERLINED;
+                            }
+                            // anything that just came into view for the first time flashes
+                            let flashing = tile
+                                .blocker
+                                .into_iter()
+                                .chain(known_item(self, x, y))
+                                .any(|id| self.flashes.contains_key(&id));
+                            if flashing {
+                                modifier |= Modifier::REVERSED;
+                            }
+                            renderable
+                        } else if let Some(remembered) = self
+                            .gamemap
+                            .get_remembered(x, y)
+                            .filter(|_| self.gamemap.is_explored(x, y))
+                        {
+                            // monsters and items stay where they were last seen, dimmed
+                            modifier = Modifier::DIM;
+                            remembered
                         } else if self.gamemap.is_explored(x, y) {
                             let last_seen = self.gamemap.get_last_seen(x, y);
+                            // footsteps are slightly brighter than the rest of the floor
+                            let walked_floor = self.show_breadcrumbs
+                                && self.gamemap.is_walked(x, y)
+                                && last_seen.glyph == Tile::new(TileType::Floor).renderable().glyph;
+                            let role = if last_seen.role == Some(ThemeColor::Trap) {
+                                // found traps stay easy to spot, so they can be avoided
+                                ThemeColor::Trap
+                            } else if walked_floor {
+                                ThemeColor::Breadcrumb
+                            } else if last_seen.glyph
+                                == Tile::new(TileType::Wall).renderable().glyph
+                            {
+                                ThemeColor::WallDark
+                            } else {
+                                ThemeColor::FloorDark
+                            };
+                            // remembered floors get a different glyph, so they can be
+                            // told apart from visible ones without relying on color
+                            let glyph = if self.theme.accents
+                                && role == ThemeColor::FloorDark
+                                && last_seen.glyph == Tile::new(TileType::Floor).renderable().glyph
+                            {
+                                modifier = Modifier::DIM;
+                                REMEMBERED_FLOOR_ACCENT
+                            } else {
+                                last_seen.glyph
+                            };
                             Renderable {
-                                glyph: last_seen.glyph,
-                                fg: Color::DarkGray,
+                                glyph,
+                                fg: self.theme.get(role),
                                 bg: Color::Reset,
+                                role: None,
                             }
                         } else {
                             gamemap::shroud_renderable()
                         }
                     },
+                    modifier,
                 };
                 frame.render_widget(ch, inner_area);
             }
         }
+
+        if self.show_ghost {
+            self.render_ghost(frame, &camera);
+        }
+        if self.settings.monster_health_bars {
+            self.render_monster_health_bars(frame, &camera);
+        }
+    }
+
+    /// draws a short health bar on the row above each hurt monster in view,
+    /// or below it when the monster is on the top row of the view
+    fn render_monster_health_bars(&self, frame: &mut Frame, camera: &Camera) {
+        for id in visible_monsters(self) {
+            let fighter = self.objects.get(&id).unwrap().fighter.as_ref().unwrap();
+            if fighter.hp >= fighter.max_hp {
+                continue;
+            }
+            let position = self.gamemap.get_position(id).unwrap();
+            let Some(view) = camera.to_view(position) else {
+                continue;
+            };
+
+            let y = match view.y {
+                0 => 1,
+                _ => view.y - 1,
+            };
+            let bar_area = Rect {
+                x: camera.area.x + view.x.saturating_sub(MONSTER_HEALTH_BAR_WIDTH / 2),
+                y: camera.area.y + y,
+                width: MONSTER_HEALTH_BAR_WIDTH,
+                height: 1,
+            }
+            .intersection(camera.area);
+            let gauge = AsciiGauge::default()
+                .set_ratio(fighter.hp as f64 / fighter.max_hp as f64)
+                .set_filled_style(Style::default().fg(self.theme.health_full))
+                .set_unfilled_style(Style::default().fg(self.theme.health_empty));
+            frame.render_widget(gauge, bar_area);
+        }
+    }
+
+    /// draws a faint marker where the last attempt on this seed was at the same time.
+    /// it stays hidden behind anything that blocks the tile, including the player
+    fn render_ghost(&self, frame: &mut Frame, camera: &Camera) {
+        let Some(position) = self
+            .ghost
+            .as_ref()
+            .and_then(|ghost| ghost.position_at(self.time, self.gamemap.level))
+        else {
+            return;
+        };
+        // later floors can be laid out differently, since the rng was used differently
+        if !self.gamemap.in_bounds(position.x as i16, position.y as i16) {
+            return;
+        }
+        let occupied = self.gamemap.is_visible(position.x, position.y)
+            && self
+                .gamemap
+                .get_ref(position.x, position.y)
+                .blocker
+                .is_some();
+        if occupied || !self.gamemap.is_explored(position.x, position.y) {
+            return;
+        }
+        if let Some(view) = camera.to_view(position) {
+            let ch = CharWidget {
+                position: view,
+                renderable: Renderable {
+                    glyph: '@',
+                    fg: self.theme.muted,
+                    bg: Color::Reset,
+                    role: None,
+                },
+                modifier: Modifier::DIM,
+            };
+            frame.render_widget(ch, camera.area);
+        }
+    }
+
+    /// draws the cells of an animation frame that the player can see
+    fn render_animation_frame(&self, frame: &mut Frame, area: Rect, animation: &AnimationFrame) {
+        let camera = self.camera(area);
+        for (position, renderable) in &animation.cells {
+            if !self.gamemap.in_bounds(position.x as i16, position.y as i16)
+                || !self.gamemap.is_visible(position.x, position.y)
+            {
+                continue;
+            }
+            if let Some(view) = camera.to_view(*position) {
+                let ch = CharWidget {
+                    position: view,
+                    renderable: renderable.clone(),
+                    modifier: Modifier::empty(),
+                };
+                frame.render_widget(ch, camera.area);
+            }
+        }
     }
 
     /// render the cursor in the map after rendering everything else
     fn render_examine_cursor(&self, frame: &mut Frame, area: Rect, cursor: &Position) {
-        // use inner_area because render_map() also renders to this
-        let inner_area = area.inner(Margin {
-            horizontal: 1,
-            vertical: 1,
-        });
-
         // swap the fg and bg colors of the cell the cursor is highlighting
-        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-        let offset_pos = match relative_coords(
-            inner_area,
-            player_pos,
-            Position {
-                x: cursor.x,
-                y: cursor.y,
-            },
-        ) {
-            Some(pos) => pos,
-            None => {
-                panic!("um.");
-            }
+        let Some(coords) = self.camera(area).to_screen(*cursor) else {
+            return;
         };
-        let coords = (inner_area.x + offset_pos.x, inner_area.y + offset_pos.y);
         let buf = frame.buffer_mut();
         let cell = &mut buf[coords];
 
@@ -419,35 +1155,49 @@ impl App {
     /// marks the specified cell as targeted in the worldmap
     /// targeted cells are highlighted magenta, and floor cells will change to '*'
     fn mark_targeted(&self, frame: &mut Frame, area: Rect, target: &Position) {
-        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-        let offset_pos = relative_coords(area, player_pos, *target).unwrap();
-        let coords = (area.x + offset_pos.x, area.y + offset_pos.y);
+        let Some(coords) = self.camera(area).to_screen(*target) else {
+            return;
+        };
         let buf = frame.buffer_mut();
         let cell = &mut buf[coords];
 
         // if the cell looks like the floor or unseen, set the char to '*'
         if cell.symbol() == Tile::new(TileType::Floor).renderable().glyph.to_string()
+            || cell.symbol() == REMEMBERED_FLOOR_ACCENT.to_string()
             || cell.symbol() == shroud_renderable().glyph.to_string()
         {
             cell.set_symbol("*");
-            cell.set_fg(Color::Magenta);
+            cell.set_fg(self.theme.target);
         } else {
             cell.set_fg(Color::Black);
-            cell.set_bg(Color::Magenta);
+            cell.set_bg(self.theme.target);
         }
     }
 
     /// marks the specified cell as the cursor for targeting mode
     /// targeted cells will have its background set to magenta
     fn mark_targeted_cursor(&self, frame: &mut Frame, area: Rect, target: &Position) {
-        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-        let offset_pos = relative_coords(area, player_pos, *target).unwrap();
-        let coords = (area.x + offset_pos.x, area.y + offset_pos.y);
+        let Some(coords) = self.camera(area).to_screen(*target) else {
+            return;
+        };
+        let buf = frame.buffer_mut();
+        let cell = &mut buf[coords];
+
+        cell.set_fg(Color::Black);
+        cell.set_bg(self.theme.target);
+    }
+
+    /// marks the first wall or monster that a line of fire runs into
+    /// blocked cells will have its background set to red
+    fn mark_line_blocked(&self, frame: &mut Frame, area: Rect, target: &Position) {
+        let Some(coords) = self.camera(area).to_screen(*target) else {
+            return;
+        };
         let buf = frame.buffer_mut();
         let cell = &mut buf[coords];
 
         cell.set_fg(Color::Black);
-        cell.set_bg(Color::Magenta);
+        cell.set_bg(self.theme.blocked);
     }
 
     /// renders an overlay in the map based on the current targeting mode
@@ -459,65 +1209,549 @@ impl App {
         targeting: &TargetingMode,
     ) {
         match targeting {
-            TargetingMode::None => {
-                panic!("game screen was set to targeting, but targeting mode was None!")
+            TargetingMode::None | TargetingMode::Item => {
+                panic!("game screen was set to targeting, but targeting mode was not for the map!")
             }
             TargetingMode::Smite => {
                 self.mark_targeted(frame, area, cursor);
                 self.mark_targeted_cursor(frame, area, cursor);
             }
             TargetingMode::Line => {
-                // change all blank tiles along the line to '*', and highlights targets
+                // change all blank tiles along the line to '*', up until the line
+                // runs into a wall or monster, which gets highlighted instead
                 let player_pos = self.gamemap.get_position(PLAYER).unwrap();
-                let path = los::bresenham(
+                let path = los::LineIter::new(
                     (player_pos.x as i32, player_pos.y as i32),
                     (cursor.x as i32, cursor.y as i32),
                 );
 
-                let last = path.last().unwrap();
-                let last_pos = Position {
-                    x: last.0 as u16,
-                    y: last.1 as u16,
-                };
-
-                let (_, path) = path.split_first().unwrap();
-                for coord in path {
+                let mut blocked_pos = None;
+                for coord in path.skip(1) {
                     let pos = Position {
                         x: coord.0 as u16,
                         y: coord.1 as u16,
                     };
-                    self.mark_targeted(frame, area, &pos);
-                    if !self.gamemap.get_ref(pos.x, pos.y).is_walkable() {
+                    let tile = self.gamemap.get_ref(pos.x, pos.y);
+                    // don't give away monsters the player can't see
+                    let seen_blocker =
+                        tile.blocker.is_some() && self.gamemap.is_visible(pos.x, pos.y);
+                    if !tile.is_walkable() || seen_blocker {
+                        blocked_pos = Some(pos);
                         break;
                     }
+                    self.mark_targeted(frame, area, &pos);
                 }
 
-                self.mark_targeted_cursor(frame, area, &last_pos);
+                self.mark_targeted_cursor(frame, area, cursor);
+                if let Some(pos) = blocked_pos {
+                    self.mark_line_blocked(frame, area, &pos);
+                }
             }
         }
     }
 
     /// displays information about items under the examine cursor
     fn render_examine_info(&self, frame: &mut Frame, area: Rect, cursor: &Position) {
+        let block = Block::default().title("examine").borders(Borders::ALL);
+        let mut text_area = block.inner(area);
+        frame.render_widget(block, area);
+
+        // a fighter under the cursor gets a health bar above its description
+        let blocker = self.gamemap.get_ref(cursor.x, cursor.y).blocker;
+        if self.gamemap.is_visible(cursor.x, cursor.y)
+            && let Some(id) = blocker.filter(|id| self.objects.get(id).unwrap().fighter.is_some())
+        {
+            let [bar_area, rest] =
+                Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(text_area);
+            let [bar_area] = Layout::horizontal([Constraint::Max(EXAMINE_HEALTH_BAR_WIDTH)])
+                .flex(Flex::Start)
+                .areas(bar_area);
+            self.render_health_bar(frame, bar_area, id);
+            text_area = rest;
+        }
+
         let lines: Vec<Line> = self
             .get_description_at_cursor(cursor)
             .into_iter()
-            .map(|x| Line::from(x))
+            .map(Line::from)
             .collect();
+        frame.render_widget(Paragraph::new(lines), text_area);
+    }
+
+    /// renders a fighter's hp as a label followed by a gauge, on a single row
+    fn render_health_bar(&self, frame: &mut Frame, area: Rect, id: usize) {
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Length(12), Constraint::Percentage(100)])
+            .split(area);
+        let label_area = layout[0];
+        let gauge_area = layout[1];
+
+        let fighter = self.objects.get(&id).unwrap().fighter.as_ref().unwrap();
+        let ratio = fighter.hp as f64 / fighter.max_hp as f64;
+
+        let label_text = format!("HP: {}/{}", fighter.hp, fighter.max_hp);
+        let health_label = Paragraph::new(label_text);
+
+        let health_gauge = AsciiGauge::default()
+            .set_ratio(ratio)
+            .set_filled_style(Style::default().fg(self.theme.health_full))
+            .set_unfilled_style(Style::default().fg(self.theme.health_empty));
+
+        frame.render_widget(health_label, label_area);
+        frame.render_widget(health_gauge, gauge_area);
+    }
+
+    /// returns the ids of the items listed in the shop screen, paired with their price
+    fn get_shop_listing(&self, shopkeeper: usize, selling: bool) -> Vec<(usize, u32)> {
+        if selling {
+            self.inventory
+                .iter()
+                .map(|&id| (id, shop::sell_price(self, id)))
+                .collect()
+        } else {
+            shop::get_stock(self, shopkeeper)
+                .iter()
+                .map(|&id| (id, shop::buy_price(self, id)))
+                .collect()
+        }
+    }
+
+    /// renders the text box for naming a new waypoint
+    fn render_waypoint_prompt(&self, frame: &mut Frame, area: Rect, name: &str) {
+        let lines = vec![
+            Line::from("name this waypoint, then press enter:"),
+            Line::from(format!("> {}_", name)),
+        ];
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("waypoint").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders the prompt for binding a freshly recorded macro to a function key
+    fn render_bind_macro_prompt(&self, frame: &mut Frame, area: Rect, length: usize) {
+        let lines = vec![
+            Line::from(format!("recorded {} keys.", length)),
+            Line::from(format!(
+                "press F1-F{} to bind them to that key. esc to throw them away.",
+                MACRO_SLOTS
+            )),
+        ];
         let paragraph =
-            Paragraph::new(lines).block(Block::default().title("examine").borders(Borders::ALL));
+            Paragraph::new(lines).block(Block::default().title("macro").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders the prompt for picking an item out of the inventory or equipment
+    fn render_choose_item_prompt(&self, frame: &mut Frame, area: Rect, text: &str) {
+        let lines = vec![
+            Line::from(text.to_string()),
+            Line::from(format!(
+                "pick an item with 1-0 or a piece of equipment with A-{}. esc to cancel.",
+                slot_key(SLOT_ORDERING.len() - 1)
+            )),
+        ];
+        let paragraph = Paragraph::new(lines)
+            .block(Block::default().title("choose item").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders a page of the manual, with tabs for the other pages and a search bar
+    fn render_manual(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        page: usize,
+        offset: usize,
+        query: &str,
+        searching: bool,
+    ) {
+        let [tabs_area, page_area, footer_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .areas(area);
+
+        let titles = manual::MANUAL_PAGES.iter().map(|page| page.title);
+        let tabs = Tabs::new(titles)
+            .select(page)
+            .highlight_style(Style::new().black().on_gray())
+            .block(Block::default().title("manual").borders(Borders::ALL));
+        frame.render_widget(tabs, tabs_area);
+
+        let lines: Vec<Line> = manual::page_lines(page)
+            .into_iter()
+            .skip(offset)
+            .map(|(kind, text)| self.manual_line(kind, text, query))
+            .collect();
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .title(manual::MANUAL_PAGES[page].title)
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(paragraph, page_area);
+
+        let footer = if searching {
+            Line::from(format!("/{}_", query))
+        } else if query.is_empty() {
+            Line::from("tab: next page  j/k: scroll  /: search  esc: close").fg(self.theme.muted)
+        } else if manual::find_match(query, page, offset, true).is_none() {
+            Line::from(format!("no matches for \"{}\"", query)).fg(self.theme.log_warning)
+        } else {
+            Line::from(format!("\"{}\"  n/N: next/previous match", query))
+        };
+        let paragraph =
+            Paragraph::new(footer).block(Block::default().title("search").borders(Borders::ALL));
+        frame.render_widget(paragraph, footer_area);
+    }
+
+    /// styles a line of the manual, highlighting where it matches the search
+    fn manual_line(
+        &self,
+        kind: manual::LineKind,
+        text: &'static str,
+        query: &str,
+    ) -> Line<'static> {
+        let (prefix, style) = match kind {
+            manual::LineKind::Title => ("", Style::new().bold().underlined()),
+            manual::LineKind::Heading => ("", Style::new().bold()),
+            manual::LineKind::Bullet => ("  * ", Style::new()),
+            manual::LineKind::Formula => ("    ", Style::new().fg(self.theme.status)),
+            manual::LineKind::Text => ("", Style::new()),
+        };
+
+        // the manual is plain ascii, so lowercasing keeps the byte offsets the same
+        let found = match query.is_empty() {
+            true => None,
+            false => text.to_lowercase().find(&query.to_lowercase()),
+        };
+        let mut spans = vec![Span::from(prefix)];
+        match found {
+            Some(start) => {
+                let end = start + query.len();
+                spans.push(Span::styled(&text[..start], style));
+                spans.push(Span::styled(&text[start..end], style.reversed()));
+                spans.push(Span::styled(&text[end..], style));
+            }
+            None => spans.push(Span::styled(text, style)),
+        }
+        Line::from(spans)
+    }
+
+    /// replaces every screen while the terminal is too small to lay them out
+    fn render_resize_prompt(&self, frame: &mut Frame, area: Rect) {
+        let lines = vec![
+            Line::from("please resize your terminal"),
+            Line::from(format!(
+                "to at least {}x{}",
+                MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT
+            )),
+            Line::from(format!("(currently {}x{})", area.width, area.height)).fg(self.theme.muted),
+        ];
+        let popup = center(area, Constraint::Percentage(100), Constraint::Length(3));
+        frame.render_widget(Paragraph::new(lines).centered(), popup);
+    }
+
+    /// renders a single line of the player's most important stats,
+    /// shown in place of the sidebar when it is hidden
+    fn render_status_line(&self, frame: &mut Frame, area: Rect) {
+        let fighter = self.objects.get(&PLAYER).unwrap().fighter.as_ref().unwrap();
+        let hp_color = if fighter.hp * 3 <= fighter.max_hp {
+            self.theme.log_danger
+        } else {
+            Color::default()
+        };
+        let mut spans = vec![
+            Span::from(format!("HP {}/{}", fighter.hp, fighter.max_hp)).fg(hp_color),
+            Span::from(format!("  Depth {:0>2}", self.gamemap.level)),
+            Span::from(format!("  Gold {}", shop::player_gold(self))),
+        ];
+        if active_alarm(self).is_some() {
+            spans.push(Span::from("  ALARM").fg(self.theme.log_danger));
+        }
+        if self.macros.is_recording() {
+            spans.push(Span::from("  recording").fg(self.theme.log_danger));
+        }
+        let burden = inventory::encumbrance(self);
+        if burden != Encumbrance::Unburdened {
+            spans.push(Span::from(format!("  {}", burden)).fg(self.theme.status));
+        }
+        spans.push(Span::from("  (i for sidebar)").fg(self.theme.muted));
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    /// renders a popup over the map describing the floor the player just left
+    fn render_level_summary(&self, frame: &mut Frame, area: Rect, summary: &LevelSummary) {
+        let explored_percent = 100 * summary.explored_tiles / summary.total_tiles.max(1);
+        let lines = vec![
+            Line::from(format!("Monsters slain: {}", summary.kills)),
+            Line::from(format!("Monsters left:  {}", summary.monsters_left)),
+            Line::from(format!("Items found:    {}", summary.items_found)),
+            Line::from(format!("Items left:     {}", summary.items_left)),
+            Line::from(format!(
+                "Time spent:     {}",
+                time_string(summary.time_spent).trim()
+            )),
+            Line::from(format!("Explored:       {}%", explored_percent)),
+            Line::from(""),
+            Line::from("press any key to continue").fg(self.theme.muted),
+        ];
+
+        let popup = center(area, Constraint::Length(32), Constraint::Length(10));
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!("depth {:0>2} summary", summary.level))
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders every key binding in place of the map, grouped by category
+    fn render_help(&self, frame: &mut Frame, area: Rect, offset: usize) {
+        let mut lines = Vec::new();
+        for category in CATEGORIES {
+            lines.push(Line::from(category.title()).bold());
+            for binding in keybindings::bindings_in(category) {
+                lines.push(Line::from(vec![
+                    Span::from(format!("  {:<18}", binding.keys)).fg(self.theme.status),
+                    Span::from(binding.action),
+                ]));
+            }
+            lines.push(Line::from(""));
+        }
+        let lines: Vec<Line> = lines.into_iter().skip(offset).collect();
+
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .title("keys (j/k to scroll, ? for the manual, esc to close)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders a popup over the map with the command being typed, and the commands
+    /// matching it below with the cursor on the one enter runs
+    fn render_command_palette(&self, frame: &mut Frame, area: Rect, input: &str, cursor: usize) {
+        let (query, _) = keybindings::split_query(input);
+        let matches = keybindings::matching_commands(query);
+
+        let mut lines = vec![Line::from(format!(":{}", input)), Line::from("")];
+        for (index, command) in matches.iter().enumerate() {
+            let line = Line::from(vec![
+                Span::from(format!("{:<20}", command.name)),
+                Span::from(command.description).fg(self.theme.muted),
+            ]);
+            lines.push(match index == cursor {
+                true => line.reversed(),
+                false => line,
+            });
+        }
+        if matches.is_empty() {
+            lines.push(Line::from("no matching commands").fg(self.theme.muted));
+        }
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let popup = center(area, Constraint::Length(64), Constraint::Length(height));
+        let paragraph = Paragraph::new(lines).block(
+            Block::default()
+                .title("commands (enter to run, esc to close)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders a popup over the map listing this run's quests, and how each went
+    fn render_quests(&self, frame: &mut Frame, area: Rect) {
+        let mut lines: Vec<Line> = self
+            .quests
+            .iter()
+            .map(|quest| {
+                let text = format!("{:<42}", quest.description());
+                match quest.state {
+                    QuestState::Waiting | QuestState::Active => Line::from(text),
+                    QuestState::Completed => Line::from(text + "  done").fg(self.theme.gold),
+                    QuestState::Failed => Line::from(text + "  failed").fg(self.theme.muted),
+                }
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from("No quests this run."));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("press any key to continue").fg(self.theme.muted));
+
+        let height = lines.len() as u16 + 2;
+        let popup = center(area, Constraint::Length(54), Constraint::Length(height));
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("quests").borders(Borders::ALL));
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders a popup over the map with what an npc is saying, and the numbered
+    /// replies the player can pick from
+    fn render_dialogue(&self, frame: &mut Frame, area: Rect, speaker: usize, node: usize) {
+        let obj = self.objects.get(&speaker).unwrap();
+        let Some(dialogue) = &obj.dialogue else {
+            return;
+        };
+        let node = &dialogue.nodes[node];
+
+        let mut lines = vec![Line::from(node.text.as_str()), Line::from("")];
+        for (index, option) in node.options.iter().enumerate() {
+            lines.push(Line::from(format!("{}. {}", index + 1, option.text)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from("esc to leave").fg(self.theme.muted));
+
+        // the npc's line wraps, so leave room for it to take up a few rows
+        let width = DIALOGUE_WIDTH.min(area.width);
+        let text_rows = node.text.len() as u16 / width.saturating_sub(2).max(1) + 1;
+        let height = text_rows + node.options.len() as u16 + 5;
+        let popup = center(area, Constraint::Length(width), Constraint::Length(height));
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+            Block::default()
+                .title(obj.name.as_str())
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(Clear, popup);
+        frame.render_widget(paragraph, popup);
+    }
+
+    /// renders the list of waypoints on this floor in place of the map
+    fn render_waypoints(&self, frame: &mut Frame, area: Rect, cursor: usize) {
+        let player_pos = self.gamemap.get_position(PLAYER).unwrap();
+        let mut lines = vec![Line::from("enter to travel, d to delete"), Line::from("")];
+        for (index, waypoint) in self.gamemap.waypoints.iter().enumerate() {
+            let line = Line::from(format!(
+                "{:<20}  {:>3} steps away",
+                waypoint.name,
+                player_pos.distance_to(waypoint.position)
+            ));
+            if index == cursor {
+                lines.push(line.style(Style::new().black().on_gray()));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("waypoints").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders the spells the player knows in place of the map
+    fn render_spells(&self, frame: &mut Frame, area: Rect, cursor: usize) {
+        let (mana, _) = spells::player_mana(self).unwrap_or((0, 0));
+        let mut lines = vec![Line::from("enter to cast"), Line::from("")];
+        for (index, spell) in spells::known_spells(self).iter().enumerate() {
+            let mut line = Line::from(format!(
+                "{:<20}  {:>2} mana",
+                spell.name(),
+                spell.mana_cost()
+            ));
+            if spell.mana_cost() > mana {
+                line = line.fg(self.theme.muted);
+            }
+            if index == cursor {
+                lines.push(line.style(Style::new().black().on_gray()));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("spells").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// renders the list of items that can be bought or sold in place of the map
+    fn render_shop(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        shopkeeper: usize,
+        cursor: usize,
+        selling: bool,
+    ) {
+        let title = if selling {
+            "shop - selling (tab to buy)"
+        } else {
+            "shop - buying (tab to sell)"
+        };
+
+        let mut lines = vec![
+            Line::from(format!("Gold: {}", shop::player_gold(self))).fg(self.theme.gold),
+            Line::from(""),
+        ];
+
+        let listing = self.get_shop_listing(shopkeeper, selling);
+        for (index, (id, price)) in listing.iter().enumerate() {
+            let line = Line::from(format!(
+                "{:>5} gold  {}",
+                price,
+                identify::stack_name(self, *id)
+            ));
+            if index == cursor {
+                lines.push(line.style(Style::new().black().on_gray()));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        if listing.is_empty() {
+            lines.push(Line::from("nothing to trade."));
+        }
+
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
+    /// displays the description of the item highlighted in the shop
+    fn render_shop_info(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        shopkeeper: usize,
+        cursor: usize,
+        selling: bool,
+    ) {
+        let lines: Vec<Line> = match self.get_shop_listing(shopkeeper, selling).get(cursor) {
+            Some(&(id, _)) => self
+                .get_object_description(id)
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            None => vec![],
+        };
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title("item").borders(Borders::ALL));
         frame.render_widget(paragraph, area);
     }
 
     /// displays the targeting info box.
     /// works like render_examine_info, but with an extra line about what you are targeting
-    fn render_targeting_info(&self, frame: &mut Frame, area: Rect, cursor: &Position, text: &str) {
+    fn render_targeting_info(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        cursor: &Position,
+        text: &str,
+        problem: Option<&str>,
+    ) {
         let mut lines = vec![Line::from(text)];
+        if let Some(problem) = problem {
+            lines.push(Line::from(format!("    {}", problem)).fg(self.theme.blocked));
+        }
         lines.extend(
             self.get_name_at_cursor(cursor)
                 .into_iter()
                 .map(|x| format!("    {}", x))
-                .map(|x| Line::from(x)),
+                .map(Line::from),
         );
         let paragraph =
             Paragraph::new(lines).block(Block::default().title("targeting").borders(Borders::ALL));
@@ -528,21 +1762,121 @@ impl App {
     fn get_object_description(&self, id: usize) -> Vec<String> {
         let object = self.objects.get(&id).unwrap();
 
+        // unidentified items only show what they look like
+        let unidentified = identify::is_unidentified(self, id);
+
         let mut description = Vec::new();
-        description.push(object.name.clone());
-        description.push(format!("    {}", object.tooltip.clone()));
+        description.push(identify::item_name(self, id));
+        if unidentified {
+            description.push(String::from("    you don't know what this is yet."));
+        } else {
+            description.push(format!("    {}", object.tooltip.clone()));
+        }
+        if object.item.is_some() && object.weight > 0 {
+            description.push(format!("    weighs {}", object.weight));
+        }
+        if object.ai.is_some() && object.fighter.is_some() {
+            description.push(format!(
+                "    hits for {}, {} defense",
+                attack_dice(self, id),
+                defense(self, id)
+            ));
+            description.push(format!(
+                "    {:.0}% chance for you to hit, {:.0}% for it to hit you",
+                hit_chance(self, PLAYER, id) * 100.0,
+                hit_chance(self, id, PLAYER) * 100.0
+            ));
+        }
+        if let Some(AIType::Melee(ai_data)) = &object.ai {
+            description.push(format!("    {}", ai_state(object, ai_data)));
+            if ai_data.post.is_some() {
+                description.push(String::from("    standing guard"));
+            }
+        }
+        if !object.equipped.is_empty() {
+            let gear: Vec<String> = object
+                .equipped
+                .iter()
+                .map(|&id| identify::stack_name(self, id))
+                .collect();
+            description.push(format!("    wearing {}", gear.join(", ")));
+        }
+        if !object.carried.is_empty() {
+            let items: Vec<String> = object
+                .carried
+                .iter()
+                .map(|&id| identify::stack_name(self, id))
+                .collect();
+            description.push(format!("    carrying {}", items.join(", ")));
+        }
+        if !unidentified && object.equipment.as_ref().is_some_and(|equip| equip.cursed) {
+            description.push(String::from("    it is cursed, and can't be taken off."));
+        }
+        if !unidentified && let Some(comparison) = self.get_equip_comparison(id) {
+            description.push(format!("    {}", comparison));
+        }
+        description.extend(
+            self.get_status_descriptions(id)
+                .into_iter()
+                .map(|x| format!("    {}", x)),
+        );
 
-        return description;
+        description
     }
 
-    fn get_tile_description(&self, tile: &Tile) -> Vec<String> {
-        if *tile == Tile::new(TileType::Floor) {
-            return vec!["the floor".to_string()];
-        } else if *tile == Tile::new(TileType::Wall) {
-            return vec!["a wall".to_string()];
-        } else {
-            return vec!["unknown tile type. this is a bug".to_string()];
+    /// describes how equipping an item would change the player's stats,
+    /// e.g. "+1 power, -1 defense vs. dagger". None if the item isn't equipment or is worn
+    fn get_equip_comparison(&self, id: usize) -> Option<String> {
+        if self.equipment.contains(&Some(id)) {
+            return None;
         }
+        let (power_delta, defense_delta, replaced) = equip_delta(self, id)?;
+        let replaced_name = match replaced.is_empty() {
+            true => String::from("nothing"),
+            false => replaced
+                .iter()
+                .map(|&replaced_id| identify::item_name(self, replaced_id))
+                .collect::<Vec<_>>()
+                .join(" and "),
+        };
+        Some(format!(
+            "{:+} power, {:+} defense vs. {}",
+            power_delta, defense_delta, replaced_name
+        ))
+    }
+
+    /// returns a line for each active status on a fighter, with the time remaining
+    fn get_status_descriptions(&self, id: usize) -> Vec<String> {
+        let Some(fighter) = self.objects.get(&id).and_then(|obj| obj.fighter.as_ref()) else {
+            return Vec::new();
+        };
+
+        fighter
+            .statuses
+            .iter()
+            .filter(|status| status.expires > self.time)
+            .map(|status| {
+                format!(
+                    "{} ({})",
+                    status.kind,
+                    time_string(status.expires - self.time).trim_end()
+                )
+            })
+            .collect()
+    }
+
+    fn get_tile_description(&self, tile: &Tile) -> Vec<String> {
+        let description = match tile.tile_type {
+            TileType::Floor => "the floor",
+            TileType::Wall => "a wall",
+            TileType::Chasm => "a chasm. it looks like a long way down",
+            TileType::Water => "shallow water. it's slow to wade through",
+            TileType::Lava => "a pool of lava. it burns anything that stands in it",
+            TileType::Rubble => "a heap of rubble. it's slow to climb over",
+            TileType::Grass => "a patch of dry grass. it catches fire easily",
+            TileType::LockedDoor => "a locked door. somewhere on this floor is a key that opens it",
+        };
+        vec![description.to_string()]
     }
 
     /// returns a vec containing the description of the object highlighted by the cursor
@@ -553,17 +1887,38 @@ impl App {
         }
 
         let tile = self.gamemap.get_ref(cursor.x, cursor.y);
+        // whatever is inside a cloud of smoke stays hidden
+        if self
+            .gamemap
+            .effect_at(cursor.x, cursor.y, EffectKind::Smoke)
+            .is_some()
+            && tile.blocker != Some(PLAYER)
+        {
+            return vec!["a cloud of smoke. nothing can see through it".to_string()];
+        }
         let mut desc = Vec::new();
         if let Some(id) = tile.blocker {
             desc.extend(self.get_object_description(id));
         }
-        if let Some(id) = tile.item {
+        if let Some(id) = known_item(self, cursor.x, cursor.y) {
             desc.extend(self.get_object_description(id));
         }
+        if self.gamemap.is_burning(cursor.x, cursor.y) {
+            desc.push("a fire. anything that stands in it catches alight".to_string());
+        }
+        if self
+            .gamemap
+            .effect_at(cursor.x, cursor.y, EffectKind::Poison)
+            .is_some()
+        {
+            desc.push(
+                "a cloud of poison gas. anything that breathes it in is poisoned".to_string(),
+            );
+        }
         if desc.is_empty() {
             desc.extend(self.get_tile_description(tile));
         }
-        return desc;
+        desc
     }
 
     /// returns the name of the object highlighted at the cursor
@@ -573,20 +1928,36 @@ impl App {
         }
 
         let tile = self.gamemap.get_ref(cursor.x, cursor.y);
+        if self
+            .gamemap
+            .effect_at(cursor.x, cursor.y, EffectKind::Smoke)
+            .is_some()
+            && tile.blocker != Some(PLAYER)
+        {
+            return vec!["a cloud of smoke".to_string()];
+        }
         if let Some(id) = tile.blocker {
             let obj = self.objects.get(&id).unwrap();
             return vec![obj.name.clone()];
         }
-        if let Some(id) = tile.item {
-            let obj = self.objects.get(&id).unwrap();
-            return vec![obj.name.clone()];
+        if let Some(id) = known_item(self, cursor.x, cursor.y) {
+            return vec![identify::item_name(self, id)];
         }
-        return self.get_tile_description(tile);
+        if self.gamemap.is_burning(cursor.x, cursor.y) {
+            return vec!["a fire".to_string()];
+        }
+        if self
+            .gamemap
+            .effect_at(cursor.x, cursor.y, EffectKind::Poison)
+            .is_some()
+        {
+            return vec!["a cloud of poison gas".to_string()];
+        }
+        self.get_tile_description(tile)
     }
 
-    /// converts the log into a list of lines,
-    /// used in `render_log` / `render_fullscreen_log`
-    fn get_lines_from_log(&self) -> Vec<Line> {
+    /// converts the log into a list of lines, used in `render_log`
+    fn get_lines_from_log(&self) -> Vec<Line<'_>> {
         self.log
             .iter()
             .map(|entry| {
@@ -595,15 +1966,55 @@ impl App {
                     time_string(entry.time),
                     entry.message.as_str()
                 ))
-                .style(entry.style)
+                .style(self.theme.log_style(entry.style))
             })
             .collect()
     }
 
+    /// renders a strip showing which visible actors will act next, and how long until they do.
+    /// the player is always first, since the game waits on the player's input
+    fn render_turn_order(&self, frame: &mut Frame, area: Rect) {
+        let player = self.objects.get(&PLAYER).unwrap();
+        let mut spans = vec![Span::styled(
+            format!("{} +{}", player.renderable.glyph, time_string(0).trim_end()),
+            Style::default().fg(player.renderable.fg_in(&self.theme)),
+        )];
+
+        let visible_actions = self.peek_actions().into_iter().filter(|action| {
+            match self.gamemap.get_position(action.id) {
+                Some(pos) => self.gamemap.is_visible(pos.x, pos.y),
+                None => false,
+            }
+        });
+
+        for action in visible_actions.take(TURN_ORDER_LENGTH) {
+            let obj = self.objects.get(&action.id).unwrap();
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(
+                format!(
+                    "{} +{}",
+                    obj.renderable.glyph,
+                    time_string(action.time.saturating_sub(self.time)).trim_end()
+                ),
+                Style::default().fg(obj.renderable.fg_in(&self.theme)),
+            ));
+        }
+
+        let paragraph = Paragraph::new(Line::from(spans))
+            .block(Block::default().title("turn order").borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
     /// renders the text in the log
     fn render_log(&self, frame: &mut Frame, area: Rect) {
+        // the log has no room when it is collapsed
+        if area.height == 0 {
+            return;
+        }
         let mut lines = self.get_lines_from_log();
-        let display_idx = lines.len().saturating_sub(area.height as usize - 2);
+        let display_idx = lines
+            .len()
+            .saturating_sub(area.height.saturating_sub(2) as usize);
         let lines_to_render = lines.split_off(display_idx);
 
         let paragraph = Paragraph::new(lines_to_render)
@@ -611,25 +2022,86 @@ impl App {
         frame.render_widget(paragraph, area);
     }
 
-    /// renders log text with offset to the fullscreen log viewer
-    /// returns the given offset clamped to be in bounds
-    fn render_fullscreen_log(&self, frame: &mut Frame, area: Rect, offset: usize) {
-        let mut lines = self.get_lines_from_log();
+    /// renders log text with offset to the fullscreen log viewer, with a search bar below it.
+    /// only messages of the filtered kind are shown, and matches of the search are highlighted
+    fn render_fullscreen_log(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        offset: usize,
+        query: &str,
+        searching: bool,
+        filter: Option<MessageKind>,
+    ) {
+        let [log_area, footer_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(LOG_SEARCH_HEIGHT)])
+                .areas(area);
+
+        let mut lines: Vec<Line> = self
+            .log
+            .filtered(filter)
+            .into_iter()
+            .map(|entry| self.log_line(entry, query))
+            .collect();
         let split_idx = lines
             .len()
-            .saturating_sub(area.height as usize + offset - 2);
+            .saturating_sub((log_area.height as usize + offset).saturating_sub(2));
 
         let _overflow_lines = lines.split_off(lines.len() - offset); // delete the bottom offset lines
-        let lines_to_render = lines.split_off(split_idx); // split off enough lines to fill the log
+        let lines_to_render = lines.split_off(split_idx.min(lines.len())); // split off enough lines to fill the log
 
+        let title = match filter {
+            Some(kind) => format!("log - {}", kind),
+            None => String::from("log"),
+        };
         let paragraph = Paragraph::new(lines_to_render)
-            .block(Block::default().title("log").borders(Borders::ALL));
-        frame.render_widget(paragraph, area);
+            .block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(paragraph, log_area);
+
+        let footer = if searching {
+            Line::from(format!("/{}_", query))
+        } else if query.is_empty() {
+            Line::from("j/k: scroll  /: search  f: filter by kind  esc: close").fg(self.theme.muted)
+        } else if self.log.find_match(query, filter, offset, true).is_none() {
+            Line::from(format!("no matches for \"{}\"", query)).fg(self.theme.log_warning)
+        } else {
+            Line::from(format!("\"{}\"  n/N: older/newer match", query))
+        };
+        let paragraph =
+            Paragraph::new(footer).block(Block::default().title("search").borders(Borders::ALL));
+        frame.render_widget(paragraph, footer_area);
+    }
+
+    /// styles a message of the log, highlighting where it matches the search
+    fn log_line(&self, entry: &LogEntry, query: &str) -> Line<'static> {
+        let style = self.theme.log_style(entry.style);
+        let text = entry.message.as_str();
+
+        // only look for matches where lowercasing keeps the byte offsets the same
+        let found = match query.is_empty() || !text.is_ascii() {
+            true => None,
+            false => text.to_lowercase().find(&query.to_lowercase()),
+        };
+        let mut spans = vec![Span::from(format!("{} ", time_string(entry.time)))];
+        match found {
+            Some(start) => {
+                let end = start + query.len();
+                spans.push(Span::from(text[..start].to_string()));
+                spans.push(Span::from(text[start..end].to_string()).reversed());
+                spans.push(Span::from(text[end..].to_string()));
+            }
+            None => spans.push(Span::from(text.to_string())),
+        }
+        Line::from(spans).style(style)
     }
 
     /// renders healthbar and stats on the left side of the screen
     fn render_status(&self, frame: &mut Frame, area: Rect) {
-        let block = Block::default().title("character").borders(Borders::ALL);
+        let title = match self.objects.get(&PLAYER).unwrap().class {
+            Some(class) => format!("character - {}", class),
+            None => String::from("character"),
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
         frame.render_widget(block, area);
 
         // first split the area vertically
@@ -647,28 +2119,27 @@ impl App {
         let dungeon_area = layout[1]; // for displaying time and dungeon depth
         let stats_area = layout[2]; // for displaying player stats
 
-        // render health bar gauge on top most area
-        let layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![Constraint::Length(12), Constraint::Percentage(100)])
+        // render health bar gauge on top most area, with the mana gauge under it
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1), Constraint::Length(1)])
             .split(gauges_area);
-        let label_area = layout[0];
-        let gauge_area = layout[1];
-
-        let player = &self.objects.get(&PLAYER).unwrap();
-        let fighter = &player.fighter.as_ref().unwrap();
-        let ratio = fighter.hp as f64 / fighter.max_hp as f64;
-
-        let label_text = format!("HP: {}/{}", fighter.hp, fighter.max_hp);
-        let health_label = Paragraph::new(label_text);
-
-        let health_gauge = AsciiGauge::default()
-            .set_ratio(ratio)
-            .set_filled_style(Style::default().fg(Color::Green))
-            .set_unfilled_style(Style::default().fg(Color::Red));
-
-        frame.render_widget(health_label, label_area);
-        frame.render_widget(health_gauge, gauge_area);
+        self.render_health_bar(frame, rows[0], PLAYER);
+
+        // only players who have learned a spell have any mana to show
+        if let Some((mana, max_mana)) = spells::player_mana(self) {
+            let layout = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Length(12), Constraint::Percentage(100)])
+                .split(rows[1]);
+            let mana_label = Paragraph::new(format!("MP: {}/{}", mana, max_mana));
+            let mana_gauge = AsciiGauge::default()
+                .set_ratio(mana as f64 / max_mana as f64)
+                .set_filled_style(Style::default().fg(self.theme.mana_full))
+                .set_unfilled_style(Style::default().fg(self.theme.mana_empty));
+            frame.render_widget(mana_label, layout[0]);
+            frame.render_widget(mana_gauge, layout[1]);
+        }
 
         // render dungeon stats in the middle
 
@@ -687,11 +2158,37 @@ impl App {
         frame.render_widget(time_paragraph, time_area);
         frame.render_widget(depth_paragraph, depth_area);
 
-        // render player stats on bottom
-        let lines: Vec<Line> = vec![
-            Line::from(format!("ATK {}", power(self, PLAYER))),
+        // render player stats on bottom, followed by any active statuses
+        let mut lines: Vec<Line> = vec![
+            Line::from(format!("ATK {}", attack_dice(self, PLAYER))),
             Line::from(format!("DEF {}", defense(self, PLAYER))),
+            Line::from(format!("Gold {}", shop::player_gold(self))),
         ];
+        lines.extend(
+            self.get_status_descriptions(PLAYER)
+                .into_iter()
+                .map(|x| Line::from(x).fg(self.theme.status)),
+        );
+        if active_alarm(self).is_some() {
+            lines.push(Line::from("ALARM").fg(self.theme.log_danger));
+        }
+        let burden = inventory::encumbrance(self);
+        if burden != Encumbrance::Unburdened {
+            lines.push(Line::from(burden.to_string()).fg(self.theme.status));
+        }
+        if self.safe_move {
+            lines.push(Line::from("safe move").fg(self.theme.muted));
+        }
+        if self.sneaking {
+            lines.push(Line::from("sneaking").fg(self.theme.muted));
+        }
+        if !lighting::player_is_lit(self) {
+            lines.push(Line::from("in the dark").fg(self.theme.muted));
+        }
+        if self.macros.is_recording() {
+            lines.push(Line::from("recording").fg(self.theme.log_danger));
+        }
+        lines.push(Line::from(format!("Seed {}", self.seed)).fg(self.theme.muted));
         let paragraph = Paragraph::new(lines);
         frame.render_widget(paragraph, stats_area);
     }
@@ -702,29 +2199,24 @@ impl App {
 
         let mut lines: Vec<Line> = Vec::new();
 
-        let chars = ["A", "B", "C"];
-        let mut index = 0;
-
-        // check: assert that the char array for equipment slot labels matches up with the actual
-        // number of slots
-        assert_eq!(chars.len(), SLOT_ORDERING.len());
-
-        while index < chars.len() {
+        for (index, slot) in SLOT_ORDERING.iter().enumerate() {
             lines.push(Line::from(format!(
-                "({}) {:8} {}",
-                chars[index],
-                format!("{}:", SLOT_ORDERING[index]),
+                "({}) {:9} {}",
+                slot_key(index),
+                format!("{}:", slot),
                 {
                     match self.equipment[index] {
-                        Some(id) => {
-                            let obj = self.objects.get(&id).unwrap();
-                            obj.name.clone()
+                        Some(id) => identify::stack_name(self, id),
+                        // the off hand is taken up by a two-handed weapon
+                        None if index == Slot::OffHand as usize
+                            && inventory::wielding_two_handed(self) =>
+                        {
+                            String::from("(two-handed)")
                         }
                         None => String::from("(empty)"),
                     }
                 }
             )));
-            index += 1;
         }
 
         let paragraph = Paragraph::new(lines);
@@ -736,21 +2228,28 @@ impl App {
     }
 
     fn render_inventory(&self, frame: &mut Frame, area: Rect) {
-        let block = Block::default().title("inventory").borders(Borders::ALL);
+        let weight = format!(
+            "inventory - {}/{}",
+            inventory::carried_weight(self),
+            inventory::carry_capacity(self)
+        );
+        let title = match inventory::encumbrance(self) {
+            Encumbrance::Unburdened => Line::from(weight),
+            burden => Line::from(format!("{} {}", weight, burden)).fg(self.theme.log_danger),
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
         frame.render_widget(block, area);
 
         let mut lines: Vec<Line> = Vec::new();
-        let mut index = 1;
-        for id in &self.inventory {
+        for (index, id) in (1..).zip(self.inventory.iter()) {
             lines.push(Line::from(format!(
                 "({}) {}",
                 index % 10,
-                self.objects.get(id).unwrap().name
+                identify::stack_name(self, *id)
             )));
-            index += 1;
         }
 
-        if self.inventory.len() == 0 {
+        if self.inventory.is_empty() {
             lines.push(Line::from("inventory is empty."));
         }
 
@@ -1,13 +1,82 @@
-use color_eyre::{Result, eyre::Ok};
+use color_eyre::Result;
+use rand::{SeedableRng, rngs::StdRng};
+use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BinaryHeap,
-    fs::File,
-    io::{Read, Write},
+    fs::{self, File},
+    io::Write,
 };
 
-use super::{App, Log, ObjectMap};
-use crate::{app::Action, gamemap::GameMap};
+use super::{App, GameScreen, Log, MessageKind, ObjectMap, fnv1a, ghost::Ghost};
+use crate::{
+    achievements::Conduct, app::Action, components::SLOT_ORDERING, gamemap::GameMap,
+    identify::Identification, quests::Quest, scores, settings::TamperPolicy,
+};
+
+// NOTE: saves are written as json followed by a line with their checksum.
+// the checksum is keyed, so editing the json by hand without also knowing the key
+// is noticed. it isn't real cryptography, just enough to catch casual edits
+
+const SAVE_FILE: &str = "savegame";
+/// written every few turns and on every new floor, in case the game doesn't get to quit
+const AUTOSAVE_FILE: &str = "autosave";
+/// exists while a run is being played. if it's still there when the game starts up,
+/// the last session never quit cleanly and the autosave is the latest state of the run
+const RUNNING_FILE: &str = "savegame.running";
+
+/// mixed into every checksum, so that it can't be recomputed from the json alone
+const SAVE_KEY: &[u8] = b"the amulet of ages is not for sale";
+
+/// returns the file name of a backup, where backup 1 is the most recent
+fn backup_file_name(backup: usize) -> String {
+    format!("{}.{}", SAVE_FILE, backup)
+}
+
+/// keyed 64 bit fnv-1a hash of the save data
+fn checksum(data: &str) -> u64 {
+    fnv1a(SAVE_KEY.iter().chain(data.as_bytes()).copied())
+}
+
+/// reasons a save couldn't be loaded
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// there is no save file
+    Missing,
+    /// the file isn't a save anymore, e.g. it was cut off while being written
+    Corrupted,
+    /// the save is intact, but was changed outside of the game
+    Tampered,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Missing => write!(f, "There is no saved game to load."),
+            LoadError::Corrupted => write!(f, "The saved game is corrupted."),
+            LoadError::Tampered => write!(f, "The saved game was changed outside of the game."),
+        }
+    }
+}
+
+/// reads and verifies a save file. saves from before checksums were added are only
+/// accepted if tampered saves are
+fn read_save(file_name: &str, policy: TamperPolicy) -> Result<(SaveData, bool), LoadError> {
+    let contents = fs::read_to_string(file_name).map_err(|_| LoadError::Missing)?;
+
+    let (json, tag) = match contents.trim_end().rsplit_once('\n') {
+        Some((json, tag)) if tag.len() == 16 => (json, Some(tag)),
+        _ => (contents.as_str(), None),
+    };
+    // anything that doesn't parse got damaged by something other than a person editing it
+    let save_data = serde_json::from_str::<SaveData>(json).map_err(|_| LoadError::Corrupted)?;
+
+    let intact = tag.is_some_and(|tag| tag == format!("{:016x}", checksum(json)));
+    if !intact && policy == TamperPolicy::Refuse {
+        return Err(LoadError::Tampered);
+    }
+    Ok((save_data, intact))
+}
 
 #[derive(Serialize, Deserialize)]
 struct SaveData {
@@ -18,11 +87,119 @@ struct SaveData {
     inventory: Vec<usize>,
     equipment: Vec<Option<usize>>,
     log: Log,
+    #[serde(default)]
+    safe_move: bool,
+    #[serde(default)]
+    kills: u32,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    identification: Identification,
+    #[serde(default)]
+    replay: Ghost,
+    #[serde(default)]
+    sneaking: bool,
+    #[serde(default)]
+    quests: Vec<Quest>,
+    #[serde(default)]
+    conduct: Conduct,
+    #[serde(default)]
+    daily: Option<String>,
 }
 
 impl App {
-    /// saves current game state to a file
+    /// saves current game state to a file, after moving the last few saves into backups
     pub fn save_game(&self) -> Result<()> {
+        // shift every backup down by one, dropping the oldest
+        let backups = self.settings.save_backups;
+        if backups > 0 {
+            for backup in (1..backups).rev() {
+                let _ = fs::rename(backup_file_name(backup), backup_file_name(backup + 1));
+            }
+            let _ = fs::copy(SAVE_FILE, backup_file_name(1));
+        }
+
+        self.write_save_file(SAVE_FILE)?;
+        // the real save is newer than the autosave now
+        self.end_autosaves();
+        Ok(())
+    }
+
+    /// saves the run to the autosave file, and marks it as still being played.
+    /// does nothing for games that don't keep records, like the demo
+    pub fn autosave(&mut self) {
+        if !self.keeps_records() {
+            return;
+        }
+        let result = self.write_save_file(AUTOSAVE_FILE).and_then(|_| {
+            File::create(RUNNING_FILE)?;
+            Ok(())
+        });
+        if let Err(error) = result {
+            self.add_to_log(
+                format!("Couldn't autosave: {}", error),
+                Color::Yellow,
+                MessageKind::System,
+            );
+        }
+    }
+
+    /// throws away the autosave, once the run is saved properly or over
+    pub fn end_autosaves(&self) {
+        let _ = fs::remove_file(AUTOSAVE_FILE);
+        let _ = fs::remove_file(RUNNING_FILE);
+    }
+
+    /// deletes the save, its backups and the autosave once the run is over, so it can't
+    /// be played again from an earlier point. wizard mode keeps the save for debugging
+    pub fn forfeit_saves(&mut self) {
+        self.end_autosaves();
+        if self.settings.wizard_mode {
+            self.add_to_log(
+                "Wizard mode is on, so your save was kept.",
+                Color::Yellow,
+                MessageKind::System,
+            );
+            return;
+        }
+        let _ = fs::remove_file(SAVE_FILE);
+        for backup in 1..=self.settings.save_backups {
+            let _ = fs::remove_file(backup_file_name(backup));
+        }
+    }
+
+    /// whether there is a run going on that quitting should save. there's nothing to save
+    /// on the menus before a run starts, and a finished run can't be picked up again
+    pub fn run_in_progress(&self) -> bool {
+        !matches!(
+            self.game_screen,
+            GameScreen::Menu
+                | GameScreen::EnterSeed { .. }
+                | GameScreen::CharacterCreation { .. }
+                | GameScreen::LoadFailed { .. }
+                | GameScreen::DataError { .. }
+                | GameScreen::Settings { in_game: false, .. }
+                | GameScreen::GameOver
+                | GameScreen::Victory
+        )
+    }
+
+    /// loads the autosave if the last session ended without quitting, e.g. in a crash
+    pub fn recover_autosave(&mut self) {
+        if fs::metadata(RUNNING_FILE).is_err() || self.load_save_file(AUTOSAVE_FILE).is_err() {
+            return;
+        }
+        self.switch_to_main_screen();
+        self.add_to_log(
+            "The game didn't shut down properly last time, so your autosave was loaded.",
+            Color::Yellow,
+            MessageKind::System,
+        );
+    }
+
+    /// writes the save to a temporary file first, then moves it over the old one, so
+    /// that a crash halfway through writing can't leave a broken save behind
+    fn write_save_file(&self, file_name: &str) -> Result<()> {
         let save_data = SaveData {
             gamemap: self.gamemap.clone(),
             objects: self.objects.clone(),
@@ -31,21 +208,46 @@ impl App {
             inventory: self.inventory.clone(),
             equipment: self.equipment.clone(),
             log: self.log.clone(),
+            safe_move: self.safe_move,
+            kills: self.kills,
+            seed: self.seed,
+            identification: self.identification.clone(),
+            replay: self.replay.clone(),
+            sneaking: self.sneaking,
+            quests: self.quests.clone(),
+            conduct: self.conduct.clone(),
+            daily: self.daily.clone(),
         };
 
         let data_str = serde_json::to_string(&save_data)?;
-        let mut file = File::create("savegame")?;
+        let temp_name = format!("{}.tmp", file_name);
+        let mut file = File::create(&temp_name)?;
         file.write_all(data_str.as_bytes())?;
+        file.write_all(format!("\n{:016x}\n", checksum(&data_str)).as_bytes())?;
+        file.sync_all()?;
+        fs::rename(temp_name, file_name)?;
         Ok(())
     }
 
-    /// loads gamestate data from a save file
-    /// NOTE: if the save file doesn't exist, it just crashes :sob:
-    pub fn load_game(&mut self) -> Result<()> {
-        let mut save_string = String::new();
-        let mut file = File::open("savegame")?;
-        file.read_to_string(&mut save_string)?;
-        let save_data = serde_json::from_str::<SaveData>(&save_string)?;
+    /// returns the most recent backup that can still be loaded, if there is one
+    pub fn newest_backup(&self) -> Option<usize> {
+        (1..=self.settings.save_backups).find(|&backup| {
+            read_save(&backup_file_name(backup), self.settings.tampered_saves).is_ok()
+        })
+    }
+
+    /// loads gamestate data from the save file
+    pub fn load_game(&mut self) -> Result<(), LoadError> {
+        self.load_save_file(SAVE_FILE)
+    }
+
+    /// loads gamestate data from one of the backups, see newest_backup()
+    pub fn load_backup(&mut self, backup: usize) -> Result<(), LoadError> {
+        self.load_save_file(&backup_file_name(backup))
+    }
+
+    fn load_save_file(&mut self, file_name: &str) -> Result<(), LoadError> {
+        let (save_data, intact) = read_save(file_name, self.settings.tampered_saves)?;
 
         self.gamemap = save_data.gamemap;
         self.objects = save_data.objects;
@@ -53,8 +255,154 @@ impl App {
         self.time = save_data.time;
         self.inventory = save_data.inventory;
         self.equipment = save_data.equipment;
+        // saves from before the off-hand and ring slots have fewer slots
+        self.equipment.resize(SLOT_ORDERING.len(), None);
         self.log = save_data.log;
+        self.safe_move = save_data.safe_move;
+        self.kills = save_data.kills;
+        self.seed = save_data.seed;
+        self.identification = save_data.identification;
+        self.replay = save_data.replay;
+        self.sneaking = save_data.sneaking;
+        self.quests = save_data.quests;
+        self.conduct = save_data.conduct;
+        self.daily = save_data.daily;
+        if self.daily.is_some() {
+            scores::lock_config(self);
+        }
+        self.load_ghost();
+        // the rng's state isn't saved, so continue from a stream derived from the seed
+        // and the current time. the same save always continues the same way
+        self.rng = StdRng::seed_from_u64(self.seed ^ self.time);
 
+        if !intact && self.settings.tampered_saves == TamperPolicy::Warn {
+            self.add_to_log(
+                "This save was changed outside of the game.",
+                Color::Yellow,
+                MessageKind::System,
+            );
+        }
         Ok(())
     }
 }
+
+// every save format the game has ever written is kept in fixtures/saves, named in the
+// order they were made. serde defaults and aliases on the saved components are what
+// migrate them, so these check that old saves still load and can be played on.
+// when the save format changes, add a save written by the new version
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+    use crate::{
+        app::{GameScreen, PLAYER},
+        engine::{InputDirection, bump_action, expire_statuses, handle_monster_turns, update_fov},
+    };
+
+    const FIXTURE_DIR: &str = "fixtures/saves";
+
+    /// returns the path of every save fixture, oldest first
+    fn fixtures() -> Vec<String> {
+        let mut paths: Vec<String> = fs::read_dir(FIXTURE_DIR)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().to_string_lossy().into_owned())
+            .collect();
+        paths.sort();
+        assert!(!paths.is_empty(), "no save fixtures in {}", FIXTURE_DIR);
+        paths
+    }
+
+    fn load_fixture(path: &str) -> App {
+        let mut app = App::new();
+        if let Err(error) = app.load_save_file(path) {
+            panic!("{} didn't load: {}", path, error);
+        }
+        app.game_screen = GameScreen::Main;
+        app
+    }
+
+    #[test]
+    fn old_saves_load() {
+        for path in fixtures() {
+            let app = load_fixture(&path);
+
+            let player = app.objects.get(&PLAYER).unwrap();
+            assert!(player.fighter.as_ref().unwrap().hp > 0, "{}", path);
+            assert!(app.gamemap.get_position(PLAYER).is_some(), "{}", path);
+
+            // everything on the map has to point at an object that still exists
+            for tile in &app.gamemap.tiles {
+                for id in tile.blocker.iter().chain(tile.item.iter()) {
+                    assert!(
+                        app.objects.get(id).is_some(),
+                        "{}: dangling id {}",
+                        path,
+                        id
+                    );
+                }
+            }
+            for id in app.inventory.iter().chain(app.equipment.iter().flatten()) {
+                assert!(
+                    app.objects.get(id).is_some(),
+                    "{}: dangling id {}",
+                    path,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn old_saves_are_playable() {
+        for path in fixtures() {
+            let mut app = load_fixture(&path);
+            let start = app.time;
+
+            for direction in [InputDirection::Right, InputDirection::Left] {
+                bump_action(&mut app, PLAYER, direction);
+                app.time += 100;
+                handle_monster_turns(&mut app);
+                expire_statuses(&mut app);
+                update_fov(&mut app);
+            }
+            assert!(app.time > start, "{}", path);
+
+            let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+        }
+    }
+
+    #[test]
+    fn saves_are_written_whole_or_not_at_all() {
+        let app = load_fixture(&fixtures().pop().unwrap());
+        let path = std::env::temp_dir().join(format!("roguelike-save-{}", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+
+        app.write_save_file(&path).unwrap();
+        assert!(fs::metadata(format!("{}.tmp", path)).is_err());
+        let mut loaded = App::new();
+        assert!(loaded.load_save_file(&path).is_ok());
+        assert_eq!(loaded.time, app.time);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn newest_save_is_intact() {
+        let newest = fixtures().pop().unwrap();
+        let (_, intact) = read_save(&newest, TamperPolicy::Refuse).unwrap();
+        assert!(intact);
+    }
+
+    #[test]
+    fn checksums_catch_edits() {
+        let newest = fixtures().pop().unwrap();
+        let contents = fs::read_to_string(newest).unwrap();
+        let (json, tag) = contents.trim_end().rsplit_once('\n').unwrap();
+        assert_eq!(tag, format!("{:016x}", checksum(json)));
+        assert_ne!(
+            tag,
+            format!("{:016x}", checksum(&json.replacen("1", "2", 1)))
+        );
+    }
+}
@@ -0,0 +1,166 @@
+// rough benchmarks of the game loop, run with
+//     cargo test --release bench -- --ignored --nocapture
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use crate::{
+    app::{Action, App, PLAYER, procgen::DungeonConfig},
+    components::{PlayerClass, Position},
+    engine::{handle_monster_turns, update_fov},
+    entities,
+    gamemap::{Alarm, idx_to_coords},
+    los::LineIter,
+};
+
+/// counts every allocation made by the test binary
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const MONSTERS: usize = 200;
+const TURNS: u64 = 50;
+
+/// fills the biggest kind of floor with monsters that all chase the player
+fn crowded_floor() -> App {
+    let mut app = App::new();
+    app.seed = 1;
+    app.new_game(PlayerClass::Fighter);
+    app.generate_dungeon(DungeonConfig::default().set_level(10));
+    app.action_queue.clear();
+
+    let fighter = app
+        .objects
+        .get_mut(&PLAYER)
+        .unwrap()
+        .fighter
+        .as_mut()
+        .unwrap();
+    fighter.max_hp = u16::MAX;
+    fighter.hp = u16::MAX;
+
+    let free_tiles: Vec<(u16, u16)> = (0..app.gamemap.tiles.len())
+        .map(|idx| idx_to_coords(idx, app.gamemap.width))
+        .filter(|&(x, y)| {
+            let tile = app.gamemap.get_ref(x, y);
+            tile.is_walkable() && tile.blocker.is_none()
+        })
+        .collect();
+    for &(x, y) in free_tiles.iter().take(MONSTERS) {
+        let id = app.objects.add(entities::orc());
+        app.gamemap.place_blocker(id, x, y);
+        app.action_queue.push(Action { time: 0, id });
+    }
+
+    // the alarm sends every monster on the floor towards the player
+    app.gamemap.alarm = Some(Alarm {
+        position: app.gamemap.get_position(PLAYER).unwrap(),
+        expires: u64::MAX,
+    });
+    app
+}
+
+#[test]
+#[ignore]
+fn bench_monster_turns() {
+    let mut app = crowded_floor();
+
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..TURNS {
+        app.time += 100;
+        handle_monster_turns(&mut app);
+        update_fov(&mut app);
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    let player = app.gamemap.get_position(PLAYER).unwrap();
+    let near_player = app
+        .objects
+        .iter()
+        .filter_map(|(&id, _)| app.gamemap.get_position(id))
+        .filter(|&pos: &Position| pos.distance_to(player) <= app.settings.view_radius)
+        .count();
+    println!(
+        "{MONSTERS} monsters on a {}x{} floor, {TURNS} turns: {:?}, {} allocations ({} per turn), {} objects near the player",
+        app.gamemap.width,
+        app.gamemap.height,
+        elapsed,
+        allocations,
+        allocations as u64 / TURNS,
+        near_player,
+    );
+}
+
+/// returns true if every tile along a line lets light through
+fn is_clear(app: &App, mut line: impl Iterator<Item = (i32, i32)>) -> bool {
+    line.all(|(x, y)| app.gamemap.get_ref(x as u16, y as u16).is_transparent())
+}
+
+#[test]
+#[ignore]
+fn bench_line_of_sight() {
+    let app = crowded_floor();
+    let player = app.gamemap.get_position(PLAYER).unwrap();
+    let origin = (player.x as i32, player.y as i32);
+    let monsters: Vec<(i32, i32)> = app
+        .objects
+        .iter()
+        .filter_map(|(&id, _)| app.gamemap.get_position(id))
+        .map(|pos| (pos.x as i32, pos.y as i32))
+        .collect();
+
+    // what every line of sight check did before lines were worked out lazily
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut collected_clear = 0;
+    for _ in 0..TURNS {
+        for &monster in &monsters {
+            let path: Vec<(i32, i32)> = LineIter::new(monster, origin).collect();
+            collected_clear += is_clear(&app, path.into_iter()) as usize;
+        }
+    }
+    let collected_time = start.elapsed();
+    let collected = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut lazy_clear = 0;
+    for _ in 0..TURNS {
+        for &monster in &monsters {
+            lazy_clear += is_clear(&app, LineIter::new(monster, origin)) as usize;
+        }
+    }
+    let lazy_time = start.elapsed();
+    let lazy = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    assert_eq!(collected_clear, lazy_clear);
+    println!(
+        "{} lines of sight for {TURNS} turns: collected {:?}, {} allocations ({} per turn), lazy {:?}, {} allocations ({} per turn)",
+        monsters.len(),
+        collected_time,
+        collected,
+        collected as u64 / TURNS,
+        lazy_time,
+        lazy,
+        lazy as u64 / TURNS,
+    );
+}
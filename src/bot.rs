@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::{
+    app::{App, GameScreen, PLAYER},
+    components::{Item, Position},
+    engine::{
+        DIRECTION_ORDERING, InputDirection, adjacent_hostile_directions, direction_to_deltas,
+        known_item, known_stairs_position, step_allowed, visible_monsters_by_distance,
+    },
+    gamemap::coords_to_idx,
+    identify, inventory,
+};
+
+// NOTE: this file contains a simple bot that plays the game by pressing keys, the same
+// way the player would. it fights whatever it sees, explores, and then takes the stairs
+
+/// how close a monster in view has to be for the bot to go after it. further off, the way
+/// there often leads around dark tiles that haven't been explored yet, and the monster
+/// drops in and out of view as the bot walks, leaving it pacing back and forth
+const CHASE_DISTANCE: u16 = 3;
+
+/// returns the key the bot presses next
+pub fn choose_key(app: &App) -> KeyEvent {
+    // popups like the level summary get closed right away
+    if app.game_screen != GameScreen::Main {
+        return KeyEvent::from(KeyCode::Esc);
+    }
+
+    // drink a healing potion it knows about when badly hurt
+    let fighter = app.objects.get(&PLAYER).unwrap().fighter.as_ref().unwrap();
+    if fighter.hp * 3 <= fighter.max_hp
+        && let Some(idx) = (0..app.inventory.len()).find(|&idx| {
+            *inventory::get_item_in_inventory(app, idx) == Item::Heal
+                && !identify::is_unidentified(app, app.inventory[idx])
+        })
+    {
+        return inventory_key(idx);
+    }
+
+    // catch its breath while nothing is around
+    if fighter.hp * 2 <= fighter.max_hp && visible_monsters_by_distance(app).is_empty() {
+        return KeyEvent::from(KeyCode::Char('R'));
+    }
+
+    // put on anything that fits an empty slot
+    if let Some(idx) = (0..app.inventory.len()).find(|&idx| {
+        *inventory::get_item_in_inventory(app, idx) == Item::Equipment
+            && inventory::displaced_equipment(app, app.inventory[idx]).is_empty()
+    }) {
+        return inventory_key(idx);
+    }
+
+    // fight anything standing next to the player
+    if let Some(&direction) = adjacent_hostile_directions(app).first() {
+        return direction_key(direction);
+    }
+
+    // chase down the closest monster in view
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let hostile = visible_monsters_by_distance(app).into_iter().find(|id| {
+        let obj = app.objects.get(id).unwrap();
+        obj.fighter.is_some()
+            && obj.ai.is_some()
+            && obj.shop.is_none()
+            && app
+                .gamemap
+                .get_position(*id)
+                .is_some_and(|pos| pos.distance_to(player_pos) <= CHASE_DISTANCE)
+    });
+    if let Some(target) = hostile
+        && let Some(target_pos) = app.gamemap.get_position(target)
+        && let Some(direction) = first_step(app, |pos| pos == target_pos)
+    {
+        return direction_key(direction);
+    }
+
+    // walk to the closest tile next to somewhere unexplored
+    let frontier = |pos: Position| {
+        DIRECTION_ORDERING.into_iter().any(|direction| {
+            let (dx, dy) = direction_to_deltas(direction);
+            neighbor(app, pos, direction).is_some_and(|next| {
+                !app.gamemap.is_explored(next.x, next.y) && step_allowed(app, pos, dx, dy)
+            })
+        })
+    };
+    if let Some(direction) = first_step(app, frontier) {
+        return direction_key(direction);
+    }
+
+    // nothing left to see on this floor, so head down
+    if let Some(stairs) = known_stairs_position(app) {
+        if stairs == player_pos {
+            return KeyEvent::from(KeyCode::Char('>'));
+        }
+        if let Some(direction) = first_step(app, |pos| pos == stairs) {
+            return direction_key(direction);
+        }
+    }
+
+    // stuck, so wait for something to happen
+    KeyEvent::from(KeyCode::Char('.'))
+}
+
+/// returns the movement key for a direction
+fn direction_key(direction: InputDirection) -> KeyEvent {
+    let c = match direction {
+        InputDirection::Up => 'k',
+        InputDirection::Down => 'j',
+        InputDirection::Left => 'h',
+        InputDirection::Right => 'l',
+        InputDirection::UpLeft => 'y',
+        InputDirection::UpRight => 'u',
+        InputDirection::DownLeft => 'b',
+        InputDirection::DownRight => 'n',
+    };
+    KeyEvent::from(KeyCode::Char(c))
+}
+
+/// returns the key that uses an inventory slot
+fn inventory_key(idx: usize) -> KeyEvent {
+    let c = match idx {
+        9 => '0',
+        _ => (b'1' + idx as u8) as char,
+    };
+    KeyEvent::from(KeyCode::Char(c))
+}
+
+/// returns the tile one step away in a direction, if it's on the map
+fn neighbor(app: &App, pos: Position, direction: InputDirection) -> Option<Position> {
+    let (dx, dy) = direction_to_deltas(direction);
+    let (x, y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+    app.gamemap.in_bounds(x, y).then_some(Position {
+        x: x as u16,
+        y: y as u16,
+    })
+}
+
+/// searches the explored part of the map for the closest tile matching the goal,
+/// and returns the direction of the first step towards it. steers clear of lava and
+/// traps the player knows about, and never walks through monsters on the way
+fn first_step(app: &App, goal: impl Fn(Position) -> bool) -> Option<InputDirection> {
+    let start = app.gamemap.get_position(PLAYER)?;
+    let width = app.gamemap.width;
+
+    // remembers the first step taken to reach each tile
+    let mut first: Vec<Option<InputDirection>> = vec![None; app.gamemap.tiles.len()];
+    let mut queue = VecDeque::from([start]);
+    let mut seen = vec![false; app.gamemap.tiles.len()];
+    seen[coords_to_idx(start.x, start.y, width)] = true;
+
+    while let Some(pos) = queue.pop_front() {
+        if pos != start && goal(pos) {
+            return first[coords_to_idx(pos.x, pos.y, width)];
+        }
+        for direction in DIRECTION_ORDERING {
+            let Some(next) = neighbor(app, pos, direction) else {
+                continue;
+            };
+            let (dx, dy) = direction_to_deltas(direction);
+            if !step_allowed(app, pos, dx, dy) {
+                continue;
+            }
+            let idx = coords_to_idx(next.x, next.y, width);
+            if seen[idx] || !app.gamemap.is_explored(next.x, next.y) {
+                continue;
+            }
+            seen[idx] = true;
+
+            let tile = app.gamemap.get_ref(next.x, next.y);
+            let trapped = known_item(app, next.x, next.y)
+                .is_some_and(|id| app.objects.get(&id).unwrap().trap.is_some());
+            // the goal itself may be a monster, but nothing past it
+            let harmful = app.gamemap.is_harmful(next.x, next.y);
+            if goal(next) && tile.is_walkable() && !tile.is_hazard() && !harmful {
+                first[idx] = first[coords_to_idx(pos.x, pos.y, width)].or(Some(direction));
+                queue.push_front(next);
+                continue;
+            }
+            if !tile.is_walkable()
+                || tile.is_hazard()
+                || harmful
+                || tile.blocker.is_some()
+                || trapped
+            {
+                continue;
+            }
+            first[idx] = first[coords_to_idx(pos.x, pos.y, width)].or(Some(direction));
+            queue.push_back(next);
+        }
+    }
+    None
+}
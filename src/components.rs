@@ -1,17 +1,33 @@
 use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    engine::Dice,
+    quests::Goal,
+    theme::{Theme, ThemeColor},
+};
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct Position {
     pub x: u16,
     pub y: u16,
 }
 
+impl Position {
+    /// number of steps between two positions, counting diagonal moves as one step
+    pub fn distance_to(&self, other: Position) -> u16 {
+        self.x.abs_diff(other.x).max(self.y.abs_diff(other.y))
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Renderable {
     pub glyph: char,
     pub fg: Color,
     pub bg: Color,
+    /// when set, fg is replaced by this color from the current theme
+    #[serde(default)]
+    pub role: Option<ThemeColor>,
 }
 
 impl Renderable {
@@ -20,6 +36,25 @@ impl Renderable {
             glyph: '_',
             fg: Color::default(),
             bg: Color::Reset,
+            role: None,
+        }
+    }
+
+    /// creates a renderable drawn with a semantic color from the theme
+    pub fn themed(glyph: char, role: ThemeColor) -> Self {
+        Self {
+            glyph,
+            fg: Theme::default().get(role),
+            bg: Color::Reset,
+            role: Some(role),
+        }
+    }
+
+    /// returns the foreground color to draw this with in the given theme
+    pub fn fg_in(&self, theme: &Theme) -> Color {
+        match self.role {
+            Some(role) => theme.get(role),
+            None => self.fg,
         }
     }
 }
@@ -44,6 +79,57 @@ pub struct Object {
     pub ai: Option<AIType>,
     pub item: Option<Item>,
     pub equipment: Option<Equipment>,
+    #[serde(default)]
+    pub gold: Option<u32>, // gold carried by this object, or the size of a gold pile
+    #[serde(default)]
+    pub value: u32, // base price of this object in shops
+    #[serde(default)]
+    pub weight: u32, // how heavy one of this item is to carry
+    #[serde(default)]
+    pub shop: Option<Shop>,
+    #[serde(default)]
+    pub class: Option<PlayerClass>, // the class the player picked at character creation
+    #[serde(default)]
+    pub stack_count: Option<u32>, // number of items in this stack, None if it can't stack
+    #[serde(default)]
+    pub trap: Option<Trap>, // goes off when the player steps on it
+    #[serde(default)]
+    pub hidden: bool, // not drawn or described until the player finds it
+    #[serde(default)]
+    pub loadout: Option<Loadout>, // the gear this monster can roll when it spawns
+    #[serde(default)]
+    pub equipped: Vec<usize>, // ids of the gear a monster is wearing. the player's is kept in App
+    #[serde(default)]
+    pub carried: Vec<usize>, // ids of items a monster is carrying, like the key to a vault
+    #[serde(default)]
+    pub boss: Option<Boss>, // scripted phases that the boss goes through as it gets hurt
+    #[serde(default)]
+    pub corpse: Option<Corpse>, // what's left of a dead monster, rots away after a while
+    #[serde(default)]
+    pub necromancy: Option<Necromancy>, // lets a monster raise corpses as zombies
+    #[serde(default)]
+    pub sorcery: Option<Sorcery>, // spells the monster casts at the player
+    #[serde(default)]
+    pub faction: Option<Faction>, // which side this object fights on, see engine::faction
+    #[serde(default)]
+    pub pack: Option<Pack>, // the group of monsters this one spawned with
+    #[serde(default)]
+    pub caster: Option<Caster>, // mana and the spells the player has learned
+    #[serde(default)]
+    pub spotted: bool, // whether this has been in the player's view before
+    #[serde(default)]
+    pub light: Option<u16>, // how many tiles around it this object lights up
+    #[serde(default)]
+    pub charges: Option<u16>, // uses left in a wand, None for items that don't run out
+    #[serde(default)]
+    pub dialogue: Option<Dialogue>, // what a friendly npc says when the player bumps into it
+}
+
+/// a group of monsters that spawned together and hunt together. followers regroup around
+/// the leader when there's nothing to fight, and spread out around their prey
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pack {
+    pub leader: usize, // id of the pack's leader. the leader's own pack points at itself
 }
 
 impl Object {
@@ -63,6 +149,28 @@ impl Object {
             ai: None,
             item: None,
             equipment: None,
+            gold: None,
+            value: 0,
+            weight: 0,
+            shop: None,
+            class: None,
+            stack_count: None,
+            trap: None,
+            hidden: false,
+            loadout: None,
+            equipped: Vec::new(),
+            carried: Vec::new(),
+            boss: None,
+            corpse: None,
+            necromancy: None,
+            sorcery: None,
+            faction: None,
+            pack: None,
+            caster: None,
+            spotted: false,
+            light: None,
+            charges: None,
+            dialogue: None,
         }
     }
 
@@ -85,6 +193,200 @@ impl Object {
         self.equipment = Some(equipment);
         self
     }
+
+    pub fn set_gold(mut self, gold: u32) -> Self {
+        self.gold = Some(gold);
+        self
+    }
+
+    pub fn set_value(mut self, value: u32) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn set_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn set_shop(mut self, shop: Shop) -> Self {
+        self.shop = Some(shop);
+        self
+    }
+
+    pub fn set_class(mut self, class: PlayerClass) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    pub fn set_trap(mut self, trap: Trap) -> Self {
+        self.trap = Some(trap);
+        self
+    }
+
+    pub fn set_hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub fn set_loadout(mut self, loadout: Loadout) -> Self {
+        self.loadout = Some(loadout);
+        self
+    }
+
+    pub fn set_corpse(mut self, rots_at: u64) -> Self {
+        self.corpse = Some(Corpse { rots_at });
+        self
+    }
+
+    pub fn set_necromancy(mut self, range: u16, cooldown: u64) -> Self {
+        self.necromancy = Some(Necromancy {
+            range,
+            cooldown,
+            ready_at: 0,
+        });
+        self
+    }
+
+    pub fn set_sorcery(mut self, sorcery: Sorcery) -> Self {
+        self.sorcery = Some(sorcery);
+        self
+    }
+
+    pub fn set_caster(mut self, max_mana: u16, spells: Vec<Spell>) -> Self {
+        self.caster = Some(Caster {
+            mana: max_mana,
+            max_mana,
+            spells,
+        });
+        self
+    }
+
+    pub fn set_light(mut self, radius: u16) -> Self {
+        self.light = Some(radius);
+        self
+    }
+
+    pub fn set_charges(mut self, charges: u16) -> Self {
+        self.charges = Some(charges);
+        self
+    }
+
+    pub fn set_dialogue(mut self, dialogue: Dialogue) -> Self {
+        self.dialogue = Some(dialogue);
+        self
+    }
+
+    pub fn set_faction(mut self, faction: Faction) -> Self {
+        self.faction = Some(faction);
+        self
+    }
+
+    pub fn set_boss(mut self, phases: Vec<BossPhase>) -> Self {
+        self.boss = Some(Boss {
+            phases,
+            phase: 0,
+            sealed: Vec::new(),
+        });
+        self
+    }
+
+    /// allows identical copies of this object to stack in the inventory
+    pub fn set_stackable(mut self) -> Self {
+        self.stack_count = Some(1);
+        self
+    }
+
+    /// returns the name of the object, with its enchantment and the number of items if it is a stack
+    pub fn stack_name(&self) -> String {
+        let name = match &self.equipment {
+            Some(equip) if equip.enchantment != 0 => {
+                format!("{:+} {}", equip.enchantment, self.name)
+            }
+            _ => self.name.clone(),
+        };
+        match self.stack_count {
+            Some(count) if count > 1 => format!("{} (x{})", name, count),
+            _ => name,
+        }
+    }
+
+    /// returns true if the other object can be merged into a stack with this one
+    pub fn stacks_with(&self, other: &Object) -> bool {
+        self.stack_count.is_some() && other.stack_count.is_some() && self.name == other.name
+    }
+}
+
+/// classes the player can pick from when starting a new game
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum PlayerClass {
+    Fighter,
+    Rogue,
+    Mage,
+}
+pub const CLASS_ORDERING: [PlayerClass; 3] =
+    [PlayerClass::Fighter, PlayerClass::Rogue, PlayerClass::Mage];
+
+impl PlayerClass {
+    /// short description shown on the character creation screen
+    pub fn description(&self) -> &'static str {
+        match self {
+            PlayerClass::Fighter => "sturdy and strong. starts with armor and a healing potion.",
+            PlayerClass::Rogue => "quick and well funded. starts with a dagger and a haste potion.",
+            PlayerClass::Mage => "frail, but knows the hexbolt spell and packs offensive scrolls.",
+        }
+    }
+}
+
+impl std::fmt::Display for PlayerClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerClass::Fighter => write!(f, "Fighter"),
+            PlayerClass::Rogue => write!(f, "Rogue"),
+            PlayerClass::Mage => write!(f, "Mage"),
+        }
+    }
+}
+
+/// component for shopkeepers, holding the ids of the objects they have for sale
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shop {
+    pub stock: Vec<usize>,
+}
+
+/// component for npcs the player can talk to. a conversation is a tree of nodes,
+/// each with a line from the npc and the replies the player can pick from
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Dialogue {
+    pub nodes: Vec<DialogueNode>,
+    pub start: usize, // index of the node every conversation starts on
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DialogueNode {
+    pub text: String,
+    pub options: Vec<DialogueOption>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DialogueOption {
+    pub text: String,
+    pub outcome: DialogueOutcome,
+}
+
+/// what happens when the player picks a reply, see dialogue.rs
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DialogueOutcome {
+    /// carries on the conversation from another node
+    Goto(usize),
+    /// starts trading with the npc, if it runs a shop
+    Trade,
+    /// hands out a quest on the current floor. later conversations start from `then`
+    OfferQuest { goal: Goal, then: usize },
+    /// ends the conversation with a line in the log
+    Flavor(String),
+    /// ends the conversation
+    Leave,
 }
 
 /// component for objects with health that can be killed
@@ -93,8 +395,20 @@ pub struct Fighter {
     pub max_hp: u16,
     pub hp: u16,
     pub defense: i16,
-    pub power: i16,
+    pub power: i16, // added onto every damage roll
+    #[serde(default)]
+    pub damage: Dice, // rolled for every attack. old saves roll nothing, and only use power
+    #[serde(default)]
+    pub accuracy: i16, // makes attacks more likely to land
+    #[serde(default)]
+    pub evasion: i16, // makes attacks against this fighter more likely to miss
+    #[serde(default)]
+    pub stealth: i16, // makes monsters less likely to notice this fighter
     pub death_callback: DeathCallback,
+    #[serde(default)]
+    pub statuses: Vec<StatusEffect>, // temporary effects currently applied to this fighter
+    #[serde(default)]
+    pub on_hit: Option<StatusEffect>, // status applied to targets this fighter damages
 }
 
 impl Fighter {
@@ -104,26 +418,324 @@ impl Fighter {
             hp: max_hp,
             defense,
             power,
+            damage: Dice::default(),
+            accuracy: 0,
+            evasion: 0,
+            stealth: 0,
             death_callback,
+            statuses: Vec::new(),
+            on_hit: None,
+        }
+    }
+
+    pub fn set_damage(mut self, damage: Dice) -> Self {
+        self.damage = damage;
+        self
+    }
+
+    pub fn set_accuracy(mut self, accuracy: i16) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    pub fn set_evasion(mut self, evasion: i16) -> Self {
+        self.evasion = evasion;
+        self
+    }
+
+    pub fn set_stealth(mut self, stealth: i16) -> Self {
+        self.stealth = stealth;
+        self
+    }
+
+    pub fn set_on_hit(mut self, kind: StatusKind, duration: u64) -> Self {
+        self.on_hit = Some(StatusEffect {
+            kind,
+            duration,
+            expires: 0,
+        });
+        self
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum StatusKind {
+    Haste,    // actions take less time
+    Slow,     // actions take more time
+    Charmed,  // fights on the player's side
+    Burning,  // takes damage every turn until the flames die down or are put out
+    Poisoned, // takes damage every turn until the poison wears off
+}
+
+impl std::fmt::Display for StatusKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusKind::Haste => write!(f, "hasted"),
+            StatusKind::Slow => write!(f, "slowed"),
+            StatusKind::Charmed => write!(f, "charmed"),
+            StatusKind::Burning => write!(f, "burning"),
+            StatusKind::Poisoned => write!(f, "poisoned"),
         }
     }
 }
 
+/// a temporary effect on a fighter that wears off once the game time reaches `expires`
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: u64, // how long the effect lasts when applied
+    pub expires: u64,  // time at which the effect wears off
+}
+
+/// which side a fighter is on. members of hostile factions attack each other on sight
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum Faction {
+    Player,
+    Monsters,
+    /// doesn't take sides, and is left alone unless someone attacks it
+    Neutral,
+}
+
+impl Faction {
+    pub fn is_hostile_to(self, other: Faction) -> bool {
+        matches!(
+            (self, other),
+            (Faction::Player, Faction::Monsters) | (Faction::Monsters, Faction::Player)
+        )
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum AIType {
     Melee(MeleeAIData),
     Ranged,
+    Spawner, // not a monster, periodically spawns wandering monsters on the floor
+    Sentry,  // never moves, raises the alarm when it sees the player
+}
+
+/// a boss fight, split into phases that the boss moves through as it loses health
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Boss {
+    pub phases: Vec<BossPhase>, // ordered from highest to lowest hp threshold
+    pub phase: usize,           // number of phases the boss has entered so far
+    pub sealed: Vec<Position>,  // tiles walled off by the boss, reopened when it dies
+}
+
+/// a scripted change in a boss's behaviour, triggered once its hp drops to
+/// `hp_percent` of its max hp
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BossPhase {
+    pub hp_percent: u16,
+    pub message: String, // announced when the phase starts
+    pub effects: Vec<PhaseEffect>,
+}
+
+/// what happens when a boss enters a new phase
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum PhaseEffect {
+    Enrage { power: i16 },                   // permanently hits harder
+    Haste { duration: u64 },                 // acts faster for a while
+    Summon { minion: Minion, count: usize }, // calls in monsters around itself
+    SealArena { radius: u16 }, // walls off the area around the boss, if the player is inside
+}
+
+/// the remains of a monster
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Corpse {
+    pub rots_at: u64, // time at which the corpse rots away completely
+}
+
+/// raising corpses back up as zombies
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Necromancy {
+    pub range: u16,    // how far away a corpse can be raised from
+    pub cooldown: u64, // time between raising corpses
+    pub ready_at: u64, // time at which the next corpse can be raised
+}
+
+/// spells a monster casts instead of moving, whenever they're ready and it sees the player
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sorcery {
+    pub minion: Minion, // summoned around the caster
+    pub summon_count: usize,
+    pub summon_cooldown: u64,
+    #[serde(alias = "bolt_power")]
+    pub bolt_damage: Dice, // damage of the bolt, before the target's defense
+    pub bolt_range: u16,
+    pub bolt_cooldown: u64,
+    pub next_summon: u64, // time at which the next summon can be cast
+    pub next_bolt: u64,   // time at which the next bolt can be cast
+}
+
+/// a pool of mana, and the spells the player can spend it on
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Caster {
+    pub mana: u16,
+    pub max_mana: u16,
+    pub spells: Vec<Spell>, // in the order they were learned, which is the order of the spell menu
+}
+
+/// spells the player learns from books. each one casts the effect of a scroll or potion
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum Spell {
+    Hexbolt,
+    Lightning,
+    Charm,
+    Heal,
+    Haste,
+}
+
+/// monsters that a boss can summon
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Minion {
+    Orc,
+    Zombie,
+}
+
+/// the kinds of gear that a monster can spawn wearing
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Loadout {
+    Orc,   // sometimes armed, sometimes wearing a helmet
+    Troll, // sometimes wearing crude armor
+}
+
+/// the different kinds of traps that can be stepped on
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub enum Trap {
+    Alarm,    // alerts every monster on the floor
+    Spike,    // hurts whoever steps on it
+    Teleport, // sends whoever steps on it somewhere else on the floor
+}
+
+/// how aware a monster is of its surroundings
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Alertness {
+    /// won't notice anything until it is woken up by noise or damage
+    Sleeping,
+    /// notices the player as soon as they come into view
+    #[default]
+    Idle,
+    /// hunting down a target
+    Alert,
+}
+
+impl std::fmt::Display for Alertness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Alertness::Sleeping => write!(f, "asleep"),
+            Alertness::Idle => write!(f, "idle"),
+            Alertness::Alert => write!(f, "alert"),
+        }
+    }
 }
 
 /// time before melee ai forgets about its target
 pub const MELEE_FORGET_TIME: u64 = 500;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// how far away most monsters can notice the player from.
+/// a little shorter than the player's view, so the player usually sees them first
+pub const DEFAULT_PERCEPTION: u16 = 6;
+
+fn default_perception() -> u16 {
+    DEFAULT_PERCEPTION
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct MeleeAIData {
     pub target: Option<usize>, // id of which object this monster is targeting
     pub last_seen_time: Option<u64>, // when this monster last saw its target
+    #[serde(default)]
+    pub last_seen_pos: Option<Position>, // where this monster last saw its target
+    #[serde(default)]
+    pub alertness: Alertness, // whether this monster is asleep, idle, or hunting
     pub move_speed: u64,       // delay between moves
     pub attack_speed: u64,     // delay between attacks
+    #[serde(default)]
+    pub smarts: Smarts, // which parts of the ai this monster is clever enough to use
+    #[serde(default)]
+    pub post: Option<GuardPost>, // where this monster stands guard, if anywhere
+    #[serde(default)]
+    pub flee: Option<Flee>, // when this monster runs from a fight it's losing
+    #[serde(default)]
+    pub keep_distance: Option<DistanceBand>, // how far from its target a ranged monster stands
+    #[serde(default = "default_perception")]
+    pub perception: u16, // how many tiles away this monster can notice the player from
+    #[serde(default)]
+    pub darkvision: bool, // sees the player in the dark, as if they were lit
+    #[serde(default)]
+    pub tunnels: bool, // digs through walls to reach its target
+}
+
+/// a monster that runs away once it's badly hurt. it stops once it's far enough away,
+/// and turns to fight if it gets cornered
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Flee {
+    pub hp_percent: u16,   // starts running at or below this much of its max hp
+    pub max_distance: u16, // stops running once it's this many tiles away
+}
+
+/// how many tiles from its target a monster likes to stand. it backs off when the target
+/// gets closer than min, and closes in when it's further than max
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DistanceBand {
+    pub min: u16,
+    pub max: u16,
+}
+
+/// a spot that a guard holds. it only chases targets within its leash of the post,
+/// and walks back to it once it gives up
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GuardPost {
+    pub position: Position,
+    pub leash: u16,
+}
+
+/// knobs for how clever a melee monster is. everything is on by default,
+/// early monsters turn some of it off to be easier to outwit
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Smarts {
+    /// keeps hunting where its target was last seen after losing sight of it
+    pub remembers_last_seen: bool,
+    /// paths around other monsters instead of queueing up behind them
+    pub coordinates_with_allies: bool,
+    /// never steps into chasms while chasing something
+    pub avoids_hazards: bool,
+}
+
+impl Smarts {
+    /// every part of the ai turned off
+    pub const MINDLESS: Smarts = Smarts {
+        remembers_last_seen: false,
+        coordinates_with_allies: false,
+        avoids_hazards: false,
+    };
+
+    pub fn set_remembers_last_seen(mut self, remembers_last_seen: bool) -> Self {
+        self.remembers_last_seen = remembers_last_seen;
+        self
+    }
+
+    pub fn set_coordinates_with_allies(mut self, coordinates_with_allies: bool) -> Self {
+        self.coordinates_with_allies = coordinates_with_allies;
+        self
+    }
+
+    pub fn set_avoids_hazards(mut self, avoids_hazards: bool) -> Self {
+        self.avoids_hazards = avoids_hazards;
+        self
+    }
+}
+
+impl Default for Smarts {
+    fn default() -> Self {
+        Smarts {
+            remembers_last_seen: true,
+            coordinates_with_allies: true,
+            avoids_hazards: true,
+        }
+    }
 }
 
 impl MeleeAIData {
@@ -131,8 +743,17 @@ impl MeleeAIData {
         MeleeAIData {
             target: None,
             last_seen_time: None,
+            last_seen_pos: None,
+            alertness: Alertness::Idle,
             move_speed: 100,
             attack_speed: 100,
+            smarts: Smarts::default(),
+            post: None,
+            flee: None,
+            keep_distance: None,
+            perception: DEFAULT_PERCEPTION,
+            darkvision: false,
+            tunnels: false,
         }
     }
 
@@ -145,23 +766,75 @@ impl MeleeAIData {
         self.attack_speed = attack_speed;
         self
     }
+
+    pub fn set_smarts(mut self, smarts: Smarts) -> Self {
+        self.smarts = smarts;
+        self
+    }
+
+    pub fn set_flee(mut self, hp_percent: u16, max_distance: u16) -> Self {
+        self.flee = Some(Flee {
+            hp_percent,
+            max_distance,
+        });
+        self
+    }
+
+    pub fn set_keep_distance(mut self, min: u16, max: u16) -> Self {
+        self.keep_distance = Some(DistanceBand { min, max });
+        self
+    }
+
+    pub fn set_perception(mut self, perception: u16) -> Self {
+        self.perception = perception;
+        self
+    }
+
+    pub fn set_darkvision(mut self) -> Self {
+        self.darkvision = true;
+        self
+    }
+
+    pub fn set_tunnels(mut self) -> Self {
+        self.tunnels = true;
+        self
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum DeathCallback {
     Player,
     Monster,
+    Undead, // like a monster, but doesn't leave a corpse behind
 }
 
 /// represents information about an item.
 /// should not store persistent data, as this will get cloned
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
 pub enum Item {
     Heal,
     Lightning,
     Hexbolt,
+    Charm,
     Fireball,
+    Haste,
+    TimeWarp,
+    Enchant,
+    Identify,
+    RemoveCurse,
+    Rope,
+    Corpse,
+    Amulet,
     Equipment,
+    Gold,
+    Spellbook(Spell),
+    Lantern,
+    Pickaxe,
+    Digging,
+    PoisonGas,
+    Smoke,
+    Key,
+    Idol,
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
@@ -169,8 +842,26 @@ pub enum Slot {
     Weapon = 0,
     Head = 1,
     Body = 2,
+    OffHand = 3,
+    Ring = 4,
+}
+pub const SLOT_ORDERING: [Slot; 5] = [
+    Slot::Weapon,
+    Slot::Head,
+    Slot::Body,
+    Slot::OffHand,
+    Slot::Ring,
+];
+
+/// returns the key that takes off the equipment in a slot, 'A' for the first slot and so on
+pub fn slot_key(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+/// returns the slot that a key takes equipment off of, if it is one of the slot keys
+pub fn slot_index(c: char) -> Option<usize> {
+    (0..SLOT_ORDERING.len()).find(|&index| slot_key(index) == c)
 }
-pub const SLOT_ORDERING: [Slot; 3] = [Slot::Weapon, Slot::Head, Slot::Body];
 
 impl std::fmt::Display for Slot {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -184,6 +875,12 @@ impl std::fmt::Display for Slot {
             Slot::Body => {
                 write!(f, "Body")
             }
+            Slot::OffHand => {
+                write!(f, "Off-hand")
+            }
+            Slot::Ring => {
+                write!(f, "Ring")
+            }
         }
     }
 }
@@ -193,4 +890,36 @@ pub struct Equipment {
     pub slot: Slot,
     pub power_bonus: i16,
     pub defense_bonus: i16,
+    #[serde(default = "default_equip_time")]
+    pub equip_time: u64, // time it takes to put on or take off, heavy armor takes several turns
+    #[serde(default)]
+    pub enchantment: i16, // added to power for weapons, and to defense for armor
+    #[serde(default)]
+    pub cursed: bool, // cursed equipment can't be taken off
+    #[serde(default)]
+    pub unidentified: bool, // enchantment and curse are hidden until the item is worn or identified
+    #[serde(default)]
+    pub two_handed: bool, // two-handed weapons leave no hand free for the off-hand slot
+}
+
+impl Equipment {
+    /// returns the power bonus of the equipment, including enchantment
+    pub fn power(&self) -> i16 {
+        match self.slot {
+            Slot::Weapon => self.power_bonus + self.enchantment,
+            _ => self.power_bonus,
+        }
+    }
+
+    /// returns the defense bonus of the equipment, including enchantment
+    pub fn defense(&self) -> i16 {
+        match self.slot {
+            Slot::Weapon => self.defense_bonus,
+            _ => self.defense_bonus + self.enchantment,
+        }
+    }
+}
+
+fn default_equip_time() -> u64 {
+    100
 }
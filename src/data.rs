@@ -0,0 +1,320 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs};
+
+use crate::{
+    app::procgen,
+    components::{
+        AIType, DEFAULT_PERCEPTION, DeathCallback, DistanceBand, Equipment, Fighter, Flee, Item,
+        MeleeAIData, Object, RenderLayer, Renderable,
+    },
+    engine::Dice,
+    theme::ThemeColor,
+};
+
+// NOTE: this file contains monsters and items described in a data file instead of in
+// entities.rs, so that new ones can be added without recompiling the game
+
+pub const ENTITIES_FILE: &str = "entities.json";
+/// how often each built in monster, item and trap spawns, in the same format as the
+/// spawn tables in the data file
+const BUILTIN_SPAWN_TABLES: &str = include_str!("../data/spawn_tables.json");
+
+/// how often something spawns, from a dungeon level onwards
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnWeight {
+    pub level: u16,
+    pub weight: usize,
+}
+
+/// returns the weight something spawns with on a level, from its spawn weights
+pub fn weight_at(weights: &[SpawnWeight], level: u16) -> usize {
+    weights
+        .iter()
+        .rev()
+        .find(|weight| level >= weight.level)
+        .map_or(0, |weight| weight.weight)
+}
+
+/// the combat stats of a monster
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterDef {
+    pub hp: u16,
+    #[serde(default)]
+    pub defense: i16,
+    #[serde(default)]
+    pub power: i16,
+    #[serde(default)]
+    pub damage: Dice,
+    #[serde(default)]
+    pub accuracy: i16,
+    #[serde(default)]
+    pub evasion: i16,
+    #[serde(default = "default_speed")]
+    pub move_speed: u64,
+    #[serde(default = "default_speed")]
+    pub attack_speed: u64,
+    #[serde(default)]
+    pub gold: u32,
+    #[serde(default)]
+    pub flee: Option<Flee>,
+    #[serde(default)]
+    pub keep_distance: Option<DistanceBand>,
+    #[serde(default = "default_perception")]
+    pub perception: u16,
+    #[serde(default)]
+    pub darkvision: bool,
+    #[serde(default)]
+    pub tunnels: bool,
+}
+
+fn default_speed() -> u64 {
+    100
+}
+
+fn default_perception() -> u16 {
+    DEFAULT_PERCEPTION
+}
+
+/// what an item does when used, and how much it's worth
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemDef {
+    pub kind: Item,
+    #[serde(default)]
+    pub equipment: Option<Equipment>,
+    #[serde(default)]
+    pub value: u32,
+    #[serde(default)]
+    pub weight: u32,
+    #[serde(default)]
+    pub stackable: bool,
+    #[serde(default)]
+    pub charges: Option<u16>,
+}
+
+/// the ais a monster in the data file can have. ranged monsters and spawners aren't
+/// finished enough to be described outside of the code
+const DATA_AIS: [&str; 2] = ["Melee", "Sentry"];
+
+/// a monster or item read from the data file
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityDef {
+    pub name: String,
+    #[serde(default)]
+    pub tooltip: String,
+    pub glyph: char,
+    pub color: ThemeColor,
+    /// which of DATA_AIS a monster acts with, melee if left out
+    #[serde(default)]
+    pub ai: Option<String>,
+    /// the weight this spawns with on each level, like the tables in data/spawn_tables.json
+    #[serde(default)]
+    pub spawn: Vec<SpawnWeight>,
+    #[serde(default)]
+    pub monster: Option<MonsterDef>,
+    #[serde(default)]
+    pub item: Option<ItemDef>,
+}
+
+impl EntityDef {
+    /// builds a fresh object from the definition
+    pub fn spawn(&self) -> Object {
+        let renderable = Renderable::themed(self.glyph, self.color);
+        let render_layer = match self.monster {
+            Some(_) => RenderLayer::Blocking,
+            None => RenderLayer::Item,
+        };
+        let mut object = Object::new(
+            self.name.clone(),
+            self.tooltip.clone(),
+            renderable,
+            render_layer,
+        );
+
+        if let Some(monster) = &self.monster {
+            object = object
+                .set_fighter(
+                    Fighter::new(
+                        monster.hp,
+                        monster.defense,
+                        monster.power,
+                        DeathCallback::Monster,
+                    )
+                    .set_damage(monster.damage)
+                    .set_accuracy(monster.accuracy)
+                    .set_evasion(monster.evasion),
+                )
+                .set_ai(match self.ai.as_deref() {
+                    Some("Sentry") => AIType::Sentry,
+                    _ => AIType::Melee(MeleeAIData {
+                        flee: monster.flee,
+                        keep_distance: monster.keep_distance,
+                        darkvision: monster.darkvision,
+                        tunnels: monster.tunnels,
+                        ..MeleeAIData::new()
+                            .set_move_speed(monster.move_speed)
+                            .set_attack_speed(monster.attack_speed)
+                            .set_perception(monster.perception)
+                    }),
+                })
+                .set_gold(monster.gold);
+        }
+
+        if let Some(item) = &self.item {
+            object = object
+                .set_item(item.kind.clone())
+                .set_value(item.value)
+                .set_weight(item.weight);
+            if let Some(equipment) = &item.equipment {
+                object = object.set_equipment(equipment.clone());
+            }
+            if item.stackable {
+                object = object.set_stackable();
+            }
+            if let Some(charges) = item.charges {
+                object = object.set_charges(charges);
+            }
+        }
+
+        object
+    }
+
+    /// returns what's wrong with the definition, if anything
+    fn problem(&self) -> Option<String> {
+        match (&self.monster, &self.item) {
+            (Some(_), Some(_)) => return Some(String::from("is both a monster and an item")),
+            (None, None) => return Some(String::from("is neither a monster nor an item")),
+            _ => {}
+        }
+        if let Some(item) = &self.item
+            && (item.kind == Item::Equipment) != item.equipment.is_some()
+        {
+            return Some(String::from(
+                "needs an equipment section exactly when its kind is Equipment",
+            ));
+        }
+        if let Some(ai) = &self.ai {
+            if self.monster.is_none() {
+                return Some(String::from("has an ai, but isn't a monster"));
+            }
+            if !DATA_AIS.contains(&ai.as_str()) {
+                return Some(format!(
+                    "has an unknown ai {}, instead of one of {}",
+                    ai,
+                    DATA_AIS.join(", ")
+                ));
+            }
+        }
+        if self.monster.as_ref().is_some_and(|monster| monster.hp == 0) {
+            return Some(String::from("has no hp"));
+        }
+        if let Some(band) = self
+            .monster
+            .as_ref()
+            .and_then(|monster| monster.keep_distance)
+            && band.min > band.max
+        {
+            return Some(String::from("keeps a min distance above its max"));
+        }
+        if !self.spawn.is_sorted_by_key(|weight| weight.level) {
+            return Some(String::from("has spawn levels out of order"));
+        }
+        None
+    }
+}
+
+/// spawn weights for the built in monsters, items and traps, by name. the built in
+/// weights come from data/spawn_tables.json, and the data file can replace any of them
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTables {
+    #[serde(default)]
+    pub monsters: BTreeMap<String, Vec<SpawnWeight>>,
+    #[serde(default)]
+    pub items: BTreeMap<String, Vec<SpawnWeight>>,
+    #[serde(default)]
+    pub traps: BTreeMap<String, Vec<SpawnWeight>>,
+}
+
+impl SpawnTables {
+    /// the spawn tables the game ships with
+    fn builtin() -> Self {
+        serde_json::from_str(BUILTIN_SPAWN_TABLES).expect("the built in spawn tables are valid")
+    }
+
+    /// replaces the weights of everything the other tables list
+    fn override_with(&mut self, other: SpawnTables) {
+        self.monsters.extend(other.monsters);
+        self.items.extend(other.items);
+        self.traps.extend(other.traps);
+    }
+}
+
+/// every entity in the data file. a definition with the same name as one of the built in
+/// monsters or items takes its place
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityData {
+    #[serde(default)]
+    pub entities: Vec<EntityDef>,
+    #[serde(default)]
+    pub spawn_tables: SpawnTables,
+}
+
+impl EntityData {
+    /// loads the entities from the data file, or the built in entities alone if there is
+    /// no data file. returns everything wrong with the file if it is malformed
+    pub fn load() -> Result<Self, Vec<String>> {
+        let Ok(contents) = fs::read_to_string(ENTITIES_FILE) else {
+            return Ok(Self::builtin());
+        };
+        Self::parse(&contents)
+    }
+
+    /// reads the contents of a data file, returning everything wrong with it if it is
+    /// malformed
+    pub fn parse(contents: &str) -> Result<Self, Vec<String>> {
+        let mut data: Self =
+            serde_json::from_str(contents).map_err(|error| vec![error.to_string()])?;
+        let mut spawn_tables = SpawnTables::builtin();
+        spawn_tables.override_with(data.spawn_tables);
+        data.spawn_tables = spawn_tables;
+
+        let mut problems: Vec<String> = data
+            .entities
+            .iter()
+            .filter_map(|def| Some(format!("{} {}.", def.name, def.problem()?)))
+            .collect();
+        problems.extend(procgen::spawn_table_problems(&data));
+        match problems.is_empty() {
+            true => Ok(data),
+            false => Err(problems),
+        }
+    }
+
+    /// returns the built in entities alone, as if there were no data file
+    pub fn builtin() -> Self {
+        EntityData {
+            entities: Vec::new(),
+            spawn_tables: SpawnTables::builtin(),
+        }
+    }
+
+    /// returns the monsters in the data file
+    pub fn monsters(&self) -> impl Iterator<Item = &EntityDef> {
+        self.entities.iter().filter(|def| def.monster.is_some())
+    }
+
+    /// returns the items in the data file
+    pub fn items(&self) -> impl Iterator<Item = &EntityDef> {
+        self.entities.iter().filter(|def| def.item.is_some())
+    }
+
+    /// returns true if the data file replaces a built in entity with this name
+    pub fn overrides(&self, name: &str) -> bool {
+        self.entities.iter().any(|def| def.name == name)
+    }
+}
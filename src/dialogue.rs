@@ -0,0 +1,171 @@
+use ratatui::style::Color;
+
+use crate::{
+    app::{App, GameScreen, MessageKind},
+    components::{Dialogue, DialogueNode, DialogueOption, DialogueOutcome},
+    quests::Goal,
+};
+
+// this file contains what friendly npcs have to say, and what happens when the player
+// picks one of the replies. conversations are trees of nodes, see components::Dialogue
+
+/// shorthand for a reply the player can pick
+fn reply(text: &str, outcome: DialogueOutcome) -> DialogueOption {
+    DialogueOption {
+        text: text.to_string(),
+        outcome,
+    }
+}
+
+/// shorthand for a line from the npc, and the replies to it
+fn node(text: &str, options: Vec<DialogueOption>) -> DialogueNode {
+    DialogueNode {
+        text: text.to_string(),
+        options,
+    }
+}
+
+/// shopkeepers trade, and pass on what they've heard
+pub fn shopkeeper_dialogue() -> Dialogue {
+    Dialogue {
+        nodes: vec![
+            node(
+                "Welcome, traveller. Care to see my wares?",
+                vec![
+                    reply("Let's trade.", DialogueOutcome::Trade),
+                    reply("Heard any news?", DialogueOutcome::Goto(1)),
+                    reply("Farewell.", DialogueOutcome::Leave),
+                ],
+            ),
+            node(
+                "They say the lich at the bottom never sleeps. Whoever takes its amulet \
+                 had better run for the stairs.",
+                vec![
+                    reply("Let's trade.", DialogueOutcome::Trade),
+                    reply("Farewell.", DialogueOutcome::Leave),
+                ],
+            ),
+        ],
+        start: 0,
+    }
+}
+
+/// a lost adventurer asks the player to finish what their company couldn't
+pub fn adventurer_dialogue(goal: Goal) -> Dialogue {
+    let (plea, accept) = match goal {
+        Goal::Slay => (
+            "Their captain cut my company down to the last. Put an end to it, and the \
+             guild will see you rewarded.",
+            "I'll hunt it down.",
+        ),
+        Goal::Retrieve => (
+            "I dropped our company's golden idol while I ran. Find it, and the guild will \
+             see you rewarded.",
+            "I'll find it.",
+        ),
+    };
+    Dialogue {
+        nodes: vec![
+            node(
+                "Thank the gods, a friendly face! I came down here with my company, but \
+                 only I made it this far.",
+                vec![
+                    reply("What happened?", DialogueOutcome::Goto(1)),
+                    reply("Good luck with that.", DialogueOutcome::Leave),
+                ],
+            ),
+            node(
+                plea,
+                vec![
+                    reply(accept, DialogueOutcome::OfferQuest { goal, then: 2 }),
+                    reply("Not my problem.", DialogueOutcome::Leave),
+                ],
+            ),
+            node(
+                "Be careful out there.",
+                vec![
+                    reply(
+                        "Any advice?",
+                        DialogueOutcome::Flavor(String::from(
+                            "\"Rest when you can. The monsters never stop coming.\"",
+                        )),
+                    ),
+                    reply("Farewell.", DialogueOutcome::Leave),
+                ],
+            ),
+        ],
+        start: 0,
+    }
+}
+
+/// starts a conversation with an npc the player bumped into
+pub fn talk_to(app: &mut App, speaker: usize) {
+    let Some(dialogue) = &app.objects.get(&speaker).unwrap().dialogue else {
+        return;
+    };
+    app.game_screen = GameScreen::Dialogue {
+        speaker,
+        node: dialogue.start,
+    };
+}
+
+/// picks one of the replies on the node the conversation is at.
+/// does nothing if there's no reply with that index
+pub fn choose(app: &mut App, speaker: usize, node: usize, option: usize) {
+    let Some(outcome) = app
+        .objects
+        .get(&speaker)
+        .unwrap()
+        .dialogue
+        .as_ref()
+        .and_then(|dialogue| dialogue.nodes[node].options.get(option))
+        .map(|option| option.outcome.clone())
+    else {
+        return;
+    };
+
+    match outcome {
+        DialogueOutcome::Goto(next) => {
+            app.game_screen = GameScreen::Dialogue {
+                speaker,
+                node: next,
+            };
+        }
+        DialogueOutcome::Trade => {
+            app.switch_to_main_screen();
+            if app.objects.get(&speaker).unwrap().shop.is_some() {
+                app.game_screen = GameScreen::Shop {
+                    shopkeeper: speaker,
+                    cursor: 0,
+                    selling: false,
+                };
+            }
+        }
+        DialogueOutcome::OfferQuest { goal, then } => {
+            app.switch_to_main_screen();
+            if app.offer_quest(goal) {
+                let description = app.quests.last().unwrap().description();
+                app.add_to_log(
+                    format!("New quest: {}.", description),
+                    Color::LightYellow,
+                    MessageKind::System,
+                );
+            } else {
+                app.add_to_log(
+                    "\"On second thought, it's hopeless. Forget I asked.\"",
+                    Color::default(),
+                    MessageKind::System,
+                );
+            }
+            // there's only one quest to hand out, so don't offer it again
+            if let Some(dialogue) = &mut app.objects.get_mut(&speaker).unwrap().dialogue {
+                dialogue.start = then;
+            }
+        }
+        DialogueOutcome::Flavor(text) => {
+            app.switch_to_main_screen();
+            app.add_to_log(text, Color::default(), MessageKind::System);
+        }
+        DialogueOutcome::Leave => app.switch_to_main_screen(),
+    }
+}
@@ -1,17 +1,51 @@
-use std::{cmp::Ordering, collections::BinaryHeap};
+use std::collections::{BinaryHeap, HashMap};
 
-use crate::{app::procgen::DungeonConfig, items, pathfinding::generate_simple_costs_array};
+use crate::{
+    app::procgen::{DungeonConfig, FINAL_LEVEL},
+    entities, items, lighting, spells,
+};
 use rand::Rng;
 use ratatui::style::{Color, Style, Stylize};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    app::{Action, App, GameScreen, PLAYER, VIEW_RADIUS},
-    components::{AIType, DeathCallback, Item, MELEE_FORGET_TIME, MeleeAIData, Position},
+    achievements,
+    app::{Action, App, GameScreen, MessageKind, PLAYER, TargetSource, fnv1a},
+    components::{
+        AIType, Alertness, DeathCallback, Faction, Fighter, Item, MELEE_FORGET_TIME, MeleeAIData,
+        Minion, Object, PhaseEffect, Position, StatusEffect, StatusKind, Trap,
+    },
+    dialogue,
+    events::{AttackOutcome, GameEvent, StatusChange},
+    gamemap::{Alarm, EffectKind, LevelSummary, TileType, coords_to_idx, idx_to_coords},
+    identify,
+    inventory::{self, Encumbrance},
     los,
-    pathfinding::Pathfinder,
+    pathfinding::{Costs, Diagonals, PathScratch, Pathfinder},
+    quests, scores, settings,
+    theme::ThemeColor,
 };
 
+/// how far the sound of a melee hit carries
+const MELEE_NOISE_RADIUS: u32 = 6;
+/// how far the player's shout carries
+pub const SHOUT_NOISE_RADIUS: u32 = 20;
+
+/// number of turns that have to be due at once before monsters plan them in
+/// parallel. below this, handing the work out to threads costs more than it saves
+const PARALLEL_AI_THRESHOLD: usize = 32;
+
+/// how long monsters keep converging on an alarm after it last went off
+const ALARM_DURATION: u64 = 1500;
+/// delay between a sentry's checks for the player
+const SENTRY_WATCH_TIME: u64 = 100;
+
+/// how much time passes during one of the player's regular turns
+const PLAYER_TURN_TIME: u64 = 100;
+
 // NOTE: this crate contains functions that control the gameplay
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum InputDirection {
     Up,
     Down,
@@ -23,7 +57,19 @@ pub enum InputDirection {
     DownRight,
 }
 
-fn direction_to_deltas(direction: InputDirection) -> (i16, i16) {
+/// every direction, starting from up and going clockwise
+pub const DIRECTION_ORDERING: [InputDirection; 8] = [
+    InputDirection::Up,
+    InputDirection::UpRight,
+    InputDirection::Right,
+    InputDirection::DownRight,
+    InputDirection::Down,
+    InputDirection::DownLeft,
+    InputDirection::Left,
+    InputDirection::UpLeft,
+];
+
+pub fn direction_to_deltas(direction: InputDirection) -> (i16, i16) {
     match direction {
         InputDirection::Up => (0, -1),
         InputDirection::Down => (0, 1),
@@ -41,6 +87,7 @@ pub enum UseResult {
     UsedUp,
     Equipped,
     Cancelled,
+    Kept(u64), // used, but stays in the inventory, after taking this long
 }
 
 /// different targeting modes for targeted abilities
@@ -49,6 +96,45 @@ pub enum TargetingMode {
     None,  // no targeting is needed to use this
     Smite, // smite target any enemy in line of sight
     Line,  // fire a projectile in a line at the target
+    Item,  // pick an item in the inventory or equipment
+}
+
+/// returns the total (power, defense) bonus granted by a set of equipment slots
+fn equipment_bonuses(app: &App, equipment: &[Option<usize>]) -> (i16, i16) {
+    equipment
+        .iter()
+        .flatten()
+        .map(|id| app.objects.get(id).unwrap().equipment.as_ref().unwrap())
+        .fold((0, 0), |(power, defense), equip| {
+            (power + equip.power(), defense + equip.defense())
+        })
+}
+
+/// returns how the player's (power, defense) would change by equipping an item,
+/// along with the ids of the items it would replace
+pub fn equip_delta(app: &App, item_id: usize) -> Option<(i16, i16, Vec<usize>)> {
+    let slot = app.objects.get(&item_id)?.equipment.as_ref()?.slot as usize;
+    let replaced = inventory::displaced_equipment(app, item_id);
+    let mut equipment = app.equipment.clone();
+    for needed in inventory::needed_slots(app, item_id) {
+        equipment[needed] = None;
+    }
+    equipment[slot] = Some(item_id);
+
+    let (cur_power, cur_defense) = equipment_bonuses(app, &app.equipment);
+    let (new_power, new_defense) = equipment_bonuses(app, &equipment);
+    Some((new_power - cur_power, new_defense - cur_defense, replaced))
+}
+
+/// returns the equipment slots worn by a fighter. monsters keep their gear on
+/// their own object, while the player's is stored in the app
+fn worn_equipment(app: &App, id: usize) -> Vec<Option<usize>> {
+    if id == PLAYER {
+        app.equipment.to_vec()
+    } else {
+        let obj = app.objects.get(&id).unwrap();
+        obj.equipped.iter().copied().map(Some).collect()
+    }
 }
 
 /// returns the true power of an fighter, after factoring in bonuses
@@ -61,24 +147,7 @@ pub fn power(app: &App, id: usize) -> i16 {
     }
 
     let base_power = obj.fighter.as_ref().unwrap().power;
-    let bonus_power: i16 = match id.cmp(&PLAYER) {
-        Ordering::Equal => {
-            // TODO: equipment calculations
-            let mut bonus: i16 = 0;
-            for id_option in &app.equipment {
-                if id_option.is_none() {
-                    continue;
-                }
-
-                let obj = app.objects.get(id_option.as_ref().unwrap()).unwrap();
-                let equip = obj.equipment.as_ref().unwrap();
-                bonus += equip.power_bonus;
-            }
-
-            bonus
-        }
-        _ => 0,
-    };
+    let bonus_power = equipment_bonuses(app, &worn_equipment(app, id)).0;
 
     base_power + bonus_power
 }
@@ -93,34 +162,163 @@ pub fn defense(app: &App, id: usize) -> i16 {
     }
 
     let base_defense = obj.fighter.as_ref().unwrap().defense;
-    let bonus_defense: i16 = match id.cmp(&PLAYER) {
-        Ordering::Equal => {
-            // TODO: equipment calculations
-            let mut bonus: i16 = 0;
-            for id_option in &app.equipment {
-                if id_option.is_none() {
-                    continue;
-                }
+    let bonus_defense = equipment_bonuses(app, &worn_equipment(app, id)).1;
+
+    base_defense + bonus_defense
+}
+
+/// a damage roll written in dice notation, e.g. "1d6+2" rolls one six sided die and adds two
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Dice {
+    pub count: u16,
+    pub sides: u16,
+    pub bonus: i16,
+}
+
+impl Dice {
+    pub const fn new(count: u16, sides: u16, bonus: i16) -> Self {
+        Self {
+            count,
+            sides,
+            bonus,
+        }
+    }
+
+    /// a roll that always comes up as the same number
+    pub const fn flat(bonus: i16) -> Self {
+        Self::new(0, 0, bonus)
+    }
+
+    /// returns the same dice with more added onto the result
+    pub fn plus(self, bonus: i16) -> Self {
+        Self {
+            bonus: self.bonus + bonus,
+            ..self
+        }
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> i16 {
+        let rolled: i16 = match self.sides {
+            0 => 0,
+            sides => (0..self.count)
+                .map(|_| rng.random_range(1..=sides) as i16)
+                .sum(),
+        };
+        rolled + self.bonus
+    }
+
+    /// the average result of a roll
+    pub fn average(&self) -> f64 {
+        let rolled = match self.sides {
+            0 => 0.0,
+            sides => self.count as f64 * (sides as f64 + 1.0) / 2.0,
+        };
+        rolled + self.bonus as f64
+    }
+}
+
+impl std::fmt::Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.count == 0 || self.sides == 0 {
+            return write!(f, "{}", self.bonus);
+        }
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.bonus.cmp(&0) {
+            std::cmp::Ordering::Greater => write!(f, "+{}", self.bonus),
+            std::cmp::Ordering::Less => write!(f, "{}", self.bonus),
+            std::cmp::Ordering::Equal => Ok(()),
+        }
+    }
+}
+
+impl std::str::FromStr for Dice {
+    type Err = String;
+
+    /// parses notation like "2d4", "d6+1", "1d8-2" or just "3"
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid dice \"{}\"", text);
+        let text = text.trim();
+
+        let Some((count, rest)) = text.split_once('d') else {
+            return text.parse().map(Dice::flat).map_err(|_| invalid());
+        };
+        let count = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| invalid())?,
+        };
+        let (sides, bonus) = match rest.find(['+', '-']) {
+            Some(idx) => {
+                let bonus = rest[idx..].trim_start_matches('+');
+                (&rest[..idx], bonus.parse().map_err(|_| invalid())?)
+            }
+            None => (rest, 0),
+        };
+        let sides = sides.parse().map_err(|_| invalid())?;
+        Ok(Dice::new(count, sides, bonus))
+    }
+}
+
+// dice are saved as their notation, so they can be read and written by hand.
+// plain numbers are read as flat rolls, which is how damage was saved before dice
+impl Serialize for Dice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Dice {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DiceVisitor;
+
+        impl serde::de::Visitor<'_> for DiceVisitor {
+            type Value = Dice;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "dice notation like \"1d6+2\", or a number")
+            }
 
-                let obj = app.objects.get(id_option.as_ref().unwrap()).unwrap();
-                let equip = obj.equipment.as_ref().unwrap();
-                bonus += equip.defense_bonus;
+            fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<Dice, E> {
+                text.parse().map_err(E::custom)
             }
 
-            bonus
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Dice, E> {
+                i16::try_from(value).map(Dice::flat).map_err(E::custom)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Dice, E> {
+                i16::try_from(value).map(Dice::flat).map_err(E::custom)
+            }
         }
-        _ => 0,
-    };
 
-    base_defense + bonus_defense
+        deserializer.deserialize_any(DiceVisitor)
+    }
+}
+
+/// returns the dice a fighter attacks with: its own damage dice, plus its power
+pub fn attack_dice(app: &App, id: usize) -> Dice {
+    let obj = app.objects.get(&id).unwrap();
+    match &obj.fighter {
+        Some(fighter) => fighter.damage.plus(power(app, id)),
+        None => Dice::default(),
+    }
 }
 
-/// returns the amount of damage an attack does.
+/// rolls the amount of damage an attack does.
 /// note: defense blocks a random amount of damage between def/2 and def
-pub fn damage(power: i16, defense: i16) -> i16 {
-    let mut rng = rand::rng();
-    let mitigated_damage = rng.random_range((defense / 2)..=defense);
-    return power.saturating_sub(mitigated_damage).max(0);
+pub fn damage(app: &mut App, dice: Dice, defense: i16) -> i16 {
+    let rolled = dice.roll(&mut app.rng);
+    let mitigated_damage = app.rng.random_range((defense / 2)..=defense);
+    rolled.saturating_sub(mitigated_damage).max(0)
+}
+
+/// returns how much of its max hp an object has left, as a percentage
+pub fn hp_percent(obj: &Object) -> u16 {
+    match &obj.fighter {
+        Some(fighter) if fighter.max_hp > 0 => {
+            (fighter.hp as u32 * 100 / fighter.max_hp as u32) as u16
+        }
+        _ => 100,
+    }
 }
 
 /// heals an entity for the specified amount
@@ -132,8 +330,70 @@ pub fn heal(app: &mut App, id: usize, heal_amount: u16) {
     }
 }
 
+/// whatever dealt a blow, so that the player's death can say what killed them
+#[derive(Clone, PartialEq, Debug)]
+pub enum DamageSource {
+    /// a monster, already written the way it's referred to, e.g. "a troll" or "the Lich"
+    Monster(String),
+    /// a trap, by its name
+    Trap(String),
+    /// falling down into a chasm
+    Fall,
+    /// standing in lava
+    Lava,
+    /// catching fire
+    Fire,
+    /// breathing in poison gas
+    Poison,
+    /// a scroll the player read, by the name of its spell
+    Spell(String),
+}
+
+impl DamageSource {
+    /// blows from a monster. bosses are one of a kind, so they get "the" instead of "a"
+    pub fn monster(app: &App, id: usize) -> Self {
+        let obj = app.objects.get(&id).unwrap();
+        if obj.boss.is_some() {
+            return DamageSource::Monster(format!("the {}", obj.name));
+        }
+        let name = obj.name.to_lowercase();
+        let article = match name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+            true => "an",
+            false => "a",
+        };
+        DamageSource::Monster(format!("{} {}", article, name))
+    }
+}
+
+impl std::fmt::Display for DamageSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DamageSource::Monster(name) => write!(f, "{}", name),
+            DamageSource::Trap(name) => write!(f, "a {}", name),
+            DamageSource::Fall => write!(f, "a fall into a chasm"),
+            DamageSource::Lava => write!(f, "a dip in the lava"),
+            DamageSource::Fire => write!(f, "the flames"),
+            DamageSource::Poison => write!(f, "a cloud of poison gas"),
+            DamageSource::Spell(name) => write!(f, "their own {}", name),
+        }
+    }
+}
+
+/// what ended a run that the player didn't survive
+#[derive(Clone, PartialEq, Debug)]
+pub struct Death {
+    pub source: DamageSource,
+    pub level: u16,
+}
+
+impl std::fmt::Display for Death {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "killed by {} on level {}", self.source, self.level)
+    }
+}
+
 /// applies damage to an entity for the specified amount
-pub fn take_damage(app: &mut App, id: usize, damage: u16) {
+pub fn take_damage(app: &mut App, id: usize, damage: u16, source: DamageSource) {
     let obj = &mut app.objects.get_mut(&id).unwrap();
     let mut death_callback = None;
     if let Some(fighter) = obj.fighter.as_mut() {
@@ -141,7 +401,7 @@ pub fn take_damage(app: &mut App, id: usize, damage: u16) {
             fighter.hp = fighter.hp.saturating_sub(damage);
         }
 
-        if fighter.hp <= 0 {
+        if fighter.hp == 0 {
             death_callback = Some(fighter.death_callback.clone());
         }
 
@@ -150,45 +410,612 @@ pub fn take_damage(app: &mut App, id: usize, damage: u16) {
 
     if let Some(callback) = death_callback {
         match callback {
-            DeathCallback::Player => player_death(app),
-            DeathCallback::Monster => monster_death(app, id),
+            DeathCallback::Player => player_death(app, source),
+            DeathCallback::Monster | DeathCallback::Undead => monster_death(app, id),
+        }
+    } else if damage > 0 && id != PLAYER {
+        // getting hurt is a sure way to wake up
+        let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+        alert_monster(app, id, player_pos);
+    }
+}
+
+/// makes a melee monster start hunting the player, heading towards the given position.
+/// returns true if the monster was asleep
+fn alert_monster(app: &mut App, id: usize, towards: Position) -> bool {
+    let Some(AIType::Melee(ai_data)) = &mut app.objects.get_mut(&id).unwrap().ai else {
+        return false;
+    };
+
+    let was_asleep = ai_data.alertness == Alertness::Sleeping;
+    ai_data.alertness = Alertness::Alert;
+    ai_data.target = Some(PLAYER);
+    ai_data.last_seen_time = Some(app.time);
+    ai_data.last_seen_pos = Some(towards);
+    was_asleep
+}
+
+/// raises the floor's alert level. until it dies down, every monster on the floor
+/// without a target of its own wakes up and heads towards the alarm
+pub fn raise_alarm(app: &mut App, position: Position) {
+    if active_alarm(app).is_none() {
+        app.emit(GameEvent::AlarmRaised);
+    }
+    app.gamemap.alarm = Some(Alarm {
+        position,
+        expires: app.time + ALARM_DURATION,
+    });
+}
+
+/// returns where the floor's alarm went off, if it is still ringing
+pub fn active_alarm(app: &App) -> Option<Position> {
+    app.gamemap
+        .alarm
+        .filter(|alarm| alarm.expires > app.time)
+        .map(|alarm| alarm.position)
+}
+
+/// makes a noise that alerts every monster within radius, measured by walking distance.
+/// monsters that hear it come to investigate where the noise came from
+pub fn make_noise(app: &mut App, origin: Position, radius: u32) {
+    let pathfinder = Pathfinder::new(
+        &app.gamemap,
+        &mut app.path_scratch,
+        Costs::Walkable,
+        (origin.x, origin.y),
+        0,
+        0,
+        app.settings.diagonals(),
+    );
+
+    let listeners: Vec<(usize, Position)> = app
+        .objects
+        .get_contents()
+        .iter()
+        .filter(|(_, obj)| {
+            matches!(&obj.ai, Some(AIType::Melee(data)) if data.alertness != Alertness::Alert)
+        })
+        .filter_map(|(&id, _)| app.gamemap.get_position(id).map(|pos| (id, pos)))
+        .filter(|(_, pos)| {
+            pathfinder
+                .distance_to((pos.x, pos.y))
+                .is_some_and(|dist| dist <= radius)
+        })
+        .collect();
+
+    let mut stirred = false;
+    for (id, pos) in listeners {
+        let woke_up = alert_monster(app, id, origin);
+        if woke_up && !app.gamemap.is_visible(pos.x, pos.y) {
+            stirred = true;
+        }
+    }
+
+    if stirred {
+        app.emit(GameEvent::NoiseHeard);
+    }
+}
+
+/// percentage of the usual time that actions take while hasted or slowed
+const HASTE_TIME_PERCENT: u64 = 50;
+const SLOW_TIME_PERCENT: u64 = 200;
+
+/// returns how long an action actually takes for an object,
+/// after scaling the base time by any haste/slow statuses
+pub fn action_time(app: &App, id: usize, base_time: u64) -> u64 {
+    let Some(fighter) = app.objects.get(&id).and_then(|obj| obj.fighter.as_ref()) else {
+        return base_time;
+    };
+
+    fighter
+        .statuses
+        .iter()
+        .filter(|status| status.expires > app.time)
+        .fold(base_time, |time, status| match status.kind {
+            StatusKind::Haste => time * HASTE_TIME_PERCENT / 100,
+            StatusKind::Slow => time * SLOW_TIME_PERCENT / 100,
+            StatusKind::Charmed | StatusKind::Burning | StatusKind::Poisoned => time,
+        })
+}
+
+const BURDENED_TIME_PERCENT: u64 = 150;
+const OVERLOADED_TIME_PERCENT: u64 = 200;
+const SNEAKING_TIME_PERCENT: u64 = 150;
+
+/// returns how long a step takes for an object before statuses are applied.
+/// wading through water and climbing over rubble is slow for everyone, and
+/// the player also slows down when carrying too much, or when sneaking
+pub fn movement_time(app: &App, id: usize, base_time: u64) -> u64 {
+    let base_time = match app.gamemap.get_position(id) {
+        Some(pos) => base_time * app.gamemap.get_ref(pos.x, pos.y).move_time_percent() / 100,
+        None => base_time,
+    };
+    if id != PLAYER {
+        return base_time;
+    }
+    let time = match inventory::encumbrance(app) {
+        Encumbrance::Unburdened => base_time,
+        Encumbrance::Burdened => base_time * BURDENED_TIME_PERCENT / 100,
+        Encumbrance::Overloaded => base_time * OVERLOADED_TIME_PERCENT / 100,
+    };
+    match app.sneaking {
+        true => time * SNEAKING_TIME_PERCENT / 100,
+        false => time,
+    }
+}
+
+/// applies a status to a fighter for the given duration.
+/// reapplying a status extends it, while haste and slow cancel each other out
+pub fn apply_status(app: &mut App, id: usize, kind: StatusKind, duration: u64) {
+    let time = app.time;
+    let obj = app.objects.get_mut(&id).unwrap();
+    let name = obj.name.clone();
+    let Some(fighter) = obj.fighter.as_mut() else {
+        return;
+    };
+
+    let opposite = match kind {
+        StatusKind::Haste => Some(StatusKind::Slow),
+        StatusKind::Slow => Some(StatusKind::Haste),
+        StatusKind::Charmed | StatusKind::Burning | StatusKind::Poisoned => None,
+    };
+
+    let change = if let Some(idx) = fighter
+        .statuses
+        .iter()
+        .position(|s| Some(s.kind) == opposite)
+    {
+        fighter.statuses.remove(idx);
+        StatusChange::Cancelled
+    } else if let Some(status) = fighter.statuses.iter_mut().find(|s| s.kind == kind) {
+        status.expires = status.expires.max(time + duration);
+        StatusChange::Extended
+    } else {
+        fighter.statuses.push(StatusEffect {
+            kind,
+            duration,
+            expires: time + duration,
+        });
+        StatusChange::Gained
+    };
+
+    app.emit(GameEvent::StatusChanged {
+        target: id,
+        target_name: name,
+        kind,
+        change,
+    });
+}
+
+/// returns every fighter on the map with a status that hasn't worn off, and where
+/// they stand, in id order. anything hurt along the way may die and drop out of the
+/// way, so callers should skip fighters that are no longer where the list says
+pub fn fighters_with_status(app: &App, kind: StatusKind) -> Vec<(usize, Position)> {
+    let time = app.time;
+    let mut fighters: Vec<(usize, Position)> = app
+        .objects
+        .iter()
+        .filter(|(_, obj)| {
+            obj.fighter.as_ref().is_some_and(|fighter| {
+                fighter
+                    .statuses
+                    .iter()
+                    .any(|status| status.kind == kind && status.expires > time)
+            })
+        })
+        .filter_map(|(&id, _)| Some((id, app.gamemap.get_position(id)?)))
+        .filter(|&(id, pos)| app.gamemap.get_ref(pos.x, pos.y).blocker == Some(id))
+        .collect();
+    fighters.sort_by_key(|&(id, _)| id);
+    fighters
+}
+
+/// removes any statuses that have worn off
+pub fn expire_statuses(app: &mut App) {
+    let time = app.time;
+    let mut player_expired = Vec::new();
+    let mut uncharmed = Vec::new();
+    for (&id, obj) in app.objects.get_contents().iter_mut() {
+        let Some(fighter) = obj.fighter.as_mut() else {
+            continue;
+        };
+
+        if fighter
+            .statuses
+            .iter()
+            .any(|status| status.kind == StatusKind::Charmed && status.expires <= time)
+        {
+            uncharmed.push(id);
+        }
+
+        if id == PLAYER {
+            player_expired.extend(
+                fighter
+                    .statuses
+                    .iter()
+                    .filter(|status| status.expires <= time)
+                    .map(|status| status.kind),
+            );
+        }
+        fighter.statuses.retain(|status| status.expires > time);
+    }
+
+    for kind in player_expired {
+        app.emit(GameEvent::StatusExpired { kind });
+    }
+
+    // former allies forget who they were fighting, and go back to hunting the player
+    uncharmed.sort();
+    for id in uncharmed {
+        set_melee_target(app, id, None);
+        if let Some(pos) = app.gamemap.get_position(id) {
+            let name = app.objects.get(&id).unwrap().name.clone();
+            app.emit(GameEvent::CharmBroken {
+                id,
+                name,
+                witnessed: app.gamemap.is_visible(pos.x, pos.y),
+            });
         }
     }
 }
 
-pub fn player_death(app: &mut App) {
+pub fn player_death(app: &mut App, source: DamageSource) {
+    // monsters can keep hitting the corpse before the turn ends
+    if app.game_screen == GameScreen::GameOver {
+        return;
+    }
+    let death = Death {
+        source,
+        level: app.gamemap.level,
+    };
+
     let player = &mut app.objects.get_mut(&PLAYER).unwrap();
     let renderable = &mut player.renderable;
     renderable.glyph = '%';
-    renderable.fg = Color::Red;
+    renderable.role = Some(ThemeColor::Danger);
 
-    app.add_to_log(String::from("You died!"), Style::new().italic().red());
+    app.emit(GameEvent::PlayerDied {
+        death: death.clone(),
+    });
+    app.death = Some(death);
+
+    // stop whatever the player was doing, and show the summary of the run
+    app.travel_destination = None;
+    app.auto_descend = false;
+    app.game_screen = GameScreen::GameOver;
+    // nobody wants to race the demo's ghost or read its morgue
+    if !app.keeps_records() {
+        return;
+    }
+    // a dead character can't be brought back by loading an older save
+    app.forfeit_saves();
+    // the next attempt on this seed races against this one. losing it isn't worth a message
+    app.record_ghost_step();
+    let _ = app.write_ghost();
+    scores::record(app);
+    let _ = app.profile.save();
+    match app.write_morgue() {
+        Ok(file_name) => app.add_to_log(
+            format!("A record of your run was written to {}.", file_name),
+            Color::default(),
+            MessageKind::System,
+        ),
+        Err(_) => app.add_to_log(
+            "Couldn't write a morgue file.",
+            Color::default(),
+            MessageKind::System,
+        ),
+    }
 }
 
 // callback to be run when a monster dies
 pub fn monster_death(app: &mut App, id: usize) {
     let monster = &mut app.objects.get_mut(&id).unwrap();
-    let message = format!("{} dies!", monster.name);
+    let name = monster.name.clone();
+    let leaves_corpse = !matches!(
+        monster
+            .fighter
+            .as_ref()
+            .map(|fighter| &fighter.death_callback),
+        Some(DeathCallback::Undead)
+    );
 
     // dead monsters don't have any ai
     monster.ai = None;
+    app.kills += 1;
+
+    let max_gold = monster.gold.unwrap_or(0);
+    let mut equipped = std::mem::take(&mut monster.equipped);
+    equipped.append(&mut monster.carried);
+    let sealed = monster
+        .boss
+        .as_mut()
+        .map(|boss| std::mem::take(&mut boss.sealed))
+        .unwrap_or_default();
 
     let monster_pos = app.gamemap.get_position(id).unwrap();
+    let witnessed = app.gamemap.is_visible(monster_pos.x, monster_pos.y);
     app.gamemap.remove_blocker(monster_pos.x, monster_pos.y);
 
-    // TODO: add blood to the tile after monster death
+    // monsters drop some of the gold they were carrying
+    let amount = app.rng.random_range(0..=max_gold);
+    if amount > 0 {
+        let gold_id = app.objects.add(entities::gold_pile(amount));
+        app.gamemap
+            .area_place_item(monster_pos.x, monster_pos.y, gold_id, &mut app.rng);
+    }
 
-    // let renderable = &mut monster.renderable;
-    // renderable.glyph = '%';
-    // renderable.fg = Color::Red;
-    //
-    // monster.blocks_movement = false;
-    // monster.render_layer = RenderLayer::Corpse;
-    // monster.alive = false;
-    // monster.fighter = None;
-    // monster.name = format!("remains of {}", monster.name);
+    // walls raised by a boss crumble away once it's dead
+    if !sealed.is_empty() {
+        for pos in sealed {
+            app.gamemap.set_tile_type(pos.x, pos.y, TileType::Floor);
+        }
+        app.emit(GameEvent::ArenaOpened);
+    }
+
+    // monsters drop everything they were wearing or carrying, if there's room for it
+    for item_id in equipped {
+        let dropped =
+            app.gamemap
+                .area_place_item(monster_pos.x, monster_pos.y, item_id, &mut app.rng);
+        if dropped.is_none() {
+            app.objects.get_contents().remove(&item_id);
+        }
+    }
+
+    // the body goes down last, so that it doesn't push the loot further away
+    if leaves_corpse {
+        let corpse = entities::corpse(app.objects.get(&id).unwrap(), app.time + CORPSE_ROT_TIME);
+        let corpse_id = app.objects.add(corpse);
+        let placed =
+            app.gamemap
+                .area_place_item(monster_pos.x, monster_pos.y, corpse_id, &mut app.rng);
+        if placed.is_none() {
+            app.objects.get_contents().remove(&corpse_id);
+        }
+    }
+
+    // monsters fighting among themselves can die anywhere on the floor
+    app.emit(GameEvent::MonsterDied {
+        id,
+        name,
+        witnessed,
+    });
+}
+
+/// time it takes for a corpse to rot away
+const CORPSE_ROT_TIME: u64 = 5000;
+
+/// removes every corpse that has rotted away, whether it's on the floor or carried
+pub fn rot_corpses(app: &mut App) {
+    let rotten: Vec<usize> = app
+        .objects
+        .iter()
+        .filter(|(_, obj)| {
+            obj.corpse
+                .as_ref()
+                .is_some_and(|corpse| corpse.rots_at <= app.time)
+        })
+        .map(|(&id, _)| id)
+        .collect();
+
+    for id in rotten {
+        if let Some(pos) = app.gamemap.get_position(id) {
+            app.gamemap.remove_item(pos.x, pos.y);
+        }
+        if let Some(inventory_idx) = app.inventory.iter().position(|&other| other == id) {
+            app.inventory.remove(inventory_idx);
+            let name = app.objects.get(&id).unwrap().name.clone();
+            app.emit(GameEvent::ItemRotted { name });
+        }
+        app.objects.get_contents().remove(&id);
+    }
+}
 
-    app.add_to_log(message, Color::Red);
+/// returns the ids of every living monster in the player's field of view,
+/// leaving out allies
+pub fn visible_monsters(app: &App) -> Vec<usize> {
+    let mut ids: Vec<usize> = app
+        .gamemap
+        .visible
+        .iter()
+        .enumerate()
+        .filter(|&(_, &visible)| visible)
+        .filter_map(|(idx, _)| app.gamemap.tiles[idx].blocker)
+        .filter(|&id| id != PLAYER)
+        .filter(|id| {
+            app.objects
+                .get(id)
+                .is_some_and(|obj| obj.fighter.is_some() && obj.ai.is_some())
+        })
+        .filter(|&id| !is_charmed(app, id))
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// returns the ids of all monsters in the player's fov, closest to the player first
+pub fn visible_monsters_by_distance(app: &App) -> Vec<usize> {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let mut ids = visible_monsters(app);
+    // visible_monsters() is sorted by id, so ties in distance stay in a stable order
+    ids.sort_by_key(|id| player_pos.distance_to(app.gamemap.get_position(*id).unwrap()));
+    ids
+}
+
+/// returns the faction an object fights on. saves from before factions didn't record
+/// one, so the player and shopkeepers are recognised and everything else sides with the monsters
+pub fn faction(app: &App, id: usize) -> Faction {
+    let obj = app.objects.get(&id).unwrap();
+    if is_charmed(app, id) {
+        return Faction::Player;
+    }
+    match obj.faction {
+        Some(faction) => faction,
+        None if id == PLAYER => Faction::Player,
+        None if obj.shop.is_some() => Faction::Neutral,
+        None => Faction::Monsters,
+    }
+}
+
+/// whether a fighter is currently charmed onto the player's side
+pub fn is_charmed(app: &App, id: usize) -> bool {
+    app.objects
+        .get(&id)
+        .and_then(|obj| obj.fighter.as_ref())
+        .is_some_and(|fighter| {
+            fighter
+                .statuses
+                .iter()
+                .any(|status| status.kind == StatusKind::Charmed && status.expires > app.time)
+        })
+}
+
+/// whether one fighter attacks another on sight, because their factions are hostile
+/// or because the other one provoked it
+pub fn is_enemy(app: &App, id: usize, other: usize) -> bool {
+    let provoked = match app.objects.get(&id).and_then(|obj| obj.ai.as_ref()) {
+        Some(AIType::Melee(data)) => data.target == Some(other),
+        _ => false,
+    };
+    id != other && (provoked || faction(app, id).is_hostile_to(faction(app, other)))
+}
+
+/// whether a monster standing at a position can see another fighter.
+/// sight is assumed to be symmetric, so the player's fov is reused for the player
+fn can_see(app: &App, id: usize, from: Position, other: usize, other_pos: Position) -> bool {
+    match other {
+        PLAYER => lighting::monster_sees_player(app, id, from),
+        _ => {
+            from.distance_to(other_pos) <= app.settings.view_radius
+                && has_clear_line(app, from, other_pos)
+        }
+    }
+}
+
+/// returns the closest living enemy that a monster can see, if any
+fn nearest_visible_enemy(app: &App, id: usize, pos: Position) -> Option<usize> {
+    app.objects
+        .iter()
+        .filter(|(_, obj)| obj.fighter.as_ref().is_some_and(|fighter| fighter.hp > 0))
+        .filter(|&(&other, _)| is_enemy(app, id, other))
+        .filter_map(|(&other, _)| Some((other, app.gamemap.get_position(other)?)))
+        .filter(|&(other, other_pos)| can_see(app, id, pos, other, other_pos))
+        // ties go to the lowest id, so that runs stay reproducible
+        .min_by_key(|&(other, other_pos)| (pos.distance_to(other_pos), other))
+        .map(|(other, _)| other)
+}
+
+/// chance for a monster to notice the player that gets taken away for each point of stealth
+const STEALTH_PERCENT: u64 = 10;
+/// sneaking divides the chance to be noticed by this much
+const SNEAK_DIVISOR: u64 = 2;
+
+/// returns the percent chance that a monster notices the player in its view this turn.
+/// it's certain right next to the monster, and falls off towards the edge of its perception
+pub fn notice_chance(app: &App, ai_data: &MeleeAIData, distance: u16) -> u64 {
+    let perception = ai_data.perception as u64;
+    if distance as u64 > perception {
+        return 0;
+    }
+    let stealth = app
+        .objects
+        .get(&PLAYER)
+        .unwrap()
+        .fighter
+        .as_ref()
+        .map_or(0, |fighter| fighter.stealth.max(0) as u64);
+
+    let closeness = (perception + 1 - distance as u64) * 100 / perception.max(1);
+    let chance = closeness.min(100).saturating_sub(stealth * STEALTH_PERCENT);
+    match app.sneaking {
+        true => chance / SNEAK_DIVISOR,
+        false => chance,
+    }
+}
+
+/// whether a monster that can see the player actually notices them this turn.
+/// monsters already hunting never lose track of the player this way
+fn notices_player(app: &App, id: usize, ai_data: &MeleeAIData, pos: Position) -> bool {
+    if ai_data.alertness == Alertness::Alert {
+        return true;
+    }
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    perception_roll(app, id) < notice_chance(app, ai_data, pos.distance_to(player_pos))
+}
+
+/// rolls from 0 to 99 for a monster's perception check. turns are planned on many
+/// threads that can't share app.rng, so the roll is hashed from the seed, the time and
+/// the monster instead, which keeps runs reproducible
+fn perception_roll(app: &App, id: usize) -> u64 {
+    fnv1a(
+        [app.seed, app.time, id as u64]
+            .iter()
+            .flat_map(|number| number.to_le_bytes()),
+    ) % 100
+}
+
+/// returns a grid marking every tile that a visible monster could attack
+/// before the player's next turn, based on how far each monster can move
+pub fn danger_map(app: &App) -> Vec<bool> {
+    let width = app.gamemap.width;
+    let mut danger = vec![false; app.gamemap.tiles.len()];
+
+    for id in visible_monsters(app) {
+        let Some(AIType::Melee(ai_data)) = &app.objects.get(&id).unwrap().ai else {
+            continue;
+        };
+        if ai_data.alertness == Alertness::Sleeping {
+            continue; // sleeping monsters won't attack anyone
+        }
+
+        // a monster gets several actions per turn if it is fast, and needs one to attack
+        let actions = (PLAYER_TURN_TIME / ai_data.move_speed.max(1)).max(1);
+
+        // find every tile the monster can walk to with its remaining actions
+        let start = app.gamemap.get_position(id).unwrap();
+        let mut reachable = vec![start];
+        let mut frontier = vec![start];
+        for _ in 1..actions {
+            let mut next_frontier = Vec::new();
+            for pos in frontier {
+                for direction in DIRECTION_ORDERING {
+                    let (dx, dy) = direction_to_deltas(direction);
+                    let (x, y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+                    if !app.gamemap.in_bounds(x, y) || !step_allowed(app, pos, dx, dy) {
+                        continue;
+                    }
+                    let next = Position {
+                        x: x as u16,
+                        y: y as u16,
+                    };
+                    let tile = app.gamemap.get_ref(next.x, next.y);
+                    let blocked = tile.blocker.is_some_and(|blocker| blocker != PLAYER);
+                    if tile.is_walkable() && !blocked && !reachable.contains(&next) {
+                        reachable.push(next);
+                        next_frontier.push(next);
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+
+        // every tile next to one the monster can reach is in striking range
+        for pos in reachable {
+            for direction in DIRECTION_ORDERING {
+                let (dx, dy) = direction_to_deltas(direction);
+                let (x, y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+                if app.gamemap.in_bounds(x, y)
+                    && step_allowed(app, pos, dx, dy)
+                    && app.gamemap.get_ref(x as u16, y as u16).is_walkable()
+                {
+                    danger[coords_to_idx(x as u16, y as u16, width)] = true;
+                }
+            }
+        }
+    }
+
+    danger
 }
 
 /// returns the id of the object at the targeted position, or None if no object there
@@ -196,20 +1023,31 @@ pub fn get_smite_target(app: &App, target: Position) -> Option<usize> {
     app.gamemap.get_ref(target.x, target.y).blocker
 }
 
+/// returns why an item can't be aimed at a tile, or None if it can
+pub fn target_problem(app: &App, item: &Item, target: Position) -> Option<&'static str> {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    if player_pos.distance_to(target) > item.target_range() {
+        Some("That is out of range.")
+    } else if item.needs_line_of_sight() && !app.gamemap.is_visible(target.x, target.y) {
+        Some("You can't see there.")
+    } else {
+        None
+    }
+}
+
 /// returns a vector of targets hit by the line from player to target,
 /// stopping at the first wall encountered
 pub fn get_line_target(app: &App, target: Position) -> Vec<usize> {
     let mut targets = Vec::new();
 
     let player = app.gamemap.get_position(PLAYER).unwrap();
-    let path = los::bresenham(
+    let path = los::LineIter::new(
         (player.x as i32, player.y as i32),
         (target.x as i32, target.y as i32),
     );
 
-    let (_, path) = path.split_first().unwrap();
-    for (x, y) in path {
-        let (x, y) = (*x as u16, *y as u16);
+    for (x, y) in path.skip(1) {
+        let (x, y) = (x as u16, y as u16);
         let tile = app.gamemap.get_ref(x, y);
         if !tile.is_walkable() {
             break;
@@ -219,7 +1057,7 @@ pub fn get_line_target(app: &App, target: Position) -> Vec<usize> {
         }
     }
 
-    return targets;
+    targets
 }
 
 impl Item {
@@ -229,14 +1067,55 @@ impl Item {
             Item::Equipment => TargetingMode::None,
             Item::Heal => TargetingMode::None,
             Item::Lightning => TargetingMode::Smite,
-            Item::Fireball => todo!(),
+            Item::Fireball => TargetingMode::Smite,
             Item::Hexbolt => TargetingMode::Line,
+            Item::Charm => TargetingMode::Smite,
+            Item::Haste => TargetingMode::None,
+            Item::TimeWarp => TargetingMode::None,
+            Item::Enchant => TargetingMode::Item,
+            Item::Identify => TargetingMode::Item,
+            Item::RemoveCurse => TargetingMode::None,
+            Item::Rope => TargetingMode::None,
+            Item::Corpse => TargetingMode::None,
+            Item::Amulet => TargetingMode::None,
+            Item::Gold => TargetingMode::None,
+            Item::Spellbook(_) => TargetingMode::None,
+            Item::Lantern => TargetingMode::None,
+            Item::Pickaxe => TargetingMode::Line,
+            Item::Digging => TargetingMode::Line,
+            Item::PoisonGas | Item::Smoke => TargetingMode::Line,
+            Item::Key | Item::Idol => TargetingMode::None,
         }
     }
 
+    /// how far away the item can be aimed, counting diagonal steps as one
+    pub fn target_range(&self) -> u16 {
+        match self {
+            Item::Lightning => settings::MAX_VIEW_RADIUS, // anything in sight
+            Item::Hexbolt => 6,
+            Item::Charm => 6,
+            Item::Fireball => items::FIREBALL_RANGE,
+            Item::Pickaxe => 1,
+            Item::Digging => items::DIGGING_RANGE,
+            Item::PoisonGas | Item::Smoke => items::THROW_RANGE,
+            _ => 0,
+        }
+    }
+
+    /// whether the item is thrown rather than used in hand
+    pub fn is_thrown(&self) -> bool {
+        matches!(self, Item::PoisonGas | Item::Smoke)
+    }
+
+    /// whether the item can only be aimed at tiles the player can see.
+    /// projectiles can be fired blindly into the dark
+    pub fn needs_line_of_sight(&self) -> bool {
+        matches!(self, Item::Lightning | Item::Charm | Item::Fireball)
+    }
+
     /// switches the game screen to the appropriate targeting mode for the item
     /// should only be called if targeting mode is not None
-    pub fn on_targeting(&self, app: &mut App, inventory_idx: usize) {
+    pub fn on_targeting(&self, app: &mut App, source: TargetSource) {
         // NOTE: need to check if item needs targeting before calling this function!
         assert_ne!(
             self.targeting_mode(),
@@ -244,20 +1123,48 @@ impl Item {
             "on targeting called for an item that doesn't need targeting!"
         );
 
+        // the text would give away what an unidentified scroll does.
+        // spells are always known, even if the scroll they copy isn't
+        let identified = matches!(source, TargetSource::Spell(_))
+            || app.identification.unidentified_appearance(self).is_none();
         let targeting_text = match self {
+            _ if !identified && self.targeting_mode() == TargetingMode::Item => {
+                String::from("Read it on which item?")
+            }
+            _ if !identified => String::from("Read it at what?"),
             Item::Lightning => String::from("Aim the bolt of lightning at what?"),
             Item::Hexbolt => String::from("Aim the hexbolt at what?"),
+            Item::Charm => String::from("Charm which monster?"),
+            Item::Fireball => String::from("Aim the fireball at what?"),
+            Item::Enchant => String::from("Enchant which item?"),
+            Item::Identify => String::from("Identify which item?"),
+            Item::Pickaxe => String::from("Dig into which wall?"),
+            Item::Digging => String::from("Aim the wand of digging where?"),
+            Item::PoisonGas => String::from("Throw the flask where?"),
+            Item::Smoke => String::from("Throw the smoke bomb where?"),
             _ => {
                 panic!("no targeting text defined for {:?}!", self)
             }
         };
 
+        // items that target other items are picked from the inventory instead of the map
+        if self.targeting_mode() == TargetingMode::Item {
+            let TargetSource::Inventory(inventory_idx) = source else {
+                panic!("no spell casts {:?}, which targets items!", self);
+            };
+            app.game_screen = GameScreen::ChooseItem {
+                text: targeting_text,
+                inventory_idx,
+            };
+            return;
+        }
+
         // all other cases, targeting is required
         let targeting = GameScreen::Targeting {
             cursor: app.gamemap.get_position(PLAYER).unwrap(),
             targeting: self.targeting_mode(),
             text: targeting_text,
-            inventory_idx,
+            source,
         };
 
         app.game_screen = targeting;
@@ -273,30 +1180,165 @@ impl Item {
             Item::Heal => items::cast_cure_wounds(app),
             Item::Lightning => items::cast_lightning(app, target.unwrap()),
             Item::Hexbolt => items::cast_hexbolt(app, target.unwrap()),
-            Item::Fireball => todo!(),
+            Item::Charm => items::cast_charm(app, target.unwrap()),
+            Item::Fireball => items::cast_fireball(app, target.unwrap()),
+            Item::Haste => items::cast_haste(app),
+            Item::TimeWarp => items::cast_time_warp(app),
+            Item::RemoveCurse => items::cast_remove_curse(app),
+            Item::Corpse => items::eat_corpse(app),
+            Item::Amulet => {
+                app.add_to_log(
+                    "The amulet hums. Carry it up the stairs to escape.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                UseResult::Cancelled
+            }
+
+            // NOTE: ropes get used up automatically when jumping into a chasm
+            Item::Rope => {
+                app.add_to_log(
+                    "Walk into a chasm to climb down with the rope.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                UseResult::Cancelled
+            }
+
+            // NOTE: items that target other items are used through on_use_on_item
+            Item::Enchant | Item::Identify => UseResult::Cancelled,
 
             // NOTE: logic for equipping items is in use_item, since removing the equipped item
             // from the inventory requires knowing the index it was stored in
             Item::Equipment => UseResult::Equipped,
+
+            // gold is never stored in the inventory, so it can't be used
+            Item::Gold => UseResult::Cancelled,
+
+            Item::Spellbook(spell) => spells::learn_spell(app, *spell),
+
+            // NOTE: lanterns light the way just by being carried
+            Item::Lantern => {
+                app.add_to_log(
+                    "The lantern lights your way while you carry it. Drop it to hide in the dark.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                UseResult::Cancelled
+            }
+
+            Item::Pickaxe => items::use_pickaxe(app, target.unwrap()),
+            Item::Digging => items::zap_digging(app, target.unwrap()),
+            Item::PoisonGas => items::throw_flask(app, target.unwrap(), EffectKind::Poison),
+            Item::Smoke => items::throw_flask(app, target.unwrap(), EffectKind::Smoke),
+
+            // NOTE: keys are used up by walking into the door they open
+            Item::Key => {
+                app.add_to_log(
+                    "Walk into the vault's locked door to open it with the key.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                UseResult::Cancelled
+            }
+            Item::Idol => {
+                app.add_to_log(
+                    "The idol is no use down here, but a shop would pay well for it.",
+                    Color::default(),
+                    MessageKind::Items,
+                );
+                UseResult::Cancelled
+            }
+        }
+    }
+
+    /// callback to be used when the item is consumed on another item, for
+    /// items with the Item targeting mode
+    pub fn on_use_on_item(&self, app: &mut App, target_id: usize) -> UseResult {
+        match self {
+            Item::Enchant => items::cast_enchant(app, target_id),
+            Item::Identify => items::cast_identify(app, target_id),
+            _ => panic!(
+                "on_use_on_item() called on {:?}, which doesn't target items!",
+                self
+            ),
         }
     }
 }
 
 /// each monster whose next scheduled action is before the current time acts
 pub fn handle_monster_turns(app: &mut App) {
+    update_pack_flow(app);
     loop {
-        let top = app.action_queue.peek();
-        let Some(action) = top else {
+        let due = take_due_actions(app);
+        if due.is_empty() {
             return;
-        };
+        }
+        // batches are planned the same way however many threads there are, so a seed
+        // always plays out the same
+        if due.len() >= PARALLEL_AI_THRESHOLD {
+            handle_monster_batch(app, due);
+            continue;
+        }
 
-        if action.time > app.time {
-            return;
+        // too few turns to be worth planning together, so they're taken one at a time
+        app.action_queue.extend(due);
+        while let Some(action) = app.action_queue.peek()
+            && action.time <= app.time
+        {
+            let action = app.action_queue.pop().unwrap();
+            perform_action(app, action);
         }
+        return;
+    }
+}
 
-        // safe to unwrap here because we checked it was Some earlier
-        let action = app.action_queue.pop().unwrap();
-        perform_action(app, action);
+/// removes every action that is due from the queue, in the order they would be taken
+fn take_due_actions(app: &mut App) -> Vec<Action> {
+    let mut due = Vec::new();
+    while let Some(action) = app.action_queue.peek()
+        && action.time <= app.time
+    {
+        due.push(app.action_queue.pop().unwrap());
+    }
+    due
+}
+
+/// takes every turn that is due at once. melee monsters plan their turns in parallel
+/// from the same state of the floor, then the turns are carried out one by one in
+/// queue order, so the result doesn't depend on how the threads were scheduled.
+/// monsters whose plans went stale, and everything else, act as usual
+fn handle_monster_batch(app: &mut App, due: Vec<Action>) {
+    // bosses change the floor when they change phase, and necromancers when they raise
+    // the dead, so they always plan on their own
+    let planned: Vec<usize> = due
+        .iter()
+        .map(|action| action.id)
+        .filter(|id| {
+            app.objects.get(id).is_some_and(|obj| {
+                matches!(obj.ai, Some(AIType::Melee(_)))
+                    && obj.boss.is_none()
+                    && obj.necromancy.is_none()
+                    && obj.sorcery.is_none()
+            })
+        })
+        .collect();
+    let snapshot: &App = app;
+    let mut plans: HashMap<usize, MeleePlan> = planned
+        .par_iter()
+        .map_init(PathScratch::default, |scratch, &id| {
+            (id, plan_melee_ai(snapshot, scratch, id))
+        })
+        .collect();
+
+    for action in due {
+        match plans.remove(&action.id) {
+            Some(plan) if plan_is_current(app, action.id, &plan) => {
+                let time_taken = apply_melee_plan(app, action.id, plan);
+                reschedule(app, action, time_taken);
+            }
+            _ => perform_action(app, action),
+        }
     }
 }
 
@@ -319,116 +1361,918 @@ pub fn perform_action(app: &mut App, action: Action) {
         AIType::Ranged => {
             todo!()
         }
+        AIType::Spawner => app.handle_spawner_turn(),
+        AIType::Sentry => handle_sentry_ai(app, action.id),
     };
 
+    reschedule(app, action, time_taken);
+}
+
+/// puts an object back into the action queue after it took a turn
+fn reschedule(app: &mut App, action: Action, time_taken: u64) {
     app.action_queue.push(Action {
-        time: action.time + time_taken,
+        time: action.time + action_time(app, action.id, time_taken),
         id: action.id,
     });
 }
 
+/// allies without anything to fight wander at most this far from the player
+const ALLY_FOLLOW_DISTANCE: u16 = 2;
+
+/// where a melee monster decided to go on its turn
+#[derive(Clone, Copy)]
+enum Step {
+    Wait,
+    Move((u16, u16)),
+    Attack((u16, u16)),
+}
+
+/// a melee monster's turn, worked out from the state of the floor without changing it
+pub struct MeleePlan {
+    from: MeleeAIData, // the monster's ai state that the plan was made from
+    ai: MeleeAIData,   // the monster's ai state after looking around
+    step: Step,
+    time: u64, // how long the turn takes
+}
+
 /// makes a monster act according to melee ai
 /// assumes that said monster has an MeleeAI component
 /// returns the amount of time that this monster's turn took
 pub fn handle_melee_ai(app: &mut App, id: usize) -> u64 {
-    let Some(monster) = app.objects.get_contents().get_mut(&id) else {
-        panic!("handle_melee_ai was passed an invalid monster id!")
+    advance_boss_phases(app, id);
+    if let Some(time_taken) = raise_dead(app, id).or_else(|| cast_sorcery(app, id)) {
+        return time_taken;
+    }
+
+    let mut scratch = std::mem::take(&mut app.path_scratch);
+    let plan = plan_melee_ai(app, &mut scratch, id);
+    app.path_scratch = scratch;
+    apply_melee_plan(app, id, plan)
+}
+
+/// decides what a melee monster does on its turn, without changing anything.
+/// pathfinding buffers come from the caller, so that plans can be made on many threads
+pub fn plan_melee_ai(app: &App, scratch: &mut PathScratch, id: usize) -> MeleePlan {
+    let alarm = active_alarm(app);
+    let Some(monster) = app.objects.get(&id) else {
+        panic!("plan_melee_ai was passed an invalid monster id!")
     };
 
-    let ai_data: &mut MeleeAIData = match &mut monster.ai {
+    let from = match &monster.ai {
         None => {
-            panic!("handle_melee_ai called on object with no AI component!")
+            panic!("plan_melee_ai called on object with no AI component!")
         }
         Some(ai_type) => match ai_type {
-            AIType::Melee(data) => data,
+            AIType::Melee(data) => data.clone(),
             _ => {
-                panic!("handle_melee_ai called on object with a non-melee AI type!")
+                panic!("plan_melee_ai called on object with a non-melee AI type!")
             }
         },
     };
+    let mut ai_data = from.clone();
+    let plan = |ai: MeleeAIData, step: Step, time: u64| MeleePlan {
+        from: from.clone(),
+        ai,
+        step,
+        time,
+    };
 
     // check if player is in line of sight
     // NOTE: rework los algorithm later, for now assume it is symmetric
+    // sleeping monsters don't do anything until they're woken up
+    if ai_data.alertness == Alertness::Sleeping {
+        if alarm.is_none() {
+            let move_time = ai_data.move_speed;
+            return plan(ai_data, Step::Wait, move_time);
+        }
+        ai_data.alertness = Alertness::Idle;
+    }
+
+    // targets that died or left the floor can't be chased any further
+    if let Some(target) = ai_data.target
+        && app.gamemap.get_position(target).is_none()
+    {
+        ai_data.alertness = Alertness::Idle;
+        ai_data.target = None;
+        ai_data.last_seen_pos = None;
+    }
+
+    // go after the closest enemy in sight, whichever faction it's from.
+    // a monster that isn't hunting yet might not notice the player, even in plain view
     let monster_pos = app.gamemap.get_position(id).unwrap();
-    if app.gamemap.is_visible(monster_pos.x, monster_pos.y) {
-        ai_data.target = Some(PLAYER);
+    let seen_enemy = nearest_visible_enemy(app, id, monster_pos)
+        .filter(|&enemy| enemy != PLAYER || notices_player(app, id, &ai_data, monster_pos));
+    let sees_target = seen_enemy.is_some();
+    if let Some(enemy) = seen_enemy {
+        ai_data.alertness = Alertness::Alert;
+        ai_data.target = Some(enemy);
         ai_data.last_seen_time = Some(app.time);
+        ai_data.last_seen_pos = app.gamemap.get_position(enemy);
     }
 
-    // forget the target if we haven't seen it recently
-    match ai_data.last_seen_time {
-        Some(seen_time) => {
-            if seen_time + MELEE_FORGET_TIME <= app.time {
-                ai_data.target = None;
-            }
+    // forget the target if we haven't seen it recently.
+    // forgetful monsters give up after a single step without seeing or hearing it
+    let forget_time = match ai_data.smarts.remembers_last_seen {
+        true => MELEE_FORGET_TIME,
+        false => ai_data.move_speed,
+    };
+    if let Some(seen_time) = ai_data.last_seen_time
+        && seen_time + forget_time <= app.time
+    {
+        ai_data.alertness = Alertness::Idle;
+        ai_data.target = None;
+        ai_data.last_seen_pos = None;
+    }
+
+    // searched where the target was last seen, and found nothing there
+    if !sees_target && ai_data.last_seen_pos == Some(monster_pos) {
+        ai_data.alertness = Alertness::Idle;
+        ai_data.target = None;
+        ai_data.last_seen_pos = None;
+    }
+
+    // guards give up on targets that would drag them too far from their post.
+    // anything right next to the edge of the leash can still be attacked
+    if let Some(post) = ai_data.post
+        && let Some(target) = ai_data.target
+    {
+        let chase_pos = match ai_data.last_seen_pos {
+            Some(pos) if !sees_target => pos,
+            _ => app.gamemap.get_position(target).unwrap(),
+        };
+        if chase_pos.distance_to(post.position) > post.leash + 1 {
+            ai_data.alertness = Alertness::Idle;
+            ai_data.target = None;
+            ai_data.last_seen_pos = None;
         }
-        None => {}
     }
 
-    // read these variables here, so we can free the reference to `ai_data`
     let attack_time = ai_data.attack_speed;
     let move_time = ai_data.move_speed;
+    let smarts = ai_data.smarts;
+    let costs = match (smarts.coordinates_with_allies, smarts.avoids_hazards) {
+        _ if ai_data.tunnels => Costs::Tunneling,
+        (true, true) => Costs::Simple,
+        _ => Costs::Careless {
+            ignore_blockers: !smarts.coordinates_with_allies,
+            into_chasms: !smarts.avoids_hazards,
+        },
+    };
 
-    let target = match ai_data.target {
-        Some(id) => id,
-        None => {
-            return move_time;
+    // badly hurt monsters run from their target, and ranged ones keep it at arm's length
+    if let Some(target) = ai_data.target {
+        let target_pos = match ai_data.last_seen_pos {
+            Some(pos) if !sees_target => pos,
+            _ => app.gamemap.get_position(target).unwrap(),
+        };
+        let distance = monster_pos.distance_to(target_pos);
+        let safe_distance = match (ai_data.flee, ai_data.keep_distance) {
+            (Some(flee), _) if hp_percent(monster) <= flee.hp_percent => Some(flee.max_distance),
+            (_, Some(band)) if sees_target && distance < band.min => Some(band.min),
+            _ => None,
+        };
+
+        if let Some(safe_distance) = safe_distance {
+            if distance >= safe_distance {
+                return plan(ai_data, Step::Wait, move_time);
+            }
+            let mut pathfinder = Pathfinder::new(
+                &app.gamemap,
+                scratch,
+                costs,
+                (target_pos.x, target_pos.y),
+                2,
+                3,
+                app.settings.diagonals(),
+            );
+            return match pathfinder.flee_step((monster_pos.x, monster_pos.y)) {
+                Some(step) => plan(ai_data, Step::Move(step), move_time),
+                // cornered, so it may as well fight
+                None if sees_target
+                    && distance == 1
+                    && step_allowed(
+                        app,
+                        monster_pos,
+                        target_pos.x as i16 - monster_pos.x as i16,
+                        target_pos.y as i16 - monster_pos.y as i16,
+                    ) =>
+                {
+                    plan(
+                        ai_data,
+                        Step::Attack((target_pos.x, target_pos.y)),
+                        attack_time,
+                    )
+                }
+                None => plan(ai_data, Step::Wait, move_time),
+            };
+        }
+
+        // ranged monsters hold their ground while the target is in their band
+        if let Some(band) = ai_data.keep_distance
+            && sees_target
+            && distance <= band.max
+        {
+            return plan(ai_data, Step::Wait, move_time);
+        }
+    }
+
+    // pack followers with nothing in sight regroup around their leader, who does the hunting
+    let leader_pos = match sees_target {
+        true => None,
+        false => pack_leader_position(app, id),
+    };
+    if let Some(leader_pos) = leader_pos
+        && monster_pos.distance_to(leader_pos) <= PACK_FOLLOW_DISTANCE
+    {
+        return plan(ai_data, Step::Wait, move_time);
+    }
+
+    // chase the target if it can be seen, otherwise head to where it was last seen.
+    // monsters without a target of their own answer the floor's alarm
+    let (destination, target_pos) = match (leader_pos, ai_data.target) {
+        (Some(leader_pos), _) => (leader_pos, None),
+        (None, Some(target)) => {
+            let target_pos = app.gamemap.get_position(target).unwrap();
+            match ai_data.last_seen_pos {
+                Some(pos) if !sees_target => (pos, Some(target_pos)),
+                _ => (target_pos, Some(target_pos)),
+            }
+        }
+        // allies with nothing to fight stay close to the player
+        (None, None) if faction(app, id) == Faction::Player => {
+            let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+            if monster_pos.distance_to(player_pos) <= ALLY_FOLLOW_DISTANCE {
+                return plan(ai_data, Step::Wait, move_time);
+            }
+            (player_pos, None)
         }
+        (None, None) => match alarm {
+            Some(pos)
+                if pos != monster_pos
+                    && ai_data
+                        .post
+                        .is_none_or(|post| pos.distance_to(post.position) <= post.leash) =>
+            {
+                (pos, None)
+            }
+            // guards with nothing to do return to their post
+            _ => match ai_data.post {
+                Some(post) if post.position != monster_pos => (post.position, None),
+                _ => return plan(ai_data, Step::Wait, move_time),
+            },
+        },
+    };
+
+    // pack hunters each close in on their own side of the prey, instead of queueing up
+    // behind each other in a corridor
+    let destination = match target_pos {
+        Some(target_pos)
+            if sees_target
+                && destination == target_pos
+                && monster_pos.distance_to(target_pos) > 1 =>
+        {
+            surround_slot(app, id, target_pos).unwrap_or(destination)
+        }
+        _ => destination,
     };
 
-    // find path to the player
     let pathfinder = Pathfinder::new(
         &app.gamemap,
-        generate_simple_costs_array(&app.gamemap),
+        scratch,
+        costs,
         (monster_pos.x, monster_pos.y),
         2,
         3,
+        app.settings.diagonals(),
     );
+    let destination_coords = (destination.x, destination.y);
+
+    match pathfinder.first_step(destination_coords) {
+        Some(next) => {
+            if next == destination_coords && Some(destination) == target_pos {
+                plan(ai_data, Step::Attack(next), attack_time)
+            } else {
+                plan(ai_data, Step::Move(next), move_time)
+            }
+        }
+        None => plan(ai_data, Step::Wait, 100),
+    }
+}
 
-    let target_pos = app.gamemap.get_position(target).unwrap();
-    let path = pathfinder.path_to((target_pos.x, target_pos.y));
+/// followers stay within this many tiles of their pack leader when there's nothing to hunt
+const PACK_FOLLOW_DISTANCE: u16 = 2;
 
-    if path.len() == 0 {
-        return 100;
-    } else if path.len() == 1 {
-        melee_action(app, id, *path.first().unwrap());
-        return attack_time;
-    } else {
-        move_action(app, id, *path.first().unwrap());
-        return move_time;
+/// returns where a pack follower's leader is, if it still has one on the floor
+fn pack_leader_position(app: &App, id: usize) -> Option<Position> {
+    let leader = app.objects.get(&id)?.pack?.leader;
+    if leader == id {
+        return None;
+    }
+    app.gamemap.get_position(leader)
+}
+
+/// picks the free tile next to the prey that a pack member should close in on, so that
+/// the pack surrounds it. members nearest the prey on the shared flow map pick first,
+/// each taking the closest tile that nobody else has taken
+fn surround_slot(app: &App, id: usize, prey: Position) -> Option<Position> {
+    let pack = app.objects.get(&id)?.pack?;
+    let flow = &app.pack_flow;
+    let walking_distance = |pos: Position| match flow.root() {
+        Some(root) if root == (prey.x, prey.y) => flow.distance(pos.x, pos.y),
+        _ => Some(pos.distance_to(prey) as u32),
+    };
+
+    let mut slots: Vec<Position> = (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .filter(|&(dx, dy)| {
+            (dx, dy) != (0, 0)
+                && app
+                    .gamemap
+                    .in_bounds(prey.x as i16 + dx, prey.y as i16 + dy)
+                && step_allowed(app, prey, dx, dy)
+        })
+        .map(|(dx, dy)| Position {
+            x: (prey.x as i16 + dx) as u16,
+            y: (prey.y as i16 + dy) as u16,
+        })
+        .filter(|slot| {
+            let tile = app.gamemap.get_ref(slot.x, slot.y);
+            tile.is_walkable() && tile.blocker.is_none() && walking_distance(*slot).is_some()
+        })
+        .collect();
+
+    // members already next to the prey keep their spot and just attack
+    let mut hunters: Vec<(u32, usize, Position)> = app
+        .objects
+        .iter()
+        .filter(|(_, obj)| obj.pack == Some(pack))
+        .filter_map(|(&member, _)| {
+            let pos = app.gamemap.get_position(member)?;
+            Some((walking_distance(pos)?, member, pos))
+        })
+        .filter(|&(_, _, pos)| pos.distance_to(prey) > 1)
+        .collect();
+    hunters.sort_unstable_by_key(|&(distance, member, _)| (distance, member));
+
+    for (_, member, pos) in hunters {
+        let (idx, _) = slots
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, slot)| slot.distance_to(pos))?;
+        let slot = slots.swap_remove(idx);
+        if member == id {
+            return Some(slot);
+        }
+    }
+    None
+}
+
+/// works out the flow map that pack monsters share this turn, if any are on the floor
+fn update_pack_flow(app: &mut App) {
+    let has_packs = app
+        .objects
+        .iter()
+        .any(|(&id, obj)| obj.pack.is_some() && app.gamemap.get_position(id).is_some());
+    let Some(player_pos) = app.gamemap.get_position(PLAYER) else {
+        return;
+    };
+    if has_packs {
+        app.pack_flow.recompute(
+            &app.gamemap,
+            &mut app.path_scratch,
+            (player_pos.x, player_pos.y),
+            app.settings.diagonals(),
+        );
+    }
+}
+
+/// carries out a melee monster's plan, returning how long its turn took
+fn apply_melee_plan(app: &mut App, id: usize, plan: MeleePlan) -> u64 {
+    if let Some(AIType::Melee(data)) = &mut app.objects.get_mut(&id).unwrap().ai {
+        *data = plan.ai;
+    }
+
+    match plan.step {
+        Step::Wait => {}
+        Step::Attack(next) => {
+            melee_action(app, id, next);
+        }
+        Step::Move((x, y)) if app.gamemap.get_ref(x, y).tile_type == TileType::Chasm => {
+            fall_into_chasm(app, id)
+        }
+        Step::Move((x, y)) if app.gamemap.get_ref(x, y).tile_type == TileType::Wall => {
+            tunnel_through(app, id, (x, y));
+            return movement_time(app, id, plan.time) * TUNNEL_TIME_PERCENT / 100;
+        }
+        Step::Move(next) => {
+            move_action(app, id, next);
+            return movement_time(app, id, plan.time);
+        }
+    }
+    plan.time
+}
+
+/// how much longer it takes a monster to dig its way into a wall than to step
+const TUNNEL_TIME_PERCENT: u64 = 300;
+
+/// a tunneling monster digs out the wall in its way, and moves into the gap
+fn tunnel_through(app: &mut App, id: usize, (x, y): (u16, u16)) {
+    if !app.gamemap.dig(x, y) {
+        return;
     }
+    app.emit(GameEvent::MonsterTunneled {
+        id,
+        name: app.objects.get(&id).unwrap().name.clone(),
+        witnessed: app.gamemap.is_visible(x, y),
+    });
+    move_action(app, id, (x, y));
+}
+
+/// a careless monster stumbles off the edge, and is gone from this floor for good
+fn fall_into_chasm(app: &mut App, id: usize) {
+    let pos = app.gamemap.get_position(id).unwrap();
+    app.emit(GameEvent::MonsterFell {
+        id,
+        name: app.objects.get(&id).unwrap().name.clone(),
+        witnessed: app.gamemap.is_visible(pos.x, pos.y),
+    });
+    app.gamemap.remove_blocker(pos.x, pos.y);
+    app.objects.get_mut(&id).unwrap().ai = None;
+}
+
+/// whether a plan made earlier in a batch still makes sense, now that the monsters
+/// before it in the batch have taken their turns
+fn plan_is_current(app: &App, id: usize, plan: &MeleePlan) -> bool {
+    let Some(AIType::Melee(data)) = app.objects.get(&id).and_then(|obj| obj.ai.as_ref()) else {
+        return false;
+    };
+    let step_is_free = match plan.step {
+        Step::Move((x, y)) => app.gamemap.get_ref(x, y).blocker.is_none(),
+        Step::Wait | Step::Attack(_) => true,
+    };
+    *data == plan.from && step_is_free
 }
 
-/// move an object to (target_x, target_y)
-pub fn move_action(app: &mut App, id: usize, (target_x, target_y): (u16, u16)) {
+/// time it takes a necromancer to raise a corpse
+const RAISE_DEAD_TIME: u64 = 100;
+
+/// makes an alert necromancer raise the closest corpse it can see as a zombie.
+/// returns how long it took, or None if the monster didn't raise anything
+fn raise_dead(app: &mut App, id: usize) -> Option<u64> {
+    let obj = app.objects.get(&id).unwrap();
+    let necromancy = obj.necromancy.as_ref()?;
+    let Some(AIType::Melee(ai_data)) = &obj.ai else {
+        return None;
+    };
+    if ai_data.alertness != Alertness::Alert || necromancy.ready_at > app.time {
+        return None;
+    }
+
+    let range = necromancy.range;
+    let cooldown = necromancy.cooldown;
+    let pos = app.gamemap.get_position(id).unwrap();
+    let (corpse_id, corpse_pos) = app
+        .objects
+        .iter()
+        .filter(|(_, obj)| obj.corpse.is_some())
+        .filter_map(|(&corpse_id, _)| Some((corpse_id, app.gamemap.get_position(corpse_id)?)))
+        .filter(|&(_, corpse_pos)| {
+            corpse_pos.distance_to(pos) <= range
+                && app
+                    .gamemap
+                    .get_ref(corpse_pos.x, corpse_pos.y)
+                    .blocker
+                    .is_none()
+                && has_clear_line(app, pos, corpse_pos)
+        })
+        .min_by_key(|&(corpse_id, corpse_pos)| (corpse_pos.distance_to(pos), corpse_id))?;
+
+    if let Some(necromancy) = &mut app.objects.get_mut(&id).unwrap().necromancy {
+        necromancy.ready_at = app.time + cooldown;
+    }
+
+    app.gamemap.remove_item(corpse_pos.x, corpse_pos.y);
+    let corpse = app.objects.get_contents().remove(&corpse_id).unwrap();
+    let zombie_id = app.objects.add(entities::zombie(&corpse.name));
+    app.gamemap
+        .place_blocker(zombie_id, corpse_pos.x, corpse_pos.y);
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    alert_monster(app, zombie_id, player_pos);
+    app.action_queue.push(Action {
+        time: app.time + PLAYER_TURN_TIME,
+        id: zombie_id,
+    });
+
+    app.emit(GameEvent::CorpseRaised {
+        id,
+        name: app.objects.get(&id).unwrap().name.clone(),
+        corpse_name: corpse.name,
+        witnessed: app.gamemap.is_visible(corpse_pos.x, corpse_pos.y),
+    });
+
+    Some(RAISE_DEAD_TIME)
+}
+
+/// returns true if nothing blocks the view between two positions
+pub fn has_clear_line(app: &App, from: Position, to: Position) -> bool {
+    // without diagonal steps, sight doesn't pass between walls touching at their corners
+    if app.settings.orthogonal_only {
+        let points: Vec<(i32, i32)> =
+            los::LineIter::new((from.x as i32, from.y as i32), (to.x as i32, to.y as i32))
+                .collect();
+        let squeezes = points.windows(2).any(|step| {
+            let ((x0, y0), (x1, y1)) = (step[0], step[1]);
+            app.gamemap
+                .sight_cuts_corner(x0 as u16, y0 as u16, (x1 - x0) as i16, (y1 - y0) as i16)
+        });
+        if squeezes {
+            return false;
+        }
+    }
+
+    // only the tiles in between can block the view
+    let mut path = los::LineIter::new((from.x as i32, from.y as i32), (to.x as i32, to.y as i32));
+    path.next();
+    path.next_back();
+    path.all(|(x, y)| !app.gamemap.blocks_sight(x as u16, y as u16))
+}
+
+/// places minions around a position, already hunting the player
+fn summon_minions(app: &mut App, around: Position, minion: Minion, count: usize) {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    for _ in 0..count {
+        let Some((x, y)) = free_tile_near(app, around, 2) else {
+            break;
+        };
+        let object = match minion {
+            Minion::Orc => entities::orc(),
+            Minion::Zombie => entities::zombie("long-dead adventurer"),
+        };
+        let minion_id = app.objects.add(object);
+        app.gamemap.place_blocker(minion_id, x, y);
+        alert_monster(app, minion_id, player_pos);
+        app.action_queue.push(Action {
+            time: app.time + PLAYER_TURN_TIME,
+            id: minion_id,
+        });
+    }
+}
+
+/// time it takes a monster to cast one of its spells
+const SORCERY_TIME: u64 = 100;
+
+/// makes a spellcasting monster that can see the player summon minions, or hurl a bolt
+/// from a distance, whichever is ready first.
+/// returns how long casting took, or None if the monster didn't cast anything
+fn cast_sorcery(app: &mut App, id: usize) -> Option<u64> {
+    let obj = app.objects.get(&id).unwrap();
+    let sorcery = obj.sorcery.clone()?;
+    let Some(AIType::Melee(ai_data)) = &obj.ai else {
+        return None;
+    };
+    let pos = app.gamemap.get_position(id).unwrap();
+    if ai_data.alertness != Alertness::Alert || !lighting::monster_sees_player(app, id, pos) {
+        return None;
+    }
+    let name = obj.name.clone();
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+
+    if sorcery.next_summon <= app.time {
+        app.emit(GameEvent::MinionsSummoned { id, name });
+        summon_minions(app, pos, sorcery.minion, sorcery.summon_count);
+        if let Some(sorcery) = &mut app.objects.get_mut(&id).unwrap().sorcery {
+            sorcery.next_summon = app.time + sorcery.summon_cooldown;
+        }
+        return Some(SORCERY_TIME);
+    }
+
+    // the bolt is for keeping the player at a distance, up close it just attacks
+    let distance = pos.distance_to(player_pos);
+    if sorcery.next_bolt <= app.time
+        && (2..=sorcery.bolt_range).contains(&distance)
+        && has_clear_line(app, pos, player_pos)
+    {
+        let path: Vec<Position> = los::LineIter::new(
+            (pos.x as i32, pos.y as i32),
+            (player_pos.x as i32, player_pos.y as i32),
+        )
+        .skip(1)
+        .map(|(x, y)| Position {
+            x: x as u16,
+            y: y as u16,
+        })
+        .collect();
+        app.animate(crate::animation::projectile(&path, '*', Color::Magenta));
+
+        let player_defense = defense(app, PLAYER);
+        let bolt_damage = damage(app, sorcery.bolt_damage, player_defense) as u16;
+        app.emit(GameEvent::BoltHurled {
+            id,
+            name,
+            damage: bolt_damage,
+        });
+        if let Some(sorcery) = &mut app.objects.get_mut(&id).unwrap().sorcery {
+            sorcery.next_bolt = app.time + sorcery.bolt_cooldown;
+        }
+        let source = DamageSource::monster(app, id);
+        take_damage(app, PLAYER, bolt_damage, source);
+        return Some(SORCERY_TIME);
+    }
+
+    None
+}
+
+/// moves a boss into every phase whose hp threshold it has dropped below,
+/// carrying out the effects of each phase in order
+fn advance_boss_phases(app: &mut App, id: usize) {
+    loop {
+        let obj = app.objects.get(&id).unwrap();
+        let (Some(boss), Some(fighter)) = (&obj.boss, &obj.fighter) else {
+            return;
+        };
+        let Some(phase) = boss.phases.get(boss.phase) else {
+            return;
+        };
+        if fighter.hp as u32 * 100 > fighter.max_hp as u32 * phase.hp_percent as u32 {
+            return;
+        }
+
+        let phase = phase.clone();
+        app.objects
+            .get_mut(&id)
+            .unwrap()
+            .boss
+            .as_mut()
+            .unwrap()
+            .phase += 1;
+        app.emit(GameEvent::BossPhaseStarted {
+            id,
+            message: phase.message,
+        });
+        for effect in phase.effects {
+            apply_phase_effect(app, id, effect);
+        }
+    }
+}
+
+fn apply_phase_effect(app: &mut App, id: usize, effect: PhaseEffect) {
+    let boss_pos = app.gamemap.get_position(id).unwrap();
+    match effect {
+        PhaseEffect::Enrage { power } => {
+            let fighter = app.objects.get_mut(&id).unwrap().fighter.as_mut().unwrap();
+            fighter.power += power;
+        }
+        PhaseEffect::Haste { duration } => {
+            apply_status(app, id, StatusKind::Haste, duration);
+        }
+        PhaseEffect::Summon { minion, count } => summon_minions(app, boss_pos, minion, count),
+        PhaseEffect::SealArena { radius } => {
+            // sealing the player out would leave the boss unreachable
+            let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+            if player_pos.distance_to(boss_pos) >= radius {
+                return;
+            }
+
+            let ring: Vec<Position> = (0..app.gamemap.tiles.len())
+                .map(|idx| idx_to_coords(idx, app.gamemap.width))
+                .map(|(x, y)| Position { x, y })
+                .filter(|pos| pos.distance_to(boss_pos) == radius)
+                .filter(|pos| {
+                    let tile = app.gamemap.get_ref(pos.x, pos.y);
+                    tile.tile_type == TileType::Floor
+                        && tile.blocker.is_none()
+                        && tile.item.is_none()
+                })
+                .collect();
+            if ring.is_empty() {
+                return;
+            }
+
+            for pos in ring.iter() {
+                app.gamemap.set_tile_type(pos.x, pos.y, TileType::Wall);
+            }
+            let boss = app.objects.get_mut(&id).unwrap().boss.as_mut().unwrap();
+            boss.sealed.extend(ring);
+            app.emit(GameEvent::ArenaSealed { id });
+        }
+    }
+}
+
+/// returns a random walkable tile without a blocker near a position, if there is one
+fn free_tile_near(app: &mut App, pos: Position, radius: u16) -> Option<(u16, u16)> {
+    let candidates: Vec<(u16, u16)> = (0..app.gamemap.tiles.len())
+        .map(|idx| idx_to_coords(idx, app.gamemap.width))
+        .filter(|&(x, y)| pos.distance_to(Position { x, y }) <= radius)
+        .filter(|&(x, y)| {
+            let tile = app.gamemap.get_ref(x, y);
+            tile.is_walkable() && tile.blocker.is_none()
+        })
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    Some(candidates[app.rng.random_range(0..candidates.len())])
+}
+
+/// makes a sentry keep watch. sentries never move, and keep the floor's alarm
+/// pointed at the player for as long as they can see them
+pub fn handle_sentry_ai(app: &mut App, id: usize) -> u64 {
+    let pos = app.gamemap.get_position(id).unwrap();
+    if lighting::monster_sees_player(app, id, pos) {
+        if active_alarm(app).is_none() {
+            let name = app.objects.get(&id).unwrap().name.clone();
+            app.emit(GameEvent::AlarmSounded { id, name });
+        }
+        let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+        raise_alarm(app, player_pos);
+    }
+    SENTRY_WATCH_TIME
+}
+
+/// moves an object to (target_x, target_y), returning false if it was blocked and nothing happened
+pub fn move_action(app: &mut App, id: usize, (target_x, target_y): (u16, u16)) -> bool {
     if !app.gamemap.get_ref(target_x, target_y).is_walkable() {
-        return; // destination is blocked by a tile
+        return false; // destination is blocked by a tile
     }
 
-    if let Some(_) = get_blocking_object_id(app, target_x, target_y) {
-        return; // destination is blocked by an object
+    if get_blocking_object_id(app, target_x, target_y).is_some() {
+        return false; // destination is blocked by an object
     }
 
     let pos = app.gamemap.get_position(id).unwrap();
     let obj = app.gamemap.remove_blocker(pos.x, pos.y);
     app.gamemap.place_blocker(obj, target_x, target_y);
 
+    // leave a trail of breadcrumbs wherever the player walks
+    if id == PLAYER {
+        app.gamemap.set_walked(pos.x, pos.y);
+        app.gamemap.set_walked(target_x, target_y);
+    }
+
     assert!(obj == id); // sanity check that we got the right object
+
+    // only the player is clumsy enough to set off traps
+    if id == PLAYER
+        && let Some(item_id) = app.gamemap.get_ref(target_x, target_y).item
+        && let Some(trap) = app.objects.get(&item_id).unwrap().trap
+    {
+        trigger_trap(
+            app,
+            item_id,
+            trap,
+            Position {
+                x: target_x,
+                y: target_y,
+            },
+        );
+    }
+
+    if id == PLAYER {
+        inventory::auto_pickup(
+            app,
+            Position {
+                x: target_x,
+                y: target_y,
+            },
+        );
+    }
+    true
+}
+
+/// damage range of a spike trap
+const SPIKE_TRAP_DAMAGE: Dice = Dice::new(1, 4, 1);
+
+/// sets off a trap that the player stepped on, revealing it for good
+fn trigger_trap(app: &mut App, trap_id: usize, trap: Trap, pos: Position) {
+    app.objects.get_mut(&trap_id).unwrap().hidden = false;
+    app.travel_destination = None;
+    app.auto_descend = false;
+
+    match trap {
+        Trap::Alarm => {
+            app.emit(GameEvent::TrapTriggered { trap, damage: 0 });
+            raise_alarm(app, pos);
+        }
+        Trap::Spike => {
+            let amount = SPIKE_TRAP_DAMAGE.roll(&mut app.rng) as u16;
+            app.emit(GameEvent::TrapTriggered {
+                trap,
+                damage: amount,
+            });
+            let name = app.objects.get(&trap_id).unwrap().name.clone();
+            take_damage(app, PLAYER, amount, DamageSource::Trap(name));
+        }
+        Trap::Teleport => {
+            let candidates: Vec<(u16, u16)> = (0..app.gamemap.tiles.len())
+                .map(|idx| idx_to_coords(idx, app.gamemap.width))
+                .filter(|&(x, y)| {
+                    let tile = app.gamemap.get_ref(x, y);
+                    tile.is_walkable() && tile.blocker.is_none()
+                })
+                .collect();
+            if candidates.is_empty() {
+                app.emit(GameEvent::TrapFizzled { trap });
+                return;
+            }
+            let (x, y) = candidates[app.rng.random_range(0..candidates.len())];
+            app.gamemap.remove_blocker(pos.x, pos.y);
+            app.gamemap.place_blocker(PLAYER, x, y);
+            app.emit(GameEvent::TrapTriggered { trap, damage: 0 });
+            update_fov(app);
+        }
+    }
 }
 
-/// returns the amount of time this action took
-pub fn melee_action(app: &mut App, attacker_id: usize, (target_x, target_y): (u16, u16)) {
+/// how far away the player can spot hidden traps when searching
+const SEARCH_RADIUS: u16 = 3;
+/// chance to spot each hidden trap in range on a single search
+const SEARCH_CHANCE: f64 = 0.5;
+
+/// looks around for hidden traps near the player, revealing some of them
+pub fn search_for_traps(app: &mut App) {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let nearby: Vec<usize> = app
+        .objects
+        .iter()
+        .filter(|(_, obj)| obj.trap.is_some() && obj.hidden)
+        .filter_map(|(&id, _)| Some((id, app.gamemap.get_position(id)?)))
+        .filter(|&(_, pos)| {
+            pos.distance_to(player_pos) <= SEARCH_RADIUS && app.gamemap.is_visible(pos.x, pos.y)
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let mut found = 0;
+    for id in nearby {
+        if app.rng.random_bool(SEARCH_CHANCE) {
+            let trap = app.objects.get_mut(&id).unwrap();
+            trap.hidden = false;
+            let message = format!("You find a {}!", trap.name);
+            app.add_to_log(message, Color::LightRed, MessageKind::System);
+            found += 1;
+        }
+    }
+    if found == 0 {
+        app.add_to_log(
+            "You search the area, but find nothing.",
+            Color::default(),
+            MessageKind::System,
+        );
+    }
+}
+
+/// returns the item on a tile, unless it's a trap the player hasn't found yet
+pub fn known_item(app: &App, x: u16, y: u16) -> Option<usize> {
+    app.gamemap
+        .get_ref(x, y)
+        .item
+        .filter(|id| !app.objects.get(id).unwrap().hidden)
+}
+
+/// chance for an attack between two evenly matched fighters to land
+const BASE_HIT_CHANCE: f64 = 0.85;
+/// how much each point of the attacker's accuracy over the target's evasion adds to the chance to hit
+const HIT_CHANCE_PER_POINT: f64 = 0.05;
+/// no matter the stats, every attack has at least this chance to hit, and to miss
+const MIN_HIT_CHANCE: f64 = 0.05;
+/// chance for an attack that lands to be a critical hit
+const CRIT_CHANCE: f64 = 0.05;
+/// critical hits multiply their damage by this much
+const CRIT_MULTIPLIER: u16 = 2;
+
+/// returns the chance for a melee attack to land, from the attacker's accuracy
+/// and the target's evasion
+pub fn hit_chance(app: &App, attacker_id: usize, target_id: usize) -> f64 {
+    let stat = |id: usize, get: fn(&Fighter) -> i16| {
+        app.objects
+            .get(&id)
+            .and_then(|obj| obj.fighter.as_ref())
+            .map_or(0, get)
+    };
+    let accuracy = stat(attacker_id, |fighter| fighter.accuracy);
+    let evasion = stat(target_id, |fighter| fighter.evasion);
+    let chance = BASE_HIT_CHANCE + (accuracy - evasion) as f64 * HIT_CHANCE_PER_POINT;
+    chance.clamp(MIN_HIT_CHANCE, 1.0 - MIN_HIT_CHANCE)
+}
+
+/// attacks whatever stands on the target tile, returning false if there was nothing there
+pub fn melee_action(app: &mut App, attacker_id: usize, (target_x, target_y): (u16, u16)) -> bool {
     // check that there is an object to attack
     let target_id = match get_blocking_object_id(app, target_x, target_y) {
         Some(x) => x,
         None => {
-            return; // should never hit this case
+            return false; // should never hit this case
         }
     };
 
-    let attacker_power = power(&app, attacker_id);
-    let target_defense = defense(&app, target_id);
-    let damage = (attacker_power - target_defense).max(0) as u16;
+    let hit = app.rng.random_bool(hit_chance(app, attacker_id, target_id));
+    let critical = hit && app.rng.random_bool(CRIT_CHANCE);
+    let attacker_dice = attack_dice(app, attacker_id);
+    let target_defense = defense(app, target_id);
+    let mut damage = damage(app, attacker_dice, target_defense) as u16;
+    if critical {
+        damage *= CRIT_MULTIPLIER;
+    }
 
     let [Some(attacker), Some(target)] = app
         .objects
@@ -438,40 +2282,456 @@ pub fn melee_action(app: &mut App, attacker_id: usize, (target_x, target_y): (u1
         panic!("invalid ids passed to melee_action()!");
     };
 
-    let attack_desc = format!("{} attacks {}", attacker.name, target.name);
-    if damage > 0 {
-        take_damage(app, target_id, damage);
-        app.add_to_log(
-            format!("{} for {} damage.", attack_desc, damage),
-            Color::default(),
-        );
+    // fights between monsters out of view go unreported
+    let witnessed = app.gamemap.is_visible(target_x, target_y);
+    let (attacker_name, target_name) = (attacker.name.clone(), target.name.clone());
+    let attack = |outcome| GameEvent::DamageDealt {
+        attacker: attacker_id,
+        attacker_name: attacker_name.clone(),
+        target: target_id,
+        target_name: target_name.clone(),
+        outcome,
+        witnessed,
+    };
+    if !hit {
+        app.emit(attack(AttackOutcome::Miss));
+    } else if damage > 0 {
+        let event = match critical {
+            true => attack(AttackOutcome::Critical(damage)),
+            false => attack(AttackOutcome::Hit(damage)),
+        };
+        let source = DamageSource::monster(app, attacker_id);
+        take_damage(app, target_id, damage, source);
+        app.emit(event);
+
+        // some attackers apply a status to whatever they hit, e.g. spider venom
+        let on_hit = app
+            .objects
+            .get(&attacker_id)
+            .and_then(|obj| obj.fighter.as_ref())
+            .and_then(|fighter| fighter.on_hit);
+        let target_alive = app
+            .objects
+            .get(&target_id)
+            .and_then(|obj| obj.fighter.as_ref())
+            .is_some_and(|fighter| fighter.hp > 0);
+        if let Some(effect) = on_hit
+            && target_alive
+        {
+            apply_status(app, target_id, effect.kind, effect.duration);
+        }
     } else {
-        app.add_to_log(
-            format!("{} but does no damage.", attack_desc),
-            Color::default(),
-        );
+        app.emit(attack(AttackOutcome::NoDamage));
     }
+
+    provoke(app, target_id, attacker_id);
+
+    // the sounds of fighting carry through the dungeon
+    make_noise(
+        app,
+        Position {
+            x: target_x,
+            y: target_y,
+        },
+        MELEE_NOISE_RADIUS,
+    );
+    true
+}
+
+/// makes a monster that was attacked fight back, even if the attacker is on its side
+fn provoke(app: &mut App, id: usize, attacker: usize) {
+    if app.objects.get(&id).is_none() || is_enemy(app, id, attacker) {
+        return;
+    }
+    set_melee_target(app, id, Some(attacker));
 }
 
-pub fn bump_action(app: &mut App, id: usize, direction: InputDirection) {
+/// points a melee monster at a new target, as if it had just spotted it,
+/// or makes it forget about its target entirely
+pub fn set_melee_target(app: &mut App, id: usize, target: Option<usize>) {
+    let target_pos = target.and_then(|target| app.gamemap.get_position(target));
+    let time = app.time;
+    if let Some(AIType::Melee(data)) = app.objects.get_mut(&id).unwrap().ai.as_mut() {
+        data.target = target;
+        data.last_seen_pos = target_pos;
+        match target {
+            Some(_) => {
+                data.alertness = Alertness::Alert;
+                data.last_seen_time = Some(time);
+            }
+            None => data.alertness = Alertness::Idle,
+        }
+    }
+}
+
+/// moves, attacks, talks or otherwise acts on the tile in the given direction.
+/// returns false if nothing could be done there, so that no time should pass
+pub fn bump_action(app: &mut App, id: usize, direction: InputDirection) -> bool {
     // check that action target is in bounds
     let pos = app.gamemap.get_position(id).unwrap();
     let deltas = direction_to_deltas(direction);
     let (dx, dy) = deltas;
     if !app.gamemap.in_bounds(pos.x as i16 + dx, pos.y as i16 + dy) {
-        return; // destination is not in bounds
+        return false; // destination is not in bounds
     }
     let (target_x, target_y) = ((pos.x as i16 + dx) as u16, (pos.y as i16 + dy) as u16);
+    if !step_allowed(app, pos, dx, dy) {
+        if id == PLAYER {
+            let message = match app.settings.diagonals() {
+                Diagonals::Forbidden => "You can only move in the four orthogonal directions.",
+                _ => "You can't squeeze past the corner.",
+            };
+            app.add_to_log(message, Color::default(), MessageKind::System);
+        }
+        return false;
+    }
 
     // decide which action to take
-    match get_blocking_object_id(app, target_x, target_y) {
-        Some(_) => {
-            melee_action(app, id, (target_x, target_y));
+    let acted = match get_blocking_object_id(app, target_x, target_y) {
+        Some(blocker) if id == PLAYER && app.objects.get(&blocker).unwrap().dialogue.is_some() => {
+            // bumping into a friendly npc starts talking to it
+            dialogue::talk_to(app, blocker);
+            true
         }
-        None => {
-            move_action(app, id, (target_x, target_y));
+        Some(blocker) if id == PLAYER && app.objects.get(&blocker).unwrap().shop.is_some() => {
+            // bumping into a shopkeeper starts trading instead of attacking
+            app.game_screen = GameScreen::Shop {
+                shopkeeper: blocker,
+                cursor: 0,
+                selling: false,
+            };
+            true
+        }
+        Some(blocker) if id == PLAYER && is_charmed(app, blocker) => {
+            swap_places(app, blocker);
+            true
         }
+        Some(_) => melee_action(app, id, (target_x, target_y)),
+        None if id == PLAYER
+            && app.gamemap.get_ref(target_x, target_y).tile_type == TileType::Chasm =>
+        {
+            jump_into_chasm(app);
+            true
+        }
+        None if id == PLAYER
+            && app.gamemap.get_ref(target_x, target_y).tile_type == TileType::LockedDoor =>
+        {
+            unlock_door(
+                app,
+                Position {
+                    x: target_x,
+                    y: target_y,
+                },
+            )
+        }
+        None => move_action(app, id, (target_x, target_y)),
+    };
+
+    // walking into the same wall twice in a row gets a reminder of what's there
+    if id == PLAYER {
+        let target = Position {
+            x: target_x,
+            y: target_y,
+        };
+        let into_wall =
+            !acted && app.gamemap.get_ref(target_x, target_y).tile_type == TileType::Wall;
+        if into_wall && app.bumped_wall == Some(target) {
+            app.add_to_log(
+                "There's a wall there.",
+                Color::default(),
+                MessageKind::System,
+            );
+        }
+        app.bumped_wall = into_wall.then_some(target);
+    }
+    acted
+}
+
+/// opens a locked door if the player carries its key, which gets used up.
+/// returns false if the player doesn't have the key
+fn unlock_door(app: &mut App, door: Position) -> bool {
+    let key = app.gamemap.lock_at(door.x, door.y).map(|lock| lock.key);
+    let Some(inventory_idx) = key.and_then(|key| app.inventory.iter().position(|&id| id == key))
+    else {
+        app.add_to_log(
+            "The door is locked. Its key must be somewhere on this floor.",
+            Color::default(),
+            MessageKind::System,
+        );
+        return false;
     };
+
+    let key_id = app.inventory.remove(inventory_idx);
+    let name = app.objects.get(&key_id).unwrap().name.clone();
+    app.objects.get_contents().remove(&key_id);
+    app.gamemap.unlock(door.x, door.y);
+    app.add_to_log(
+        format!("The {} turns in the lock, and the door swings open.", name),
+        Color::default(),
+        MessageKind::System,
+    );
+    true
+}
+
+/// the player squeezes past an ally, which takes the player's old spot
+fn swap_places(app: &mut App, ally: usize) {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let ally_pos = app.gamemap.get_position(ally).unwrap();
+    app.gamemap.remove_blocker(ally_pos.x, ally_pos.y);
+    move_action(app, PLAYER, (ally_pos.x, ally_pos.y));
+    app.gamemap.place_blocker(ally, player_pos.x, player_pos.y);
+
+    let name = app.objects.get(&ally).unwrap().name.clone();
+    app.add_to_log(
+        format!("You swap places with the {}.", name),
+        Color::default(),
+        MessageKind::System,
+    );
+}
+
+/// whether the movement settings allow a step from a position, which must stay in bounds.
+/// attacks follow the same rules as steps
+pub fn step_allowed(app: &App, from: Position, dx: i16, dy: i16) -> bool {
+    match app.settings.diagonals() {
+        Diagonals::Free => true,
+        Diagonals::NoCornerCutting => !app.gamemap.cuts_corner(from.x, from.y, dx, dy),
+        Diagonals::Forbidden => dx == 0 || dy == 0,
+    }
+}
+
+/// returns the id of the hostile monster next to an object in the given direction, if any
+fn adjacent_hostile(app: &App, id: usize, direction: InputDirection) -> Option<usize> {
+    let pos = app.gamemap.get_position(id).unwrap();
+    let (dx, dy) = direction_to_deltas(direction);
+    if !app.gamemap.in_bounds(pos.x as i16 + dx, pos.y as i16 + dy) {
+        return None;
+    }
+    if !step_allowed(app, pos, dx, dy) {
+        return None;
+    }
+    let (x, y) = ((pos.x as i16 + dx) as u16, (pos.y as i16 + dy) as u16);
+
+    get_blocking_object_id(app, x, y).filter(|&blocker| {
+        app.objects
+            .get(&blocker)
+            .is_some_and(|obj| obj.fighter.is_some() && obj.ai.is_some())
+            && is_enemy(app, id, blocker)
+    })
+}
+
+/// returns the directions of every hostile monster adjacent to the player
+pub fn adjacent_hostile_directions(app: &App) -> Vec<InputDirection> {
+    DIRECTION_ORDERING
+        .into_iter()
+        .filter(|&direction| adjacent_hostile(app, PLAYER, direction).is_some())
+        .collect()
+}
+
+/// moves in the given direction like bump_action, except that it never starts a fight.
+/// returns false if a monster or anything else was in the way and nothing was done
+pub fn safe_move_action(app: &mut App, id: usize, direction: InputDirection) -> bool {
+    if let Some(blocker) = adjacent_hostile(app, id, direction) {
+        let name = app.objects.get(&blocker).unwrap().name.clone();
+        app.add_to_log(
+            format!("The {} is in the way. Use (a) to attack it.", name),
+            Color::default(),
+            MessageKind::Combat,
+        );
+        return false;
+    }
+
+    // don't step off the edge, or into lava, fire or gas by accident
+    let pos = app.gamemap.get_position(id).unwrap();
+    let (dx, dy) = direction_to_deltas(direction);
+    let (target_x, target_y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+    if app.gamemap.in_bounds(target_x, target_y) {
+        let message = match app
+            .gamemap
+            .get_ref(target_x as u16, target_y as u16)
+            .tile_type
+        {
+            TileType::Chasm => Some("You stop at the edge of the chasm."),
+            TileType::Lava => Some("You stop at the edge of the lava."),
+            _ if app.gamemap.is_burning(target_x as u16, target_y as u16) => {
+                Some("You stop at the edge of the fire.")
+            }
+            _ if app.gamemap.is_harmful(target_x as u16, target_y as u16) => {
+                Some("You stop at the edge of the poison gas.")
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            return false;
+        }
+    }
+
+    bump_action(app, id, direction)
+}
+
+/// whether moving in the given direction would take the player from solid ground into
+/// lava they know is there, rather than attacking something standing in it
+pub fn steps_into_lava(app: &App, direction: InputDirection) -> bool {
+    let pos = app.gamemap.get_position(PLAYER).unwrap();
+    let (dx, dy) = direction_to_deltas(direction);
+    let (target_x, target_y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+    if !app.gamemap.in_bounds(target_x, target_y) {
+        return false;
+    }
+    let (target_x, target_y) = (target_x as u16, target_y as u16);
+    let target = app.gamemap.get_ref(target_x, target_y);
+    target.tile_type == TileType::Lava
+        && target.blocker.is_none()
+        && app.gamemap.is_explored(target_x, target_y)
+        && app.gamemap.get_ref(pos.x, pos.y).tile_type != TileType::Lava
+}
+
+/// attacks the hostile monster in the given direction without ever moving.
+/// returns false if there was nothing there to attack
+pub fn attack_action(app: &mut App, id: usize, direction: InputDirection) -> bool {
+    let Some(target_id) = adjacent_hostile(app, id, direction) else {
+        return false;
+    };
+    let target_pos = app.gamemap.get_position(target_id).unwrap();
+    melee_action(app, id, (target_pos.x, target_pos.y));
+    true
+}
+
+/// takes a single step along the path to the player's travel destination.
+/// returns false once the player has arrived or had to stop travelling
+pub fn travel_step(app: &mut App) -> bool {
+    let Some(destination) = app.travel_destination else {
+        return false;
+    };
+
+    let stop_reason = {
+        let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+        if player_pos == destination {
+            Some("You arrive at your destination.")
+        } else if !visible_monsters(app).is_empty() {
+            Some("You stop travelling, there are monsters nearby.")
+        } else {
+            let pathfinder = Pathfinder::new(
+                &app.gamemap,
+                &mut app.path_scratch,
+                Costs::Simple,
+                (player_pos.x, player_pos.y),
+                2,
+                3,
+                app.settings.diagonals(),
+            );
+            match pathfinder.first_step((destination.x, destination.y)) {
+                Some(next) => {
+                    move_action(app, PLAYER, next);
+                    if app.gamemap.get_position(PLAYER).unwrap() == player_pos {
+                        Some("Something is blocking your path.")
+                    } else {
+                        None
+                    }
+                }
+                _ => Some("You can't find a way there."),
+            }
+        }
+    };
+
+    match stop_reason {
+        Some(message) => {
+            app.travel_destination = None;
+            app.add_to_log(message, Color::default(), MessageKind::System);
+            false
+        }
+        None => true,
+    }
+}
+
+/// the player heals a point of health every time this much time passes
+const REGEN_TIME: u64 = 1000;
+/// resting stops on its own after this many turns, even if the player isn't fully healed
+const MAX_REST_TURNS: u64 = 500;
+
+/// heals the player for every multiple of REGEN_TIME the clock went past
+pub fn regenerate(app: &mut App, from: u64, to: u64) {
+    let points = to / REGEN_TIME - from / REGEN_TIME;
+    if points > 0 {
+        heal(app, PLAYER, points as u16);
+    }
+    spells::regenerate_mana(app, from, to);
+}
+
+/// damage dealt to anything standing in lava, every turn it stays there
+const LAVA_DAMAGE: Dice = Dice::new(1, 6, 2);
+
+/// burns every fighter standing in lava once for every turn the clock went past
+pub fn burn_in_lava(app: &mut App, from: u64, to: u64) {
+    let turns = to / 100 - from / 100;
+    if turns == 0 {
+        return;
+    }
+
+    let burning: Vec<(usize, Position)> = app
+        .gamemap
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| tile.tile_type == TileType::Lava)
+        .filter_map(|(idx, tile)| {
+            let (x, y) = idx_to_coords(idx, app.gamemap.width);
+            tile.blocker.map(|id| (id, Position { x, y }))
+        })
+        .collect();
+    for (id, pos) in burning {
+        // something earlier in the list may have died and dropped out of the way
+        if app.gamemap.get_position(id) != Some(pos)
+            || app.objects.get(&id).unwrap().fighter.is_none()
+        {
+            continue;
+        }
+        let amount: u16 = (0..turns)
+            .map(|_| LAVA_DAMAGE.roll(&mut app.rng) as u16)
+            .sum();
+        let target_name = app.objects.get(&id).unwrap().name.clone();
+        app.emit(GameEvent::HazardDamage {
+            target: id,
+            target_name,
+            source: DamageSource::Lava,
+            amount,
+            witnessed: id == PLAYER || app.gamemap.is_visible(pos.x, pos.y),
+        });
+        take_damage(app, id, amount, DamageSource::Lava);
+    }
+}
+
+/// whether the player has any health left to heal
+pub fn player_is_hurt(app: &App) -> bool {
+    let fighter = app.objects.get(&PLAYER).unwrap().fighter.as_ref().unwrap();
+    fighter.hp < fighter.max_hp
+}
+
+/// checks whether the player should keep resting, like travel_step does for travelling.
+/// returns true if the player waits another turn, or logs how long they rested and
+/// returns false once they're healed, a monster shows up, or they've rested long enough
+pub fn rest_step(app: &mut App) -> bool {
+    let Some(start) = app.resting_since else {
+        return false;
+    };
+
+    let turns = (app.time - start) / 100;
+    let message = if !player_is_hurt(app) {
+        format!("You rest for {} turns and feel fully healed.", turns)
+    } else if !visible_monsters(app).is_empty() {
+        format!(
+            "You stop resting after {} turns, there are monsters nearby.",
+            turns
+        )
+    } else if turns >= MAX_REST_TURNS {
+        format!("You rest for {} turns, but can't sleep any longer.", turns)
+    } else {
+        return true;
+    };
+
+    app.resting_since = None;
+    app.add_to_log(message, Color::default(), MessageKind::System);
+    false
 }
 
 pub fn get_blocking_object_id(app: &App, x: u16, y: u16) -> Option<usize> {
@@ -479,70 +2739,263 @@ pub fn get_blocking_object_id(app: &App, x: u16, y: u16) -> Option<usize> {
 }
 
 // recompute visible area based on the player's fov
-pub fn update_fov(app: &mut App, radius: u16) {
+// the expensive ray sweep is skipped if neither the player nor the map's opacity changed
+pub fn update_fov(app: &mut App) {
     // TODO: use a different symmetric algo to calculate line of sight
 
+    let radius = app.settings.view_radius;
     let position = app.gamemap.get_position(PLAYER).unwrap();
     let (player_x, player_y) = (position.x, position.y);
 
-    app.gamemap.visible.fill(false);
-
     // calculate bounds for visibility
     let (xlow, xhigh) = (
-        (player_x.saturating_sub(radius)).max(0),
+        player_x.saturating_sub(radius),
         (player_x + radius).min(app.gamemap.width - 1),
     );
     let (ylow, yhigh) = (
-        (player_y.saturating_sub(radius)).max(0),
+        player_y.saturating_sub(radius),
         (player_y + radius).min(app.gamemap.height - 1),
     );
 
-    // loop through each x, y to check visibility
-    for target_x in xlow..=xhigh {
-        for target_y in ylow..=yhigh {
-            // calculate los path from player to target square
-            let path: Vec<(u16, u16)> = los::bresenham(
-                (player_x.into(), player_y.into()),
-                (target_x.into(), target_y.into()),
-            )
-            .iter()
-            .map(|&(x, y)| (x as u16, y as u16))
-            .collect();
+    // the player sees lit tiles in their line of sight, and dark ones close enough
+    lighting::update_lighting(app);
+    let sight = lighting::player_sight_radius(app);
+
+    let fov_key = app
+        .gamemap
+        .fov_key(position, radius, sight, app.settings.diagonals());
+    if !app.gamemap.is_fov_current(fov_key) {
+        if app.fov_rays.radius() != radius {
+            app.fov_rays = los::RayTable::new(radius);
+        }
+
+        app.gamemap.visible.fill(false);
+        app.gamemap.clear_in_sight();
+
+        // walk along each precomputed ray to check for visibility.
+        // without diagonal steps, a gap between two walls touching at their corners
+        // can't be walked through, so it can't be seen through either
+        let orthogonal_only = app.settings.orthogonal_only;
+        for ray in app.fov_rays.iter() {
+            let mut prev = (player_x as i16, player_y as i16);
+            for &(dx, dy) in ray {
+                let (x, y) = (player_x as i16 + dx, player_y as i16 + dy);
+                if !app.gamemap.in_bounds(x, y) {
+                    break;
+                }
+                if orthogonal_only
+                    && app.gamemap.sight_cuts_corner(
+                        prev.0 as u16,
+                        prev.1 as u16,
+                        x - prev.0,
+                        y - prev.1,
+                    )
+                {
+                    break;
+                }
+                prev = (x, y);
 
-            // walk along the path to check for visibility
-            for (x, y) in path {
-                if !app.gamemap.get_ref(x, y).is_transparent() {
+                let (x, y) = (x as u16, y as u16);
+                app.gamemap.set_in_sight(x, y, true);
+                if app.gamemap.is_lit(x, y) || position.distance_to(Position { x, y }) <= sight {
                     app.gamemap.set_visible(x, y, true);
+                }
+                if app.gamemap.blocks_sight(x, y) {
                     break;
                 }
-                app.gamemap.set_visible(x, y, true);
             }
         }
-    }
 
-    // explored |= visible
-    for (e, &v) in app
-        .gamemap
-        .explored
-        .iter_mut()
-        .zip(app.gamemap.visible.iter())
-    {
-        *e |= v;
+        // explored |= visible
+        for (e, &v) in app
+            .gamemap
+            .explored
+            .iter_mut()
+            .zip(app.gamemap.visible.iter())
+        {
+            *e |= v;
+        }
+
+        app.gamemap.set_fov_key(fov_key);
     }
 
     // for each visible tile, update the renderable it was last seen as
+    // NOTE: this runs even when the fov is cached, since objects may have moved
+    let mut spotted = Vec::new();
     for x in xlow..=xhigh {
         for y in ylow..=yhigh {
             if app.gamemap.is_visible(x, y) {
                 let tile = app.gamemap.get_ref(x, y);
-                app.gamemap.set_last_seen(
-                    x,
-                    y,
-                    crate::app::render::tile_topmost_renderable(app, tile),
-                );
+                let last_seen = crate::app::render::tile_topmost_renderable(app, x, y);
+                let remembered = crate::app::render::remembered_renderable(app, tile);
+                let blocker = tile.blocker;
+                app.gamemap.set_last_seen(x, y, last_seen);
+                app.gamemap.set_remembered(x, y, remembered);
+                let candidates = blocker.into_iter().chain(known_item(app, x, y));
+                spotted.extend(candidates.filter(|&id| is_newly_spotted(app, id)));
             }
         }
     }
+
+    for id in spotted {
+        announce_spotted(app, id);
+    }
+}
+
+/// how many ticks an object's glyph flashes for when it is first seen
+const SPOTTED_FLASH_TICKS: u64 = 10;
+
+/// whether an object in view is a monster or item the player hasn't seen before
+fn is_newly_spotted(app: &App, id: usize) -> bool {
+    let obj = app.objects.get(&id).unwrap();
+    let monster = obj.fighter.is_some() && obj.ai.is_some();
+    let item = obj.item.is_some() || obj.gold.is_some();
+    id != PLAYER && !obj.spotted && (monster || item)
+}
+
+/// marks an object as seen, logging what it is and flashing its glyph
+fn announce_spotted(app: &mut App, id: usize) {
+    let obj = app.objects.get_mut(&id).unwrap();
+    obj.spotted = true;
+    let monster = obj.ai.is_some();
+    let name = match monster {
+        true => obj.name.clone(),
+        false => identify::stack_name(app, id),
+    };
+    app.flashes.insert(id, app.ticks + SPOTTED_FLASH_TICKS);
+    app.emit(GameEvent::Spotted { id, name, monster });
+}
+
+/// returns where the stairs are, if the player has explored the tile they are on
+pub fn known_stairs_position(app: &App) -> Option<Position> {
+    app.objects
+        .iter()
+        .filter(|(_, obj)| obj.name == "Stairs")
+        .filter_map(|(&id, _)| app.gamemap.get_position(id))
+        .find(|pos| app.gamemap.is_explored(pos.x, pos.y))
+}
+
+/// replaces the current floor with a freshly generated one, a level deeper
+/// tallies up what happened on the current floor
+pub fn level_summary(app: &App) -> LevelSummary {
+    let on_floor = |id: usize| app.objects.get(&id).unwrap();
+    let monsters_left = app
+        .gamemap
+        .tiles
+        .iter()
+        .filter_map(|tile| tile.blocker)
+        .filter(|&id| id != PLAYER && on_floor(id).fighter.is_some() && on_floor(id).ai.is_some())
+        .count();
+    let items_left = app
+        .gamemap
+        .tiles
+        .iter()
+        .filter_map(|tile| tile.item)
+        .filter(|&id| on_floor(id).item.is_some() || on_floor(id).gold.is_some())
+        .filter(|&id| on_floor(id).corpse.is_none())
+        .count();
+    let walkable = |idx: &usize| app.gamemap.tiles[*idx].tile_type != TileType::Wall;
+
+    let stats = &app.gamemap.stats;
+    LevelSummary {
+        level: app.gamemap.level,
+        kills: app.kills - stats.start_kills,
+        monsters_left: monsters_left as u32,
+        items_found: stats.items_found,
+        items_left: items_left as u32,
+        time_spent: app.time - stats.start_time,
+        explored_tiles: (0..app.gamemap.tiles.len())
+            .filter(walkable)
+            .filter(|&idx| app.gamemap.explored[idx])
+            .count(),
+        total_tiles: (0..app.gamemap.tiles.len()).filter(walkable).count(),
+    }
+}
+
+fn descend(app: &mut App) {
+    // show the player how the floor they're leaving went
+    app.game_screen = GameScreen::LevelSummary {
+        summary: level_summary(app),
+    };
+
+    // clear the action queue, so enemies from the previous floor stop taking actions
+    app.action_queue = BinaryHeap::new();
+
+    achievements::leave_floor(app);
+    quests::leave_floor(app);
+
+    // NOTE: code to generate next stage
+    let cur_level = app.gamemap.level;
+    app.generate_dungeon(DungeonConfig::default().set_level(cur_level + 1));
+    app.autosave();
+}
+
+/// returns the position of the stairs out of the dungeon, if this floor has them
+/// and the player has found them
+pub fn known_up_stairs_position(app: &App) -> Option<Position> {
+    app.objects
+        .iter()
+        .filter(|(_, obj)| obj.name == "Up Stairs")
+        .filter_map(|(&id, _)| app.gamemap.get_position(id))
+        .find(|pos| app.gamemap.is_explored(pos.x, pos.y))
+}
+
+/// leaves the dungeon, winning the game if the player is carrying the amulet.
+/// returns true if successful, false if not
+pub fn go_up_stairs(app: &mut App) -> bool {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    if known_up_stairs_position(app) != Some(player_pos) {
+        app.add_to_log(
+            "Can't go up, not standing on stairs.",
+            Color::default(),
+            MessageKind::System,
+        );
+        return false;
+    }
+
+    let has_amulet = app
+        .inventory
+        .iter()
+        .any(|id| app.objects.get(id).unwrap().item == Some(Item::Amulet));
+    if !has_amulet {
+        app.add_to_log(
+            "You can't leave without the Amulet of Ages.",
+            Color::default(),
+            MessageKind::System,
+        );
+        return false;
+    }
+
+    app.add_to_log(
+        "You climb out of the dungeon with the Amulet of Ages!",
+        Style::new().bold().yellow(),
+        MessageKind::System,
+    );
+    app.travel_destination = None;
+    app.auto_descend = false;
+    app.game_screen = GameScreen::Victory;
+    achievements::escape(app);
+    if !app.keeps_records() {
+        return true;
+    }
+    app.forfeit_saves();
+    app.record_ghost_step();
+    let _ = app.write_ghost();
+    scores::record(app);
+    let _ = app.profile.save();
+    match app.write_morgue() {
+        Ok(file_name) => app.add_to_log(
+            format!("A record of your run was written to {}.", file_name),
+            Color::default(),
+            MessageKind::System,
+        ),
+        Err(_) => app.add_to_log(
+            "Couldn't write a morgue file.",
+            Color::default(),
+            MessageKind::System,
+        ),
+    }
+    true
 }
 
 /// attempts to go down stairs at the current location.
@@ -565,33 +3018,22 @@ pub fn go_down_stairs(app: &mut App) -> bool {
     //     .count()
     //     > 0;
 
-    let on_stairs = {
-        let item = app.gamemap.get_ref(player_pos.x, player_pos.y).item;
-        if let Some(id) = item {
-            let item = app.objects.get(&id).unwrap();
-            item.name == "Stairs"
-        } else {
-            false
-        }
-    };
-
-    if !on_stairs {
-        app.add_to_log("Can't go down, not standing on stairs.", Color::default());
+    if known_stairs_position(app) != Some(player_pos) {
+        app.add_to_log(
+            "Can't go down, not standing on stairs.",
+            Color::default(),
+            MessageKind::System,
+        );
         return false;
     }
 
-    // clear the action queue, so enemies from the previous floor stop taking actions
-    app.action_queue = BinaryHeap::new();
-
-    // NOTE: code to generate next stage
-    let cur_level = app.gamemap.level;
-    app.generate_dungeon(DungeonConfig::default().set_level(cur_level + 1));
+    descend(app);
     app.add_to_log(
         "As you dive deeper into the dungeon, you find a moment to rest and recover.",
         Color::Magenta,
+        MessageKind::System,
     );
-    app.add_to_log("You feel stronger.", Color::Magenta);
-    update_fov(app, VIEW_RADIUS);
+    update_fov(app);
 
     let player_fighter = app
         .objects
@@ -602,6 +3044,117 @@ pub fn go_down_stairs(app: &mut App) -> bool {
         .unwrap();
     player_fighter.max_hp += 5;
     player_fighter.hp = player_fighter.max_hp;
+    let max_hp = player_fighter.max_hp;
+    app.emit(GameEvent::LevelUp {
+        level: app.gamemap.level,
+        max_hp,
+    });
+
+    announce_level_feeling(app);
 
     true
 }
+
+/// damage taken from falling into a chasm without a rope
+const CHASM_FALL_DAMAGE: u16 = 5;
+
+/// drops the player down a chasm to the floor below, landing as close as possible
+/// to the matching spot under where they fell from. a rope lets them climb down safely, otherwise they
+/// take fall damage. unlike the stairs, there's no time to rest on the way down
+pub fn jump_into_chasm(app: &mut App) {
+    let fall_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let (old_width, old_height) = (app.gamemap.width as u32, app.gamemap.height as u32);
+    let rope_idx = app
+        .inventory
+        .iter()
+        .position(|id| matches!(app.objects.get(id).unwrap().item, Some(Item::Rope)));
+
+    descend(app);
+
+    // deeper floors can be bigger, so scale the spot above onto this floor
+    let below = Position {
+        x: (fall_pos.x as u32 * app.gamemap.width as u32 / old_width) as u16,
+        y: (fall_pos.y as u32 * app.gamemap.height as u32 / old_height) as u16,
+    };
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    let landing = (0..app.gamemap.tiles.len())
+        .map(|idx| idx_to_coords(idx, app.gamemap.width))
+        .filter(|&(x, y)| {
+            let tile = app.gamemap.get_ref(x, y);
+            tile.is_walkable() && tile.blocker.is_none_or(|id| id == PLAYER)
+        })
+        .min_by_key(|&(x, y)| below.distance_to(Position { x, y }))
+        .unwrap();
+    app.gamemap.remove_blocker(player_pos.x, player_pos.y);
+    app.gamemap.place_blocker(PLAYER, landing.0, landing.1);
+    update_fov(app);
+
+    match rope_idx {
+        Some(idx) => {
+            let rope_id = inventory::take_one_from_inventory(app, idx);
+            app.objects.get_contents().remove(&rope_id);
+            app.emit(GameEvent::EnteredChasm { fall_damage: None });
+        }
+        None => {
+            app.emit(GameEvent::EnteredChasm {
+                fall_damage: Some(CHASM_FALL_DAMAGE),
+            });
+            take_damage(app, PLAYER, CHASM_FALL_DAMAGE, DamageSource::Fall);
+        }
+    }
+
+    announce_level_feeling(app);
+}
+
+/// returns a rough estimate of how threatening a fighter is,
+/// based on how hard it hits and how long it takes to bring down
+fn threat(app: &App, id: usize) -> f64 {
+    let Some(fighter) = app.objects.get(&id).unwrap().fighter.as_ref() else {
+        return 0.0;
+    };
+    let power = attack_dice(app, id).average().max(1.0);
+    let durability = fighter.max_hp as f64 + 2.0 * defense(app, id).max(0) as f64;
+    power * durability
+}
+
+/// compares the total threat of the monsters on the current floor against the player's
+fn danger_score(app: &App) -> f64 {
+    let monster_threat: f64 = app
+        .gamemap
+        .tiles
+        .iter()
+        .filter_map(|tile| tile.blocker)
+        .filter(|&id| id != PLAYER && app.objects.get(&id).unwrap().ai.is_some())
+        .map(|id| threat(app, id))
+        .sum();
+
+    monster_threat / threat(app, PLAYER).max(1.0)
+}
+
+/// logs a message hinting at how dangerous the monsters on this floor actually are
+pub fn announce_level_feeling(app: &mut App) {
+    if app.gamemap.level >= FINAL_LEVEL {
+        app.add_to_log(
+            "The air is cold and still. Something ancient waits here with the Amulet of Ages.",
+            Color::LightMagenta,
+            MessageKind::System,
+        );
+        return;
+    }
+
+    let danger = danger_score(app);
+    let (message, color) = if danger < 1.5 {
+        ("This floor seems quiet.", Color::Gray)
+    } else if danger < 3.0 {
+        ("You feel slightly uneasy.", Color::default())
+    } else if danger < 5.0 {
+        ("You sense danger lurking nearby.", Color::Yellow)
+    } else if danger < 8.0 {
+        ("A chill runs down your spine.", Color::LightRed)
+    } else {
+        ("You feel a terrible presence on this floor!", Color::Red)
+    };
+
+    app.add_to_log(message, color, MessageKind::System);
+    quests::announce(app);
+}
@@ -1,119 +1,497 @@
 // this file contains a list of spawnable entities
 
 use crate::components::{
-    AIType, DeathCallback, Equipment, Fighter, Item, MeleeAIData, Object, RenderLayer, Renderable,
-    Slot,
+    AIType, BossPhase, DeathCallback, Equipment, Faction, Fighter, Item, Loadout, MeleeAIData,
+    Minion, Object, PhaseEffect, PlayerClass, RenderLayer, Renderable, Shop, Slot, Smarts, Sorcery,
+    Spell, StatusKind, Trap,
 };
-use ratatui::style::Color;
+use crate::engine::Dice;
+use crate::quests::Goal;
+use crate::theme::ThemeColor;
+use crate::{dialogue, items};
 
 pub fn stairs() -> Object {
     let name = "Stairs".to_string();
     let tooltip = "stairs leading to the next floor".to_string();
 
-    let renderable = Renderable {
-        glyph: '>',
-        fg: Color::Gray,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('>', ThemeColor::Stairs);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+}
+
+/// stairs back up to the surface, only found on the last floor of the dungeon
+pub fn up_stairs() -> Object {
+    let name = "Up Stairs".to_string();
+    let tooltip = "stairs leading out of the dungeon, for those carrying the amulet".to_string();
+
+    let renderable = Renderable::themed('<', ThemeColor::Stairs);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
 }
 
+/// what the player came down here for. escaping with it wins the game
+pub fn amulet() -> Object {
+    let name = "Amulet of Ages".to_string();
+    let tooltip = "the prize at the bottom of the dungeon. carry it back up the stairs".to_string();
+
+    let renderable = Renderable::themed('"', ThemeColor::RareItem);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Amulet)
+        .set_weight(1)
+}
+
+/// alerts the whole floor when the player steps on it
+pub fn alarm_trap() -> Object {
+    let name = "alarm trap".to_string();
+    let tooltip =
+        "a tripwire hooked up to a bell. stepping on it alerts the whole floor".to_string();
+
+    let renderable = Renderable::themed('^', ThemeColor::Trap);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_trap(Trap::Alarm)
+        .set_hidden()
+}
+
+pub fn spike_trap() -> Object {
+    let name = "spike trap".to_string();
+    let tooltip = "a pressure plate that drives spikes up through the floor".to_string();
+
+    let renderable = Renderable::themed('^', ThemeColor::Trap);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_trap(Trap::Spike)
+        .set_hidden()
+}
+
+pub fn teleport_trap() -> Object {
+    let name = "teleport trap".to_string();
+    let tooltip =
+        "a faintly glowing rune. stepping on it sends you elsewhere on the floor".to_string();
+
+    let renderable = Renderable::themed('^', ThemeColor::Trap);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_trap(Trap::Teleport)
+        .set_hidden()
+}
+
+const MAGE_STARTING_MANA: u16 = 10;
+
+/// creates a player with the starting stats of the given class
+pub fn player_of_class(class: PlayerClass) -> Object {
+    let (max_hp, defense, power, damage, gold) = match class {
+        PlayerClass::Fighter => (30, 1, 1, Dice::new(1, 4, 0), 0),
+        PlayerClass::Rogue => (20, 0, 0, Dice::new(1, 4, 0), 30),
+        PlayerClass::Mage => (15, 0, 0, Dice::new(1, 2, 0), 10),
+    };
+
+    // rogues are quick on their feet, find the gaps in armor, and are hard to spot
+    let (accuracy, evasion, stealth) = match class {
+        PlayerClass::Rogue => (2, 2, 3),
+        _ => (0, 0, 0),
+    };
+
+    let mut player = player().set_class(class).set_gold(gold);
+    player.fighter = Some(
+        Fighter::new(max_hp, defense, power, DeathCallback::Player)
+            .set_damage(damage)
+            .set_accuracy(accuracy)
+            .set_evasion(evasion)
+            .set_stealth(stealth),
+    );
+    // mages come knowing a spell, with the mana to cast it a few times
+    if class == PlayerClass::Mage {
+        player = player.set_caster(MAGE_STARTING_MANA, vec![Spell::Hexbolt]);
+    }
+    player
+}
+
+/// returns the items that a class starts the game with in its inventory
+pub fn starting_inventory(class: PlayerClass) -> Vec<Object> {
+    match class {
+        PlayerClass::Fighter => vec![leather_armor(), items::potion_cure_wounds()],
+        PlayerClass::Rogue => vec![weapon_dagger(), items::potion_haste()],
+        PlayerClass::Mage => vec![
+            items::scroll_hexbolt(),
+            items::scroll_lightning(),
+            items::scroll_time_warp(),
+        ],
+    }
+}
+
 pub fn player() -> Object {
     let name = "Player".to_string();
     let tooltip = "this is you :D".to_string();
 
-    let renderable = Renderable {
-        glyph: '@',
-        fg: Color::default(),
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('@', ThemeColor::Player);
     let render_layer = RenderLayer::Blocking;
 
-    Object::new(name, tooltip, renderable, render_layer).set_fighter({
-        let max_hp = 20;
-        let defense = 0;
-        let power = 2;
-        Fighter::new(max_hp, defense, power, DeathCallback::Player)
-    })
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 20;
+            let defense = 0;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Player)
+                .set_damage(Dice::new(1, 3, 0))
+        })
+        .set_gold(0)
+        .set_faction(Faction::Player)
+}
+
+/// an invisible object that is never placed on the map. it takes turns through the
+/// action queue to repopulate the floor with wandering monsters
+pub fn monster_spawner() -> Object {
+    let name = "Spawner".to_string();
+    let tooltip = "spawns wandering monsters".to_string();
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, Renderable::default(), render_layer).set_ai(AIType::Spawner)
 }
 
 pub fn orc() -> Object {
     let name = "Orc".to_string();
     let tooltip = "orcs are evil creatures :(".to_string();
 
-    let renderable = Renderable {
-        glyph: 'o',
-        fg: Color::Red,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('o', ThemeColor::Enemy);
     let render_layer = RenderLayer::Blocking;
-    let ai_component = AIType::Melee(MeleeAIData::new());
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_smarts(Smarts::default().set_coordinates_with_allies(false))
+            .set_flee(30, 8),
+    );
 
     Object::new(name, tooltip, renderable, render_layer)
         .set_fighter({
             let max_hp = 6;
             let defense = 0;
-            let power = 2;
+            let power = 0;
             Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 3, 0))
         })
         .set_ai(ai_component)
+        .set_gold(6)
+        .set_loadout(Loadout::Orc)
 }
 
 pub fn rat() -> Object {
     let name = "Rat".to_string();
     let tooltip = "speedy evil creature".to_string();
 
-    let renderable = Renderable {
-        glyph: 'r',
-        fg: Color::Yellow,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('r', ThemeColor::Vermin);
     let render_layer = RenderLayer::Blocking;
-    let ai_component = AIType::Melee(MeleeAIData::new().set_move_speed(75).set_attack_speed(75));
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_move_speed(75)
+            .set_attack_speed(75)
+            .set_smarts(Smarts::MINDLESS)
+            .set_flee(40, 6)
+            .set_darkvision(),
+    );
 
     Object::new(name, tooltip, renderable, render_layer)
         .set_fighter({
             let max_hp = 5;
             let defense = 0;
-            let power = 2;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 3, 0))
+                .set_evasion(2)
+        })
+        .set_ai(ai_component)
+        .set_gold(1)
+}
+
+/// weak on its own, but hunts in packs that surround their prey
+pub fn jackal() -> Object {
+    let name = "Jackal".to_string();
+    let tooltip = "a scrawny scavenger that never hunts alone".to_string();
+
+    let renderable = Renderable::themed('j', ThemeColor::Vermin);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_move_speed(80)
+            .set_attack_speed(100)
+            .set_flee(30, 8)
+            .set_perception(8),
+    );
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 4;
+            let defense = 0;
+            let power = 0;
             Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 2, 0))
+                .set_evasion(1)
         })
         .set_ai(ai_component)
+        .set_gold(1)
 }
 
 pub fn troll() -> Object {
     let name = "Troll".to_string();
     let tooltip = "slow and heavy creature".to_string();
 
-    let renderable = Renderable {
-        glyph: 'T',
-        fg: Color::Green,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('T', ThemeColor::Brute);
     let render_layer = RenderLayer::Blocking;
-    let ai_component = AIType::Melee(MeleeAIData::new().set_move_speed(150).set_attack_speed(150));
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_move_speed(150)
+            .set_attack_speed(150)
+            .set_smarts(
+                Smarts::default()
+                    .set_remembers_last_seen(false)
+                    .set_avoids_hazards(false),
+            )
+            .set_perception(4),
+    );
 
     Object::new(name, tooltip, renderable, render_layer)
         .set_fighter({
             let max_hp = 10;
             let defense = 1;
-            let power = 5;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(2, 4, 0))
+                .set_accuracy(-2)
+        })
+        .set_ai(ai_component)
+        .set_gold(15)
+        .set_loadout(Loadout::Troll)
+}
+
+pub fn spider() -> Object {
+    let name = "Spider".to_string();
+    let tooltip = "its venomous bite slows its prey".to_string();
+
+    let renderable = Renderable::themed('s', ThemeColor::Venomous);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(MeleeAIData::new().set_darkvision());
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 4;
+            let defense = 0;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 2, 0))
+                .set_evasion(1)
+                .set_on_hit(StatusKind::Slow, 300)
+        })
+        .set_ai(ai_component)
+        .set_gold(2)
+}
+
+pub fn sentry() -> Object {
+    let name = "Sentry".to_string();
+    let tooltip = "a watchful guard that raises the alarm instead of fighting".to_string();
+
+    let renderable = Renderable::themed('S', ThemeColor::Sentry);
+    let render_layer = RenderLayer::Blocking;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 4;
+            let defense = 1;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+        })
+        .set_ai(AIType::Sentry)
+        .set_gold(3)
+}
+
+/// raises the corpses around it as zombies
+pub fn necromancer() -> Object {
+    let name = "Necromancer".to_string();
+    let tooltip = "a robed figure that calls the dead back up to fight".to_string();
+
+    let renderable = Renderable::themed('n', ThemeColor::Enemy);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(MeleeAIData::new().set_keep_distance(3, 5).set_darkvision());
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 10;
+            let defense = 0;
+            let power = 0;
             Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 3, 0))
         })
         .set_ai(ai_component)
+        .set_gold(12)
+        .set_necromancy(6, 500)
+}
+
+/// bores its way through the walls to get at its prey, instead of going around
+pub fn rock_worm() -> Object {
+    let name = "Rock worm".to_string();
+    let tooltip = "a blind, armored worm that tunnels straight through solid rock".to_string();
+
+    let renderable = Renderable::themed('w', ThemeColor::Brute);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_move_speed(150)
+            .set_darkvision()
+            .set_tunnels(),
+    );
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 12;
+            let defense = 2;
+            let power = 1;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 6, 0))
+        })
+        .set_ai(ai_component)
+        .set_gold(5)
+}
+
+/// a corpse raised by a necromancer. slow, and leaves nothing behind when it dies again
+pub fn zombie(corpse_name: &str) -> Object {
+    let name = "Zombie".to_string();
+    let tooltip = format!("the {}, risen from the dead", corpse_name);
+
+    let renderable = Renderable::themed('z', ThemeColor::Brute);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(
+        MeleeAIData::new()
+            .set_move_speed(150)
+            .set_smarts(Smarts::MINDLESS)
+            .set_darkvision(),
+    );
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 8;
+            let defense = 0;
+            let power = 0;
+            Fighter::new(max_hp, defense, power, DeathCallback::Undead)
+                .set_damage(Dice::new(1, 6, 0))
+        })
+        .set_ai(ai_component)
+}
+
+/// what's left of a monster after it dies. can be eaten, or raised by a necromancer
+pub fn corpse(monster: &Object, rots_at: u64) -> Object {
+    let name = format!("{} corpse", monster.name.to_lowercase());
+    let tooltip = "a dead body. it won't stay fresh for long".to_string();
+
+    let renderable = Renderable {
+        glyph: '%',
+        ..monster.renderable.clone()
+    };
+    let render_layer = RenderLayer::Corpse;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Corpse)
+        .set_weight(10)
+        .set_corpse(rots_at)
+}
+
+/// a boss that calls for help and seals the room once hurt, then goes berserk
+pub fn orc_warlord() -> Object {
+    let name = "Orc Warlord".to_string();
+    let tooltip = "a hulking orc, scarred from countless battles".to_string();
+
+    let renderable = Renderable::themed('O', ThemeColor::Boss);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(MeleeAIData::new());
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 30;
+            let defense = 2;
+            let power = 1;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(1, 6, 0))
+                .set_accuracy(1)
+        })
+        .set_ai(ai_component)
+        .set_gold(40)
+        .set_loadout(Loadout::Orc)
+        .set_boss(vec![
+            BossPhase {
+                hp_percent: 60,
+                message: "The Orc Warlord bellows for reinforcements!".to_string(),
+                effects: vec![
+                    PhaseEffect::SealArena { radius: 6 },
+                    PhaseEffect::Summon {
+                        minion: Minion::Orc,
+                        count: 2,
+                    },
+                ],
+            },
+            BossPhase {
+                hp_percent: 30,
+                message: "The Orc Warlord flies into a rage!".to_string(),
+                effects: vec![
+                    PhaseEffect::Enrage { power: 2 },
+                    PhaseEffect::Haste { duration: 500 },
+                ],
+            },
+        ])
+}
+
+/// the final boss, guarding the amulet. raises the dead around it and hurls bolts of
+/// darkness at the player from afar
+pub fn lich() -> Object {
+    let name = "Lich".to_string();
+    let tooltip = "an ancient sorcerer that refused to die, clutching the amulet".to_string();
+
+    let renderable = Renderable::themed('L', ThemeColor::Boss);
+    let render_layer = RenderLayer::Blocking;
+    let ai_component = AIType::Melee(MeleeAIData::new().set_keep_distance(2, 5).set_darkvision());
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_fighter({
+            let max_hp = 60;
+            let defense = 3;
+            let power = 1;
+            Fighter::new(max_hp, defense, power, DeathCallback::Monster)
+                .set_damage(Dice::new(2, 4, 0))
+                .set_accuracy(2)
+        })
+        .set_ai(ai_component)
+        .set_gold(100)
+        .set_sorcery(Sorcery {
+            minion: Minion::Zombie,
+            summon_count: 2,
+            summon_cooldown: 1500,
+            bolt_damage: Dice::new(2, 4, 1),
+            bolt_range: 6,
+            bolt_cooldown: 300,
+            next_summon: 0,
+            next_bolt: 0,
+        })
+        .set_boss(vec![BossPhase {
+            hp_percent: 50,
+            message: "The Lich shrieks, and the floor heaves with the dead!".to_string(),
+            effects: vec![
+                PhaseEffect::Summon {
+                    minion: Minion::Zombie,
+                    count: 3,
+                },
+                PhaseEffect::Haste { duration: 500 },
+            ],
+        }])
 }
 
 pub fn weapon_dagger() -> Object {
     let name = "dagger".to_string();
     let tooltip = "a small dagger".to_string();
 
-    let renderable = Renderable {
-        glyph: '(',
-        fg: Color::default(),
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('(', ThemeColor::Item);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
@@ -122,18 +500,21 @@ pub fn weapon_dagger() -> Object {
             slot: Slot::Weapon,
             power_bonus: 2,
             defense_bonus: 0,
+            equip_time: 50,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
         })
+        .set_value(15)
+        .set_weight(3)
 }
 
 pub fn weapon_longsword() -> Object {
     let name = "longsword".to_string();
     let tooltip = "a large longsword".to_string();
 
-    let renderable = Renderable {
-        glyph: '(',
-        fg: Color::Blue,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('(', ThemeColor::RareItem);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
@@ -142,18 +523,113 @@ pub fn weapon_longsword() -> Object {
             slot: Slot::Weapon,
             power_bonus: 4,
             defense_bonus: 0,
+            equip_time: 100,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
+        })
+        .set_value(40)
+        .set_weight(8)
+}
+
+pub fn weapon_axe() -> Object {
+    let name = "axe".to_string();
+    let tooltip = "a crude but heavy axe".to_string();
+
+    let renderable = Renderable::themed('(', ThemeColor::RareItem);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Equipment)
+        .set_equipment(Equipment {
+            slot: Slot::Weapon,
+            power_bonus: 3,
+            defense_bonus: 0,
+            equip_time: 100,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
+        })
+        .set_value(25)
+        .set_weight(6)
+}
+
+pub fn weapon_greatsword() -> Object {
+    let name = "greatsword".to_string();
+    let tooltip = "a huge sword that takes both hands to swing".to_string();
+
+    let renderable = Renderable::themed('(', ThemeColor::RareItem);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Equipment)
+        .set_equipment(Equipment {
+            slot: Slot::Weapon,
+            power_bonus: 6,
+            defense_bonus: 0,
+            equip_time: 150,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: true,
+        })
+        .set_value(60)
+        .set_weight(15)
+}
+
+pub fn shield() -> Object {
+    let name = "shield".to_string();
+    let tooltip = "a round wooden shield".to_string();
+
+    let renderable = Renderable::themed(')', ThemeColor::Item);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Equipment)
+        .set_equipment(Equipment {
+            slot: Slot::OffHand,
+            power_bonus: 0,
+            defense_bonus: 1,
+            equip_time: 100,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
+        })
+        .set_value(25)
+        .set_weight(8)
+}
+
+pub fn ring_protection() -> Object {
+    let name = "ring of protection".to_string();
+    let tooltip = "a plain iron ring that wards off blows".to_string();
+
+    let renderable = Renderable::themed('=', ThemeColor::RareItem);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Equipment)
+        .set_equipment(Equipment {
+            slot: Slot::Ring,
+            power_bonus: 0,
+            defense_bonus: 1,
+            equip_time: 50,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
         })
+        .set_value(50)
+        .set_weight(1)
 }
 
 pub fn helmet() -> Object {
     let name = String::from("helmet");
     let tooltip = "a sturdy helmet".to_string();
 
-    let renderable = Renderable {
-        glyph: ']',
-        fg: Color::default(),
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed(']', ThemeColor::Item);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
@@ -162,18 +638,21 @@ pub fn helmet() -> Object {
             slot: Slot::Head,
             power_bonus: 0,
             defense_bonus: 1,
+            equip_time: 100,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
         })
+        .set_value(25)
+        .set_weight(5)
 }
 
 pub fn leather_armor() -> Object {
     let name = "leather armor".to_string();
     let tooltip = "supple leather armor".to_string();
 
-    let renderable = Renderable {
-        glyph: '[',
-        fg: Color::default(),
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('[', ThemeColor::Item);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
@@ -182,18 +661,21 @@ pub fn leather_armor() -> Object {
             slot: Slot::Body,
             power_bonus: 0,
             defense_bonus: 1,
+            equip_time: 200,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
         })
+        .set_value(20)
+        .set_weight(10)
 }
 
 pub fn plate_armor() -> Object {
     let name = "plate armor".to_string();
     let tooltip = "sturdy plate armor".to_string();
 
-    let renderable = Renderable {
-        glyph: '[',
-        fg: Color::Blue,
-        bg: Color::Reset,
-    };
+    let renderable = Renderable::themed('[', ThemeColor::RareItem);
     let render_layer = RenderLayer::Item;
 
     Object::new(name, tooltip, renderable, render_layer)
@@ -202,5 +684,52 @@ pub fn plate_armor() -> Object {
             slot: Slot::Body,
             power_bonus: 0,
             defense_bonus: 2,
+            equip_time: 400,
+            enchantment: 0,
+            cursed: false,
+            unidentified: false,
+            two_handed: false,
         })
+        .set_value(60)
+        .set_weight(25)
+}
+
+/// a pile of gold on the floor, picking it up adds to the player's purse
+pub fn gold_pile(amount: u32) -> Object {
+    let name = format!("{} gold", amount);
+    let tooltip = "shiny coins, good for trading".to_string();
+
+    let renderable = Renderable::themed('$', ThemeColor::Gold);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Gold)
+        .set_gold(amount)
+}
+
+/// a shopkeeper that trades the given stock. bump into them to trade
+pub fn shopkeeper(stock: Vec<usize>) -> Object {
+    let name = "Shopkeeper".to_string();
+    let tooltip = "a merchant willing to buy and sell wares".to_string();
+
+    let renderable = Renderable::themed('@', ThemeColor::Npc);
+    let render_layer = RenderLayer::Blocking;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_shop(Shop { stock })
+        .set_dialogue(dialogue::shopkeeper_dialogue())
+        .set_faction(Faction::Neutral)
+}
+
+/// the last of a company of adventurers, with a quest for the player
+pub fn lost_adventurer(goal: Goal) -> Object {
+    let name = "Lost Adventurer".to_string();
+    let tooltip = "a weary adventurer, the last of their company".to_string();
+
+    let renderable = Renderable::themed('@', ThemeColor::Npc);
+    let render_layer = RenderLayer::Blocking;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_dialogue(dialogue::adventurer_dialogue(goal))
+        .set_faction(Faction::Neutral)
 }
@@ -0,0 +1,717 @@
+use ratatui::style::{Color, Style, Stylize};
+
+use crate::{
+    achievements,
+    app::{App, MessageKind, PLAYER},
+    components::{Item, StatusKind, Trap},
+    engine::{DamageSource, Death},
+    inventory::Encumbrance,
+    quests,
+};
+
+// NOTE: this file contains the events that combat, items and monsters report when something
+// happens in the game. events are turned into log lines in one place, and are kept around
+// until the next turn starts so that other systems can react to them too
+
+/// how a melee attack turned out
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AttackOutcome {
+    Miss,
+    NoDamage,
+    Hit(u16),
+    Critical(u16),
+}
+
+/// how a status on a fighter changed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatusChange {
+    Gained,
+    Extended,
+    /// the new status cancelled out its opposite, e.g. haste and slow
+    Cancelled,
+}
+
+/// what a scroll of enchant equipment did to an item
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnchantOutcome {
+    Succeeded,
+    /// the item resisted, and nothing happened
+    Fizzled,
+    /// the scroll backfired and cursed the item
+    Cursed,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    /// one fighter attacked another in melee
+    DamageDealt {
+        attacker: usize,
+        attacker_name: String,
+        target: usize,
+        target_name: String,
+        outcome: AttackOutcome,
+        witnessed: bool,
+    },
+    /// the floor hurt a fighter standing on it, like lava, fire or poison gas
+    HazardDamage {
+        target: usize,
+        target_name: String,
+        source: DamageSource,
+        amount: u16,
+        witnessed: bool,
+    },
+    /// a damaging scroll the player read hit a fighter
+    ItemHit {
+        item: Item,
+        target: usize,
+        target_name: String,
+        damage: u16,
+    },
+    /// a monster was killed
+    MonsterDied {
+        id: usize,
+        name: String,
+        witnessed: bool,
+    },
+    /// the player was killed
+    PlayerDied { death: Death },
+    /// a fighter gained a status, or had one change
+    StatusChanged {
+        target: usize,
+        target_name: String,
+        kind: StatusKind,
+        change: StatusChange,
+    },
+    /// one of the player's statuses wore off
+    StatusExpired { kind: StatusKind },
+    /// a charmed monster turned on the player again
+    CharmBroken {
+        id: usize,
+        name: String,
+        witnessed: bool,
+    },
+    /// the player picked up an item
+    ItemPickedUp { id: usize, name: String },
+    /// the player picked up gold
+    GoldPickedUp { amount: u32 },
+    /// the player dropped an item
+    ItemDropped { id: usize, name: String },
+    /// the player put on a piece of equipment
+    Equipped { id: usize, name: String },
+    /// the player took off a piece of equipment
+    Unequipped { id: usize, name: String },
+    /// a carried corpse rotted away
+    ItemRotted { name: String },
+    /// a wand used up its last charge
+    ItemCrumbled { id: usize, name: String },
+    /// what the player carries got heavier or lighter enough to change how burdened they are
+    EncumbranceChanged {
+        before: Encumbrance,
+        after: Encumbrance,
+    },
+    /// the player bought an item from a shopkeeper
+    ItemBought { id: usize, name: String, price: u32 },
+    /// the player sold an item to a shopkeeper
+    ItemSold { id: usize, name: String, price: u32 },
+    /// the player finished one of the run's quests
+    QuestCompleted { description: String },
+    /// the player left a floor without finishing its quest
+    QuestFailed { description: String },
+    /// finishing a quest paid out gold
+    RewardGold { amount: u32 },
+    /// finishing a quest paid out an item, left at the player's feet if their pack was full
+    RewardItem {
+        id: usize,
+        name: String,
+        at_feet: bool,
+    },
+    /// the player used up an item whose effect reads the same every time
+    ItemUsed { item: Item },
+    /// a monster shrugged off a scroll of charm monster
+    CharmResisted { id: usize, name: String },
+    /// the player read a scroll of enchant equipment on an item
+    Enchanted {
+        id: usize,
+        name: String,
+        outcome: EnchantOutcome,
+    },
+    /// enchanting an item lifted its curse
+    CurseLifted { id: usize },
+    /// the player read a scroll of remove curse, lifting any curses if there were some
+    CursesRemoved { lifted: bool },
+    /// a wand of digging was zapped, boring through this many tiles
+    TunnelDug { tiles: usize },
+    /// the floor's alarm started ringing
+    AlarmRaised,
+    /// a sentry spotted the player and set off the alarm
+    AlarmSounded { id: usize, name: String },
+    /// a noise woke up monsters somewhere out of sight
+    NoiseHeard,
+    /// the player stepped on a trap. damage is only dealt by spike traps
+    TrapTriggered { trap: Trap, damage: u16 },
+    /// a trap went off, but had nothing to do
+    TrapFizzled { trap: Trap },
+    /// the player went down into a chasm, falling if they had no rope
+    EnteredChasm { fall_damage: Option<u16> },
+    /// a monster dug its way through a wall
+    MonsterTunneled {
+        id: usize,
+        name: String,
+        witnessed: bool,
+    },
+    /// a monster stumbled into a chasm, and is gone from the floor
+    MonsterFell {
+        id: usize,
+        name: String,
+        witnessed: bool,
+    },
+    /// a necromancer raised a corpse as a zombie
+    CorpseRaised {
+        id: usize,
+        name: String,
+        corpse_name: String,
+        witnessed: bool,
+    },
+    /// a spellcaster summoned minions to its side
+    MinionsSummoned { id: usize, name: String },
+    /// a spellcaster hurled a bolt of darkness at the player
+    BoltHurled {
+        id: usize,
+        name: String,
+        damage: u16,
+    },
+    /// a boss moved into its next phase
+    BossPhaseStarted { id: usize, message: String },
+    /// a boss walled the player in with it
+    ArenaSealed { id: usize },
+    /// a boss died, and the walls it raised crumbled
+    ArenaOpened,
+    /// the player grew stronger on the way down the stairs
+    LevelUp { level: u16, max_hp: u16 },
+    /// a monster or item came into the player's view for the first time
+    Spotted {
+        id: usize,
+        name: String,
+        monster: bool,
+    },
+}
+
+impl GameEvent {
+    /// returns the log line for the event, or None if the player didn't see it happen
+    pub fn log_line(&self) -> Option<(String, Style, MessageKind)> {
+        let (message, style, kind) = match self {
+            GameEvent::DamageDealt {
+                attacker_name,
+                target_name,
+                outcome,
+                witnessed,
+                ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                let attack_desc = format!("{} attacks {}", attacker_name, target_name);
+                let (message, style) = match outcome {
+                    AttackOutcome::Miss => (
+                        format!("{} but misses.", attack_desc),
+                        Style::from(Color::DarkGray),
+                    ),
+                    AttackOutcome::NoDamage => (
+                        format!("{} but does no damage.", attack_desc),
+                        Style::from(Color::default()),
+                    ),
+                    AttackOutcome::Hit(damage) => (
+                        format!("{} for {} damage.", attack_desc, damage),
+                        Style::from(Color::default()),
+                    ),
+                    AttackOutcome::Critical(damage) => (
+                        format!("{} for {} damage. A critical hit!", attack_desc, damage),
+                        Style::new().bold().light_yellow(),
+                    ),
+                };
+                (message, style, MessageKind::Combat)
+            }
+            GameEvent::HazardDamage {
+                target,
+                target_name,
+                source,
+                amount,
+                witnessed,
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                let message = match (*target == PLAYER, source) {
+                    (true, DamageSource::Lava) => {
+                        format!("The lava burns you for {} damage!", amount)
+                    }
+                    (false, DamageSource::Lava) => {
+                        format!("The {} burns in the lava.", target_name)
+                    }
+                    (true, DamageSource::Fire) => {
+                        format!("The flames burn you for {} damage!", amount)
+                    }
+                    (false, DamageSource::Fire) => {
+                        format!("The {} burns for {} damage.", target_name, amount)
+                    }
+                    (true, DamageSource::Poison) => {
+                        format!("The poison hurts you for {} damage!", amount)
+                    }
+                    (false, DamageSource::Poison) => format!(
+                        "The {} chokes on the poison for {} damage.",
+                        target_name, amount
+                    ),
+                    (true, _) => format!("You take {} damage from {}!", amount, source),
+                    (false, _) => format!(
+                        "The {} takes {} damage from {}.",
+                        target_name, amount, source
+                    ),
+                };
+                let color = match *target == PLAYER {
+                    true => Color::Red,
+                    false => Color::default(),
+                };
+                (message, Style::from(color), MessageKind::Combat)
+            }
+            // the fireball burns everything caught in it, the player included
+            GameEvent::ItemHit {
+                item: Item::Fireball,
+                target,
+                target_name,
+                damage,
+            } => {
+                let message = match *target == PLAYER {
+                    true => format!("The fireball burns you for {} damage!", damage),
+                    false => format!(
+                        "The fireball burns the {} for {} damage.",
+                        target_name, damage
+                    ),
+                };
+                (message, Style::from(Color::LightRed), MessageKind::Combat)
+            }
+            GameEvent::ItemHit {
+                item,
+                target_name,
+                damage,
+                ..
+            } => {
+                let attack_desc = match item {
+                    Item::Lightning => format!("Lightning smites the {}", target_name),
+                    _ => format!("The hexbolt blasts the {}", target_name),
+                };
+                let (message, color) = match damage {
+                    0 => (
+                        format!("{} but does no damage.", attack_desc),
+                        Color::default(),
+                    ),
+                    _ => (
+                        format!("{} for {} damage.", attack_desc, damage),
+                        Color::LightBlue,
+                    ),
+                };
+                (message, Style::from(color), MessageKind::Combat)
+            }
+            GameEvent::MonsterDied {
+                name, witnessed, ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                (
+                    format!("{} dies!", name),
+                    Style::from(Color::Red),
+                    MessageKind::Combat,
+                )
+            }
+            GameEvent::PlayerDied { death } => (
+                format!("You died, {}!", death),
+                Style::new().italic().red(),
+                MessageKind::Combat,
+            ),
+            GameEvent::StatusChanged {
+                target,
+                target_name,
+                kind,
+                change,
+            } => {
+                let message = match (*target == PLAYER, change) {
+                    // standing in a fire or a cloud of gas renews the status every turn
+                    _ if matches!(kind, StatusKind::Burning | StatusKind::Poisoned)
+                        && *change == StatusChange::Extended =>
+                    {
+                        return None;
+                    }
+                    (true, StatusChange::Gained) if *kind == StatusKind::Burning => {
+                        String::from("You catch fire!")
+                    }
+                    (false, StatusChange::Gained) if *kind == StatusKind::Burning => {
+                        format!("The {} catches fire!", target_name)
+                    }
+                    (true, StatusChange::Cancelled) => {
+                        String::from("You feel your speed return to normal.")
+                    }
+                    (false, StatusChange::Cancelled) => {
+                        format!("The {} returns to its normal speed.", target_name)
+                    }
+                    (true, StatusChange::Extended) => format!("You feel more {}.", kind),
+                    (false, StatusChange::Extended) => {
+                        format!("The {} looks more {}.", target_name, kind)
+                    }
+                    (true, StatusChange::Gained) => format!("You feel {}.", kind),
+                    (false, StatusChange::Gained) => {
+                        format!("The {} looks {}.", target_name, kind)
+                    }
+                };
+                (message, Style::from(Color::LightCyan), MessageKind::Combat)
+            }
+            GameEvent::StatusExpired { kind } => (
+                format!("You are no longer {}.", kind),
+                Style::from(Color::LightCyan),
+                MessageKind::Combat,
+            ),
+            GameEvent::CharmBroken {
+                name, witnessed, ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                (
+                    format!("The {} shakes off the charm!", name),
+                    Style::from(Color::LightCyan),
+                    MessageKind::Combat,
+                )
+            }
+            GameEvent::ItemPickedUp { name, .. } => (
+                format!("Picked up {}.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::GoldPickedUp { amount } => (
+                format!("Picked up {} gold.", amount),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::ItemDropped { name, .. } => (
+                format!("Dropped {}.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::Equipped { name, .. } => (
+                format!("You put on {}.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::Unequipped { name, .. } => (
+                format!("You take off {}.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::ItemRotted { name } => (
+                format!("Your {} rots away.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::ItemCrumbled { name, .. } => (
+                format!("The {} crumbles to dust.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::EncumbranceChanged { before, after } => {
+                let message = match after {
+                    Encumbrance::Unburdened => {
+                        String::from("Your movements are no longer burdened.")
+                    }
+                    _ if after > before => format!("You are {} by your load.", after),
+                    _ => format!("You are only {} by your load now.", after),
+                };
+                (message, Style::from(Color::default()), MessageKind::Items)
+            }
+            GameEvent::ItemBought { name, price, .. } => (
+                format!("Bought {} for {} gold.", name, price),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::ItemSold { name, price, .. } => (
+                format!("Sold {} for {} gold.", name, price),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::QuestCompleted { description } => (
+                format!("Quest complete: {}!", description),
+                Style::from(Color::LightYellow),
+                MessageKind::System,
+            ),
+            GameEvent::QuestFailed { description } => (
+                format!("Quest failed: {}.", description),
+                Style::from(Color::DarkGray),
+                MessageKind::System,
+            ),
+            GameEvent::RewardGold { amount } => (
+                format!("You are rewarded with {} gold.", amount),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::RewardItem {
+                name,
+                at_feet: false,
+                ..
+            } => (
+                format!("You are rewarded with {}.", name),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::RewardItem {
+                name,
+                at_feet: true,
+                ..
+            } => (
+                format!("Your pack is full, so {} is left at your feet.", name),
+                Style::from(Color::Yellow),
+                MessageKind::Items,
+            ),
+            GameEvent::ItemUsed { item } => {
+                let (message, color, kind) = match item {
+                    Item::Heal => (
+                        "Your wounds start to close.",
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
+                    Item::Fireball => (
+                        "The fireball explodes!",
+                        Color::LightRed,
+                        MessageKind::Combat,
+                    ),
+                    Item::TimeWarp => (
+                        "Time warps around you.",
+                        Color::LightCyan,
+                        MessageKind::Combat,
+                    ),
+                    Item::Corpse => (
+                        "You eat the corpse. It tastes awful, but you feel a bit better.",
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
+                    Item::Pickaxe => (
+                        "You dig your way through the wall.",
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
+                    Item::Smoke => (
+                        "The smoke bomb bursts into a thick cloud.",
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
+                    Item::PoisonGas => (
+                        "The flask shatters into a cloud of poison gas!",
+                        Color::default(),
+                        MessageKind::Items,
+                    ),
+                    _ => return None,
+                };
+                (String::from(message), Style::from(color), kind)
+            }
+            GameEvent::CharmResisted { name, .. } => (
+                format!("The {} resists the charm!", name),
+                Style::from(Color::default()),
+                MessageKind::Combat,
+            ),
+            GameEvent::Enchanted { name, outcome, .. } => {
+                let (message, color) = match outcome {
+                    EnchantOutcome::Succeeded => {
+                        (format!("Your {} glows blue.", name), Color::LightBlue)
+                    }
+                    EnchantOutcome::Fizzled => (
+                        format!("Your {} glows faintly, but nothing happens.", name),
+                        Color::default(),
+                    ),
+                    EnchantOutcome::Cursed => (
+                        format!("Your {} glows black. It feels cursed!", name),
+                        Color::Red,
+                    ),
+                };
+                (message, Style::from(color), MessageKind::Items)
+            }
+            GameEvent::CurseLifted { .. } => (
+                String::from("The curse lifts."),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::CursesRemoved { lifted: true } => (
+                String::from("You feel like someone is helping you."),
+                Style::from(Color::LightBlue),
+                MessageKind::Items,
+            ),
+            GameEvent::CursesRemoved { lifted: false } => (
+                String::from("You feel like you need some help."),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+            GameEvent::TunnelDug { tiles } => {
+                let message = match tiles {
+                    0 => "The wand's beam finds no rock to dig through.",
+                    _ => "The wand bores a tunnel through the rock!",
+                };
+                (
+                    String::from(message),
+                    Style::from(Color::default()),
+                    MessageKind::Items,
+                )
+            }
+            GameEvent::AlarmRaised => (
+                String::from("An alarm blares through the floor!"),
+                Style::from(Color::Red),
+                MessageKind::Combat,
+            ),
+            GameEvent::AlarmSounded { name, .. } => (
+                format!("The {} sounds the alarm!", name),
+                Style::from(Color::Red),
+                MessageKind::Combat,
+            ),
+            GameEvent::NoiseHeard => (
+                String::from("You hear something stir."),
+                Style::from(Color::DarkGray),
+                MessageKind::Combat,
+            ),
+            GameEvent::TrapTriggered { trap, damage } => {
+                let (message, color) = match trap {
+                    Trap::Alarm => (String::from("You trip over an alarm trap!"), Color::Red),
+                    Trap::Spike => (
+                        format!(
+                            "Spikes shoot up out of the floor! You take {} damage.",
+                            damage
+                        ),
+                        Color::Red,
+                    ),
+                    Trap::Teleport => (
+                        String::from(
+                            "You step on a teleport trap, and the world lurches around you!",
+                        ),
+                        Color::LightMagenta,
+                    ),
+                };
+                (message, Style::from(color), MessageKind::Combat)
+            }
+            GameEvent::TrapFizzled { .. } => (
+                String::from("The rune under you flickers and dies."),
+                Style::from(Color::default()),
+                MessageKind::Combat,
+            ),
+            GameEvent::EnteredChasm { fall_damage: None } => (
+                String::from("You tie off a rope and climb down into the chasm."),
+                Style::from(Color::default()),
+                MessageKind::Combat,
+            ),
+            GameEvent::EnteredChasm {
+                fall_damage: Some(damage),
+            } => (
+                format!("You fall into the chasm, taking {} damage!", damage),
+                Style::from(Color::Red),
+                MessageKind::Combat,
+            ),
+            GameEvent::MonsterTunneled {
+                name, witnessed, ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                (
+                    format!("The {} tunnels through the wall!", name),
+                    Style::from(Color::default()),
+                    MessageKind::Combat,
+                )
+            }
+            GameEvent::MonsterFell {
+                name, witnessed, ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                (
+                    format!("The {} stumbles into the chasm!", name),
+                    Style::from(Color::default()),
+                    MessageKind::Combat,
+                )
+            }
+            GameEvent::CorpseRaised {
+                name,
+                corpse_name,
+                witnessed,
+                ..
+            } => {
+                if !witnessed {
+                    return None;
+                }
+                (
+                    format!("The {} raises the {}!", name, corpse_name),
+                    Style::from(Color::Red),
+                    MessageKind::Combat,
+                )
+            }
+            GameEvent::MinionsSummoned { name, .. } => (
+                format!("The {} calls the dead to its side!", name),
+                Style::from(Color::LightRed),
+                MessageKind::Combat,
+            ),
+            GameEvent::BoltHurled { name, damage, .. } => (
+                format!(
+                    "The {} hurls a bolt of darkness at you for {} damage!",
+                    name, damage
+                ),
+                Style::from(Color::Magenta),
+                MessageKind::Combat,
+            ),
+            GameEvent::BossPhaseStarted { message, .. } => (
+                message.clone(),
+                Style::from(Color::LightRed),
+                MessageKind::Combat,
+            ),
+            GameEvent::ArenaSealed { .. } => (
+                String::from("The walls rumble shut around you!"),
+                Style::from(Color::LightRed),
+                MessageKind::Combat,
+            ),
+            GameEvent::ArenaOpened => (
+                String::from("The walls around you crumble away."),
+                Style::from(Color::default()),
+                MessageKind::Combat,
+            ),
+            GameEvent::LevelUp { .. } => (
+                String::from("You feel stronger."),
+                Style::from(Color::Magenta),
+                MessageKind::System,
+            ),
+            GameEvent::Spotted {
+                name,
+                monster: true,
+                ..
+            } => (
+                format!("{} comes into view.", name),
+                Style::from(Color::default()),
+                MessageKind::Combat,
+            ),
+            GameEvent::Spotted {
+                name,
+                monster: false,
+                ..
+            } => (
+                format!("You see {}.", name),
+                Style::from(Color::default()),
+                MessageKind::Items,
+            ),
+        };
+        Some((message, style, kind))
+    }
+}
+
+impl App {
+    /// reports something that happened in the game. it's written to the log if the player
+    /// saw it, and queued up for anything else that wants to know about it
+    pub fn emit(&mut self, event: GameEvent) {
+        if let Some((message, style, kind)) = event.log_line() {
+            self.add_to_log(message, style, kind);
+        }
+        quests::on_event(self, &event);
+        achievements::on_event(self, &event);
+        self.events.push(event);
+    }
+}
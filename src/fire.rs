@@ -0,0 +1,187 @@
+use rand::Rng;
+use ratatui::style::Color;
+
+use crate::{
+    app::{App, MessageKind, PLAYER},
+    components::{Position, StatusKind},
+    engine::{DamageSource, Dice, apply_status, fighters_with_status, take_damage},
+    events::GameEvent,
+    gamemap::{EffectKind, TileEffect, TileType},
+};
+
+// this file contains fire, which spreads over grass and corpses, sets whatever stands
+// in it alight, and dies down once it has burnt through everything on its tile
+
+/// how far a fire lights up the floor around it
+pub const FIRE_LIGHT_RADIUS: u16 = 2;
+const GRASS_FUEL: u16 = 3; // turns a fire burns on grass
+const CORPSE_FUEL: u16 = 4; // turns a fire burns on a corpse
+const BARE_FUEL: u16 = 1; // turns a fire lasts with nothing to feed on
+const SPREAD_CHANCE: f64 = 0.5; // chance a fire spreads to each flammable neighbour per turn
+const BURNING_DURATION: u64 = 300;
+const BURNING_DAMAGE: Dice = Dice::new(1, 4, 1);
+
+/// returns how many turns a fire could feed on what's on a tile.
+/// zero means there's nothing on it that burns
+fn fuel(app: &App, x: u16, y: u16) -> u16 {
+    let tile = app.gamemap.get_ref(x, y);
+    let grass = match tile.is_flammable() {
+        true => GRASS_FUEL,
+        false => 0,
+    };
+    let corpse = match tile.item {
+        Some(id) if app.objects.get(&id).unwrap().corpse.is_some() => CORPSE_FUEL,
+        _ => 0,
+    };
+    grass + corpse
+}
+
+/// sets a tile on fire. fires can't burn on water, lava, or anything that isn't floor.
+/// returns true if the tile caught
+pub fn ignite(app: &mut App, x: u16, y: u16) -> bool {
+    let tile = app.gamemap.get_ref(x, y);
+    if !tile.is_walkable() || matches!(tile.tile_type, TileType::Water | TileType::Lava) {
+        return false;
+    }
+    let strength = fuel(app, x, y).max(BARE_FUEL);
+    app.gamemap.add_effect(TileEffect {
+        position: Position { x, y },
+        kind: EffectKind::Fire,
+        strength,
+    });
+    true
+}
+
+/// sets a fighter on fire, or keeps it burning for longer if it already was
+pub fn set_alight(app: &mut App, id: usize) {
+    if app.objects.get(&id).unwrap().fighter.is_some() {
+        apply_status(app, id, StatusKind::Burning, BURNING_DURATION);
+    }
+}
+
+/// runs every fire on the floor once for every turn the clock went past
+pub fn burn(app: &mut App, from: u64, to: u64) {
+    let turns = to / 100 - from / 100;
+    for _ in 0..turns {
+        fire_turn(app);
+    }
+}
+
+fn fire_turn(app: &mut App) {
+    let mut fires: Vec<Position> = app
+        .gamemap
+        .effects
+        .iter()
+        .filter(|effect| effect.kind == EffectKind::Fire)
+        .map(|effect| effect.position)
+        .collect();
+    fires.sort_by_key(|pos| (pos.y, pos.x));
+
+    // anything standing in a fire catches, or keeps burning
+    for pos in fires.iter() {
+        if let Some(id) = app.gamemap.get_ref(pos.x, pos.y).blocker {
+            set_alight(app, id);
+        }
+    }
+
+    burn_fighters(app);
+
+    // fires spread to flammable tiles next to them before they burn down,
+    // so a fire on the last tuft of grass still gets a chance to jump
+    let mut spread: Vec<Position> = Vec::new();
+    for pos in fires.iter() {
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (x, y) = (pos.x as i16 + dx, pos.y as i16 + dy);
+            if !app.gamemap.in_bounds(x, y) {
+                continue;
+            }
+            let next = Position {
+                x: x as u16,
+                y: y as u16,
+            };
+            if fuel(app, next.x, next.y) == 0
+                || app.gamemap.is_burning(next.x, next.y)
+                || spread.contains(&next)
+            {
+                continue;
+            }
+            if app.rng.random_bool(SPREAD_CHANCE) {
+                spread.push(next);
+            }
+        }
+    }
+
+    for pos in app.gamemap.decay_effects(EffectKind::Fire) {
+        burn_out(app, pos);
+    }
+
+    for pos in spread {
+        ignite(app, pos.x, pos.y);
+    }
+}
+
+/// leaves behind the ashes of whatever a fire burnt through
+fn burn_out(app: &mut App, pos: Position) {
+    if app.gamemap.get_ref(pos.x, pos.y).is_flammable() {
+        app.gamemap.set_tile_type(pos.x, pos.y, TileType::Floor);
+    }
+
+    let Some(id) = app.gamemap.get_ref(pos.x, pos.y).item else {
+        return;
+    };
+    if app.objects.get(&id).unwrap().corpse.is_none() {
+        return;
+    }
+    app.gamemap.remove_item(pos.x, pos.y);
+    let name = app.objects.get(&id).unwrap().name.clone();
+    app.objects.get_contents().remove(&id);
+    if app.gamemap.is_visible(pos.x, pos.y) {
+        app.add_to_log(
+            format!("The {} burns to ashes.", name),
+            Color::default(),
+            MessageKind::Combat,
+        );
+    }
+}
+
+/// hurts every fighter that's on fire, unless they're standing in water to put it out
+fn burn_fighters(app: &mut App) {
+    for (id, pos) in fighters_with_status(app, StatusKind::Burning) {
+        if app.gamemap.get_position(id) != Some(pos) {
+            continue;
+        }
+        let name = app.objects.get(&id).unwrap().name.clone();
+        let visible = app.gamemap.is_visible(pos.x, pos.y);
+
+        if app.gamemap.get_ref(pos.x, pos.y).tile_type == TileType::Water {
+            let fighter = app.objects.get_mut(&id).unwrap().fighter.as_mut().unwrap();
+            fighter
+                .statuses
+                .retain(|status| status.kind != StatusKind::Burning);
+            if id == PLAYER {
+                app.add_to_log(
+                    "The water puts out the flames.",
+                    Color::LightCyan,
+                    MessageKind::Combat,
+                );
+            } else if visible {
+                app.add_to_log(
+                    format!("The water puts out the flames on the {}.", name),
+                    Color::default(),
+                    MessageKind::Combat,
+                );
+            }
+            continue;
+        }
+
+        let amount = BURNING_DAMAGE.roll(&mut app.rng) as u16;
+        app.emit(GameEvent::HazardDamage {
+            target: id,
+            target_name: name,
+            source: DamageSource::Fire,
+            amount,
+            witnessed: id == PLAYER || visible,
+        });
+        take_damage(app, id, amount, DamageSource::Fire);
+    }
+}
@@ -4,17 +4,25 @@ use std::{
 };
 
 use crate::components::{Position, Renderable};
+use crate::pathfinding::Diagonals;
+use crate::theme::ThemeColor;
 
-use rand::{rng, seq::SliceRandom};
+use rand::{Rng, seq::SliceRandom};
 use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 
 const ITEM_DROP_RADIUS: u16 = 2;
 
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Floor,
     Wall,
+    Chasm,      // drops anything that goes in down to the next floor
+    Water,      // slow to wade through
+    Lava,       // burns anything standing in it
+    Rubble,     // slow to pick a way over
+    Grass,      // burns away to bare floor
+    LockedDoor, // needs the right key to open
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
@@ -36,30 +44,57 @@ impl Tile {
     // NOTE: walkable tiles are those on which items and blockers can be placed
     pub fn is_walkable(&self) -> bool {
         match self.tile_type {
-            TileType::Floor => true,
-            TileType::Wall => false,
+            TileType::Floor
+            | TileType::Water
+            | TileType::Lava
+            | TileType::Rubble
+            | TileType::Grass => true,
+            TileType::Wall | TileType::LockedDoor => false,
+            TileType::Chasm => false,
+        }
+    }
+
+    /// whether stepping into this tile hurts, so that careful walkers stay out of it
+    pub fn is_hazard(&self) -> bool {
+        matches!(self.tile_type, TileType::Chasm | TileType::Lava)
+    }
+
+    /// whether a fire can feed on the tile itself, rather than just what lies on it
+    pub fn is_flammable(&self) -> bool {
+        self.tile_type == TileType::Grass
+    }
+
+    /// how long it takes to move into this tile, as a percentage of a normal step
+    pub fn move_time_percent(&self) -> u64 {
+        match self.tile_type {
+            TileType::Water => 200,
+            TileType::Rubble => 150,
+            _ => 100,
         }
     }
 
     pub fn is_transparent(&self) -> bool {
         match self.tile_type {
-            TileType::Floor => true,
-            TileType::Wall => false,
+            TileType::Floor
+            | TileType::Water
+            | TileType::Lava
+            | TileType::Rubble
+            | TileType::Grass => true,
+            TileType::Wall | TileType::LockedDoor => false,
+            TileType::Chasm => true,
         }
     }
 
     pub fn renderable(&self) -> Renderable {
         match self.tile_type {
-            TileType::Wall => Renderable {
-                glyph: '#',
-                fg: Color::Gray,
-                bg: Color::Reset,
-            },
-            TileType::Floor => Renderable {
-                glyph: '.',
-                fg: Color::Gray,
-                bg: Color::Reset,
-            },
+            TileType::Wall => Renderable::themed('#', ThemeColor::WallLit),
+            TileType::Floor => Renderable::themed('.', ThemeColor::FloorLit),
+            TileType::Chasm => Renderable::themed(':', ThemeColor::Chasm),
+            TileType::Water => Renderable::themed('~', ThemeColor::Water),
+            TileType::Lava => Renderable::themed('≈', ThemeColor::Lava),
+            TileType::Rubble => Renderable::themed(',', ThemeColor::FloorLit),
+            TileType::Grass => Renderable::themed('"', ThemeColor::Grass),
+            TileType::LockedDoor => Renderable::themed('+', ThemeColor::Gold),
         }
     }
 }
@@ -70,6 +105,7 @@ pub fn shroud_renderable() -> Renderable {
         glyph: ' ',
         fg: Color::Reset,
         bg: Color::Reset,
+        role: None,
     }
 }
 
@@ -83,6 +119,76 @@ pub fn idx_to_coords(idx: usize, width: u16) -> (u16, u16) {
     (idx % width, idx / width)
 }
 
+/// a named location on the map that the player can travel back to
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Waypoint {
+    pub name: String,
+    pub position: Position,
+}
+
+/// the floor's alert state. while it lasts, every monster on the floor converges on it
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Alarm {
+    pub position: Position, // where the alarm went off
+    pub expires: u64,       // time at which monsters stop answering the alarm
+}
+
+/// kinds of effects that linger on a tile and change every turn
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum EffectKind {
+    Fire,   // spreads to anything flammable nearby, and burns whatever stands in it
+    Poison, // a cloud of gas that poisons whatever breathes it in
+    Smoke,  // a cloud that nothing can see through
+}
+
+impl EffectKind {
+    /// whether the effect hurts anything standing in it, so that careful walkers go around
+    pub fn is_harmful(self) -> bool {
+        matches!(self, EffectKind::Fire | EffectKind::Poison)
+    }
+
+    /// whether the effect blocks line of sight like a wall does
+    pub fn blocks_sight(self) -> bool {
+        self == EffectKind::Smoke
+    }
+}
+
+/// an effect lingering on a single tile of the floor
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TileEffect {
+    pub position: Position,
+    pub kind: EffectKind,
+    pub strength: u16, // turns left before the effect dies out
+}
+
+/// a locked door, and the key that opens it
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct Lock {
+    pub position: Position,
+    pub key: usize, // id of the key object that fits the lock
+}
+
+/// running counters for the floor, used to summarize it when the player leaves
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LevelStats {
+    pub start_time: u64,  // time the player arrived on the floor
+    pub start_kills: u32, // kills the player had when they arrived
+    pub items_found: u32, // items and gold piles picked up on this floor
+}
+
+/// what happened on a floor, shown to the player after they leave it
+#[derive(Clone, PartialEq, Eq)]
+pub struct LevelSummary {
+    pub level: u16,
+    pub kills: u32,
+    pub monsters_left: u32,
+    pub items_found: u32,
+    pub items_left: u32,
+    pub time_spent: u64,
+    pub explored_tiles: usize,
+    pub total_tiles: usize,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct GameMap {
     pub width: u16,
@@ -92,7 +198,57 @@ pub struct GameMap {
     pub visible: Vec<bool>, // whether any given tile is visible
     pub explored: Vec<bool>, // whether any given tile has been explored
     pub last_seen: Vec<Renderable>, // the state of the tile when it was last seen
+    #[serde(default)]
+    walked: Vec<bool>, // whether the player has stepped on any given tile
+    #[serde(default)]
+    remembered: Vec<Option<Renderable>>, // the monster or item last seen on each tile, if any
     objects: HashMap<usize, Position>, // objects present in this gamemap, mapped to their position
+    #[serde(default)]
+    pub wanderers_spawned: usize, // number of wandering monsters spawned on this floor so far
+    #[serde(default)]
+    pub waypoints: Vec<Waypoint>, // locations the player has bookmarked on this floor
+    #[serde(default)]
+    pub alarm: Option<Alarm>, // shared by every monster on the floor, set when an alarm goes off
+    #[serde(default)]
+    pub effects: Vec<TileEffect>, // fires and other effects lingering on the floor
+    #[serde(default)]
+    pub locks: Vec<Lock>, // the locked doors on the floor
+    #[serde(default)]
+    pub stats: LevelStats, // what the player has done on this floor so far
+    #[serde(default)]
+    pub lighting: bool, // whether dark tiles are hidden. floors from older saves are lit everywhere
+    #[serde(skip)]
+    lit: Vec<bool>, // whether a light source on the floor lights up any given tile
+    #[serde(skip)]
+    in_sight: Vec<bool>, // whether the player has a clear line to any given tile, lit or not
+    #[serde(skip)]
+    opacity_revision: u64, // bumped every time a tile changes transparency
+    #[serde(skip)]
+    light_revision: u64, // bumped every time the lit grid changes
+    #[serde(skip)]
+    fov_key: Option<FovKey>, // the inputs that `visible` was last computed from
+    #[serde(skip)]
+    light_key: Option<LightKey>, // the inputs that `lit` was last computed from
+}
+
+/// the inputs that determine the result of a fov computation.
+/// if none of these change, the visible grid does not need to be recomputed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FovKey {
+    pub origin: Position,
+    pub radius: u16,
+    pub sight: u16,           // how far the player can see without any light
+    pub diagonals: Diagonals, // without diagonal steps, sight doesn't pass between touching corners
+    pub opacity_revision: u64,
+    pub light_revision: u64,
+}
+
+/// the inputs that determine which tiles are lit. like FovKey, the lit grid only
+/// needs to be recomputed when one of these changes
+#[derive(Clone, PartialEq, Eq)]
+pub struct LightKey {
+    pub sources: Vec<(Position, u16)>, // every light on the floor, and how far it shines
+    pub opacity_revision: u64,
 }
 
 impl GameMap {
@@ -105,18 +261,217 @@ impl GameMap {
             visible: vec![false; (width * height) as usize],
             explored: vec![false; (width * height) as usize],
             last_seen: vec![Renderable::default(); (width * height) as usize],
+            walked: vec![false; (width * height) as usize],
+            remembered: vec![None; (width * height) as usize],
             objects: HashMap::new(),
+            wanderers_spawned: 0,
+            waypoints: Vec::new(),
+            alarm: None,
+            effects: Vec::new(),
+            locks: Vec::new(),
+            stats: LevelStats::default(),
+            lighting: false,
+            lit: Vec::new(),
+            in_sight: vec![false; (width * height) as usize],
+            opacity_revision: 0,
+            light_revision: 0,
+            fov_key: None,
+            light_key: None,
         }
     }
 
     // get a reference to a tile of the gamemap
     pub fn get_ref(&self, x: u16, y: u16) -> &Tile {
-        return &self.tiles[coords_to_idx(x, y, self.width)];
+        &self.tiles[coords_to_idx(x, y, self.width)]
     }
 
     // get a mutable reference to a tile of the gamemap
     pub fn get_mut(&mut self, x: u16, y: u16) -> &mut Tile {
-        return &mut self.tiles[coords_to_idx(x, y, self.width)];
+        &mut self.tiles[coords_to_idx(x, y, self.width)]
+    }
+
+    /// changes the type of a tile, keeping any objects on it.
+    /// invalidates the cached fov if the tile's transparency changed
+    pub fn set_tile_type(&mut self, x: u16, y: u16, tile_type: TileType) {
+        let tile = self.get_mut(x, y);
+        let was_transparent = tile.is_transparent();
+        tile.tile_type = tile_type;
+        if tile.is_transparent() != was_transparent {
+            self.opacity_revision += 1;
+        }
+    }
+
+    /// whether the tile is a wall that can be dug out. the edge of the map is solid rock
+    pub fn is_diggable(&self, x: u16, y: u16) -> bool {
+        x > 0
+            && y > 0
+            && x < self.width - 1
+            && y < self.height - 1
+            && self.get_ref(x, y).tile_type == TileType::Wall
+    }
+
+    /// digs out a wall, turning it into floor. returns false if it can't be dug
+    pub fn dig(&mut self, x: u16, y: u16) -> bool {
+        if !self.is_diggable(x, y) {
+            return false;
+        }
+        self.set_tile_type(x, y, TileType::Floor);
+        true
+    }
+
+    /// returns the lock on a door, if there is one
+    pub fn lock_at(&self, x: u16, y: u16) -> Option<&Lock> {
+        self.locks
+            .iter()
+            .find(|lock| lock.position == Position { x, y })
+    }
+
+    /// opens up a locked door, turning it into floor
+    pub fn unlock(&mut self, x: u16, y: u16) {
+        self.locks.retain(|lock| lock.position != Position { x, y });
+        self.set_tile_type(x, y, TileType::Floor);
+    }
+
+    /// returns the effect of the given kind on a tile, if there is one
+    pub fn effect_at(&self, x: u16, y: u16, kind: EffectKind) -> Option<&TileEffect> {
+        self.effects
+            .iter()
+            .find(|effect| effect.kind == kind && effect.position == Position { x, y })
+    }
+
+    /// whether a fire is burning on a tile
+    pub fn is_burning(&self, x: u16, y: u16) -> bool {
+        self.effect_at(x, y, EffectKind::Fire).is_some()
+    }
+
+    /// whether anything on a tile would hurt whatever steps into it
+    pub fn is_harmful(&self, x: u16, y: u16) -> bool {
+        self.effects
+            .iter()
+            .any(|effect| effect.kind.is_harmful() && effect.position == Position { x, y })
+    }
+
+    /// whether the view through a tile is blocked, by a wall or by a cloud of smoke
+    pub fn blocks_sight(&self, x: u16, y: u16) -> bool {
+        !self.get_ref(x, y).is_transparent()
+            || self
+                .effects
+                .iter()
+                .any(|effect| effect.kind.blocks_sight() && effect.position == Position { x, y })
+    }
+
+    /// adds an effect to a tile. an effect of the same kind already there
+    /// keeps whichever strength is higher
+    pub fn add_effect(&mut self, effect: TileEffect) {
+        if effect.kind.blocks_sight() {
+            self.opacity_revision += 1;
+        }
+        match self
+            .effects
+            .iter_mut()
+            .find(|other| other.kind == effect.kind && other.position == effect.position)
+        {
+            Some(other) => other.strength = other.strength.max(effect.strength),
+            None => self.effects.push(effect),
+        }
+    }
+
+    /// weakens every effect of a kind by one, and clears away the ones that are spent.
+    /// returns the positions of the effects that were cleared, sorted by position
+    pub fn decay_effects(&mut self, kind: EffectKind) -> Vec<Position> {
+        let mut spent = Vec::new();
+        self.effects.retain_mut(|effect| {
+            if effect.kind != kind {
+                return true;
+            }
+            effect.strength = effect.strength.saturating_sub(1);
+            if effect.strength == 0 {
+                spent.push(effect.position);
+            }
+            effect.strength > 0
+        });
+        if kind.blocks_sight() && !spent.is_empty() {
+            self.opacity_revision += 1;
+        }
+        spent.sort_by_key(|pos| (pos.y, pos.x));
+        spent
+    }
+
+    /// returns the key describing the fov that would be computed from origin with radius,
+    /// for a player who can see sight tiles into the dark and move as diagonals allows
+    pub fn fov_key(
+        &self,
+        origin: Position,
+        radius: u16,
+        sight: u16,
+        diagonals: Diagonals,
+    ) -> FovKey {
+        FovKey {
+            origin,
+            radius,
+            sight,
+            diagonals,
+            opacity_revision: self.opacity_revision,
+            light_revision: self.light_revision,
+        }
+    }
+
+    /// returns the key describing the lit grid for the given light sources
+    pub fn light_key(&self, sources: Vec<(Position, u16)>) -> LightKey {
+        LightKey {
+            sources,
+            opacity_revision: self.opacity_revision,
+        }
+    }
+
+    /// returns true if the lit grid was already computed for this key
+    pub fn is_light_current(&self, key: &LightKey) -> bool {
+        self.light_key.as_ref() == Some(key)
+    }
+
+    /// replaces the lit grid, and records the key it was computed from
+    pub fn set_lit(&mut self, lit: Vec<bool>, key: LightKey) {
+        self.lit = lit;
+        self.light_key = Some(key);
+        self.light_revision += 1;
+    }
+
+    /// whether a tile is lit. floors without lighting are lit everywhere
+    pub fn is_lit(&self, x: u16, y: u16) -> bool {
+        !self.lighting
+            || self
+                .lit
+                .get(coords_to_idx(x, y, self.width))
+                .copied()
+                .unwrap_or(false)
+    }
+
+    /// whether the player has a clear line to a tile, even if it's too dark to see
+    pub fn is_in_sight(&self, x: u16, y: u16) -> bool {
+        self.in_sight
+            .get(coords_to_idx(x, y, self.width))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// clears the in sight grid before a fov computation, sizing it for the map
+    pub fn clear_in_sight(&mut self) {
+        self.in_sight.clear();
+        self.in_sight.resize(self.tiles.len(), false);
+    }
+
+    pub fn set_in_sight(&mut self, x: u16, y: u16, value: bool) {
+        self.in_sight[coords_to_idx(x, y, self.width)] = value;
+    }
+
+    /// returns true if the visible grid was already computed for this key
+    pub fn is_fov_current(&self, key: FovKey) -> bool {
+        self.fov_key == Some(key)
+    }
+
+    /// records that the visible grid now matches the given key
+    pub fn set_fov_key(&mut self, key: FovKey) {
+        self.fov_key = Some(key);
     }
 
     pub fn is_visible(&self, x: u16, y: u16) -> bool {
@@ -136,6 +491,22 @@ impl GameMap {
         self.explored[coords_to_idx(x, y, self.width)] = value;
     }
 
+    pub fn is_walked(&self, x: u16, y: u16) -> bool {
+        // saves from before breadcrumbs were tracked have no walked grid
+        self.walked
+            .get(coords_to_idx(x, y, self.width))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// records that the player has stepped on a tile
+    pub fn set_walked(&mut self, x: u16, y: u16) {
+        if self.walked.len() != self.tiles.len() {
+            self.walked = vec![false; self.tiles.len()];
+        }
+        self.walked[coords_to_idx(x, y, self.width)] = true;
+    }
+
     /// returns a copy of the last seen version of a given tile
     pub fn get_last_seen(&self, x: u16, y: u16) -> Renderable {
         self.last_seen[coords_to_idx(x, y, self.width)].clone()
@@ -145,9 +516,45 @@ impl GameMap {
         self.last_seen[coords_to_idx(x, y, self.width)] = value;
     }
 
+    /// returns the monster or item the player last saw on a tile
+    pub fn get_remembered(&self, x: u16, y: u16) -> Option<Renderable> {
+        // saves from before objects were remembered have no memory grid
+        self.remembered
+            .get(coords_to_idx(x, y, self.width))
+            .cloned()
+            .flatten()
+    }
+
+    pub fn set_remembered(&mut self, x: u16, y: u16, value: Option<Renderable>) {
+        if self.remembered.len() != self.tiles.len() {
+            self.remembered = vec![None; self.tiles.len()];
+        }
+        self.remembered[coords_to_idx(x, y, self.width)] = value;
+    }
+
     // quickly check if an index is in bounds
     pub fn in_bounds(&self, x: i16, y: i16) -> bool {
-        return 0 <= x && x < self.width as i16 && 0 <= y && y < self.height as i16;
+        0 <= x && x < self.width as i16 && 0 <= y && y < self.height as i16
+    }
+
+    /// returns true if a diagonal step from (x, y) squeezes past a corner, i.e. one of
+    /// the two tiles beside the step can't be walked on. the step itself must be in bounds
+    pub fn cuts_corner(&self, x: u16, y: u16, dx: i16, dy: i16) -> bool {
+        if dx == 0 || dy == 0 {
+            return false;
+        }
+        let (beside_x, beside_y) = ((x as i16 + dx) as u16, (y as i16 + dy) as u16);
+        !self.get_ref(beside_x, y).is_walkable() || !self.get_ref(x, beside_y).is_walkable()
+    }
+
+    /// returns true if a diagonal step from (x, y) passes between two tiles that both block
+    /// sight, like a gap between two walls that touch at their corners
+    pub fn sight_cuts_corner(&self, x: u16, y: u16, dx: i16, dy: i16) -> bool {
+        if dx == 0 || dy == 0 {
+            return false;
+        }
+        let (beside_x, beside_y) = ((x as i16 + dx) as u16, (y as i16 + dy) as u16);
+        self.blocks_sight(beside_x, y) && self.blocks_sight(x, beside_y)
     }
 
     /// gets the position of either a blocker or item matching that id
@@ -179,6 +586,18 @@ impl GameMap {
         }
     }
 
+    /// puts an object in the item slot of a wall, like a torch in a bracket.
+    /// panics if the tile isn't a wall, or already has something mounted on it
+    pub fn mount_on_wall(&mut self, id: usize, x: u16, y: u16) {
+        let tile = self.get_mut(x, y);
+        if tile.tile_type == TileType::Wall && tile.item.is_none() {
+            tile.item = Some(id);
+            self.objects.insert(id, Position { x, y });
+        } else {
+            panic!("failed to mount object on wall!")
+        }
+    }
+
     /// removes a blocker from a specified location
     /// returns the id of the removed blocker if there was one
     /// panics if there was no blocker there
@@ -209,7 +628,13 @@ impl GameMap {
 
     /// attempts to place an item at a given location, or somewhere nearby if possible
     /// returns the position that the item was added to
-    pub fn area_place_item(&mut self, x: u16, y: u16, id: usize) -> Option<Position> {
+    pub fn area_place_item(
+        &mut self,
+        x: u16,
+        y: u16,
+        id: usize,
+        rng: &mut impl Rng,
+    ) -> Option<Position> {
         let mut visited: HashSet<(u16, u16)> = HashSet::new();
         let mut queue: VecDeque<(u16, u16)> = VecDeque::new();
         queue.push_back((x, y));
@@ -238,8 +663,7 @@ impl GameMap {
 
             // directions are shuffled to add some randomness to how items drop
             let mut dirs = [(-1, 0), (1, 0), (0, -1), (0, 1)];
-            let mut rng = rng();
-            dirs.shuffle(&mut rng);
+            dirs.shuffle(rng);
 
             for (dx, dy) in dirs {
                 let (new_x, new_y) = (cur_x as i16 + dx, cur_y as i16 + dy);
@@ -257,6 +681,6 @@ impl GameMap {
             }
         }
 
-        return None;
+        None
     }
 }
@@ -0,0 +1,116 @@
+use crate::{
+    app::{App, PLAYER},
+    components::{Position, StatusKind},
+    engine::{DamageSource, Dice, apply_status, fighters_with_status, take_damage},
+    events::GameEvent,
+    gamemap::{EffectKind, TileEffect},
+};
+
+// this file contains clouds of gas, which drift outwards from where they were released
+// and thin out every turn. poison gas hurts whatever breathes it in, and smoke hides
+// everything inside it from view
+
+const GAS_KINDS: [EffectKind; 2] = [EffectKind::Poison, EffectKind::Smoke];
+const SPREAD_MIN: u16 = 3; // clouds thinner than this stop drifting outwards
+const SPREAD_LOSS: u16 = 2; // how much thinner a cloud gets as it drifts into the next tile
+const POISON_DURATION: u64 = 300;
+const POISON_DAMAGE: Dice = Dice::new(1, 3, 0);
+
+/// releases a cloud of gas on a tile. the strength is how many turns it lasts there,
+/// and how far it drifts before it thins out
+pub fn release(app: &mut App, pos: Position, kind: EffectKind, strength: u16) {
+    if !app.gamemap.get_ref(pos.x, pos.y).is_walkable() {
+        return;
+    }
+    app.gamemap.add_effect(TileEffect {
+        position: pos,
+        kind,
+        strength,
+    });
+}
+
+/// lets every cloud on the floor drift and thin out once for every turn the clock went past
+pub fn drift(app: &mut App, from: u64, to: u64) {
+    let turns = to / 100 - from / 100;
+    for _ in 0..turns {
+        for kind in GAS_KINDS {
+            spread(app, kind);
+            app.gamemap.decay_effects(kind);
+        }
+        poison_fighters(app);
+    }
+}
+
+/// spreads the thicker clouds of a kind into the open tiles around them
+fn spread(app: &mut App, kind: EffectKind) {
+    let mut clouds: Vec<TileEffect> = app
+        .gamemap
+        .effects
+        .iter()
+        .filter(|effect| effect.kind == kind && effect.strength >= SPREAD_MIN)
+        .copied()
+        .collect();
+    clouds.sort_by_key(|cloud| (cloud.position.y, cloud.position.x));
+
+    let mut drifted = Vec::new();
+    for cloud in clouds {
+        let strength = cloud.strength - SPREAD_LOSS;
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (x, y) = (cloud.position.x as i16 + dx, cloud.position.y as i16 + dy);
+            if !app.gamemap.in_bounds(x, y) {
+                continue;
+            }
+            let (x, y) = (x as u16, y as u16);
+            let thicker = app
+                .gamemap
+                .effect_at(x, y, kind)
+                .is_some_and(|other| other.strength >= strength);
+            if app.gamemap.get_ref(x, y).is_walkable() && !thicker {
+                drifted.push(TileEffect {
+                    position: Position { x, y },
+                    kind,
+                    strength,
+                });
+            }
+        }
+    }
+    for cloud in drifted {
+        app.gamemap.add_effect(cloud);
+    }
+}
+
+/// poisons everything standing in poison gas, then hurts every fighter that's poisoned
+fn poison_fighters(app: &mut App) {
+    let mut clouds: Vec<Position> = app
+        .gamemap
+        .effects
+        .iter()
+        .filter(|effect| effect.kind == EffectKind::Poison)
+        .map(|effect| effect.position)
+        .collect();
+    clouds.sort_by_key(|pos| (pos.y, pos.x));
+    for pos in clouds {
+        let Some(id) = app.gamemap.get_ref(pos.x, pos.y).blocker else {
+            continue;
+        };
+        if app.objects.get(&id).unwrap().fighter.is_some() {
+            apply_status(app, id, StatusKind::Poisoned, POISON_DURATION);
+        }
+    }
+
+    for (id, pos) in fighters_with_status(app, StatusKind::Poisoned) {
+        if app.gamemap.get_position(id) != Some(pos) {
+            continue;
+        }
+        let amount = POISON_DAMAGE.roll(&mut app.rng) as u16;
+        let target_name = app.objects.get(&id).unwrap().name.clone();
+        app.emit(GameEvent::HazardDamage {
+            target: id,
+            target_name,
+            source: DamageSource::Poison,
+            amount,
+            witnessed: id == PLAYER || app.gamemap.is_visible(pos.x, pos.y),
+        });
+        take_damage(app, id, amount, DamageSource::Poison);
+    }
+}
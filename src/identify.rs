@@ -0,0 +1,222 @@
+// potions and scrolls look different every run, and the player has to figure out
+// which is which by using them or reading a scroll of identify
+
+use std::collections::{HashMap, HashSet};
+
+use rand::{Rng, seq::SliceRandom};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{App, MessageKind},
+    components::{Item, Renderable},
+};
+
+/// kinds of potions that get a random appearance each run
+const POTIONS: [Item; 2] = [Item::Heal, Item::Haste];
+/// kinds of scrolls that get a random label each run
+const SCROLLS: [Item; 8] = [
+    Item::Lightning,
+    Item::Fireball,
+    Item::Hexbolt,
+    Item::Charm,
+    Item::TimeWarp,
+    Item::Enchant,
+    Item::Identify,
+    Item::RemoveCurse,
+];
+
+const POTION_APPEARANCES: [(&str, Color); 8] = [
+    ("swirly", Color::LightMagenta),
+    ("bubbling", Color::LightGreen),
+    ("murky", Color::Yellow),
+    ("fizzy", Color::LightCyan),
+    ("cloudy", Color::White),
+    ("glowing", Color::LightYellow),
+    ("smoky", Color::Gray),
+    ("crimson", Color::Red),
+];
+const SCROLL_LABELS: [&str; 10] = [
+    "ZELGO MER",
+    "FOOBIE BLETCH",
+    "XIXAXA",
+    "ELBIB YLOH",
+    "VENZAR BORGAVVE",
+    "KERNOD WEL",
+    "DAIYEN FOOELS",
+    "PRATYAVAYAH",
+    "NR 9",
+    "TEMOV",
+];
+const SCROLL_COLOR: Color = Color::White;
+
+pub fn is_potion(item: &Item) -> bool {
+    POTIONS.contains(item)
+}
+
+pub fn is_scroll(item: &Item) -> bool {
+    SCROLLS.contains(item)
+}
+
+/// what an unidentified potion or scroll looks like
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Appearance {
+    pub name: String,
+    pub color: Color,
+}
+
+/// the appearances of every potion and scroll for this run, and which of them
+/// the player has identified
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Identification {
+    appearances: HashMap<Item, Appearance>,
+    identified: HashSet<Item>,
+}
+
+impl Identification {
+    /// shuffles the appearances between the kinds of potions and scrolls
+    pub fn new(rng: &mut impl Rng) -> Self {
+        let mut appearances = HashMap::new();
+
+        let mut potions = POTION_APPEARANCES.to_vec();
+        potions.shuffle(rng);
+        for (item, (adjective, color)) in POTIONS.into_iter().zip(potions) {
+            let name = format!("{} potion", adjective);
+            appearances.insert(item, Appearance { name, color });
+        }
+
+        let mut labels = SCROLL_LABELS.to_vec();
+        labels.shuffle(rng);
+        for (item, label) in SCROLLS.into_iter().zip(labels) {
+            let name = format!("scroll labeled {}", label);
+            let color = SCROLL_COLOR;
+            appearances.insert(item, Appearance { name, color });
+        }
+
+        Self {
+            appearances,
+            identified: HashSet::new(),
+        }
+    }
+
+    /// returns what the kind of item looks like, if the player hasn't identified it yet.
+    /// saves from before identification existed have no appearances, so everything is known
+    pub fn unidentified_appearance(&self, item: &Item) -> Option<&Appearance> {
+        if self.identified.contains(item) {
+            return None;
+        }
+        self.appearances.get(item)
+    }
+
+    /// marks a kind of item as identified. returns true if it wasn't known before
+    pub fn identify(&mut self, item: &Item) -> bool {
+        self.unidentified_appearance(item).is_some() && self.identified.insert(item.clone())
+    }
+}
+
+/// returns the name of an object as the player knows it
+pub fn item_name(app: &App, id: usize) -> String {
+    let obj = app.objects.get(&id).unwrap();
+    if let Some(appearance) = obj
+        .item
+        .as_ref()
+        .and_then(|item| app.identification.unidentified_appearance(item))
+    {
+        return appearance.name.clone();
+    }
+
+    let name = match &obj.equipment {
+        Some(equip) if equip.enchantment != 0 && !equip.unidentified => {
+            format!("{:+} {}", equip.enchantment, obj.name)
+        }
+        _ => obj.name.clone(),
+    };
+    match obj.charges {
+        Some(1) => format!("{} (1 charge)", name),
+        Some(charges) => format!("{} ({} charges)", name, charges),
+        None => name,
+    }
+}
+
+/// returns the name of an object as the player knows it, along with the size of its stack
+pub fn stack_name(app: &App, id: usize) -> String {
+    let name = item_name(app, id);
+    match app.objects.get(&id).unwrap().stack_count {
+        Some(count) if count > 1 => format!("{} (x{})", name, count),
+        _ => name,
+    }
+}
+
+/// returns how an object looks on the map, hiding the color of unidentified potions
+pub fn item_renderable(app: &App, id: usize) -> Renderable {
+    let obj = app.objects.get(&id).unwrap();
+    let mut renderable = obj.renderable.clone();
+    if let Some(appearance) = obj
+        .item
+        .as_ref()
+        .and_then(|item| app.identification.unidentified_appearance(item))
+    {
+        renderable.fg = appearance.color;
+        renderable.role = None;
+    }
+    renderable
+}
+
+/// identifies a potion or scroll, logging what it was.
+/// equipment has its enchantment and curse revealed instead.
+/// returns false if there was nothing left to learn about the object
+pub fn identify_object(app: &mut App, id: usize) -> bool {
+    let old_name = item_name(app, id);
+    let obj = app.objects.get_mut(&id).unwrap();
+
+    if let Some(equip) = obj.equipment.as_mut() {
+        if !std::mem::replace(&mut equip.unidentified, false) {
+            return false;
+        }
+        let cursed = equip.cursed;
+        let enchanted = equip.enchantment != 0;
+        let new_name = item_name(app, id);
+        if cursed {
+            app.add_to_log(
+                format!("Your {} feels cold. It is a cursed {}!", old_name, new_name),
+                Color::Red,
+                MessageKind::Items,
+            );
+        } else if enchanted {
+            app.add_to_log(
+                format!("Your {} is a {}.", old_name, new_name),
+                Color::LightCyan,
+                MessageKind::Items,
+            );
+        }
+        return true;
+    }
+
+    let Some(item) = obj.item.clone() else {
+        return false;
+    };
+    if !app.identification.identify(&item) {
+        return false;
+    }
+    let new_name = item_name(app, id);
+    app.add_to_log(
+        format!("The {} is a {}.", old_name, new_name),
+        Color::LightCyan,
+        MessageKind::Items,
+    );
+    true
+}
+
+/// returns true if the player doesn't know everything about the object yet
+pub fn is_unidentified(app: &App, id: usize) -> bool {
+    let obj = app.objects.get(&id).unwrap();
+    let unknown_kind = obj
+        .item
+        .as_ref()
+        .is_some_and(|item| app.identification.unidentified_appearance(item).is_some());
+    let unknown_gear = obj
+        .equipment
+        .as_ref()
+        .is_some_and(|equip| equip.unidentified);
+    unknown_kind || unknown_gear
+}
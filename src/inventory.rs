@@ -1,53 +1,296 @@
 use ratatui::style::Color;
 
 use crate::{
-    app::{App, INVENTORY_SIZE, PLAYER},
-    components::{Item, Object, Position},
-    engine::UseResult,
+    app::{App, MessageKind, PLAYER},
+    components::{Item, Object, Position, Slot},
+    engine::{UseResult, known_item},
+    events::GameEvent,
+    identify, shop,
 };
 
+/// picks up the item at a tile the player just walked onto,
+/// if auto pickup is on and wants that kind of item
+pub fn auto_pickup(app: &mut App, pos: Position) {
+    let settings = &app.settings.auto_pickup;
+    if !settings.enabled {
+        return;
+    }
+    let Some(id) = known_item(app, pos.x, pos.y) else {
+        return;
+    };
+
+    let obj = app.objects.get(&id).unwrap();
+    let wanted = match &obj.item {
+        Some(Item::Gold) => true,
+        Some(item) if identify::is_potion(item) => settings.potions,
+        Some(item) if identify::is_scroll(item) => settings.scrolls,
+        _ => false,
+    };
+    // a full pack would complain on every step, so leave the item be
+    if wanted && (obj.gold.is_some() || has_room_for(app, id)) {
+        pick_item_up(app, id);
+    }
+}
+
 /// moves and item from the gamemap into the player inventory based on object id
 pub fn pick_item_up(app: &mut App, id: usize) {
-    if app.inventory.len() >= INVENTORY_SIZE {
-        app.add_to_log(format!("Cannot hold that many items."), Color::default());
+    // gold goes straight into the player's purse, and doesn't take up a slot
+    if let Some(amount) = app.objects.get(&id).unwrap().gold {
+        let item_pos = app.gamemap.get_position(id).unwrap();
+        app.gamemap.remove_item(item_pos.x, item_pos.y);
+        app.objects.get_contents().remove(&id);
+        shop::add_player_gold(app, amount);
+        app.gamemap.stats.items_found += 1;
+        app.emit(GameEvent::GoldPickedUp { amount });
+        return;
+    }
+
+    // traps and stairs sit in the item slot of a tile, but can't be carried
+    if app.objects.get(&id).unwrap().item.is_none() {
+        app.add_to_log(
+            "There is nothing here to pick up.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return;
+    }
+
+    if !has_room_for(app, id) {
+        app.add_to_log(
+            "Cannot hold that many items.".to_string(),
+            Color::default(),
+            MessageKind::Items,
+        );
     } else {
         // remove it from the map
         let item_pos = app.gamemap.get_position(id).unwrap();
         app.gamemap.remove_item(item_pos.x, item_pos.y);
 
-        // add the item to the inventory
-        app.inventory.push(id);
-
         // print a message to log
-        let item_obj = app.objects.get(&id).unwrap();
-        let message = format!("Picked up {}.", item_obj.name);
-        app.add_to_log(message, Color::default());
+        let name = identify::item_name(app, id);
+        app.emit(GameEvent::ItemPickedUp { id, name });
+
+        // add the item to the inventory. corpses aren't loot, so they aren't counted
+        let is_corpse = app.objects.get(&id).unwrap().corpse.is_some();
+        add_to_inventory(app, id);
+        if !is_corpse {
+            app.gamemap.stats.items_found += 1;
+        }
+    }
+}
+
+/// returns the inventory index of a stack that the object can merge into, if there is one
+fn find_stack(app: &App, id: usize) -> Option<usize> {
+    let obj = app.objects.get(&id).unwrap();
+    app.inventory.iter().position(|&other_id| {
+        other_id != id && app.objects.get(&other_id).unwrap().stacks_with(obj)
+    })
+}
+
+/// returns true if the object fits in the inventory, either in a stack or a free slot
+pub fn has_room_for(app: &App, id: usize) -> bool {
+    app.inventory.len() < app.settings.inventory_size || find_stack(app, id).is_some()
+}
+
+/// adds an object into the inventory, merging it into a matching stack if there is one.
+/// should only be called after checking has_room_for()
+pub fn add_to_inventory(app: &mut App, id: usize) {
+    match find_stack(app, id) {
+        Some(inventory_idx) => {
+            let merged = app.objects.get_contents().remove(&id).unwrap();
+            let stack_id = app.inventory[inventory_idx];
+            let stack = app.objects.get_mut(&stack_id).unwrap();
+            stack.stack_count = Some(stack.stack_count.unwrap() + merged.stack_count.unwrap());
+        }
+        None => {
+            app.inventory.push(id);
+        }
+    }
+}
+
+/// returns the number of items in a stack, which is 1 for items that don't stack
+pub fn stack_count(app: &App, id: usize) -> u32 {
+    app.objects.get(&id).unwrap().stack_count.unwrap_or(1)
+}
+
+/// how much weight the player can carry before being slowed down
+const BASE_CAPACITY: u32 = 30;
+/// each point of the player's own power, not counting equipment, adds this much capacity
+const CAPACITY_PER_POWER: u32 = 10;
+
+/// how weighed down the player is by everything they carry
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Encumbrance {
+    Unburdened,
+    /// carrying more than their capacity
+    Burdened,
+    /// carrying more than one and a half times their capacity
+    Overloaded,
+}
+
+impl std::fmt::Display for Encumbrance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encumbrance::Unburdened => write!(f, "unburdened"),
+            Encumbrance::Burdened => write!(f, "burdened"),
+            Encumbrance::Overloaded => write!(f, "overloaded"),
+        }
+    }
+}
+
+/// returns the total weight of the player's inventory and worn equipment
+pub fn carried_weight(app: &App) -> u32 {
+    app.inventory
+        .iter()
+        .chain(app.equipment.iter().flatten())
+        .map(|&id| app.objects.get(&id).unwrap().weight * stack_count(app, id))
+        .sum()
+}
+
+/// returns how much the player can carry without being slowed down
+pub fn carry_capacity(app: &App) -> u32 {
+    let power = app
+        .objects
+        .get(&PLAYER)
+        .unwrap()
+        .fighter
+        .as_ref()
+        .unwrap()
+        .power;
+    BASE_CAPACITY + CAPACITY_PER_POWER * power.max(0) as u32
+}
+
+pub fn encumbrance(app: &App) -> Encumbrance {
+    let (weight, capacity) = (carried_weight(app), carry_capacity(app));
+    if weight <= capacity {
+        Encumbrance::Unburdened
+    } else if weight * 2 <= capacity * 3 {
+        Encumbrance::Burdened
+    } else {
+        Encumbrance::Overloaded
+    }
+}
+
+/// reports it if the player's load got heavier or lighter than it was before
+pub fn announce_encumbrance(app: &mut App, before: Encumbrance) {
+    let after = encumbrance(app);
+    if after != before {
+        app.emit(GameEvent::EncumbranceChanged { before, after });
+    }
+}
+
+/// creates a copy of a stacked item with a count of 1, without changing the original stack.
+/// returns the id of the new object
+fn split_one(app: &mut App, id: usize) -> usize {
+    let mut single = app.objects.get(&id).unwrap().clone();
+    single.stack_count = Some(1);
+    app.objects.add(single)
+}
+
+/// removes a single item from a slot in the inventory, splitting it off of a stack if needed.
+/// returns the id of the removed item
+pub fn take_one_from_inventory(app: &mut App, inventory_idx: usize) -> usize {
+    let id = app.inventory[inventory_idx];
+    let count = stack_count(app, id);
+    if count > 1 {
+        let single = split_one(app, id);
+        app.objects.get_mut(&id).unwrap().stack_count = Some(count - 1);
+        single
+    } else {
+        app.inventory.remove(inventory_idx)
     }
 }
 
 /// drops an item from the inventory back onto the ground
 pub fn drop_item(app: &mut App, inventory_idx: usize) {
     if inventory_idx >= app.inventory.len() {
-        app.add_to_log("No item to drop.", Color::default());
+        app.add_to_log("No item to drop.", Color::default(), MessageKind::Items);
         return;
     }
 
+    // only a single item is dropped from a stack
+    let id = app.inventory[inventory_idx];
+    let is_stacked = stack_count(app, id) > 1;
+    let drop_id = if is_stacked { split_one(app, id) } else { id };
+
     // attempt to drop the item at the player
     let pos = app.gamemap.get_position(PLAYER).unwrap();
-    let id = app.inventory[inventory_idx];
-    let drop_loc = app.gamemap.area_place_item(pos.x, pos.y, id);
+    let drop_loc = app
+        .gamemap
+        .area_place_item(pos.x, pos.y, drop_id, &mut app.rng);
 
     match drop_loc {
         Some(_) => {
             // succesfully dropped it, remove it from inventory
-            let item = app.objects.get(&id).unwrap();
-            app.add_to_log(format!("Dropped {}.", item.name), Color::default());
-            app.inventory.remove(inventory_idx);
+            let name = identify::item_name(app, drop_id);
+            app.emit(GameEvent::ItemDropped { id: drop_id, name });
+            if is_stacked {
+                let stack = app.objects.get_mut(&id).unwrap();
+                stack.stack_count = stack.stack_count.map(|count| count - 1);
+            } else {
+                app.inventory.remove(inventory_idx);
+            }
         }
         None => {
-            app.add_to_log("No space to drop item.", Color::default());
+            if is_stacked {
+                app.objects.get_contents().remove(&drop_id);
+            }
+            app.add_to_log(
+                "No space to drop item.",
+                Color::default(),
+                MessageKind::Items,
+            );
+        }
+    }
+}
+
+/// returns how long it takes to put on or take off a piece of equipment
+pub fn equip_time(app: &App, id: usize) -> u64 {
+    let obj = app.objects.get(&id).unwrap();
+    match &obj.equipment {
+        Some(equip) => equip.equip_time,
+        None => panic!("equip_time() called, but object does not have an equipment component!"),
+    }
+}
+
+/// returns the index of the equipment slot that a piece of equipment goes in
+pub fn equip_slot(app: &App, id: usize) -> usize {
+    let obj = app.objects.get(&id).unwrap();
+    match &obj.equipment {
+        Some(equip) => equip.slot as usize,
+        None => panic!("equip_slot() called, but object does not have an equipment component!"),
+    }
+}
+
+/// returns true if the player is wielding a weapon that takes both hands
+pub fn wielding_two_handed(app: &App) -> bool {
+    app.equipment[Slot::Weapon as usize].is_some_and(|weapon| {
+        let obj = app.objects.get(&weapon).unwrap();
+        obj.equipment.as_ref().unwrap().two_handed
+    })
+}
+
+/// returns the equipment slots a piece of equipment needs free to be worn. a two-handed
+/// weapon needs the off hand too, and a shield can't be held next to one
+pub fn needed_slots(app: &App, id: usize) -> Vec<usize> {
+    let equip = app.objects.get(&id).unwrap().equipment.as_ref().unwrap();
+    let mut slots = vec![equip.slot as usize];
+    if equip.two_handed || (matches!(equip.slot, Slot::OffHand) && wielding_two_handed(app)) {
+        for slot in [Slot::Weapon as usize, Slot::OffHand as usize] {
+            if !slots.contains(&slot) {
+                slots.push(slot);
+            }
         }
     }
+    slots
+}
+
+/// returns the worn equipment that would have to come off to wear a piece of equipment
+pub fn displaced_equipment(app: &App, id: usize) -> Vec<usize> {
+    needed_slots(app, id)
+        .into_iter()
+        .filter_map(|slot| app.equipment[slot])
+        .collect()
 }
 
 /// returns the item for a given index in the inventory
@@ -62,6 +305,7 @@ pub fn get_item_in_inventory(app: &App, inventory_idx: usize) -> &Item {
 }
 
 /// returns the object for a given index in the inventory
+#[allow(dead_code)]
 pub fn get_object_in_inventory(app: &App, inventory_idx: usize) -> &Object {
     let item_id = app.inventory[inventory_idx];
     match app.objects.get(&item_id) {
@@ -72,6 +316,71 @@ pub fn get_object_in_inventory(app: &App, inventory_idx: usize) -> &Object {
     }
 }
 
+/// spends a charge of the item at the index in the inventory. returns false if it had
+/// none to spare, so it's used up entirely
+fn spend_charge(app: &mut App, inventory_idx: usize) -> bool {
+    let obj = app.objects.get_mut(&app.inventory[inventory_idx]).unwrap();
+    match obj.charges {
+        Some(charges) if charges > 1 => {
+            obj.charges = Some(charges - 1);
+            true
+        }
+        Some(_) => {
+            let event = GameEvent::ItemCrumbled {
+                id: app.inventory[inventory_idx],
+                name: obj.name.clone(),
+            };
+            app.emit(event);
+            false
+        }
+        None => false,
+    }
+}
+
+/// takes off the item in an equipment slot and drops it at the player's feet.
+/// returns the item, or None if it couldn't come off
+pub fn drop_equipped(app: &mut App, slot: usize) -> Option<usize> {
+    let id = app.equipment[slot]?;
+    let name = identify::item_name(app, id);
+    if app
+        .objects
+        .get(&id)
+        .unwrap()
+        .equipment
+        .as_ref()
+        .unwrap()
+        .cursed
+    {
+        app.add_to_log(
+            format!("Cannot unequip: your {} is cursed!", name),
+            Color::Red,
+            MessageKind::Items,
+        );
+        return None;
+    }
+
+    let pos = app.gamemap.get_position(PLAYER).unwrap();
+    if app
+        .gamemap
+        .area_place_item(pos.x, pos.y, id, &mut app.rng)
+        .is_none()
+    {
+        app.add_to_log(
+            "No space to drop item.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return None;
+    }
+    app.equipment[slot] = None;
+    app.emit(GameEvent::Unequipped {
+        id,
+        name: name.clone(),
+    });
+    app.emit(GameEvent::ItemDropped { id, name });
+    Some(id)
+}
+
 /// uses an item from the specified index in the inventory
 pub fn use_item(app: &mut App, inventory_idx: usize, target: Option<Position>) -> UseResult {
     let item = get_item_in_inventory(app, inventory_idx).clone();
@@ -79,36 +388,89 @@ pub fn use_item(app: &mut App, inventory_idx: usize, target: Option<Position>) -
 
     match use_result {
         UseResult::UsedUp => {
-            // delete item after being used
-            app.inventory.remove(inventory_idx);
+            app.conduct.record_use(&item);
+            // using up a potion or scroll shows the player what it was
+            identify::identify_object(app, app.inventory[inventory_idx]);
+            // wands only crumble away once their last charge is spent.
+            // otherwise, delete item after being used, only taking one off of a stack
+            if !spend_charge(app, inventory_idx) {
+                take_one_from_inventory(app, inventory_idx);
+            }
         }
         UseResult::Cancelled => {
             // item wasn't used, don't delete it
         }
+        UseResult::Kept(_) => {
+            // tools and wands with charges left stay in the inventory
+        }
         UseResult::Equipped => {
-            // try to equip item by moving it from the inventory to the equipment slot
+            // equip the item by moving it from the inventory to the equipment slot
+            let id = app.inventory[inventory_idx];
+            let displaced = displaced_equipment(app, id);
 
-            // get the index that this item is supposed to be equipped in
-            let obj = get_object_in_inventory(app, inventory_idx);
-            let equip = obj.equipment.as_ref().unwrap();
-            let equip_idx = equip.slot as usize;
-
-            // check if the slot is empty or not
-            if app.equipment[equip_idx].is_some() {
+            for &old_id in &displaced {
+                let old = app.objects.get(&old_id).unwrap();
+                if old.equipment.as_ref().unwrap().cursed {
+                    let old_name = identify::item_name(app, old_id);
+                    app.add_to_log(
+                        format!("Cannot swap: your {} is cursed!", old_name),
+                        Color::Red,
+                        MessageKind::Items,
+                    );
+                    return UseResult::Cancelled;
+                }
+            }
+            // the new item frees up one inventory slot, anything else taken off needs room
+            if app.inventory.len() + displaced.len() > app.settings.inventory_size + 1 {
                 app.add_to_log(
-                    format!("Already have an item equipped on your {}!", equip.slot),
+                    "Cannot swap: not enough space in inventory.",
                     Color::default(),
+                    MessageKind::Items,
                 );
                 return UseResult::Cancelled;
             }
 
-            // if equipment slot isn't empty, equip it
-            app.equipment[equip_idx] = Some(app.inventory[inventory_idx]);
+            for slot in needed_slots(app, id) {
+                app.equipment[slot] = None;
+            }
+            match displaced.split_first() {
+                Some((&first, rest)) => {
+                    // the old item takes the new one's place in the inventory,
+                    // so swapping works even when the inventory is full
+                    app.inventory[inventory_idx] = first;
+                    app.inventory.extend_from_slice(rest);
+                }
+                None => {
+                    app.inventory.remove(inventory_idx);
+                }
+            }
+            for old_id in displaced {
+                let name = identify::item_name(app, old_id);
+                app.emit(GameEvent::Unequipped { id: old_id, name });
+            }
 
-            // remove equipped item from inventory
-            app.inventory.remove(inventory_idx);
+            let equip_idx = equip_slot(app, id);
+            app.equipment[equip_idx] = Some(id);
+            let name = identify::item_name(app, id);
+            app.emit(GameEvent::Equipped { id, name });
+            // wearing equipment reveals its enchantment, and whether it is cursed
+            identify::identify_object(app, id);
         }
     };
 
     use_result
 }
+
+/// uses an item from the specified index in the inventory on another carried or equipped item
+pub fn use_item_on(app: &mut App, inventory_idx: usize, target_id: usize) -> UseResult {
+    let item = get_item_in_inventory(app, inventory_idx).clone();
+    let use_result = item.on_use_on_item(app, target_id);
+
+    if let UseResult::UsedUp = use_result {
+        app.conduct.record_use(&item);
+        identify::identify_object(app, app.inventory[inventory_idx]);
+        take_one_from_inventory(app, inventory_idx);
+    }
+
+    use_result
+}
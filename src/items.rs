@@ -1,12 +1,21 @@
+use rand::Rng;
 use ratatui::style::Color;
 
 use crate::{
-    app::{App, PLAYER},
-    components::{Item, Object, Position, RenderLayer, Renderable},
-    engine::{self, UseResult, damage, defense, heal, take_damage},
+    animation,
+    app::{App, MessageKind, PLAYER},
+    components::{AIType, Item, Object, Position, RenderLayer, Renderable, StatusKind},
+    engine::{
+        self, DamageSource, Dice, UseResult, apply_status, damage, defense, heal, take_damage,
+    },
+    events::{EnchantOutcome, GameEvent},
+    fire,
+    gamemap::{EffectKind, TileType},
+    gas, identify, los,
+    theme::ThemeColor,
 };
 
-/// this file contains consumable items and their associated effects when used
+// this file contains consumable items and tools, and their associated effects when used
 
 const HEAL_AMOUNT: u16 = 10;
 pub fn potion_cure_wounds() -> Object {
@@ -17,10 +26,15 @@ pub fn potion_cure_wounds() -> Object {
         glyph: '!',
         fg: Color::Magenta,
         bg: Color::Reset,
+        role: None,
     };
     let render_layer = RenderLayer::Item;
 
-    Object::new(name, tooltip, renderable, render_layer).set_item(Item::Heal)
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Heal)
+        .set_value(20)
+        .set_weight(2)
+        .set_stackable()
 }
 
 /// effects of a potion of healing. heals the player
@@ -36,19 +50,17 @@ pub fn cast_cure_wounds(app: &mut App) -> UseResult {
         app.add_to_log(
             String::from("You are already at full health."),
             Color::default(),
+            MessageKind::Items,
         );
         UseResult::Cancelled
     } else {
         heal(app, PLAYER, HEAL_AMOUNT);
-        app.add_to_log(
-            String::from("Your wounds start to close."),
-            Color::default(),
-        );
+        app.emit(GameEvent::ItemUsed { item: Item::Heal });
         UseResult::UsedUp
     }
 }
 
-const LIGHTNING_DAMAGE: i16 = 8;
+const LIGHTNING_DAMAGE: Dice = Dice::new(2, 6, 1);
 /// scroll of lightning smites a chosen target within line of sight
 pub fn scroll_lightning() -> Object {
     let name = "scroll of lightning".to_string();
@@ -59,24 +71,37 @@ pub fn scroll_lightning() -> Object {
         glyph: '?',
         fg: Color::Cyan,
         bg: Color::Reset,
+        role: None,
     };
     let render_layer = RenderLayer::Item;
 
-    Object::new(name, tooltip, renderable, render_layer).set_item(Item::Lightning)
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Lightning)
+        .set_value(40)
+        .set_weight(1)
+        .set_stackable()
 }
 
 pub fn cast_lightning(app: &mut App, target: Position) -> UseResult {
     let target_id = match engine::get_smite_target(app, target) {
         Some(x) => {
             if x == PLAYER {
-                app.add_to_log(String::from("Can't target yourself!"), Color::default());
+                app.add_to_log(
+                    String::from("Can't target yourself!"),
+                    Color::default(),
+                    MessageKind::Items,
+                );
                 return UseResult::Cancelled;
             } else {
                 x
             }
         }
         None => {
-            app.add_to_log(String::from("No targets there."), Color::default());
+            app.add_to_log(
+                String::from("No targets there."),
+                Color::default(),
+                MessageKind::Items,
+            );
             return UseResult::Cancelled;
         }
     };
@@ -88,28 +113,31 @@ pub fn cast_lightning(app: &mut App, target: Position) -> UseResult {
         }
     };
 
-    let damage_dealt = damage(LIGHTNING_DAMAGE, defense(app, target_id));
+    let target_pos = app.gamemap.get_position(target_id).unwrap();
+    app.animate(animation::explosion(target_pos, 1, Color::LightCyan));
 
-    let target_obj = app.objects.get(&target_id).unwrap();
-    let attack_desc = format!("Lightning smites the {}", target_obj.name);
+    let target_defense = defense(app, target_id);
+    let damage_dealt = damage(app, LIGHTNING_DAMAGE, target_defense);
 
+    app.emit(GameEvent::ItemHit {
+        item: Item::Lightning,
+        target: target_id,
+        target_name: app.objects.get(&target_id).unwrap().name.clone(),
+        damage: damage_dealt as u16,
+    });
     if damage_dealt > 0 {
-        app.add_to_log(
-            format!("{} for {} damage.", attack_desc, damage_dealt),
-            Color::LightBlue,
-        );
-        take_damage(app, target_id, damage_dealt as u16);
-    } else {
-        app.add_to_log(
-            format!("{} but does no damage.", attack_desc),
-            Color::default(),
+        take_damage(
+            app,
+            target_id,
+            damage_dealt as u16,
+            DamageSource::Spell(String::from("lightning")),
         );
     }
 
     UseResult::UsedUp
 }
 
-const HEXBOLT_DAMAGE: i16 = 5;
+const HEXBOLT_DAMAGE: Dice = Dice::new(2, 4, 0);
 /// scroll of hexbolt fires a projectile in a line
 pub fn scroll_hexbolt() -> Object {
     let name = "scroll of hexbolt".to_string();
@@ -121,24 +149,37 @@ pub fn scroll_hexbolt() -> Object {
         glyph: '?',
         fg: Color::Blue,
         bg: Color::Reset,
+        role: None,
     };
     let render_layer = RenderLayer::Item;
 
-    Object::new(name, tooltip, renderable, render_layer).set_item(Item::Hexbolt)
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Hexbolt)
+        .set_value(25)
+        .set_weight(1)
+        .set_stackable()
 }
 
 pub fn cast_hexbolt(app: &mut App, target: Position) -> UseResult {
     let player_pos = app.gamemap.get_position(PLAYER).unwrap();
     if target == player_pos {
-        app.add_to_log(String::from("Can't target yourself!"), Color::default());
+        app.add_to_log(
+            String::from("Can't target yourself!"),
+            Color::default(),
+            MessageKind::Items,
+        );
         return UseResult::Cancelled;
     }
 
     let targets = engine::get_line_target(app, target);
-    let target_id = match targets.iter().nth(0) {
-        Some(x) => x.clone(),
+    let target_id = match targets.first() {
+        Some(x) => *x,
         None => {
-            app.add_to_log(String::from("No enemies targeted."), Color::default());
+            app.add_to_log(
+                String::from("No enemies targeted."),
+                Color::default(),
+                MessageKind::Items,
+            );
             return UseResult::Cancelled;
         }
     };
@@ -147,37 +188,688 @@ pub fn cast_hexbolt(app: &mut App, target: Position) -> UseResult {
         panic!("trying to cast hexbolt, but target_id does not have a fighter component!")
     }
 
-    let damage_dealt = damage(HEXBOLT_DAMAGE, defense(app, target_id));
+    // the bolt flies from the player up to whatever it hit
+    let hit_pos = app.gamemap.get_position(target_id).unwrap();
+    let path: Vec<Position> = los::LineIter::new(
+        (player_pos.x as i32, player_pos.y as i32),
+        (hit_pos.x as i32, hit_pos.y as i32),
+    )
+    .skip(1)
+    .map(|(x, y)| Position {
+        x: x as u16,
+        y: y as u16,
+    })
+    .collect();
+    app.animate(animation::projectile(&path, '*', Color::Blue));
 
-    let target_obj = app.objects.get(&target_id).unwrap();
-    let attack_desc = format!("The hexbolt blasts the {}", target_obj.name);
+    let target_defense = defense(app, target_id);
+    let damage_dealt = damage(app, HEXBOLT_DAMAGE, target_defense);
 
+    app.emit(GameEvent::ItemHit {
+        item: Item::Hexbolt,
+        target: target_id,
+        target_name: app.objects.get(&target_id).unwrap().name.clone(),
+        damage: damage_dealt as u16,
+    });
     if damage_dealt > 0 {
+        take_damage(
+            app,
+            target_id,
+            damage_dealt as u16,
+            DamageSource::Spell(String::from("hexbolt")),
+        );
+    }
+
+    UseResult::UsedUp
+}
+
+const CHARM_DURATION: u64 = 2000;
+/// scroll of charm monster turns a monster into an ally for a while
+pub fn scroll_charm() -> Object {
+    let name = "scroll of charm monster".to_string();
+    let tooltip = format!(
+        "makes a monster fight by your side for {} turns.",
+        CHARM_DURATION / 100
+    );
+
+    let renderable = Renderable {
+        glyph: '?',
+        fg: Color::LightRed,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Charm)
+        .set_value(45)
+        .set_weight(1)
+        .set_stackable()
+}
+
+pub fn cast_charm(app: &mut App, target: Position) -> UseResult {
+    let target_id = match engine::get_smite_target(app, target) {
+        Some(PLAYER) => {
+            app.add_to_log(
+                String::from("You are already quite taken with yourself."),
+                Color::default(),
+                MessageKind::Items,
+            );
+            return UseResult::Cancelled;
+        }
+        Some(x) if app.objects.get(&x).unwrap().ai.is_some() => x,
+        _ => {
+            app.add_to_log(
+                String::from("There is nothing there to charm."),
+                Color::default(),
+                MessageKind::Items,
+            );
+            return UseResult::Cancelled;
+        }
+    };
+
+    let target_obj = app.objects.get(&target_id).unwrap();
+    let name = target_obj.name.clone();
+    let charmable = target_obj.boss.is_none() && matches!(target_obj.ai, Some(AIType::Melee(_)));
+    let target_pos = app.gamemap.get_position(target_id).unwrap();
+    app.animate(animation::explosion(target_pos, 1, Color::LightRed));
+
+    // the scroll is spent either way, even if the monster shrugs it off
+    if !charmable {
+        app.emit(GameEvent::CharmResisted {
+            id: target_id,
+            name,
+        });
+        return UseResult::UsedUp;
+    }
+
+    // it stops hunting the player, and picks a new target on its next turn
+    engine::set_melee_target(app, target_id, None);
+    apply_status(app, target_id, StatusKind::Charmed, CHARM_DURATION);
+    UseResult::UsedUp
+}
+
+/// how far away the fireball can be aimed
+pub const FIREBALL_RANGE: u16 = 8;
+const FIREBALL_RADIUS: u16 = 1;
+const FIREBALL_DAMAGE: Dice = Dice::new(2, 6, 0);
+/// scroll of fireball blows up a spot in sight, burning everything around it
+pub fn scroll_fireball() -> Object {
+    let name = "scroll of fireball".to_string();
+    let tooltip =
+        "launches a fireball that explodes where it lands, setting everything nearby alight."
+            .to_string();
+
+    let renderable = Renderable {
+        glyph: '?',
+        fg: Color::Red,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Fireball)
+        .set_value(60)
+        .set_weight(1)
+        .set_stackable()
+}
+
+/// effects of a scroll of fireball. hurts every fighter in the blast, including the player
+/// if they're too close, and sets the tiles around the target on fire
+pub fn cast_fireball(app: &mut App, target: Position) -> UseResult {
+    if app.gamemap.get_position(PLAYER) == Some(target) {
         app.add_to_log(
-            format!("{} for {} damage.", attack_desc, damage_dealt),
-            Color::LightBlue,
+            String::from("Can't target yourself!"),
+            Color::default(),
+            MessageKind::Items,
         );
-        take_damage(app, target_id, damage_dealt as u16);
-    } else {
+        return UseResult::Cancelled;
+    }
+
+    app.animate(animation::explosion(
+        target,
+        FIREBALL_RADIUS,
+        Color::LightRed,
+    ));
+    app.emit(GameEvent::ItemUsed {
+        item: Item::Fireball,
+    });
+
+    let radius = FIREBALL_RADIUS as i16;
+    let mut blast = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let (x, y) = (target.x as i16 + dx, target.y as i16 + dy);
+            if app.gamemap.in_bounds(x, y) && app.gamemap.get_ref(x as u16, y as u16).is_walkable()
+            {
+                blast.push(Position {
+                    x: x as u16,
+                    y: y as u16,
+                });
+            }
+        }
+    }
+
+    for pos in blast.iter() {
+        let Some(id) = app.gamemap.get_ref(pos.x, pos.y).blocker else {
+            continue;
+        };
+        if app.objects.get(&id).unwrap().fighter.is_none() {
+            continue;
+        }
+        let target_defense = defense(app, id);
+        let damage_dealt = damage(app, FIREBALL_DAMAGE, target_defense) as u16;
+        app.emit(GameEvent::ItemHit {
+            item: Item::Fireball,
+            target: id,
+            target_name: app.objects.get(&id).unwrap().name.clone(),
+            damage: damage_dealt,
+        });
+        take_damage(
+            app,
+            id,
+            damage_dealt,
+            DamageSource::Spell(String::from("fireball")),
+        );
+        // whatever survives the blast is left burning
+        if app.gamemap.get_position(id).is_some() {
+            fire::set_alight(app, id);
+        }
+    }
+
+    for pos in blast {
+        fire::ignite(app, pos.x, pos.y);
+    }
+
+    UseResult::UsedUp
+}
+
+const HASTE_DURATION: u64 = 1000;
+/// potion of haste makes the player act faster for a while
+pub fn potion_haste() -> Object {
+    let name = "potion of haste".to_string();
+    let tooltip = format!(
+        "halves the time your actions take for {} turns.",
+        HASTE_DURATION / 100
+    );
+
+    let renderable = Renderable {
+        glyph: '!',
+        fg: Color::LightCyan,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Haste)
+        .set_value(35)
+        .set_weight(2)
+        .set_stackable()
+}
+
+pub fn cast_haste(app: &mut App) -> UseResult {
+    apply_status(app, PLAYER, StatusKind::Haste, HASTE_DURATION);
+    UseResult::UsedUp
+}
+
+const TIME_WARP_DURATION: u64 = 800;
+/// scroll of time warp slows down every visible monster
+pub fn scroll_time_warp() -> Object {
+    let name = "scroll of time warp".to_string();
+    let tooltip = format!(
+        "slows every monster in sight for {} turns.",
+        TIME_WARP_DURATION / 100
+    );
+
+    let renderable = Renderable {
+        glyph: '?',
+        fg: Color::LightCyan,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::TimeWarp)
+        .set_value(45)
+        .set_weight(1)
+        .set_stackable()
+}
+
+pub fn cast_time_warp(app: &mut App) -> UseResult {
+    let targets = engine::visible_monsters(app);
+    if targets.is_empty() {
         app.add_to_log(
-            format!("{} but does no damage.", attack_desc),
+            String::from("There is nothing to warp."),
             Color::default(),
+            MessageKind::Combat,
         );
+        return UseResult::Cancelled;
+    }
+
+    app.emit(GameEvent::ItemUsed {
+        item: Item::TimeWarp,
+    });
+    for id in targets {
+        apply_status(app, id, StatusKind::Slow, TIME_WARP_DURATION);
     }
 
     UseResult::UsedUp
 }
 
-pub fn scroll_fireball() -> Object {
-    let name = "scroll of fireball".to_string();
-    let tooltip = "lauches a massive fireball at an enemy, causing an explosion".to_string();
+/// enchantment level up to which enchanting always succeeds
+const ENCHANT_SAFE_LEVEL: i16 = 2;
+/// how much the success chance drops for each level above the safe level
+const ENCHANT_FAIL_STEP: f64 = 0.2;
+const ENCHANT_MIN_CHANCE: f64 = 0.1;
+/// chance that the scroll backfires and curses the item instead
+const ENCHANT_CURSE_CHANCE: f64 = 0.05;
+/// scroll of enchant equipment permanently improves a weapon or piece of armor
+pub fn scroll_enchant() -> Object {
+    let name = "scroll of enchant equipment".to_string();
+    let tooltip = String::from(
+        "adds +1 to a weapon's power or an armor's defense. highly enchanted items may resist.",
+    );
 
     let renderable = Renderable {
         glyph: '?',
-        fg: Color::Red,
+        fg: Color::LightMagenta,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Enchant)
+        .set_value(50)
+        .set_weight(1)
+        .set_stackable()
+}
+
+/// returns the chance that enchanting an item at the given enchantment level succeeds
+fn enchant_chance(enchantment: i16) -> f64 {
+    let excess = (enchantment - ENCHANT_SAFE_LEVEL).max(0) as f64;
+    (1.0 - excess * ENCHANT_FAIL_STEP).max(ENCHANT_MIN_CHANCE)
+}
+
+pub fn cast_enchant(app: &mut App, target_id: usize) -> UseResult {
+    let Some(equip) = &app.objects.get(&target_id).unwrap().equipment else {
+        app.add_to_log(
+            String::from("That can't be enchanted."),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    };
+
+    let chance = enchant_chance(equip.enchantment);
+    let cursed = app.rng.random_bool(ENCHANT_CURSE_CHANCE);
+    let succeeded = !cursed && app.rng.random_bool(chance);
+
+    let name = identify::item_name(app, target_id);
+    let equip = app
+        .objects
+        .get_mut(&target_id)
+        .unwrap()
+        .equipment
+        .as_mut()
+        .unwrap();
+
+    let mut was_cursed = false;
+    let outcome = if cursed {
+        equip.enchantment -= 1;
+        equip.cursed = true;
+        EnchantOutcome::Cursed
+    } else if succeeded {
+        equip.enchantment += 1;
+        was_cursed = std::mem::replace(&mut equip.cursed, false);
+        EnchantOutcome::Succeeded
+    } else {
+        EnchantOutcome::Fizzled
+    };
+
+    app.emit(GameEvent::Enchanted {
+        id: target_id,
+        name,
+        outcome,
+    });
+    if was_cursed {
+        app.emit(GameEvent::CurseLifted { id: target_id });
+    }
+
+    UseResult::UsedUp
+}
+
+/// scroll of identify reveals what an item is
+pub fn scroll_identify() -> Object {
+    let name = "scroll of identify".to_string();
+    let tooltip = String::from(
+        "reveals what kind of potion or scroll an item is, or the enchantment of equipment.",
+    );
+
+    let renderable = Renderable {
+        glyph: '?',
+        fg: Color::LightBlue,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Identify)
+        .set_value(20)
+        .set_weight(1)
+        .set_stackable()
+}
+
+pub fn cast_identify(app: &mut App, target_id: usize) -> UseResult {
+    if !identify::identify_object(app, target_id) {
+        app.add_to_log(
+            String::from("You already know what that is."),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    }
+    UseResult::UsedUp
+}
+
+/// scroll of remove curse lifts the curse from everything the player carries
+pub fn scroll_remove_curse() -> Object {
+    let name = "scroll of remove curse".to_string();
+    let tooltip = String::from("lifts the curse from all carried and worn equipment.");
+
+    let renderable = Renderable {
+        glyph: '?',
+        fg: Color::White,
         bg: Color::Reset,
+        role: None,
     };
     let render_layer = RenderLayer::Item;
 
-    Object::new(name, tooltip, renderable, render_layer).set_item(Item::Fireball)
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::RemoveCurse)
+        .set_value(40)
+        .set_weight(1)
+        .set_stackable()
+}
+
+pub fn cast_remove_curse(app: &mut App) -> UseResult {
+    let carried: Vec<usize> = app
+        .inventory
+        .iter()
+        .copied()
+        .chain(app.equipment.iter().flatten().copied())
+        .collect();
+
+    let mut lifted = false;
+    for id in carried {
+        if let Some(equip) = &mut app.objects.get_mut(&id).unwrap().equipment {
+            lifted |= std::mem::replace(&mut equip.cursed, false);
+        }
+    }
+
+    app.emit(GameEvent::CursesRemoved { lifted });
+    UseResult::UsedUp
+}
+
+const CORPSE_HEAL_AMOUNT: u16 = 3;
+pub fn eat_corpse(app: &mut App) -> UseResult {
+    app.emit(GameEvent::ItemUsed { item: Item::Corpse });
+    heal(app, PLAYER, CORPSE_HEAL_AMOUNT);
+    UseResult::UsedUp
+}
+
+/// rope lets the player climb down into a chasm instead of falling
+pub fn rope() -> Object {
+    let name = "rope".to_string();
+    let tooltip =
+        String::from("used up to climb safely down a chasm, instead of taking fall damage.");
+
+    let renderable = Renderable {
+        glyph: '&',
+        fg: Color::Yellow,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Rope)
+        .set_value(15)
+        .set_weight(4)
+        .set_stackable()
+}
+
+/// how long it takes to dig through a wall with a pickaxe
+const PICKAXE_DIG_TIME: u64 = 500;
+
+/// a pickaxe digs through one wall at a time, and never wears out
+pub fn pickaxe() -> Object {
+    let name = "pickaxe".to_string();
+    let tooltip = String::from("digs through a wall next to you. it takes a few turns.");
+
+    let renderable = Renderable {
+        glyph: '(',
+        fg: Color::Gray,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Pickaxe)
+        .set_value(30)
+        .set_weight(10)
+}
+
+pub fn use_pickaxe(app: &mut App, target: Position) -> UseResult {
+    if !app.gamemap.dig(target.x, target.y) {
+        app.add_to_log(
+            "There's no wall there that you can dig through.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    }
+
+    app.emit(GameEvent::ItemUsed {
+        item: Item::Pickaxe,
+    });
+    UseResult::Kept(PICKAXE_DIG_TIME)
+}
+
+/// how many tiles a wand of digging bores through
+pub const DIGGING_RANGE: u16 = 8;
+const WAND_OF_DIGGING_CHARGES: u16 = 4;
+
+/// wand of digging bores a tunnel in a straight line, spending a charge each time
+pub fn wand_of_digging() -> Object {
+    let name = "wand of digging".to_string();
+    let tooltip = format!(
+        "bores a tunnel through the rock in a line, up to {DIGGING_RANGE} tiles long. crumbles once its charges are spent."
+    );
+
+    let renderable = Renderable {
+        glyph: '/',
+        fg: Color::Yellow,
+        bg: Color::Reset,
+        role: None,
+    };
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Digging)
+        .set_value(60)
+        .set_weight(1)
+        .set_charges(WAND_OF_DIGGING_CHARGES)
+}
+
+pub fn zap_digging(app: &mut App, target: Position) -> UseResult {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    if target == player_pos {
+        app.add_to_log(
+            String::from("Can't target yourself!"),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    }
+
+    // the beam carries on past the target out to the wand's full range
+    let (dx, dy) = (
+        target.x as i32 - player_pos.x as i32,
+        target.y as i32 - player_pos.y as i32,
+    );
+    let steps = dx.abs().max(dy.abs());
+    let range = DIGGING_RANGE as i32;
+    let end = (
+        player_pos.x as i32 + dx * range / steps,
+        player_pos.y as i32 + dy * range / steps,
+    );
+
+    let mut path = Vec::new();
+    let mut dug = 0;
+    for (x, y) in los::LineIter::new((player_pos.x as i32, player_pos.y as i32), end).skip(1) {
+        if !app.gamemap.in_bounds(x as i16, y as i16) {
+            break;
+        }
+        let (x, y) = (x as u16, y as u16);
+        let tile_type = app.gamemap.get_ref(x, y).tile_type;
+        if app.gamemap.dig(x, y) {
+            dug += 1;
+        } else if tile_type == TileType::Wall {
+            // the edge of the map is too hard to dig through
+            break;
+        }
+        path.push(Position { x, y });
+    }
+    app.animate(animation::projectile(&path, '*', Color::Yellow));
+
+    app.emit(GameEvent::TunnelDug { tiles: dug });
+    UseResult::UsedUp
+}
+
+/// how far a flask can be thrown
+pub const THROW_RANGE: u16 = 6;
+const POISON_GAS_STRENGTH: u16 = 8;
+const SMOKE_STRENGTH: u16 = 10;
+/// how far away monsters hear a thrown flask shatter
+const SHATTER_NOISE_RADIUS: u32 = 8;
+
+/// flask of poison gas shatters where it lands, releasing a cloud that poisons anything inside
+pub fn flask_of_poison_gas() -> Object {
+    let name = "flask of poison gas".to_string();
+    let tooltip = format!(
+        "thrown up to {THROW_RANGE} tiles. shatters into a cloud of gas that poisons anything breathing it."
+    );
+
+    let renderable = Renderable::themed('!', ThemeColor::Gas);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::PoisonGas)
+        .set_value(40)
+        .set_weight(1)
+        .set_stackable()
+}
+
+/// smoke bomb bursts into a thick cloud that nothing can see through
+pub fn smoke_bomb() -> Object {
+    let name = "smoke bomb".to_string();
+    let tooltip = format!(
+        "thrown up to {THROW_RANGE} tiles. bursts into a cloud of smoke that blocks line of sight."
+    );
+
+    let renderable = Renderable::themed('!', ThemeColor::Smoke);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Smoke)
+        .set_value(30)
+        .set_weight(1)
+        .set_stackable()
+}
+
+/// throws a flask towards the target. it flies until it hits a monster or a wall,
+/// and breaks open there, releasing a cloud of gas
+pub fn throw_flask(app: &mut App, target: Position, kind: EffectKind) -> UseResult {
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    if target == player_pos {
+        app.add_to_log(
+            String::from("Can't target yourself!"),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    }
+
+    let mut path = Vec::new();
+    for (x, y) in los::LineIter::new(
+        (player_pos.x as i32, player_pos.y as i32),
+        (target.x as i32, target.y as i32),
+    )
+    .skip(1)
+    {
+        let (x, y) = (x as u16, y as u16);
+        let tile = app.gamemap.get_ref(x, y);
+        if !tile.is_walkable() {
+            break;
+        }
+        path.push(Position { x, y });
+        if tile.blocker.is_some() {
+            break;
+        }
+    }
+    let Some(&landed) = path.last() else {
+        app.add_to_log(
+            String::from("There's no room to throw it."),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    };
+    app.animate(animation::projectile(&path, '!', Color::White));
+
+    let (strength, item) = match kind {
+        EffectKind::Smoke => (SMOKE_STRENGTH, Item::Smoke),
+        _ => (POISON_GAS_STRENGTH, Item::PoisonGas),
+    };
+    app.emit(GameEvent::ItemUsed { item });
+    gas::release(app, landed, kind, strength);
+    engine::make_noise(app, landed, SHATTER_NOISE_RADIUS);
+    UseResult::UsedUp
+}
+
+/// vault key opens the locked door of a vault on the floor it was found on
+pub fn vault_key() -> Object {
+    let name = "vault key".to_string();
+    let tooltip = "a heavy iron key. it opens the locked door of a vault somewhere on this floor."
+        .to_string();
+
+    let renderable = Renderable::themed('-', ThemeColor::Gold);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Key)
+        .set_value(0)
+        .set_weight(1)
+}
+
+/// golden idol is what a retrieve quest sends the player after, see quests.rs.
+/// it does nothing, but shops pay well for it
+pub fn golden_idol() -> Object {
+    let name = "golden idol".to_string();
+    let tooltip =
+        "a small idol of solid gold, worn smooth by the hands of worshippers.".to_string();
+
+    let renderable = Renderable::themed('&', ThemeColor::Gold);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Idol)
+        .set_value(150)
+        .set_weight(3)
 }
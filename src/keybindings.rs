@@ -0,0 +1,365 @@
+use crossterm::event::{KeyCode, KeyModifiers};
+
+// this file contains the table of every key the game responds to on the main screen,
+// grouped the way the help screen lists them. the help screen is drawn from this table,
+// so a key added to the game only needs a line here to show up in it. it also has the
+// commands of the command palette, which run by pressing the same keys
+
+/// the groups the help screen lists the keys under
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Category {
+    Moving,
+    Fighting,
+    Items,
+    Talking,
+    Looking,
+    Macros,
+    Screen,
+}
+
+/// every category, in the order the help screen lists them
+pub const CATEGORIES: [Category; 7] = [
+    Category::Moving,
+    Category::Fighting,
+    Category::Items,
+    Category::Talking,
+    Category::Looking,
+    Category::Macros,
+    Category::Screen,
+];
+
+impl Category {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Category::Moving => "moving around",
+            Category::Fighting => "fighting",
+            Category::Items => "items",
+            Category::Talking => "talking",
+            Category::Looking => "looking around",
+            Category::Macros => "macros",
+            Category::Screen => "screen",
+        }
+    }
+}
+
+pub struct KeyBinding {
+    pub category: Category,
+    /// the keys as they're written on the help screen
+    pub keys: &'static str,
+    pub action: &'static str,
+    /// a few of the actual key presses the keys stand for, so a test can check the game
+    /// really answers to them. empty for the lines that aren't keys on the main screen
+    #[allow(dead_code)]
+    pub presses: &'static [(KeyCode, KeyModifiers)],
+}
+
+/// shorthand for a line of the table
+const fn bind(
+    category: Category,
+    keys: &'static str,
+    action: &'static str,
+    presses: &'static [(KeyCode, KeyModifiers)],
+) -> KeyBinding {
+    KeyBinding {
+        category,
+        keys,
+        action,
+        presses,
+    }
+}
+
+const fn code(code: KeyCode) -> (KeyCode, KeyModifiers) {
+    (code, KeyModifiers::NONE)
+}
+
+const fn ch(c: char) -> (KeyCode, KeyModifiers) {
+    (KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+const fn alt(c: char) -> (KeyCode, KeyModifiers) {
+    (KeyCode::Char(c), KeyModifiers::ALT)
+}
+
+const fn ctrl(c: char) -> (KeyCode, KeyModifiers) {
+    (KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+pub const KEY_BINDINGS: &[KeyBinding] = &[
+    bind(
+        Category::Moving,
+        "h j k l / arrows",
+        "move left, down, up, right",
+        &[
+            ch('h'),
+            ch('j'),
+            ch('k'),
+            ch('l'),
+            code(KeyCode::Left),
+            code(KeyCode::Down),
+            code(KeyCode::Up),
+            code(KeyCode::Right),
+        ],
+    ),
+    bind(
+        Category::Moving,
+        "y u b n",
+        "move diagonally",
+        &[ch('y'), ch('u'), ch('b'), ch('n')],
+    ),
+    bind(
+        Category::Moving,
+        "alt + direction",
+        "move without attacking",
+        &[
+            alt('h'),
+            alt('j'),
+            alt('k'),
+            alt('l'),
+            alt('y'),
+            alt('u'),
+            alt('b'),
+            alt('n'),
+        ],
+    ),
+    bind(Category::Moving, ".", "wait a turn", &[ch('.')]),
+    bind(Category::Moving, "R", "rest until healed", &[ch('R')]),
+    bind(Category::Moving, "m", "toggle safe move", &[ch('m')]),
+    bind(
+        Category::Moving,
+        ">",
+        "go down the stairs, or walk to them",
+        &[ch('>')],
+    ),
+    bind(
+        Category::Moving,
+        "<",
+        "climb out with the amulet",
+        &[ch('<')],
+    ),
+    bind(Category::Moving, "w", "mark a waypoint", &[ch('w')]),
+    bind(Category::Moving, "W", "travel to a waypoint", &[ch('W')]),
+    bind(
+        Category::Fighting,
+        "a",
+        "attack an adjacent monster",
+        &[ch('a')],
+    ),
+    bind(Category::Fighting, "s", "toggle sneaking", &[ch('s')]),
+    bind(
+        Category::Fighting,
+        "Y",
+        "yell, drawing monsters to you",
+        &[ch('Y')],
+    ),
+    bind(
+        Category::Fighting,
+        "S",
+        "search for hidden traps",
+        &[ch('S')],
+    ),
+    bind(Category::Fighting, "z", "cast a spell", &[ch('z')]),
+    bind(Category::Items, "g", "pick up an item", &[ch('g')]),
+    bind(Category::Items, "P", "toggle auto pickup", &[ch('P')]),
+    bind(
+        Category::Items,
+        "1-9, 0",
+        "use or equip an inventory item",
+        &[ch('1'), ch('5'), ch('9'), ch('0')],
+    ),
+    bind(
+        Category::Items,
+        "alt + 1-9, 0",
+        "drop an inventory item",
+        &[alt('1'), alt('5'), alt('9'), alt('0')],
+    ),
+    bind(
+        Category::Items,
+        "A-E",
+        "take off equipment",
+        &[ch('A'), ch('C'), ch('E')],
+    ),
+    bind(
+        Category::Items,
+        "alt + A-E",
+        "take off and drop equipment",
+        &[alt('A'), alt('C'), alt('E')],
+    ),
+    bind(Category::Talking, "walk into an npc", "start talking", &[]),
+    bind(Category::Talking, "1-9", "pick a reply", &[]),
+    bind(Category::Looking, "x", "examine a tile", &[ch('x')]),
+    bind(
+        Category::Looking,
+        "tab / shift-tab",
+        "cycle between visible monsters",
+        &[code(KeyCode::Tab), code(KeyCode::BackTab)],
+    ),
+    bind(Category::Looking, "Q", "list this run's quests", &[ch('Q')]),
+    bind(
+        Category::Macros,
+        "ctrl-r",
+        "start or stop recording",
+        &[ctrl('r')],
+    ),
+    bind(
+        Category::Macros,
+        "F1-F12",
+        "bind or play back a macro",
+        &[code(KeyCode::F(1)), code(KeyCode::F(12))],
+    ),
+    bind(
+        Category::Screen,
+        "?",
+        "this list of keys, ? again for the manual",
+        &[ch('?')],
+    ),
+    bind(Category::Screen, ":", "run a command by name", &[ch(':')]),
+    bind(Category::Screen, "O", "change the settings", &[ch('O')]),
+    bind(Category::Screen, "ctrl-l", "full message log", &[ctrl('l')]),
+    bind(
+        Category::Screen,
+        "t",
+        "toggle the turn order strip",
+        &[ch('t')],
+    ),
+    bind(
+        Category::Screen,
+        "d",
+        "toggle the danger overlay",
+        &[ch('d')],
+    ),
+    bind(Category::Screen, "f", "toggle footsteps", &[ch('f')]),
+    bind(
+        Category::Screen,
+        "G",
+        "toggle the ghost of your last attempt",
+        &[ch('G')],
+    ),
+    bind(Category::Screen, "i", "toggle the sidebar", &[ch('i')]),
+    bind(Category::Screen, "L", "toggle the log", &[ch('L')]),
+    bind(
+        Category::Screen,
+        "esc",
+        "back to the game",
+        &[code(KeyCode::Esc)],
+    ),
+    bind(Category::Screen, "ctrl-q", "save and quit", &[ctrl('q')]),
+];
+
+/// the key bindings in a category, in the order they're listed in the table
+pub fn bindings_in(category: Category) -> impl Iterator<Item = &'static KeyBinding> {
+    KEY_BINDINGS
+        .iter()
+        .filter(move |binding| binding.category == category)
+}
+
+/// what running a command from the palette does
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommandAction {
+    /// presses the key on the main screen, exactly as if it had been typed
+    Key(KeyCode, KeyModifiers),
+    /// drops the inventory item whose number follows the command
+    Drop,
+    /// throws the inventory item whose number follows the command
+    Throw,
+    /// saves the game without quitting
+    Save,
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub action: CommandAction,
+}
+
+/// shorthand for a command that presses a key without modifiers
+const fn key_command(name: &'static str, description: &'static str, key: char) -> Command {
+    Command {
+        name,
+        description,
+        action: CommandAction::Key(KeyCode::Char(key), KeyModifiers::NONE),
+    }
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "drop",
+        description: "drop an item, followed by its number",
+        action: CommandAction::Drop,
+    },
+    Command {
+        name: "throw",
+        description: "throw a flask, followed by its number",
+        action: CommandAction::Throw,
+    },
+    key_command("descend", "go down the stairs, or walk to them", '>'),
+    key_command(
+        "toggle autopickup",
+        "pick up items by walking over them",
+        'P',
+    ),
+    Command {
+        name: "save",
+        description: "save the game and keep playing",
+        action: CommandAction::Save,
+    },
+    key_command("rest", "rest until healed", 'R'),
+    key_command("search", "search for hidden traps", 'S'),
+    key_command("yell", "draw monsters to you", 'Y'),
+    key_command("toggle sneaking", "move quietly at half speed", 's'),
+    key_command("toggle safe move", "stop before walking into monsters", 'm'),
+    key_command("cast", "cast a spell", 'z'),
+    key_command("mark waypoint", "name this spot to travel back to", 'w'),
+    key_command("travel", "travel to a waypoint", 'W'),
+    key_command("quests", "list this run's quests", 'Q'),
+    key_command("help", "list every key", '?'),
+    key_command("settings", "change the settings", 'O'),
+    Command {
+        name: "log",
+        description: "read the full message log",
+        action: CommandAction::Key(KeyCode::Char('l'), KeyModifiers::CONTROL),
+    },
+    Command {
+        name: "quit",
+        description: "save and quit",
+        action: CommandAction::Key(KeyCode::Char('q'), KeyModifiers::CONTROL),
+    },
+];
+
+/// how badly the query fits the name, or None if the letters of the query don't appear
+/// in the name in order. letters skipped over in the name count against it
+fn fuzzy_score(query: &str, name: &str) -> Option<usize> {
+    let mut skipped = 0;
+    let mut name_chars = name.chars();
+    for wanted in query.chars() {
+        loop {
+            let c = name_chars.next()?;
+            if c.eq_ignore_ascii_case(&wanted) {
+                break;
+            }
+            skipped += 1;
+        }
+    }
+    Some(skipped)
+}
+
+/// splits what was typed into the palette into the command and the number after it
+pub fn split_query(input: &str) -> (&str, Option<usize>) {
+    let input = input.trim();
+    match input.rsplit_once(' ') {
+        Some((query, number)) if number.parse::<usize>().is_ok() => {
+            (query.trim_end(), number.parse().ok())
+        }
+        _ => (input, None),
+    }
+}
+
+/// the commands matching the query, best match first
+pub fn matching_commands(query: &str) -> Vec<&'static Command> {
+    let mut matches: Vec<(usize, &Command)> = COMMANDS
+        .iter()
+        .filter_map(|command| Some((fuzzy_score(query, command.name)?, command)))
+        .collect();
+    // NOTE: the sort is stable, so ties keep the order of the table
+    matches.sort_by_key(|&(score, _)| score);
+    matches.into_iter().map(|(_, command)| command).collect()
+}
@@ -0,0 +1,166 @@
+use crate::{
+    app::{App, PLAYER},
+    components::{AIType, Item, Object, Position, RenderLayer, Renderable},
+    engine::has_clear_line,
+    fire,
+    gamemap::{EffectKind, coords_to_idx},
+    theme::ThemeColor,
+};
+
+// this file contains light sources, and works out which tiles of the floor they light up.
+// on floors with lighting, the player only sees lit tiles, and the ones right next to them
+
+/// how far the player can make things out without any light
+pub const DARK_SIGHT_RADIUS: u16 = 1;
+const TORCH_RADIUS: u16 = 8;
+const LANTERN_RADIUS: u16 = 4;
+const MUSHROOM_RADIUS: u16 = 2;
+
+/// a torch set into a wall, lighting up the room around it
+pub fn torch() -> Object {
+    let name = "torch".to_string();
+    let tooltip = "a torch burning in a bracket on the wall.".to_string();
+
+    let renderable = Renderable::themed('*', ThemeColor::Gold);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer).set_light(TORCH_RADIUS)
+}
+
+/// a clump of mushrooms that gives off a faint glow
+pub fn glowing_mushroom() -> Object {
+    let name = "glowing mushroom".to_string();
+    let tooltip = "a clump of pale mushrooms, glowing faintly in the dark.".to_string();
+
+    let renderable = Renderable::themed('"', ThemeColor::Npc);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer).set_light(MUSHROOM_RADIUS)
+}
+
+/// lights up the player's surroundings while it's carried, but also gives them away
+pub fn lantern() -> Object {
+    let name = "lantern".to_string();
+    let tooltip = "lights up the tiles around you while you carry it. monsters can see the light."
+        .to_string();
+
+    let renderable = Renderable::themed('(', ThemeColor::Gold);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Lantern)
+        .set_light(LANTERN_RADIUS)
+        .set_value(40)
+        .set_weight(5)
+}
+
+/// returns every light lying on the floor or burning on it, with how far it shines,
+/// sorted by position
+fn floor_lights(app: &App) -> Vec<(Position, u16)> {
+    let mut sources: Vec<(Position, u16)> = app
+        .objects
+        .iter()
+        .filter_map(|(id, obj)| Some((app.gamemap.get_position(*id)?, obj.light?)))
+        .collect();
+    // fires light up the tiles around them while they burn
+    sources.extend(
+        app.gamemap
+            .effects
+            .iter()
+            .filter(|effect| effect.kind == EffectKind::Fire)
+            .map(|effect| (effect.position, fire::FIRE_LIGHT_RADIUS)),
+    );
+    sources.sort_by_key(|&(pos, radius)| (pos.y, pos.x, radius));
+    sources
+}
+
+/// returns the tiles a light shines out from. a light mounted on a wall shines out of
+/// the open tiles beside it, so that the rest of the wall doesn't block it
+fn light_origins(app: &App, source: Position) -> Vec<Position> {
+    if app.gamemap.get_ref(source.x, source.y).is_transparent() {
+        return vec![source];
+    }
+    [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .map(|(dx, dy)| (source.x as i16 + dx, source.y as i16 + dy))
+        .filter(|&(x, y)| app.gamemap.in_bounds(x, y))
+        .map(|(x, y)| Position {
+            x: x as u16,
+            y: y as u16,
+        })
+        .filter(|pos| app.gamemap.get_ref(pos.x, pos.y).is_transparent())
+        .collect()
+}
+
+/// works out which tiles the lights on the floor reach, if anything moved since last time.
+/// a light reaches every tile in its radius that it has a clear line to
+pub fn update_lighting(app: &mut App) {
+    if !app.gamemap.lighting {
+        return;
+    }
+    let key = app.gamemap.light_key(floor_lights(app));
+    if app.gamemap.is_light_current(&key) {
+        return;
+    }
+
+    let width = app.gamemap.width;
+    let mut lit = vec![false; app.gamemap.tiles.len()];
+    for &(source, radius) in &key.sources {
+        let origins = light_origins(app, source);
+        lit[coords_to_idx(source.x, source.y, width)] = true;
+        let (xlow, xhigh) = (
+            source.x.saturating_sub(radius),
+            (source.x + radius).min(app.gamemap.width - 1),
+        );
+        let (ylow, yhigh) = (
+            source.y.saturating_sub(radius),
+            (source.y + radius).min(app.gamemap.height - 1),
+        );
+        for x in xlow..=xhigh {
+            for y in ylow..=yhigh {
+                let pos = Position { x, y };
+                if origins
+                    .iter()
+                    .any(|&origin| origin == pos || has_clear_line(app, origin, pos))
+                {
+                    lit[coords_to_idx(x, y, width)] = true;
+                }
+            }
+        }
+    }
+    app.gamemap.set_lit(lit, key);
+}
+
+/// returns how far the light the player is carrying shines, or 0 without one
+pub fn carried_light(app: &App) -> u16 {
+    app.inventory
+        .iter()
+        .filter_map(|id| app.objects.get(id).unwrap().light)
+        .max()
+        .unwrap_or(0)
+}
+
+/// returns how far the player can see into the dark
+pub fn player_sight_radius(app: &App) -> u16 {
+    carried_light(app).max(DARK_SIGHT_RADIUS)
+}
+
+/// whether the player stands in the light, or carries their own
+pub fn player_is_lit(app: &App) -> bool {
+    let pos = app.gamemap.get_position(PLAYER).unwrap();
+    carried_light(app) > 0 || app.gamemap.is_lit(pos.x, pos.y)
+}
+
+/// whether a monster standing at from can see the player. it needs a clear line to them,
+/// and either light to see them by, darkvision, or to be close enough to make them out
+pub fn monster_sees_player(app: &App, id: usize, from: Position) -> bool {
+    if !app.gamemap.is_in_sight(from.x, from.y) {
+        return false;
+    }
+    let darkvision = matches!(
+        &app.objects.get(&id).unwrap().ai,
+        Some(AIType::Melee(ai_data)) if ai_data.darkvision
+    );
+    let player_pos = app.gamemap.get_position(PLAYER).unwrap();
+    darkvision || player_is_lit(app) || from.distance_to(player_pos) <= DARK_SIGHT_RADIUS
+}
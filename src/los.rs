@@ -1,46 +1,203 @@
 // let's implement some line of sight algorithms!!!
 
-// draws a line from start to end
-// see csustan.csustan.edu/~tom/Lecture-Notes/Graphics/Bresenham-Line.pdf
-// en.wikipedia.org/wiki/Bresenham's_line_algorithm#Algorithm
-pub fn bresenham(start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
-    let (x1, y1) = start;
-    let (x2, y2) = end;
-
-    let dx = (x2 - x1).abs();
-    let dy = (y2 - y1).abs();
-    let stepx = { if x2 - x1 > 0 { 1 } else { -1 } };
-    let stepy = { if y2 - y1 > 0 { 1 } else { -1 } };
-
-    let mut x = x1;
-    let mut y = y1;
-
-    let mut path: Vec<(i32, i32)> = Vec::new();
-    path.push((x, y));
-
-    if dx > dy {
-        let mut fraction = 2 * dy - dx;
-        while x != x2 {
-            x += stepx;
-            if fraction >= 0 {
-                y += stepy;
-                fraction -= 2 * dx;
+/// the tiles on a line from start to end, including both ends, worked out one at a time
+/// instead of allocating the whole line up front.
+/// the line from end to start is the same tiles in reverse, so whether one tile can see
+/// another never depends on which of them is looking
+/// see en.wikipedia.org/wiki/Bresenham's_line_algorithm
+#[derive(Clone)]
+pub struct LineIter {
+    // the line is always worked out from its smaller end, so both directions round the same
+    origin: (i32, i32),
+    delta: (i32, i32),
+    steps: i32,
+    reversed: bool,
+    // the next tiles to hand out from the front and the back, counted from the start
+    front: i32,
+    back: i32,
+}
+
+impl LineIter {
+    pub fn new(start: (i32, i32), end: (i32, i32)) -> Self {
+        let reversed = end < start;
+        let (origin, other) = if reversed { (end, start) } else { (start, end) };
+        let delta = (other.0 - origin.0, other.1 - origin.1);
+        let steps = delta.0.abs().max(delta.1.abs());
+        Self {
+            origin,
+            delta,
+            steps,
+            reversed,
+            front: 0,
+            back: steps,
+        }
+    }
+
+    /// returns the tile a number of steps along the line from its smaller end.
+    /// the long axis moves one tile per step and the short one is rounded to the nearest
+    /// tile, which is what bresenham's error term works out incrementally
+    fn tile_at(&self, step: i32) -> (i32, i32) {
+        if self.steps == 0 {
+            return self.origin;
+        }
+        let along = |delta: i32| (2 * step * delta + self.steps).div_euclid(2 * self.steps);
+        (
+            self.origin.0 + along(self.delta.0),
+            self.origin.1 + along(self.delta.1),
+        )
+    }
+
+    /// returns the tile a number of steps along the line from where it was started
+    fn tile_from_start(&self, step: i32) -> (i32, i32) {
+        match self.reversed {
+            true => self.tile_at(self.steps - step),
+            false => self.tile_at(step),
+        }
+    }
+}
+
+impl Iterator for LineIter {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front > self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.tile_from_start(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front + 1).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for LineIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front > self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.tile_from_start(self.back + 1))
+    }
+}
+
+impl ExactSizeIterator for LineIter {}
+
+/// precomputed bresenham rays from the origin to every cell within a square
+/// of the given radius. rays are stored as offsets relative to the origin, so the same
+/// table can be reused for any fov origin without reallocating paths each turn
+pub struct RayTable {
+    radius: u16,
+    rays: Vec<Vec<(i16, i16)>>,
+}
+
+impl RayTable {
+    pub fn new(radius: u16) -> Self {
+        let r = radius as i32;
+        let mut rays = Vec::new();
+        for target_x in -r..=r {
+            for target_y in -r..=r {
+                let ray = LineIter::new((0, 0), (target_x, target_y))
+                    .map(|(x, y)| (x as i16, y as i16))
+                    .collect();
+                rays.push(ray);
             }
-            fraction += 2 * dy;
-            path.push((x, y));
-        }
-    } else {
-        let mut fraction = 2 * dx - dy;
-        while y != y2 {
-            if fraction >= 0 {
-                x += stepx;
-                fraction -= 2 * dy;
+        }
+        Self { radius, rays }
+    }
+
+    pub fn radius(&self) -> u16 {
+        self.radius
+    }
+
+    /// iterate over every ray. each ray starts at the origin (0, 0)
+    pub fn iter(&self) -> impl Iterator<Item = &[(i16, i16)]> {
+        self.rays.iter().map(|ray| ray.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: i32 = 9;
+
+    /// every pair of points in a small square around the origin
+    fn endpoints() -> impl Iterator<Item = ((i32, i32), (i32, i32))> {
+        let points: Vec<(i32, i32)> = (-RANGE..=RANGE)
+            .flat_map(|x| (-RANGE..=RANGE).map(move |y| (x, y)))
+            .collect();
+        let ends = points.clone();
+        points
+            .into_iter()
+            .flat_map(move |start| ends.clone().into_iter().map(move |end| (start, end)))
+    }
+
+    fn line(start: (i32, i32), end: (i32, i32)) -> Vec<(i32, i32)> {
+        LineIter::new(start, end).collect()
+    }
+
+    #[test]
+    fn lines_include_both_endpoints() {
+        for (start, end) in endpoints() {
+            let path = line(start, end);
+            assert_eq!(path.first(), Some(&start));
+            assert_eq!(path.last(), Some(&end));
+        }
+    }
+
+    #[test]
+    fn lines_are_8_connected() {
+        for (start, end) in endpoints() {
+            let path = line(start, end);
+            let steps = (end.0 - start.0).abs().max((end.1 - start.1).abs());
+            assert_eq!(path.len() as i32, steps + 1, "{:?} to {:?}", start, end);
+            for pair in path.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let dist = (a.0 - b.0).abs().max((a.1 - b.1).abs());
+                assert_eq!(
+                    dist, 1,
+                    "{:?} to {:?} skips from {:?} to {:?}",
+                    start, end, a, b
+                );
             }
-            y += stepy;
-            fraction += 2 * dx;
-            path.push((x, y));
         }
     }
 
-    return path;
+    #[test]
+    fn lines_are_symmetric() {
+        for (start, end) in endpoints() {
+            let mut backwards = line(end, start);
+            backwards.reverse();
+            assert_eq!(line(start, end), backwards, "{:?} to {:?}", start, end);
+        }
+    }
+
+    #[test]
+    fn lines_can_be_walked_from_either_end() {
+        for (start, end) in endpoints() {
+            let iter = LineIter::new(start, end);
+            assert_eq!(iter.len(), line(start, end).len());
+            let mut backwards: Vec<(i32, i32)> = iter.rev().collect();
+            backwards.reverse();
+            assert_eq!(line(start, end), backwards, "{:?} to {:?}", start, end);
+        }
+
+        // taking from both ends meets in the middle without repeating a tile
+        let mut iter = LineIter::new((0, 0), (3, 1));
+        assert_eq!(iter.next(), Some((0, 0)));
+        assert_eq!(iter.next_back(), Some((3, 1)));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn straight_lines_stay_straight() {
+        assert_eq!(line((0, 0), (3, 0)), vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+        assert_eq!(line((0, 0), (0, -2)), vec![(0, 0), (0, -1), (0, -2)]);
+        assert_eq!(line((0, 0), (-2, 2)), vec![(0, 0), (-1, 1), (-2, 2)]);
+        assert_eq!(line((4, 4), (4, 4)), vec![(4, 4)]);
+    }
 }
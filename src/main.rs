@@ -1,19 +1,54 @@
 use color_eyre::Result;
 
+mod achievements;
+mod animation;
 mod app;
+#[cfg(test)]
+mod bench;
+mod bot;
 mod components;
+mod data;
+mod dialogue;
 mod engine;
 mod entities;
+mod events;
+mod fire;
 mod gamemap;
+mod gas;
+mod identify;
 mod inventory;
 mod items;
+mod keybindings;
+mod lighting;
 mod los;
+mod manual;
 mod pathfinding;
+mod quests;
+mod scores;
+mod settings;
+mod shop;
+mod spells;
+mod theme;
+
+/// keys the bot presses before a simulated game is cut short
+const SIMULATE_MAX_KEYS: usize = 20_000;
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+
+    // `roguelike --simulate <games> [seed]` has the bot play without a terminal,
+    // and prints how each game went
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--simulate") {
+        let games = args.get(2).and_then(|arg| arg.parse().ok()).unwrap_or(10);
+        let seed = args.get(3).and_then(|arg| arg.parse().ok());
+        app::headless::simulate(games, seed, SIMULATE_MAX_KEYS);
+        return Ok(());
+    }
+
     let terminal = ratatui::init();
     let mut app = app::App::new();
+    app.recover_autosave();
     let result = app.run(terminal);
     ratatui::restore();
     result
@@ -0,0 +1,85 @@
+// the in-game manual, bundled into the binary from the markdown files in manual/
+
+/// a page of the manual, written in a small subset of markdown:
+/// `#` and `##` headings, `-` bullet points, and indented lines for formulas
+pub struct ManualPage {
+    pub title: &'static str,
+    pub text: &'static str,
+}
+
+pub const MANUAL_PAGES: [ManualPage; 4] = [
+    ManualPage {
+        title: "controls",
+        text: include_str!("../manual/controls.md"),
+    },
+    ManualPage {
+        title: "combat",
+        text: include_str!("../manual/combat.md"),
+    },
+    ManualPage {
+        title: "items",
+        text: include_str!("../manual/items.md"),
+    },
+    ManualPage {
+        title: "statuses",
+        text: include_str!("../manual/statuses.md"),
+    },
+];
+
+/// how a line of the manual should be drawn
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Title,
+    Heading,
+    Bullet,
+    Formula,
+    Text,
+}
+
+/// splits a page into lines, stripping the markdown markers off of them
+pub fn page_lines(page: usize) -> Vec<(LineKind, &'static str)> {
+    MANUAL_PAGES[page]
+        .text
+        .lines()
+        .map(|line| {
+            if let Some(rest) = line.strip_prefix("## ") {
+                (LineKind::Heading, rest)
+            } else if let Some(rest) = line.strip_prefix("# ") {
+                (LineKind::Title, rest)
+            } else if let Some(rest) = line.strip_prefix("- ") {
+                (LineKind::Bullet, rest)
+            } else if line.starts_with("    ") {
+                (LineKind::Formula, line.trim_start())
+            } else {
+                (LineKind::Text, line)
+            }
+        })
+        .collect()
+}
+
+/// returns the page and line of the next line containing the query, searching forwards
+/// from just after the given position (or backwards from just before it) and wrapping
+/// around the whole manual
+pub fn find_match(query: &str, page: usize, line: usize, forwards: bool) -> Option<(usize, usize)> {
+    if query.is_empty() {
+        return None;
+    }
+    let query = query.to_lowercase();
+
+    let positions: Vec<(usize, usize)> = (0..MANUAL_PAGES.len())
+        .flat_map(|page| (0..page_lines(page).len()).map(move |line| (page, line)))
+        .collect();
+    let start = positions
+        .iter()
+        .position(|&position| position == (page, line))
+        .unwrap_or(0);
+
+    let count = positions.len();
+    (1..=count)
+        .map(|step| match forwards {
+            true => (start + step) % count,
+            false => (start + count - step % count) % count,
+        })
+        .map(|idx| positions[idx])
+        .find(|&(page, line)| page_lines(page)[line].1.to_lowercase().contains(&query))
+}
@@ -1,67 +1,244 @@
-use crate::gamemap::{GameMap, coords_to_idx, idx_to_coords};
-use std::{collections::BinaryHeap, u32};
-
-/// returns a costs array as follows:
-///     unwalkable:     0
-///     walkable:       1
-///     has blocker:    5
-pub fn generate_simple_costs_array(gamemap: &GameMap) -> Vec<u32> {
-    let mut costs = vec![0; (gamemap.height * gamemap.width) as usize];
-    for y in 0..gamemap.height {
-        for x in 0..gamemap.width {
-            if gamemap.get_ref(x, y).is_walkable() {
-                costs[coords_to_idx(x, y, gamemap.width)] = 1;
-            }
-            if gamemap.get_ref(x, y).blocker.is_some() {
-                costs[coords_to_idx(x, y, gamemap.width)] = 5;
-            }
+use crate::gamemap::{GameMap, Tile, TileType, coords_to_idx, idx_to_coords};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// how much it costs to step into each tile when pathfinding
+#[derive(Clone, Copy)]
+pub enum Costs {
+    /// unwalkable or hazardous: 0, walkable: 1 or more for slow terrain, has blocker: 5,
+    /// on fire or in poison gas: 10
+    Simple,
+    /// every walkable tile costs 1, ignoring blockers and terrain
+    Walkable,
+    /// like Simple, for monsters that aren't clever enough to walk around other monsters
+    /// or to stay out of chasms and lava
+    Careless {
+        ignore_blockers: bool,
+        into_chasms: bool,
+    },
+    /// like Simple, for monsters that dig through any wall but the edge of the map
+    Tunneling,
+}
+
+/// how much stepping into a wall costs a tunneling monster, which has to dig it out first
+const TUNNEL_COST: u32 = 3;
+/// how much stepping into a fire or poison gas costs, so that careful walkers go
+/// around it if they can
+const HARMFUL_COST: u32 = 10;
+
+/// buffers reused by every pathfinder, so that monsters looking for a path
+/// don't allocate fresh grids each turn
+#[derive(Default)]
+pub struct PathScratch {
+    costs: Vec<u32>,  // cost for edges that move into this cell
+    dists: Vec<u32>,  // distance dp for dijkstra
+    prev: Vec<usize>, // stores ancestor of each vertex, used to recover the path
+    heap: BinaryHeap<Reverse<(u32, (u16, u16))>>, // frontier of dijkstra
+    settings: Option<PathSettings>, // what the distances in the buffers were computed with
+    safety: Vec<i64>, // inverted distances used for running away
+    safety_heap: BinaryHeap<Reverse<(i64, (u16, u16))>>, // frontier for the safety map
+}
+
+impl Costs {
+    /// returns the cost of stepping into a tile
+    fn of(self, tile: &Tile) -> u32 {
+        let walkable = match self {
+            Costs::Careless {
+                into_chasms: true, ..
+            } => tile.is_walkable() || tile.tile_type == TileType::Chasm,
+            Costs::Walkable => tile.is_walkable(),
+            _ => tile.is_walkable() && !tile.is_hazard(),
+        };
+        let avoids_blockers = match self {
+            Costs::Simple | Costs::Tunneling => true,
+            Costs::Walkable => false,
+            Costs::Careless {
+                ignore_blockers, ..
+            } => !ignore_blockers,
+        };
+        match (walkable, tile.blocker.is_some() && avoids_blockers) {
+            (_, true) => 5,
+            (true, _) if matches!(self, Costs::Walkable) => 1,
+            (true, _) => tile.move_time_percent().div_ceil(100) as u32,
+            (false, _) => 0,
+        }
+    }
+
+    /// returns the cost of stepping into the tile at x, y
+    fn at(self, gamemap: &GameMap, x: u16, y: u16) -> u32 {
+        match self {
+            Costs::Tunneling if gamemap.is_diggable(x, y) => TUNNEL_COST,
+            Costs::Simple | Costs::Tunneling => match self.of(gamemap.get_ref(x, y)) {
+                0 => 0,
+                cost if gamemap.is_harmful(x, y) => cost.max(HARMFUL_COST),
+                cost => cost,
+            },
+            _ => self.of(gamemap.get_ref(x, y)),
+        }
+    }
+}
+
+/// which diagonal steps a path can take
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Diagonals {
+    /// any diagonal step between two walkable tiles
+    Free,
+    /// only diagonal steps that don't squeeze past the corner of a wall
+    NoCornerCutting,
+    /// no diagonal steps at all, only the four orthogonal ones
+    Forbidden,
+}
+
+/// everything a dijkstra map was computed with, so that it can be updated later
+#[derive(Clone, Copy)]
+struct PathSettings {
+    costs: Costs,
+    root: (u16, u16),
+    cardinal: u32,
+    diagonal: u32,
+    diagonals: Diagonals,
+}
+
+impl PathScratch {
+    /// fills the costs buffer for the gamemap
+    fn fill_costs(&mut self, gamemap: &GameMap, costs: Costs) {
+        self.costs.clear();
+        self.costs.extend((0..gamemap.tiles.len()).map(|idx| {
+            let (x, y) = idx_to_coords(idx, gamemap.width);
+            costs.at(gamemap, x, y)
+        }));
+    }
+}
+
+/// distances from one tile to every other, kept around after the pathfinder that worked
+/// them out is gone, so that many monsters can share a single dijkstra each turn
+#[derive(Default)]
+pub struct FlowMap {
+    width: u16,
+    root: Option<(u16, u16)>,
+    dists: Vec<u32>,
+}
+
+impl FlowMap {
+    /// works out the distance of every walkable tile from root, ignoring blockers
+    pub fn recompute(
+        &mut self,
+        gamemap: &GameMap,
+        scratch: &mut PathScratch,
+        root: (u16, u16),
+        diagonals: Diagonals,
+    ) {
+        Pathfinder::new(gamemap, scratch, Costs::Walkable, root, 2, 3, diagonals);
+        self.width = gamemap.width;
+        self.root = Some(root);
+        self.dists.clone_from(&scratch.dists);
+    }
+
+    /// returns the tile the distances are measured from, if they've been worked out
+    pub fn root(&self) -> Option<(u16, u16)> {
+        self.root
+    }
+
+    /// returns the walking distance from the root to a tile, or None if it can't be reached
+    pub fn distance(&self, x: u16, y: u16) -> Option<u32> {
+        match self.dists.get(coords_to_idx(x, y, self.width)) {
+            Some(&u32::MAX) | None => None,
+            Some(&dist) => Some(dist),
         }
     }
-    return costs;
+}
+
+/// how much running away is worth compared to distance, as a fraction. above one, so that
+/// fleeing things prefer long escape routes over cowering in the nearest dead end
+const FLEE_COEFFICIENT_NUM: i64 = 6;
+const FLEE_COEFFICIENT_DEN: i64 = 5;
+
+/// returns the tiles around (x, y) that are in bounds, along with the step to each one
+fn neighbours(
+    gamemap: &GameMap,
+    x: u16,
+    y: u16,
+) -> impl Iterator<Item = (u16, u16, i16, i16)> + '_ {
+    const DIRECTIONS: [(i16, i16); 8] = [
+        (1, 0),
+        (0, 1),
+        (-1, 0),
+        (0, -1),
+        (1, 1),
+        (-1, 1),
+        (1, -1),
+        (-1, -1),
+    ];
+    DIRECTIONS.iter().filter_map(move |&(dx, dy)| {
+        if !gamemap.in_bounds(x as i16 + dx, y as i16 + dy) {
+            return None; // destination is not in bounds
+        }
+        Some(((x as i16 + dx) as u16, (y as i16 + dy) as u16, dx, dy))
+    })
 }
 
 // NOTE: values less than or equal to 0 in the cost array represent inaccessible tiles
 pub struct Pathfinder<'a> {
-    gamemap: &'a GameMap, // underlying gamemap for this pathfinder
-    costs: Vec<u32>,      // cost for edges that move into this cell
-    dists: Vec<u32>,      // distance dp for dijkstra
-    prev: Vec<usize>,     // stores ancestor of each vertex, used to recover the path
-    root: (u16, u16),     // root location from where distance is calculated
-    cardinal: u32,        // additional cost for cardinal movement
-    diagonal: u32,        // additional cost for diagonal movement
+    gamemap: &'a GameMap,         // underlying gamemap for this pathfinder
+    scratch: &'a mut PathScratch, // costs, distances and ancestors for every tile
+    root: (u16, u16),             // root location from where distance is calculated
+    cardinal: u32,                // additional cost for cardinal movement
+    diagonal: u32,                // additional cost for diagonal movement
+    diagonals: Diagonals,         // which diagonal steps can be taken
 }
 
 impl<'a> Pathfinder<'a> {
     pub fn new(
         gamemap: &'a GameMap,
-        costs: Vec<u32>,
+        scratch: &'a mut PathScratch,
+        costs: Costs,
         root: (u16, u16),
         cardinal: u32,
         diagonal: u32,
+        diagonals: Diagonals,
     ) -> Self {
-        assert_eq!(
-            costs.len(),
-            (gamemap.width * gamemap.height) as usize,
-            "costs len does not match up with gamemap dimensions!"
-        );
+        scratch.fill_costs(gamemap, costs);
+        scratch.settings = Some(PathSettings {
+            costs,
+            root,
+            cardinal,
+            diagonal,
+            diagonals,
+        });
 
         let mut pathfinder = Pathfinder {
             gamemap,
-            costs,
-            dists: Vec::new(),
-            prev: Vec::new(),
+            scratch,
             root,
             cardinal,
             diagonal,
+            diagonals,
         };
         pathfinder.dijkstra();
         pathfinder
     }
 
+    /// returns the first step of the shortest path from root to dest, without building
+    /// the whole path. None if dest is unreachable or is the root itself
+    pub fn first_step(&self, dest: (u16, u16)) -> Option<(u16, u16)> {
+        let width = self.gamemap.width;
+        if dest == self.root || self.scratch.dists[coords_to_idx(dest.0, dest.1, width)] == u32::MAX
+        {
+            return None;
+        }
+
+        let root_idx = coords_to_idx(self.root.0, self.root.1, width);
+        let mut cur = coords_to_idx(dest.0, dest.1, width);
+        while self.scratch.prev[cur] != root_idx {
+            cur = self.scratch.prev[cur];
+        }
+        Some(idx_to_coords(cur, width))
+    }
+
     // returns shortest path from root to dest
     // last element is always dest, first element is tile adjacent to root
+    #[allow(dead_code)]
     pub fn path_to(&self, dest: (u16, u16)) -> Vec<(u16, u16)> {
-        if self.dists[coords_to_idx(dest.0, dest.1, self.gamemap.width)] == u32::MAX {
+        if self.scratch.dists[coords_to_idx(dest.0, dest.1, self.gamemap.width)] == u32::MAX {
             return vec![(490, 490)];
         }
 
@@ -70,7 +247,7 @@ impl<'a> Pathfinder<'a> {
         while cur != self.root {
             path.push(cur);
             cur = idx_to_coords(
-                self.prev[coords_to_idx(cur.0, cur.1, self.gamemap.width)],
+                self.scratch.prev[coords_to_idx(cur.0, cur.1, self.gamemap.width)],
                 self.gamemap.width,
             );
         }
@@ -79,50 +256,348 @@ impl<'a> Pathfinder<'a> {
         path
     }
 
+    /// returns the total cost of the shortest path from root to dest, or None if unreachable
+    pub fn distance_to(&self, dest: (u16, u16)) -> Option<u32> {
+        match self.scratch.dists[coords_to_idx(dest.0, dest.1, self.gamemap.width)] {
+            u32::MAX => None,
+            dist => Some(dist),
+        }
+    }
+
+    /// returns the step away from the root that something at from should take to get away
+    /// from it, or None if it's cornered. the distances are flipped and scaled up, then
+    /// smoothed out again with dijkstra, so rolling downhill leads away from the root
+    /// while still finding the way out of dead ends, even when that means running past it.
+    /// see roguebasin.com/index.php/The_Incredible_Power_of_Dijkstra_Maps
+    pub fn flee_step(&mut self, from: (u16, u16)) -> Option<(u16, u16)> {
+        let width = self.gamemap.width;
+        let scratch = &mut *self.scratch;
+        scratch.safety.clear();
+        scratch.safety_heap.clear();
+        for (idx, &dist) in scratch.dists.iter().enumerate() {
+            let safety = match dist {
+                u32::MAX => i64::MAX,
+                dist => -(dist as i64 * FLEE_COEFFICIENT_NUM / FLEE_COEFFICIENT_DEN),
+            };
+            scratch.safety.push(safety);
+            if safety != i64::MAX {
+                scratch
+                    .safety_heap
+                    .push(Reverse((safety, idx_to_coords(idx, width))));
+            }
+        }
+
+        while let Some(Reverse((safety, (x, y)))) = self.scratch.safety_heap.pop() {
+            if safety > self.scratch.safety[coords_to_idx(x, y, width)] {
+                continue;
+            }
+            for (target_x, target_y, dx, dy) in neighbours(self.gamemap, x, y) {
+                let target_idx = coords_to_idx(target_x, target_y, width);
+                if self.scratch.costs[target_idx] == 0 {
+                    continue;
+                }
+                let Some(step_cost) = self.step_cost(x, y, dx, dy) else {
+                    continue;
+                };
+                let target_safety = safety + (self.scratch.costs[target_idx] + step_cost) as i64;
+                if self.scratch.safety[target_idx] > target_safety {
+                    self.scratch.safety[target_idx] = target_safety;
+                    self.scratch
+                        .safety_heap
+                        .push(Reverse((target_safety, (target_x, target_y))));
+                }
+            }
+        }
+
+        // step to the safest free tile next to us, if any is safer than where we are
+        let here = self.scratch.safety[coords_to_idx(from.0, from.1, width)];
+        neighbours(self.gamemap, from.0, from.1)
+            .filter(|&(x, y, dx, dy)| {
+                let tile = self.gamemap.get_ref(x, y);
+                self.scratch.costs[coords_to_idx(x, y, width)] != 0
+                    && tile.blocker.is_none()
+                    && self.step_cost(from.0, from.1, dx, dy).is_some()
+            })
+            .map(|(x, y, _, _)| (self.scratch.safety[coords_to_idx(x, y, width)], (x, y)))
+            .filter(|&(safety, _)| safety < here)
+            .min()
+            .map(|(_, step)| step)
+    }
+
+    /// brings the dijkstra map left in the scratch buffers by the last pathfinder up to date
+    /// after a few tiles changed, e.g. a door opened or a monster moved, instead of
+    /// recomputing it from scratch. only tiles whose distance depended on the changed ones
+    /// are worked out again. panics if no pathfinder has used the scratch buffers yet
+    #[allow(dead_code)]
+    pub fn update(
+        gamemap: &'a GameMap,
+        scratch: &'a mut PathScratch,
+        changed: &[(u16, u16)],
+    ) -> Self {
+        let settings = scratch
+            .settings
+            .expect("updating a pathfinder that was never computed!");
+        assert_eq!(scratch.costs.len(), gamemap.tiles.len());
+
+        let mut pathfinder = Pathfinder {
+            gamemap,
+            scratch,
+            root: settings.root,
+            cardinal: settings.cardinal,
+            diagonal: settings.diagonal,
+            diagonals: settings.diagonals,
+        };
+
+        // a tile changing whether it can be walked on also changes which diagonal steps
+        // squeeze past it, so its neighbours' distances need another look too
+        let mut touched: Vec<(u16, u16)> = Vec::new();
+        for &(x, y) in changed {
+            let idx = coords_to_idx(x, y, gamemap.width);
+            pathfinder.scratch.costs[idx] = settings.costs.at(gamemap, x, y);
+            touched.push((x, y));
+            if settings.diagonals == Diagonals::NoCornerCutting {
+                touched.extend(neighbours(gamemap, x, y).map(|(x, y, _, _)| (x, y)));
+            }
+        }
+
+        // anything whose shortest path now costs more, or can't be walked anymore,
+        // is forgotten along with every tile that was reached through it
+        let mut forgotten: Vec<(u16, u16)> = Vec::new();
+        for &(x, y) in &touched {
+            if !pathfinder.still_valid(x, y) {
+                pathfinder.forget_subtree(x, y, &mut forgotten);
+            }
+        }
+
+        // then the touched and forgotten tiles are reached again from their neighbours,
+        // and dijkstra carries on outwards from wherever that made a difference
+        pathfinder.scratch.heap.clear();
+        for &(x, y) in touched.iter().chain(forgotten.iter()) {
+            pathfinder.relax_from_neighbours(x, y);
+        }
+        pathfinder.settle();
+        pathfinder
+    }
+
+    /// returns the cost of a step, not counting the tile it steps into,
+    /// or None if the step isn't allowed
+    fn step_cost(&self, x: u16, y: u16, dx: i16, dy: i16) -> Option<u32> {
+        if dx.abs() + dy.abs() == 1 {
+            return Some(self.cardinal);
+        }
+        match self.diagonals {
+            Diagonals::Free => Some(self.diagonal),
+            Diagonals::NoCornerCutting if self.gamemap.cuts_corner(x, y, dx, dy) => None,
+            Diagonals::NoCornerCutting => Some(self.diagonal),
+            Diagonals::Forbidden => None,
+        }
+    }
+
+    /// returns true if a tile's recorded distance still matches the step it was reached by
+    fn still_valid(&self, x: u16, y: u16) -> bool {
+        let width = self.gamemap.width;
+        let idx = coords_to_idx(x, y, width);
+        let prev = self.scratch.prev[idx];
+        if (x, y) == self.root || prev == usize::MAX {
+            return true;
+        }
+        let cost = self.scratch.costs[idx];
+        let (prev_x, prev_y) = idx_to_coords(prev, width);
+        let (dx, dy) = (x as i16 - prev_x as i16, y as i16 - prev_y as i16);
+        match self.step_cost(prev_x, prev_y, dx, dy) {
+            Some(step) if cost != 0 => {
+                self.scratch.dists[prev] + cost + step == self.scratch.dists[idx]
+            }
+            _ => false,
+        }
+    }
+
+    /// marks a tile and everything reached through it as unreachable, recording them
+    fn forget_subtree(&mut self, x: u16, y: u16, forgotten: &mut Vec<(u16, u16)>) {
+        let width = self.gamemap.width;
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            let idx = coords_to_idx(x, y, width);
+            if self.scratch.dists[idx] == u32::MAX {
+                continue;
+            }
+            // a tile's ancestor is always right next to it, so its descendants are too
+            let children: Vec<(u16, u16)> = neighbours(self.gamemap, x, y)
+                .filter(|&(nx, ny, _, _)| self.scratch.prev[coords_to_idx(nx, ny, width)] == idx)
+                .map(|(nx, ny, _, _)| (nx, ny))
+                .collect();
+            self.scratch.dists[idx] = u32::MAX;
+            self.scratch.prev[idx] = usize::MAX;
+            forgotten.push((x, y));
+            stack.extend(children);
+        }
+    }
+
+    /// works out the best way into a tile from its neighbours, queueing it up if that
+    /// beats its current distance
+    fn relax_from_neighbours(&mut self, x: u16, y: u16) {
+        let width = self.gamemap.width;
+        let idx = coords_to_idx(x, y, width);
+        if (x, y) == self.root || self.scratch.costs[idx] == 0 {
+            return;
+        }
+        let best = neighbours(self.gamemap, x, y)
+            .filter_map(|(nx, ny, dx, dy)| {
+                let from = coords_to_idx(nx, ny, width);
+                let dist = self.scratch.dists[from];
+                let step = self.step_cost(nx, ny, -dx, -dy)?;
+                (dist != u32::MAX).then(|| (dist + self.scratch.costs[idx] + step, from))
+            })
+            .min();
+        if let Some((dist, from)) = best
+            && dist < self.scratch.dists[idx]
+        {
+            self.scratch.dists[idx] = dist;
+            self.scratch.prev[idx] = from;
+            self.scratch.heap.push(Reverse((dist, (x, y))));
+        }
+    }
+
     fn dijkstra(&mut self) {
         // dijkstra is calculated once here!!!
         // and results are reused everywhere else...
-        self.dists.resize(self.costs.len(), u32::MAX);
-        self.prev.resize(self.costs.len(), usize::MAX);
+        let scratch = &mut *self.scratch;
+        let len = scratch.costs.len();
+        scratch.dists.clear();
+        scratch.dists.resize(len, u32::MAX);
+        scratch.prev.clear();
+        scratch.prev.resize(len, usize::MAX);
 
         // heap is a max-heap, so we wrap everything we pass into it with reverse
-        let mut heap = BinaryHeap::new();
-        heap.push(std::cmp::Reverse((0, self.root)));
-
-        let cardinal_dirs = vec![(1, 0), (0, 1), (-1, 0), (0, -1)];
-        let diagonal_dirs = vec![(1, 1), (-1, 1), (1, -1), (-1, -1)];
+        scratch.heap.clear();
+        scratch.heap.push(Reverse((0, self.root)));
+        scratch.dists[coords_to_idx(self.root.0, self.root.1, self.gamemap.width)] = 0;
+        self.settle();
+    }
 
-        while let Some(std::cmp::Reverse((cost, (x, y)))) = heap.pop() {
+    /// expands the frontier until every reachable tile has its shortest distance
+    fn settle(&mut self) {
+        let width = self.gamemap.width;
+        while let Some(Reverse((cost, (x, y)))) = self.scratch.heap.pop() {
             // this is not the current best distance
-            if cost > self.dists[coords_to_idx(x, y, self.gamemap.width)] {
+            let cur_idx = coords_to_idx(x, y, width);
+            if cost > self.scratch.dists[cur_idx] {
                 continue;
             }
 
-            for (dx, dy) in cardinal_dirs.iter().chain(diagonal_dirs.iter()) {
-                if !self.gamemap.in_bounds(x as i16 + dx, y as i16 + dy) {
-                    continue; // destination is not in bounds
-                }
-
-                let (target_x, target_y) = ((x as i16 + dx) as u16, (y as i16 + dy) as u16);
-                let target_idx = coords_to_idx(target_x, target_y, self.gamemap.width);
-                let cur_idx = coords_to_idx(x, y, self.gamemap.width);
+            for (target_x, target_y, dx, dy) in neighbours(self.gamemap, x, y) {
+                let target_idx = coords_to_idx(target_x, target_y, width);
 
                 // if we can't walk into the target tile, continue
-                if self.costs[target_idx] <= 0 {
+                if self.scratch.costs[target_idx] == 0 {
+                    continue;
+                }
+                let Some(step_cost) = self.step_cost(x, y, dx, dy) else {
                     continue;
+                };
+                let target_dist = cost + self.scratch.costs[target_idx] + step_cost;
+
+                if self.scratch.dists[target_idx] > target_dist {
+                    self.scratch.dists[target_idx] = target_dist;
+                    self.scratch.prev[target_idx] = cur_idx;
+                    self.scratch
+                        .heap
+                        .push(Reverse((target_dist, (target_x, target_y))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    const WIDTH: u16 = 24;
+    const HEIGHT: u16 = 16;
+
+    /// a map with floor everywhere except its border and a scattering of walls
+    fn random_map(rng: &mut StdRng) -> GameMap {
+        let mut gamemap = GameMap::new(WIDTH, HEIGHT, 1);
+        for x in 1..WIDTH - 1 {
+            for y in 1..HEIGHT - 1 {
+                if rng.random_bool(0.7) {
+                    gamemap.set_tile_type(x, y, TileType::Floor);
                 }
+            }
+        }
+        gamemap.set_tile_type(1, 1, TileType::Floor);
+        gamemap
+    }
 
-                let step_cost = if dx.abs() + dy.abs() == 1 {
-                    self.cardinal
-                } else {
-                    self.diagonal
+    /// flips a few tiles between wall and floor, returning the ones that changed
+    fn flip_tiles(gamemap: &mut GameMap, rng: &mut StdRng, count: usize) -> Vec<(u16, u16)> {
+        (0..count)
+            .map(|_| {
+                let (x, y) = (
+                    rng.random_range(2..WIDTH - 1),
+                    rng.random_range(2..HEIGHT - 1),
+                );
+                let flipped = match gamemap.get_ref(x, y).tile_type {
+                    TileType::Floor => TileType::Wall,
+                    _ => TileType::Floor,
                 };
-                let target_dist = cost + self.costs[target_idx] + step_cost;
+                gamemap.set_tile_type(x, y, flipped);
+                (x, y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn updates_match_a_fresh_dijkstra() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for round in 0..200 {
+            let diagonals = [
+                Diagonals::Free,
+                Diagonals::NoCornerCutting,
+                Diagonals::Forbidden,
+            ][round % 3];
+            let mut gamemap = random_map(&mut rng);
+            let mut scratch = PathScratch::default();
+            Pathfinder::new(
+                &gamemap,
+                &mut scratch,
+                Costs::Simple,
+                (1, 1),
+                2,
+                3,
+                diagonals,
+            );
+
+            for _ in 0..5 {
+                let count = rng.random_range(1..4);
+                let changed = flip_tiles(&mut gamemap, &mut rng, count);
+                let updated = Pathfinder::update(&gamemap, &mut scratch, &changed);
+                let updated: Vec<Option<u32>> = (0..gamemap.tiles.len())
+                    .map(|idx| updated.distance_to(idx_to_coords(idx, WIDTH)))
+                    .collect();
 
-                if self.dists[target_idx] > target_dist {
-                    self.dists[target_idx] = target_dist;
-                    self.prev[target_idx] = cur_idx;
-                    heap.push(std::cmp::Reverse((target_dist, (target_x, target_y))));
+                let mut fresh_scratch = PathScratch::default();
+                let fresh = Pathfinder::new(
+                    &gamemap,
+                    &mut fresh_scratch,
+                    Costs::Simple,
+                    (1, 1),
+                    2,
+                    3,
+                    diagonals,
+                );
+                for (idx, &dist) in updated.iter().enumerate() {
+                    let coords = idx_to_coords(idx, WIDTH);
+                    assert_eq!(
+                        dist,
+                        fresh.distance_to(coords),
+                        "round {} at {:?}",
+                        round,
+                        coords
+                    );
                 }
             }
         }
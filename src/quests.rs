@@ -0,0 +1,175 @@
+use rand::Rng;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    achievements::{self, Achievement},
+    app::{App, MessageKind, PLAYER, procgen::FINAL_LEVEL},
+    events::GameEvent,
+    identify, inventory, shop,
+};
+
+// this file contains the optional quests handed out at the start of each run. every quest
+// belongs to one floor, and is given up on if the player goes down the stairs without
+// finishing it. finishing one pays out gold and an item from deeper down
+
+const QUESTS_PER_RUN: usize = 3;
+const QUEST_MIN_LEVEL: u16 = 2;
+const REWARD_GOLD_PER_LEVEL: u32 = 20;
+/// how many floors deeper the reward item is rolled from
+const REWARD_LOOT_LEVELS: u16 = 2;
+/// how much tougher a captain is than the monster it was made from
+pub const CAPTAIN_HP_MULTIPLIER: u16 = 2;
+pub const CAPTAIN_POWER_BONUS: i16 = 2;
+
+/// what has to be done to finish a quest
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Goal {
+    /// kill the captain leading the monsters on the floor
+    Slay,
+    /// pick up the idol hidden on the floor
+    Retrieve,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum QuestState {
+    /// the player hasn't reached the quest's floor yet
+    Waiting,
+    /// the quest's floor has been generated, and its target is somewhere on it
+    Active,
+    Completed,
+    /// the player left the quest's floor without finishing it
+    Failed,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Quest {
+    pub goal: Goal,
+    pub level: u16,
+    pub state: QuestState,
+    /// id of the captain or idol, once the quest's floor has been generated
+    pub target: Option<usize>,
+    /// name of the captain or idol, once the quest's floor has been generated
+    pub target_name: Option<String>,
+}
+
+impl Quest {
+    /// a line describing what the quest asks for
+    pub fn description(&self) -> String {
+        match (self.goal, &self.target_name) {
+            (Goal::Slay, Some(name)) => format!("Slay the {} on depth {}", name, self.level),
+            (Goal::Slay, None) => format!("Slay the captain on depth {}", self.level),
+            (Goal::Retrieve, _) => format!("Retrieve the golden idol from depth {}", self.level),
+        }
+    }
+}
+
+/// picks the quests for a new run, each on a different floor above the last one
+pub fn generate(rng: &mut impl Rng) -> Vec<Quest> {
+    let mut levels: Vec<u16> = (QUEST_MIN_LEVEL..FINAL_LEVEL).collect();
+    let mut quests = Vec::new();
+    while quests.len() < QUESTS_PER_RUN && !levels.is_empty() {
+        let level = levels.remove(rng.random_range(0..levels.len()));
+        let goal = match rng.random_bool(0.5) {
+            true => Goal::Slay,
+            false => Goal::Retrieve,
+        };
+        quests.push(Quest {
+            goal,
+            level,
+            state: QuestState::Waiting,
+            target: None,
+            target_name: None,
+        });
+    }
+    quests.sort_by_key(|quest| quest.level);
+    quests
+}
+
+/// tells the player about the quest on the floor they just arrived on, if there is one
+pub fn announce(app: &mut App) {
+    let level = app.gamemap.level;
+    let Some(quest) = app
+        .quests
+        .iter()
+        .find(|quest| quest.level == level && quest.state == QuestState::Active)
+    else {
+        return;
+    };
+    let message = match quest.goal {
+        Goal::Slay => format!(
+            "Rumour has it that the monsters on this floor follow the {}.",
+            quest.target_name.as_deref().unwrap_or("captain")
+        ),
+        Goal::Retrieve => String::from("Somewhere on this floor lies a golden idol."),
+    };
+    app.add_to_log(message, Color::LightYellow, MessageKind::System);
+}
+
+/// gives up on the quests of the floor the player is leaving
+pub fn leave_floor(app: &mut App) {
+    let level = app.gamemap.level;
+    let mut failed = Vec::new();
+    for quest in app.quests.iter_mut() {
+        if quest.level == level && quest.state == QuestState::Active {
+            quest.state = QuestState::Failed;
+            failed.push(quest.description());
+        }
+    }
+    for description in failed {
+        app.emit(GameEvent::QuestFailed { description });
+    }
+}
+
+/// finishes any quest the event completes
+pub fn on_event(app: &mut App, event: &GameEvent) {
+    let (goal, id) = match event {
+        GameEvent::MonsterDied { id, .. } => (Goal::Slay, *id),
+        GameEvent::ItemPickedUp { id, .. } => (Goal::Retrieve, *id),
+        _ => return,
+    };
+    let Some(idx) = app.quests.iter().position(|quest| {
+        quest.goal == goal && quest.target == Some(id) && quest.state == QuestState::Active
+    }) else {
+        return;
+    };
+    app.quests[idx].state = QuestState::Completed;
+    let description = app.quests[idx].description();
+    let level = app.quests[idx].level;
+    app.emit(GameEvent::QuestCompleted { description });
+    reward(app, level);
+    achievements::unlock(app, Achievement::Questing);
+}
+
+/// pays out gold and an item rolled from deeper down. the item goes in the pack,
+/// or at the player's feet if it's full
+fn reward(app: &mut App, level: u16) {
+    let gold = REWARD_GOLD_PER_LEVEL * level as u32;
+    shop::add_player_gold(app, gold);
+    app.emit(GameEvent::RewardGold { amount: gold });
+
+    let item_id = app.random_item(level + REWARD_LOOT_LEVELS);
+    let name = identify::item_name(app, item_id);
+    if inventory::has_room_for(app, item_id) {
+        inventory::add_to_inventory(app, item_id);
+        app.emit(GameEvent::RewardItem {
+            id: item_id,
+            name,
+            at_feet: false,
+        });
+        return;
+    }
+    let pos = app.gamemap.get_position(PLAYER).unwrap();
+    let placed = app
+        .gamemap
+        .area_place_item(pos.x, pos.y, item_id, &mut app.rng);
+    if placed.is_some() {
+        app.emit(GameEvent::RewardItem {
+            id: item_id,
+            name,
+            at_feet: true,
+        });
+    } else {
+        app.objects.get_contents().remove(&item_id);
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    achievements::Profile,
+    app::{App, GameScreen, PLAYER, fnv1a},
+    components::{CLASS_ORDERING, PlayerClass},
+    data::EntityData,
+    settings::Settings,
+    shop,
+};
+
+// this file contains the high score table kept in the profile, and the daily challenge:
+// a dungeon everyone gets the same seed for on the same day, with its own table so that
+// runs on it can be compared
+
+/// how many runs each table keeps
+const MAX_SCORES: usize = 10;
+const POINTS_PER_DEPTH: u32 = 100;
+const POINTS_PER_KILL: u32 = 5;
+const POINTS_FOR_ESCAPING: u32 = 1000;
+
+/// how a finished run went
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u32,
+    pub class: Option<PlayerClass>,
+    pub depth: u16,
+    pub kills: u32,
+    pub turns: u64,
+    pub won: bool,
+    pub seed: u64,
+    /// the day of the daily challenge the run was on, or None for ordinary runs
+    pub daily: Option<String>,
+}
+
+/// returns today's date as year-month-day, in utc so that everyone shares the same day
+pub fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// turns a count of days since 1970-01-01 into a year, month and day.
+/// see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// the seed of the daily challenge on a date
+pub fn daily_seed(date: &str) -> u64 {
+    fnv1a(date.bytes())
+}
+
+/// the class everyone plays the daily challenge on a seed as
+pub fn daily_class(seed: u64) -> PlayerClass {
+    CLASS_ORDERING[(seed % CLASS_ORDERING.len() as u64) as usize]
+}
+
+impl Profile {
+    /// returns the best runs in a table, best first. the daily table for a date only
+    /// holds the runs on that day's challenge
+    pub fn high_scores(&self, daily: Option<&str>) -> Vec<&Score> {
+        let mut scores: Vec<&Score> = self
+            .scores
+            .iter()
+            .filter(|score| score.daily.as_deref() == daily)
+            .collect();
+        scores.sort_by_key(|score| std::cmp::Reverse(score.points));
+        scores
+    }
+
+    /// adds a run to its table, dropping the worst run if the table is full
+    fn add_score(&mut self, score: Score) {
+        let daily = score.daily.clone();
+        self.scores.push(score);
+        let table: Vec<usize> = (0..self.scores.len())
+            .filter(|&idx| self.scores[idx].daily == daily)
+            .collect();
+        if table.len() > MAX_SCORES {
+            // on a tie, the newer run is the one that misses out
+            let worst = table
+                .into_iter()
+                .rev()
+                .min_by_key(|&idx| self.scores[idx].points)
+                .unwrap();
+            self.scores.remove(worst);
+        }
+    }
+}
+
+/// puts back every setting that changes how the game plays to its default, and leaves
+/// out the entities data file, so that every daily run plays the same way
+pub fn lock_config(app: &mut App) {
+    let defaults = Settings::default();
+    app.settings.forbid_corner_cutting = defaults.forbid_corner_cutting;
+    app.settings.orthogonal_only = defaults.orthogonal_only;
+    app.settings.wizard_mode = defaults.wizard_mode;
+    app.settings.view_radius = defaults.view_radius;
+    app.settings.inventory_size = defaults.inventory_size;
+    app.entity_data = EntityData::builtin();
+}
+
+/// records the run that just ended in the high score table
+pub fn record(app: &mut App) {
+    let won = app.game_screen == GameScreen::Victory;
+    let depth = app.gamemap.level;
+    let points = depth as u32 * POINTS_PER_DEPTH
+        + app.kills * POINTS_PER_KILL
+        + shop::player_gold(app)
+        + if won { POINTS_FOR_ESCAPING } else { 0 };
+    let score = Score {
+        points,
+        class: app.objects.get(&PLAYER).unwrap().class,
+        depth,
+        kills: app.kills,
+        turns: app.time / 100,
+        won,
+        seed: app.seed,
+        daily: app.daily.clone(),
+    };
+    app.profile.add_score(score);
+}
@@ -0,0 +1,296 @@
+use color_eyre::Result;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::{
+    app::{App, MAX_INVENTORY_SIZE, MessageKind},
+    pathfinding::Diagonals,
+    theme::Theme,
+};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+/// the view radius can be set anywhere in this range
+pub const MIN_VIEW_RADIUS: u16 = 4;
+pub const MAX_VIEW_RADIUS: u16 = 12;
+
+/// what to do with a save that was changed outside of the game
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperPolicy {
+    /// load it anyway, without saying anything
+    Allow,
+    /// load it, but note in the log that it was changed
+    #[default]
+    Warn,
+    /// don't load it at all. saves from before checksums are refused too
+    Refuse,
+}
+
+/// which kinds of items get picked up just by walking over them
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct AutoPickup {
+    /// turned on and off in game. gold is always picked up while this is on
+    pub enabled: bool,
+    pub potions: bool,
+    pub scrolls: bool,
+}
+
+impl Default for AutoPickup {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            potions: true,
+            scrolls: true,
+        }
+    }
+}
+
+/// how quickly animations play, by how many ticks each frame stays on screen
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationSpeed {
+    /// animations are skipped entirely
+    Off,
+    /// every other frame is skipped
+    Fast,
+    #[default]
+    Normal,
+    /// every frame is shown twice
+    Slow,
+}
+
+impl AnimationSpeed {
+    /// the speed after this one on the settings screen
+    pub fn next(self) -> Self {
+        match self {
+            AnimationSpeed::Off => AnimationSpeed::Fast,
+            AnimationSpeed::Fast => AnimationSpeed::Normal,
+            AnimationSpeed::Normal => AnimationSpeed::Slow,
+            AnimationSpeed::Slow => AnimationSpeed::Off,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationSpeed::Off => "off",
+            AnimationSpeed::Fast => "fast",
+            AnimationSpeed::Normal => "normal",
+            AnimationSpeed::Slow => "slow",
+        }
+    }
+}
+
+/// the settings that can be changed on the settings screen, in the order it lists them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SettingRow {
+    ViewRadius,
+    AutoPickup,
+    ConfirmQuit,
+    Theme,
+    AnimationSpeed,
+}
+
+pub const SETTING_ROWS: [SettingRow; 5] = [
+    SettingRow::ViewRadius,
+    SettingRow::AutoPickup,
+    SettingRow::ConfirmQuit,
+    SettingRow::Theme,
+    SettingRow::AnimationSpeed,
+];
+
+impl SettingRow {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SettingRow::ViewRadius => "view radius",
+            SettingRow::AutoPickup => "auto pickup",
+            SettingRow::ConfirmQuit => "confirm before quitting",
+            SettingRow::Theme => "theme",
+            SettingRow::AnimationSpeed => "animations",
+        }
+    }
+}
+
+/// options read from the settings file. fields missing from the file keep their defaults
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub tampered_saves: TamperPolicy,
+    /// number of older saves kept around, in case the newest one gets corrupted
+    pub save_backups: usize,
+    /// how many turns pass between autosaves, or 0 to only autosave on new floors
+    pub autosave_turns: u64,
+    pub auto_pickup: AutoPickup,
+    /// stops diagonal steps between two tiles unless both tiles beside the step are walkable,
+    /// for the player and monsters alike
+    pub forbid_corner_cutting: bool,
+    /// allows only the four orthogonal steps, for the player and monsters alike
+    pub orthogonal_only: bool,
+    /// draws a small health bar above every hurt monster in view
+    pub monster_health_bars: bool,
+    /// keeps the save around when the player dies, for debugging. normally a finished
+    /// run's save is deleted so it can't be loaded again
+    pub wizard_mode: bool,
+    /// how far the player can see, in tiles
+    pub view_radius: u16,
+    /// how many items fit in the pack, up to one per number key
+    pub inventory_size: usize,
+    /// asks before saving and quitting with ctrl-q
+    pub confirm_quit: bool,
+    /// name of a theme preset to use instead of the theme file
+    pub theme: Option<String>,
+    pub animation_speed: AnimationSpeed,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            tampered_saves: TamperPolicy::default(),
+            save_backups: 3,
+            autosave_turns: 50,
+            auto_pickup: AutoPickup::default(),
+            forbid_corner_cutting: false,
+            orthogonal_only: false,
+            monster_health_bars: false,
+            wizard_mode: false,
+            view_radius: 8,
+            inventory_size: MAX_INVENTORY_SIZE,
+            confirm_quit: true,
+            theme: None,
+            animation_speed: AnimationSpeed::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// loads the settings from the config file, falling back to the defaults if the
+    /// file is missing or malformed. returns the settings along with any warnings
+    pub fn load() -> (Self, Vec<String>) {
+        let Ok(contents) = fs::read_to_string(SETTINGS_FILE) else {
+            return (Self::default(), Vec::new());
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(mut settings) => {
+                settings.view_radius = settings.view_radius.clamp(MIN_VIEW_RADIUS, MAX_VIEW_RADIUS);
+                settings.inventory_size = settings.inventory_size.clamp(1, MAX_INVENTORY_SIZE);
+                (settings, Vec::new())
+            }
+            Err(_) => (
+                Self::default(),
+                vec![format!(
+                    "Could not read {SETTINGS_FILE}, using the default settings."
+                )],
+            ),
+        }
+    }
+
+    /// which diagonal steps the movement settings allow
+    pub fn diagonals(&self) -> Diagonals {
+        if self.orthogonal_only {
+            Diagonals::Forbidden
+        } else if self.forbid_corner_cutting {
+            Diagonals::NoCornerCutting
+        } else {
+            Diagonals::Free
+        }
+    }
+
+    /// writes the settings to the config file, so they're used the next time the game starts
+    pub fn save(&self) -> Result<()> {
+        let data_str = serde_json::to_string_pretty(&self)?;
+        fs::write(SETTINGS_FILE, data_str)?;
+        Ok(())
+    }
+}
+
+/// the value of a row, as the settings screen shows it
+pub fn row_value(app: &App, row: SettingRow) -> String {
+    let on_off = |on: bool| String::from(if on { "on" } else { "off" });
+    match row {
+        SettingRow::ViewRadius if app.daily.is_some() => {
+            format!(
+                "{} (kept for the daily challenge)",
+                app.settings.view_radius
+            )
+        }
+        SettingRow::ViewRadius => app.settings.view_radius.to_string(),
+        SettingRow::AutoPickup => on_off(app.settings.auto_pickup.enabled),
+        SettingRow::ConfirmQuit => on_off(app.settings.confirm_quit),
+        SettingRow::Theme => match &app.settings.theme {
+            Some(name) => name.clone(),
+            None => String::from("from theme.json"),
+        },
+        SettingRow::AnimationSpeed => app.settings.animation_speed.name().to_string(),
+    }
+}
+
+/// changes the setting on a row by one step, forward or back, and saves it right away
+pub fn adjust(app: &mut App, row: SettingRow, forward: bool) {
+    let settings = &mut app.settings;
+    match row {
+        // everyone plays the daily challenge with the same view
+        SettingRow::ViewRadius if app.daily.is_some() => return,
+        SettingRow::ViewRadius => {
+            settings.view_radius = match forward {
+                true => settings.view_radius + 1,
+                false => settings.view_radius.saturating_sub(1),
+            }
+            .clamp(MIN_VIEW_RADIUS, MAX_VIEW_RADIUS);
+        }
+        SettingRow::AutoPickup => settings.auto_pickup.enabled = !settings.auto_pickup.enabled,
+        SettingRow::ConfirmQuit => settings.confirm_quit = !settings.confirm_quit,
+        SettingRow::Theme => {
+            // the theme file comes before the first preset and after the last one
+            let current = settings
+                .theme
+                .as_deref()
+                .and_then(|name| Theme::PRESETS.iter().position(|&preset| preset == name));
+            let count = Theme::PRESETS.len() + 1;
+            let index = current.map_or(0, |idx| idx + 1);
+            let index = match forward {
+                true => (index + 1) % count,
+                false => (index + count - 1) % count,
+            };
+            settings.theme = index
+                .checked_sub(1)
+                .map(|idx| String::from(Theme::PRESETS[idx]));
+            app.theme = match settings.theme.as_deref().and_then(Theme::preset) {
+                Some(theme) => theme,
+                None => Theme::load().0,
+            };
+        }
+        SettingRow::AnimationSpeed => {
+            // there are only four speeds, so going back is going forward three times
+            let steps = if forward { 1 } else { 3 };
+            for _ in 0..steps {
+                settings.animation_speed = settings.animation_speed.next();
+            }
+        }
+    }
+    save_screen_settings(app);
+}
+
+/// writes the settings from the settings screen into the settings file, leaving
+/// everything else in it alone. the rest of app.settings may have been locked for a
+/// daily run, and shouldn't overwrite what the player chose
+fn save_screen_settings(app: &mut App) {
+    if app.headless {
+        return;
+    }
+    let (mut saved, _) = Settings::load();
+    if app.daily.is_none() {
+        saved.view_radius = app.settings.view_radius;
+    }
+    saved.auto_pickup.enabled = app.settings.auto_pickup.enabled;
+    saved.confirm_quit = app.settings.confirm_quit;
+    saved.theme = app.settings.theme.clone();
+    saved.animation_speed = app.settings.animation_speed;
+    if let Err(error) = saved.save() {
+        app.add_to_log(
+            format!("Couldn't save the settings: {}", error),
+            Color::Yellow,
+            MessageKind::System,
+        );
+    }
+}
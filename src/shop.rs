@@ -0,0 +1,108 @@
+use ratatui::style::Color;
+
+use crate::{
+    app::{App, MessageKind, PLAYER},
+    components::Item,
+    events::GameEvent,
+    identify, inventory,
+};
+
+// NOTE: this file contains the logic for trading with shopkeepers
+
+/// returns how much gold the player is carrying
+pub fn player_gold(app: &App) -> u32 {
+    app.objects.get(&PLAYER).unwrap().gold.unwrap_or(0)
+}
+
+/// adds gold to the player's purse
+pub fn add_player_gold(app: &mut App, amount: u32) {
+    let player = app.objects.get_mut(&PLAYER).unwrap();
+    player.gold = Some(player.gold.unwrap_or(0) + amount);
+}
+
+/// shops sell items at their full value
+pub fn buy_price(app: &App, id: usize) -> u32 {
+    app.objects.get(&id).unwrap().value
+}
+
+/// shops buy items back at half their value
+pub fn sell_price(app: &App, id: usize) -> u32 {
+    app.objects.get(&id).unwrap().value / 2
+}
+
+/// returns the ids of the items a shopkeeper has for sale
+pub fn get_stock(app: &App, shopkeeper: usize) -> &Vec<usize> {
+    match &app.objects.get(&shopkeeper).unwrap().shop {
+        Some(shop) => &shop.stock,
+        None => panic!("get_stock() called, but object does not have a shop component!"),
+    }
+}
+
+fn get_stock_mut(app: &mut App, shopkeeper: usize) -> &mut Vec<usize> {
+    match &mut app.objects.get_mut(&shopkeeper).unwrap().shop {
+        Some(shop) => &mut shop.stock,
+        None => panic!("get_stock_mut() called, but object does not have a shop component!"),
+    }
+}
+
+/// buys an item from the shopkeeper's stock, moving it into the player's inventory
+pub fn buy_item(app: &mut App, shopkeeper: usize, stock_idx: usize) {
+    let Some(&id) = get_stock(app, shopkeeper).get(stock_idx) else {
+        app.add_to_log("Nothing to buy.", Color::default(), MessageKind::Items);
+        return;
+    };
+
+    if !inventory::has_room_for(app, id) {
+        app.add_to_log(
+            "Cannot hold that many items.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return;
+    }
+
+    let price = buy_price(app, id);
+    let gold = player_gold(app);
+    if gold < price {
+        app.add_to_log(
+            format!("You can't afford that, it costs {} gold.", price),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return;
+    }
+
+    app.objects.get_mut(&PLAYER).unwrap().gold = Some(gold - price);
+    get_stock_mut(app, shopkeeper).remove(stock_idx);
+    let name = identify::item_name(app, id);
+    inventory::add_to_inventory(app, id);
+
+    app.emit(GameEvent::ItemBought { id, name, price });
+}
+
+/// sells an item from the player's inventory to the shopkeeper
+pub fn sell_item(app: &mut App, shopkeeper: usize, inventory_idx: usize) {
+    if inventory_idx >= app.inventory.len() {
+        app.add_to_log("Nothing to sell.", Color::default(), MessageKind::Items);
+        return;
+    }
+
+    let id = app.inventory[inventory_idx];
+    if app.objects.get(&id).unwrap().item == Some(Item::Amulet) {
+        app.add_to_log(
+            "The shopkeeper won't touch the amulet.",
+            Color::default(),
+            MessageKind::Items,
+        );
+        return;
+    }
+
+    // only a single item is sold off of a stack
+    let id = inventory::take_one_from_inventory(app, inventory_idx);
+    let price = sell_price(app, id);
+    add_player_gold(app, price);
+    get_stock_mut(app, shopkeeper).push(id);
+
+    let name = identify::item_name(app, id);
+    app.emit(GameEvent::ItemSold { id, name, price });
+}
@@ -0,0 +1,165 @@
+use ratatui::style::Color;
+
+use crate::{
+    app::{App, MessageKind, PLAYER},
+    components::{Caster, Item, Object, Position, RenderLayer, Renderable, Spell},
+    engine::UseResult,
+    theme::ThemeColor,
+};
+
+// this file contains the spells the player learns from books and casts with mana
+
+/// mana pool the player gets from the first spell they learn
+const FIRST_SPELL_MANA: u16 = 8;
+/// every spell learned after that deepens the pool a little
+const MANA_PER_SPELL: u16 = 2;
+/// time it takes to get back one point of mana
+const MANA_REGEN_TIME: u64 = 300;
+
+impl Spell {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Spell::Hexbolt => "hexbolt",
+            Spell::Lightning => "lightning",
+            Spell::Charm => "charm monster",
+            Spell::Heal => "cure wounds",
+            Spell::Haste => "haste",
+        }
+    }
+
+    pub fn mana_cost(&self) -> u16 {
+        match self {
+            Spell::Hexbolt => 3,
+            Spell::Lightning => 6,
+            Spell::Charm => 8,
+            Spell::Heal => 5,
+            Spell::Haste => 6,
+        }
+    }
+
+    /// the scroll or potion whose effect the spell casts, which also decides how it's aimed
+    pub fn effect(&self) -> Item {
+        match self {
+            Spell::Hexbolt => Item::Hexbolt,
+            Spell::Lightning => Item::Lightning,
+            Spell::Charm => Item::Charm,
+            Spell::Heal => Item::Heal,
+            Spell::Haste => Item::Haste,
+        }
+    }
+}
+
+/// a book that teaches the player a spell when read
+pub fn spellbook(spell: Spell) -> Object {
+    let name = format!("book of {}", spell.name());
+    let tooltip = format!(
+        "teaches the {} spell, which costs {} mana to cast.",
+        spell.name(),
+        spell.mana_cost()
+    );
+
+    let renderable = Renderable::themed('+', ThemeColor::RareItem);
+    let render_layer = RenderLayer::Item;
+
+    Object::new(name, tooltip, renderable, render_layer)
+        .set_item(Item::Spellbook(spell))
+        .set_value(80)
+        .set_weight(3)
+}
+
+/// returns the spells the player knows, in the order they show up in the spell menu
+pub fn known_spells(app: &App) -> Vec<Spell> {
+    match &app.objects.get(&PLAYER).unwrap().caster {
+        Some(caster) => caster.spells.clone(),
+        None => Vec::new(),
+    }
+}
+
+/// returns the player's current and maximum mana, or None if they can't cast spells
+pub fn player_mana(app: &App) -> Option<(u16, u16)> {
+    let caster = app.objects.get(&PLAYER).unwrap().caster.as_ref()?;
+    Some((caster.mana, caster.max_mana))
+}
+
+/// checks that the player has enough mana to cast a spell, logging why not if they don't
+pub fn has_mana_for(app: &mut App, spell: Spell) -> bool {
+    let (mana, _) = player_mana(app).unwrap_or((0, 0));
+    if mana < spell.mana_cost() {
+        app.add_to_log(
+            format!(
+                "You need {} mana to cast {}, but only have {}.",
+                spell.mana_cost(),
+                spell.name(),
+                mana
+            ),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return false;
+    }
+    true
+}
+
+/// reading a spellbook adds its spell to the spell menu, starting a mana pool if needed
+pub fn learn_spell(app: &mut App, spell: Spell) -> UseResult {
+    let player = app.objects.get_mut(&PLAYER).unwrap();
+    let caster = player.caster.get_or_insert(Caster {
+        mana: 0,
+        max_mana: 0,
+        spells: Vec::new(),
+    });
+
+    if caster.spells.contains(&spell) {
+        app.add_to_log(
+            format!("You already know how to cast {}.", spell.name()),
+            Color::default(),
+            MessageKind::Items,
+        );
+        return UseResult::Cancelled;
+    }
+
+    let gained = if caster.spells.is_empty() {
+        FIRST_SPELL_MANA
+    } else {
+        MANA_PER_SPELL
+    };
+    caster.max_mana += gained;
+    caster.mana += gained;
+    caster.spells.push(spell);
+
+    app.add_to_log(
+        format!("You learn to cast {}. Press z to cast it.", spell.name()),
+        Color::default(),
+        MessageKind::Items,
+    );
+    UseResult::UsedUp
+}
+
+/// casts the known spell at the given index, paying for it with mana if it goes off
+pub fn cast_spell(app: &mut App, index: usize, target: Option<Position>) -> UseResult {
+    let spell = known_spells(app)[index];
+    if !has_mana_for(app, spell) {
+        return UseResult::Cancelled;
+    }
+
+    let result = spell.effect().on_use(app, target);
+    if let UseResult::UsedUp = result {
+        let caster = app
+            .objects
+            .get_mut(&PLAYER)
+            .unwrap()
+            .caster
+            .as_mut()
+            .unwrap();
+        caster.mana -= spell.mana_cost();
+    }
+    result
+}
+
+/// gives the player back mana for the time that passed, like engine::regenerate does health
+pub fn regenerate_mana(app: &mut App, from: u64, to: u64) {
+    let points = to / MANA_REGEN_TIME - from / MANA_REGEN_TIME;
+    if let Some(caster) = &mut app.objects.get_mut(&PLAYER).unwrap().caster {
+        caster.mana = (caster.mana as u64 + points).min(caster.max_mana as u64) as u16;
+    }
+}
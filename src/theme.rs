@@ -0,0 +1,534 @@
+// semantic colors used when drawing the game, so they can be swapped out as a whole
+
+use std::fs;
+
+use ratatui::style::{Color, Style};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// file the theme is loaded from, relative to where the game is run
+pub const THEME_FILE: &str = "theme.json";
+
+/// a role that something on screen plays, looked up in the theme when drawn
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+pub enum ThemeColor {
+    Player,
+    Enemy,
+    Vermin,
+    Brute,
+    Venomous,
+    Sentry,
+    Boss,
+    Npc,
+    Item,
+    RareItem,
+    Gold,
+    Stairs,
+    Trap,
+    WallLit,
+    WallDark,
+    FloorLit,
+    FloorDark,
+    Breadcrumb,
+    Chasm,
+    Water,
+    Lava,
+    Grass,
+    Fire,
+    Gas,
+    Smoke,
+    Target,
+    Blocked,
+    Danger,
+    HealthFull,
+    HealthEmpty,
+    Status,
+    Muted,
+    LogWarning,
+    LogDanger,
+}
+
+impl ThemeColor {
+    /// whether this color marks something that wants to hurt the player
+    pub fn is_hostile(&self) -> bool {
+        matches!(
+            self,
+            ThemeColor::Enemy
+                | ThemeColor::Vermin
+                | ThemeColor::Brute
+                | ThemeColor::Venomous
+                | ThemeColor::Sentry
+                | ThemeColor::Boss
+        )
+    }
+}
+
+/// every color should be at least this readable against the black background
+const MIN_CONTRAST: f64 = 2.0;
+
+/// colors that must be told apart, like hostiles and the player, need at least this
+/// much contrast between each other, since their hues may look alike
+const MIN_DISTINCT_CONTRAST: f64 = 1.5;
+
+// the okabe-ito palette, which stays distinct under the common kinds of color blindness
+const ORANGE: Color = Color::Rgb(230, 159, 0);
+const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+const BLUISH_GREEN: Color = Color::Rgb(0, 158, 115);
+const YELLOW: Color = Color::Rgb(240, 228, 66);
+const BLUE: Color = Color::Rgb(0, 114, 178);
+const VERMILLION: Color = Color::Rgb(213, 94, 0);
+const REDDISH_PURPLE: Color = Color::Rgb(204, 121, 167);
+
+/// maps every semantic color to the color it is actually drawn with.
+/// fields missing from the config file are filled in from the preset it is based on
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct Theme {
+    pub player: Color,
+    pub enemy: Color,
+    pub vermin: Color,
+    pub brute: Color,
+    pub venomous: Color,
+    pub sentry: Color,
+    pub boss: Color,
+    pub npc: Color,
+    pub item: Color,
+    pub rare_item: Color,
+    pub gold: Color,
+    pub stairs: Color,
+    pub trap: Color,
+    pub wall_lit: Color,
+    pub wall_dark: Color,
+    pub floor_lit: Color,
+    pub floor_dark: Color,
+    pub breadcrumb: Color,
+    pub chasm: Color,
+    pub water: Color,
+    pub lava: Color,
+    pub grass: Color,
+    pub fire: Color,
+    pub gas: Color,
+    pub smoke: Color,
+    pub target: Color,
+    pub blocked: Color,
+    pub danger: Color,
+    pub health_full: Color,
+    pub health_empty: Color,
+    pub mana_full: Color,
+    pub mana_empty: Color,
+    pub status: Color,
+    pub muted: Color,
+    pub log_warning: Color,
+    pub log_danger: Color,
+    /// also mark hostiles and remembered tiles with glyph decorations, not just color
+    pub accents: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            player: Color::default(),
+            enemy: Color::Red,
+            vermin: Color::Yellow,
+            brute: Color::Green,
+            venomous: Color::Magenta,
+            sentry: Color::LightYellow,
+            boss: Color::LightRed,
+            npc: Color::Yellow,
+            item: Color::default(),
+            rare_item: Color::Blue,
+            gold: Color::Yellow,
+            stairs: Color::Gray,
+            trap: Color::LightRed,
+            wall_lit: Color::Gray,
+            wall_dark: Color::DarkGray,
+            floor_lit: Color::Gray,
+            floor_dark: Color::DarkGray,
+            breadcrumb: Color::Gray,
+            chasm: Color::DarkGray,
+            water: Color::Blue,
+            lava: Color::Red,
+            grass: Color::Green,
+            fire: Color::Yellow,
+            gas: Color::LightGreen,
+            smoke: Color::Gray,
+            target: Color::Magenta,
+            blocked: Color::Red,
+            danger: Color::Red,
+            health_full: Color::Green,
+            health_empty: Color::Red,
+            mana_full: Color::LightBlue,
+            mana_empty: Color::DarkGray,
+            status: Color::LightCyan,
+            muted: Color::DarkGray,
+            log_warning: Color::Yellow,
+            log_danger: Color::Red,
+            accents: false,
+        }
+    }
+}
+
+impl Theme {
+    /// bright colors on black, with lit and remembered tiles kept far apart
+    pub fn high_contrast() -> Self {
+        Self {
+            player: Color::White,
+            enemy: Color::LightRed,
+            vermin: Color::Yellow,
+            brute: Color::Green,
+            venomous: Color::LightMagenta,
+            sentry: Color::Yellow,
+            boss: Color::LightRed,
+            npc: Color::LightCyan,
+            item: Color::White,
+            rare_item: Color::LightBlue,
+            gold: Color::LightYellow,
+            stairs: Color::White,
+            trap: Color::LightRed,
+            wall_lit: Color::White,
+            wall_dark: Color::DarkGray,
+            floor_lit: Color::Gray,
+            floor_dark: Color::DarkGray,
+            breadcrumb: Color::Gray,
+            chasm: Color::Blue,
+            water: Color::LightBlue,
+            lava: Color::LightRed,
+            grass: Color::LightGreen,
+            fire: Color::LightYellow,
+            gas: Color::LightGreen,
+            smoke: Color::White,
+            target: Color::LightMagenta,
+            blocked: Color::LightRed,
+            danger: Color::LightRed,
+            health_full: Color::LightGreen,
+            health_empty: Color::LightRed,
+            mana_full: Color::LightCyan,
+            mana_empty: Color::Gray,
+            status: Color::LightCyan,
+            muted: Color::Gray,
+            log_warning: Color::LightYellow,
+            log_danger: Color::LightRed,
+            accents: false,
+        }
+    }
+
+    /// safe for red-green color blindness (deuteranopia and protanopia).
+    /// hostiles are warm colors, everything the player should feel safe about is blue
+    pub fn deuteranopia() -> Self {
+        Self {
+            player: Color::White,
+            enemy: VERMILLION,
+            vermin: ORANGE,
+            brute: BLUE,
+            venomous: REDDISH_PURPLE,
+            sentry: ORANGE,
+            boss: VERMILLION,
+            npc: SKY_BLUE,
+            item: Color::White,
+            rare_item: SKY_BLUE,
+            gold: YELLOW,
+            stairs: Color::White,
+            trap: VERMILLION,
+            wall_lit: Color::Gray,
+            wall_dark: Color::DarkGray,
+            floor_lit: Color::Gray,
+            floor_dark: Color::DarkGray,
+            breadcrumb: Color::Gray,
+            chasm: BLUE,
+            water: SKY_BLUE,
+            lava: VERMILLION,
+            grass: BLUISH_GREEN,
+            fire: ORANGE,
+            gas: REDDISH_PURPLE,
+            smoke: Color::Gray,
+            target: SKY_BLUE,
+            blocked: VERMILLION,
+            danger: VERMILLION,
+            health_full: SKY_BLUE,
+            health_empty: VERMILLION,
+            mana_full: BLUE,
+            mana_empty: Color::DarkGray,
+            status: SKY_BLUE,
+            muted: Color::DarkGray,
+            log_warning: ORANGE,
+            log_danger: VERMILLION,
+            accents: true,
+        }
+    }
+
+    /// safe for blue-yellow color blindness (tritanopia).
+    /// hostiles are reds and pinks, everything the player should feel safe about is teal
+    pub fn tritanopia() -> Self {
+        Self {
+            player: Color::White,
+            enemy: VERMILLION,
+            vermin: REDDISH_PURPLE,
+            brute: Color::Rgb(170, 0, 0),
+            venomous: REDDISH_PURPLE,
+            sentry: ORANGE,
+            boss: VERMILLION,
+            npc: BLUISH_GREEN,
+            item: Color::White,
+            rare_item: BLUISH_GREEN,
+            gold: ORANGE,
+            stairs: Color::White,
+            trap: VERMILLION,
+            wall_lit: Color::Gray,
+            wall_dark: Color::DarkGray,
+            floor_lit: Color::Gray,
+            floor_dark: Color::DarkGray,
+            breadcrumb: Color::Gray,
+            chasm: BLUISH_GREEN,
+            water: SKY_BLUE,
+            lava: VERMILLION,
+            grass: BLUISH_GREEN,
+            fire: ORANGE,
+            gas: REDDISH_PURPLE,
+            smoke: Color::Gray,
+            target: REDDISH_PURPLE,
+            blocked: VERMILLION,
+            danger: VERMILLION,
+            health_full: Color::White,
+            health_empty: VERMILLION,
+            mana_full: BLUISH_GREEN,
+            mana_empty: Color::DarkGray,
+            status: BLUISH_GREEN,
+            muted: Color::DarkGray,
+            log_warning: ORANGE,
+            log_danger: VERMILLION,
+            accents: true,
+        }
+    }
+
+    /// names of every preset, in the order the settings screen cycles through them
+    pub const PRESETS: [&str; 4] = ["default", "high_contrast", "deuteranopia", "tritanopia"];
+
+    /// returns the preset with the given name, if there is one
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "high_contrast" => Some(Self::high_contrast()),
+            "deuteranopia" => Some(Self::deuteranopia()),
+            "tritanopia" => Some(Self::tritanopia()),
+            _ => None,
+        }
+    }
+
+    /// loads the theme from the config file, falling back to the default theme if the
+    /// file is missing or malformed. the file can name a "preset" to start from, and
+    /// override any of its colors by field name.
+    /// returns the theme along with warnings about anything wrong with it
+    pub fn load() -> (Self, Vec<String>) {
+        let Ok(contents) = fs::read_to_string(THEME_FILE) else {
+            return (Self::default(), Vec::new());
+        };
+        match Self::from_json(&contents) {
+            Some(theme) => {
+                let warnings = theme.contrast_warnings();
+                (theme, warnings)
+            }
+            None => (
+                Self::default(),
+                vec![format!(
+                    "Could not read {THEME_FILE}, using the default theme."
+                )],
+            ),
+        }
+    }
+
+    /// checks that every color is readable, and that colors which have to be told
+    /// apart differ in brightness and not just hue
+    pub fn contrast_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        let Value::Object(fields) = serde_json::to_value(self).unwrap() else {
+            unreachable!("themes always serialize to an object");
+        };
+        for (name, value) in fields {
+            let Ok(color) = serde_json::from_value::<Color>(value) else {
+                continue;
+            };
+            if contrast(color, Color::Black) < MIN_CONTRAST {
+                warnings.push(format!("Theme color {name} is hard to see on black."));
+            }
+        }
+
+        let mut pairs = vec![
+            ("wall_lit", self.wall_lit, "wall_dark", self.wall_dark),
+            ("floor_lit", self.floor_lit, "floor_dark", self.floor_dark),
+        ];
+        let hostiles = [
+            ("enemy", self.enemy),
+            ("vermin", self.vermin),
+            ("brute", self.brute),
+            ("venomous", self.venomous),
+            ("sentry", self.sentry),
+            ("boss", self.boss),
+        ];
+        for (name, color) in hostiles {
+            pairs.push(("player", self.player, name, color));
+        }
+        for (name_a, a, name_b, b) in pairs {
+            if contrast(a, b) < MIN_DISTINCT_CONTRAST {
+                warnings.push(format!(
+                    "Theme colors {name_a} and {name_b} are too similar to tell apart."
+                ));
+            }
+        }
+
+        warnings
+    }
+
+    /// parses a theme config, see load()
+    fn from_json(contents: &str) -> Option<Self> {
+        let Value::Object(config) = serde_json::from_str::<Value>(contents).ok()? else {
+            return None;
+        };
+
+        let base = match config.get("preset") {
+            Some(Value::String(name)) => Self::preset(name)?,
+            Some(_) => return None,
+            None => Self::default(),
+        };
+
+        // lay the overrides on top of the preset's colors
+        let Value::Object(mut fields) = serde_json::to_value(base).ok()? else {
+            return None;
+        };
+        for (key, value) in config {
+            if key != "preset" {
+                fields.insert(key, value);
+            }
+        }
+        serde_json::from_value(Value::Object(fields)).ok()
+    }
+
+    /// returns the color that a semantic color is drawn with
+    pub fn get(&self, color: ThemeColor) -> Color {
+        match color {
+            ThemeColor::Player => self.player,
+            ThemeColor::Enemy => self.enemy,
+            ThemeColor::Vermin => self.vermin,
+            ThemeColor::Brute => self.brute,
+            ThemeColor::Venomous => self.venomous,
+            ThemeColor::Sentry => self.sentry,
+            ThemeColor::Boss => self.boss,
+            ThemeColor::Npc => self.npc,
+            ThemeColor::Item => self.item,
+            ThemeColor::RareItem => self.rare_item,
+            ThemeColor::Gold => self.gold,
+            ThemeColor::Stairs => self.stairs,
+            ThemeColor::Trap => self.trap,
+            ThemeColor::WallLit => self.wall_lit,
+            ThemeColor::WallDark => self.wall_dark,
+            ThemeColor::FloorLit => self.floor_lit,
+            ThemeColor::FloorDark => self.floor_dark,
+            ThemeColor::Breadcrumb => self.breadcrumb,
+            ThemeColor::Chasm => self.chasm,
+            ThemeColor::Water => self.water,
+            ThemeColor::Lava => self.lava,
+            ThemeColor::Grass => self.grass,
+            ThemeColor::Fire => self.fire,
+            ThemeColor::Gas => self.gas,
+            ThemeColor::Smoke => self.smoke,
+            ThemeColor::Target => self.target,
+            ThemeColor::Blocked => self.blocked,
+            ThemeColor::Danger => self.danger,
+            ThemeColor::HealthFull => self.health_full,
+            ThemeColor::HealthEmpty => self.health_empty,
+            ThemeColor::Status => self.status,
+            ThemeColor::Muted => self.muted,
+            ThemeColor::LogWarning => self.log_warning,
+            ThemeColor::LogDanger => self.log_danger,
+        }
+    }
+
+    /// log messages are written with the default theme's colors. this swaps the warning
+    /// and danger colors out for the ones in this theme
+    pub fn log_style(&self, style: Style) -> Style {
+        let default = Self::default();
+        match style.fg {
+            Some(fg) if fg == default.log_danger => style.fg(self.log_danger),
+            Some(fg) if fg == default.log_warning => style.fg(self.log_warning),
+            _ => style,
+        }
+    }
+}
+
+/// approximates the rgb value a terminal shows for a color, using xterm's defaults
+fn to_rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 0, 0),
+        Color::Green => (0, 205, 0),
+        Color::Yellow => (205, 205, 0),
+        Color::Blue => (0, 0, 238),
+        Color::Magenta => (205, 0, 205),
+        Color::Cyan => (0, 205, 205),
+        Color::Gray => (229, 229, 229),
+        Color::DarkGray => (127, 127, 127),
+        Color::LightRed => (255, 0, 0),
+        Color::LightGreen => (0, 255, 0),
+        Color::LightYellow => (255, 255, 0),
+        Color::LightBlue => (92, 92, 255),
+        Color::LightMagenta => (255, 0, 255),
+        Color::LightCyan => (0, 255, 255),
+        Color::White => (255, 255, 255),
+        // the terminal's own foreground, which is usually light gray
+        Color::Reset => (229, 229, 229),
+        Color::Indexed(index) => match index {
+            0..=15 => to_rgb(ANSI_COLORS[index as usize]),
+            16..=231 => {
+                // 6x6x6 color cube
+                let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+                let index = index - 16;
+                (level(index / 36), level(index / 6 % 6), level(index % 6))
+            }
+            232..=255 => {
+                let gray = 8 + (index - 232) * 10;
+                (gray, gray, gray)
+            }
+        },
+    }
+}
+
+/// the first 16 indexed colors are the named ones
+const ANSI_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// relative luminance of a color, from 0 for black to 1 for white.
+/// see www.w3.org/TR/WCAG21/#dfn-relative-luminance
+fn luminance(color: Color) -> f64 {
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = to_rgb(color);
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// contrast ratio between two colors, from 1 for identical brightness up to 21
+fn contrast(a: Color, b: Color) -> f64 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}